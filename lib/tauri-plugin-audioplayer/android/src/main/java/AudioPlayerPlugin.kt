@@ -51,6 +51,12 @@ internal class SeekArgs {
     var seek = 0f
 }
 
+@InvokeArg
+internal class VolumeArgs {
+    lateinit var key: String
+    var volume = 1f
+}
+
 @InvokeArg
 internal class UpdateMetadataArgs {
     lateinit var metadata: MetadataArgs
@@ -198,6 +204,14 @@ class AudioPlayerPlugin(private val activity: Activity): Plugin(activity) {
         invoke.resolve(ret)
     }
 
+    @Command
+    fun setVolume(invoke: Invoke) {
+        val args = invoke.parseArgs(VolumeArgs::class.java)
+        implementation.controls?.setVolume(args.key, args.volume)
+        val ret = JSObject()
+        invoke.resolve(ret)
+    }
+
     @Command
     fun updateNotification(invoke: Invoke) {
         val args = invoke.parseArgs(UpdateMetadataArgs::class.java)
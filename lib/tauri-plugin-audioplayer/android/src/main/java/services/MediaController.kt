@@ -131,6 +131,10 @@ class MediaController(private val mContext: Context) {
                 playbackManager.seek(key, time)
             }
 
+            override fun setVolume(key: String, volume: Float) {
+                playbackManager.setVolume(key, volume)
+            }
+
             override fun load(key: String, src: String, autoplay: Boolean) {
                 playbackManager.load(key, mContext, src, autoplay)
             }
@@ -56,6 +56,10 @@ class PlaybackManager(mContext: Context, private val playerListeners: PlayerList
         players[key]?.progress = pos
     }
 
+    fun setVolume(key: String, volume: Float) {
+        players[key]?.volume = volume
+    }
+
     fun canPlay(key: String, song: Song): Boolean {
         return players[key]?.canPlay(song) == true
     }
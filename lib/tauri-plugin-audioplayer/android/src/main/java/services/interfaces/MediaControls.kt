@@ -27,6 +27,8 @@ interface MediaControls {
 
     fun seek(key: String, time: Int)
 
+    fun setVolume(key: String, volume: Float)
+
     fun load(key: String, src: String, autoplay: Boolean)
 
     fun updateMetadata(metadata: MetadataArgs?)
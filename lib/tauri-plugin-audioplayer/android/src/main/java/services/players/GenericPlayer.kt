@@ -29,6 +29,8 @@ abstract class GenericPlayer {
     abstract fun release()
 
     abstract var progress: Int
+    // Ranges from 0 to 1
+    abstract var volume: Float
     abstract val isPlaying: Boolean
 
     abstract fun setPlayerListeners(playerListeners: PlayerListeners)
@@ -118,6 +118,7 @@ class LibrespotPlayer(private val context: Context): GenericPlayer() {
     override var progress: Int
         get() = 0
         set(value) {}
+    override var volume: Float = 1.0F
     override val isPlaying: Boolean
         get() = false
 
@@ -38,6 +38,12 @@ class LocalPlayer : GenericPlayer() {
         get() = playerInstance.currentPosition
         set(value) { playerInstance.seekTo(value) }
 
+    override var volume: Float = 1.0F
+        set(value) {
+            field = value
+            playerInstance.setVolume(value, value)
+        }
+
     private var ignoreSongEnded = false
 
     override val isPlaying: Boolean
@@ -91,7 +97,7 @@ class LocalPlayer : GenericPlayer() {
         val uri = buildUri(src)
         Log.d("TAG", "load: got uri $uri")
         playerInstance.setDataSource(mContext, uri)
-        playerInstance.setVolume(1.0F, 1.0F)
+        playerInstance.setVolume(volume, volume)
 
         playerInstance.setOnPreparedListener {
             if (autoPlay) {
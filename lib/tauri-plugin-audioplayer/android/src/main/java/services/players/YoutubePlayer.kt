@@ -118,6 +118,12 @@ class YoutubePlayer(mContext: Context) : GenericPlayer() {
             _progress = value.toFloat() / 1000
         }
 
+    override var volume: Float = 1.0F
+        set(value) {
+            field = value
+            playerInstance?.setVolume((value * 100).toInt())
+        }
+
     private var _isPlaying: Boolean = false
     override val isPlaying: Boolean
         get() = _isPlaying
@@ -14,7 +14,7 @@
 // You should have received a copy of the GNU General Public License
 // along with this program.  If not, see <http://www.gnu.org/licenses/>.
 
-const COMMANDS: &[&str] = &["registerListener", "load", "play", "pause", "stop", "seek"];
+const COMMANDS: &[&str] = &["registerListener", "load", "play", "pause", "stop", "seek", "setVolume"];
 
 fn main() {
     tauri_plugin::Builder::new(COMMANDS)
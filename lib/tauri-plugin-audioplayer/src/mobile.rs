@@ -77,6 +77,13 @@ struct SeekArgs {
     seek: f64,
 }
 
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct VolumeArgs {
+    key: String,
+    volume: f64,
+}
+
 #[derive(Serialize)]
 #[serde(rename_all = "camelCase")]
 struct UpdateNotificationArgs {
@@ -155,6 +162,15 @@ impl<R: Runtime> Audioplayer<R> {
         Ok(())
     }
 
+    /// Volume ranges from 0 to 1
+    pub fn set_volume(&self, key: String, volume: f64) -> Result<()> {
+        let res: serde_json::Value = self
+            .0
+            .run_mobile_plugin("setVolume", VolumeArgs { key, volume })
+            .map_err(|e| MoosyncError::String(e.to_string()))?;
+        Ok(())
+    }
+
     pub fn update_notification(&self, metadata: MprisPlayerDetails) -> Result<()> {
         let res: serde_json::Value = self
             .0
@@ -0,0 +1,12 @@
+-- Your SQL goes here
+CREATE TABLE `radio_stations`(
+	`station_id` TEXT PRIMARY KEY,
+	`station_name` TEXT NOT NULL,
+	`station_url` TEXT NOT NULL UNIQUE,
+	`homepage` TEXT,
+	`cover` TEXT,
+	`genre` TEXT,
+	`codec` TEXT,
+	`bitrate` DOUBLE,
+	`date_added` BIGINT
+);
@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+DROP TABLE IF EXISTS `queue_state`;
+DROP TABLE IF EXISTS `play_queue`;
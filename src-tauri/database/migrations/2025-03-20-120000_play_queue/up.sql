@@ -0,0 +1,12 @@
+-- Your SQL goes here
+CREATE TABLE `play_queue`(
+	`position` INTEGER NOT NULL PRIMARY KEY,
+	`song_id` TEXT NOT NULL,
+	`song` TEXT NOT NULL
+);
+
+CREATE TABLE `queue_state`(
+	`id` INTEGER NOT NULL PRIMARY KEY,
+	`current_index` INTEGER NOT NULL DEFAULT 0,
+	`repeat_mode` INTEGER NOT NULL DEFAULT 0
+);
@@ -0,0 +1,12 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE queue_state
+DROP COLUMN shuffle;
+
+ALTER TABLE queue_state
+DROP COLUMN avoid_same_artist;
+
+ALTER TABLE queue_state
+DROP COLUMN original_order;
+
+ALTER TABLE queue_state
+DROP COLUMN history;
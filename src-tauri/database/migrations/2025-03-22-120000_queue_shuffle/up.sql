@@ -0,0 +1,12 @@
+-- Your SQL goes here
+ALTER TABLE queue_state
+ADD COLUMN shuffle BOOLEAN NOT NULL DEFAULT FALSE;
+
+ALTER TABLE queue_state
+ADD COLUMN avoid_same_artist BOOLEAN NOT NULL DEFAULT FALSE;
+
+ALTER TABLE queue_state
+ADD COLUMN original_order TEXT NOT NULL DEFAULT '[]';
+
+ALTER TABLE queue_state
+ADD COLUMN history TEXT NOT NULL DEFAULT '[]';
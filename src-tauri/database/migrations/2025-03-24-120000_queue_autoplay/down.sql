@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE queue_state
+DROP COLUMN autoplay;
@@ -0,0 +1,3 @@
+-- Your SQL goes here
+ALTER TABLE queue_state
+ADD COLUMN autoplay BOOLEAN NOT NULL DEFAULT FALSE;
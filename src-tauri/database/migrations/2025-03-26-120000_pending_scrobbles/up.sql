@@ -0,0 +1,7 @@
+-- Your SQL goes here
+CREATE TABLE `pending_scrobbles`(
+	`id` INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+	`service` TEXT NOT NULL,
+	`track` TEXT NOT NULL,
+	`attempts` INTEGER NOT NULL DEFAULT 0
+);
@@ -0,0 +1,6 @@
+-- Your SQL goes here
+CREATE TABLE `resume_points`(
+	`song_id` TEXT NOT NULL PRIMARY KEY,
+	`position` DOUBLE NOT NULL,
+	`updated_at` BIGINT NOT NULL
+);
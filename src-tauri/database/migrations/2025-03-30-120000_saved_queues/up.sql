@@ -0,0 +1,10 @@
+-- Your SQL goes here
+CREATE TABLE `saved_queues`(
+	`id` TEXT NOT NULL PRIMARY KEY,
+	`name` TEXT NOT NULL,
+	`songs` TEXT NOT NULL,
+	`current_index` INTEGER NOT NULL DEFAULT 0,
+	`position` DOUBLE NOT NULL DEFAULT 0,
+	`repeat_mode` INTEGER NOT NULL DEFAULT 0,
+	`updated_at` BIGINT NOT NULL
+);
@@ -15,6 +15,8 @@
 // along with this program.  If not, see <http://www.gnu.org/licenses/>.
 
 use std::cmp::min;
+use std::collections::{HashMap, HashSet};
+use std::time::{SystemTime, UNIX_EPOCH};
 
 use std::fmt::Write;
 use std::str::FromStr;
@@ -24,7 +26,8 @@ use diesel::{
     connection::SimpleConnection,
     delete, insert_into,
     r2d2::{self, ConnectionManager, Pool, PooledConnection},
-    update, Connection, ExpressionMethods, QueryDsl, RunQueryDsl, SqliteConnection,
+    update, Connection, ExpressionMethods, OptionalExtension, QueryDsl, RunQueryDsl,
+    SqliteConnection,
 };
 use diesel::{BoolExpressionMethods, Insertable, TextExpressionMethods};
 use diesel_logger::LoggingConnection;
@@ -36,8 +39,20 @@ use uuid::Uuid;
 use types::common::{BridgeUtils, SearchByTerm};
 use types::entities::{Analytics, EntityInfo, PlaylistBridge, SearchResult};
 use types::errors::{MoosyncError, Result};
+use types::queue::{
+    QueryableQueueState, QueryableSavedQueue, Queue, QueueItem, QueueState, SavedQueue,
+    ShuffleState,
+};
+use types::radio::QueryableStation;
 use types::schema::analytics::dsl::analytics;
+use types::schema::pending_scrobbles::dsl::pending_scrobbles;
+use types::schema::play_queue::dsl::play_queue;
 use types::schema::playlists::dsl::playlists;
+use types::schema::queue_state::dsl::queue_state;
+use types::schema::radio_stations::dsl::radio_stations;
+use types::schema::resume_points::dsl::resume_points;
+use types::schema::saved_queues::dsl::saved_queues;
+use types::scrobble::{NewPendingScrobble, PendingScrobble, ScrobbleService, ScrobbleTrack};
 use types::songs::{AllAnalytics, SearchableSong};
 use types::{
     schema::{
@@ -60,7 +75,15 @@ use types::{
     },
 };
 
-use super::migrations::run_migrations;
+use super::{migrations::run_migrations, radio::parse_station_list};
+
+/// Songs released this many years apart are considered to be from the same era
+const SIMILAR_ERA_YEARS: i32 = 2;
+
+// Years are stored as text, either just the year or a full date
+fn parse_year(year: Option<&str>) -> Option<i32> {
+    year?.get(..4)?.parse().ok()
+}
 
 #[derive(Debug, Clone)]
 pub struct Database {
@@ -328,6 +351,11 @@ impl Database {
                         schema::playlist_bridge::song.eq(id.clone()),
                     ))
                     .execute(conn)?;
+                    delete(QueryDsl::filter(
+                        resume_points,
+                        schema::resume_points::song_id.eq(id.clone()),
+                    ))
+                    .execute(conn)?;
 
                     // Finally delete the song itself
                     delete(QueryDsl::filter(allsongs, _id.eq(id.clone()))).execute(conn)?;
@@ -1200,6 +1228,544 @@ impl Database {
         })
     }
 
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub fn get_stations(&self) -> Result<Vec<QueryableStation>> {
+        let mut conn = self.pool.get().unwrap();
+        let stations = QueryDsl::order(radio_stations, schema::radio_stations::station_name.asc())
+            .load::<QueryableStation>(&mut conn)?;
+        Ok(stations)
+    }
+
+    /// Stations whose url already exists are skipped. Returns the stations which were added.
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub fn add_stations(&self, stations: Vec<QueryableStation>) -> Result<Vec<QueryableStation>> {
+        trace!("Adding stations");
+        let mut conn = self.pool.get().unwrap();
+        let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_millis() as i64;
+
+        let added = conn.transaction::<_, MoosyncError, _>(|conn| {
+            let mut existing: HashSet<String> =
+                QueryDsl::select(radio_stations, schema::radio_stations::station_url)
+                    .load::<String>(conn)?
+                    .into_iter()
+                    .collect();
+
+            let mut added = vec![];
+            for mut station in stations {
+                if station.station_url.is_empty() || existing.contains(&station.station_url) {
+                    debug!("Skipping station {}", station.station_url);
+                    continue;
+                }
+
+                if station.station_id.is_none() {
+                    station.station_id = Some(Uuid::new_v4().to_string());
+                }
+                if station.station_name.is_empty() {
+                    station.station_name = station.station_url.clone();
+                }
+                if station.date_added.is_none() {
+                    station.date_added = Some(now);
+                }
+
+                insert_into(radio_stations).values(&station).execute(conn)?;
+                existing.insert(station.station_url.clone());
+                added.push(station);
+            }
+            Ok(added)
+        })?;
+
+        info!("Added {} stations", added.len());
+        Ok(added)
+    }
+
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub fn update_station(&self, station: QueryableStation) -> Result<()> {
+        trace!("Updating station");
+        let mut conn = self.pool.get().unwrap();
+        update(radio_stations)
+            .filter(schema::radio_stations::station_id.eq(station.station_id.clone()))
+            .set(station)
+            .execute(&mut conn)?;
+        info!("Updated station");
+        Ok(())
+    }
+
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub fn remove_station(&self, id: String) -> Result<()> {
+        trace!("Removing station");
+        let mut conn = self.pool.get().unwrap();
+        delete(radio_stations)
+            .filter(schema::radio_stations::station_id.eq(id))
+            .execute(&mut conn)?;
+        info!("Removed station");
+        Ok(())
+    }
+
+    #[tracing::instrument(level = "debug", skip(self, content))]
+    pub fn import_stations(&self, content: String) -> Result<Vec<QueryableStation>> {
+        let stations = parse_station_list(&content)?;
+        self.add_stations(stations)
+    }
+
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub fn get_queue_state(&self) -> Result<QueueState> {
+        let mut conn = self.pool.get().unwrap();
+        let items = QueryDsl::order(play_queue, schema::play_queue::position.asc())
+            .load::<QueueItem>(&mut conn)?;
+        let details = queue_state.first::<QueryableQueueState>(&mut conn).ok();
+
+        let mut queue = Queue::default();
+        for item in items {
+            if !queue.data.contains_key(&item.song_id) {
+                match serde_json::from_str::<Song>(&item.song) {
+                    Ok(song) => {
+                        queue.data.insert(item.song_id.clone(), song);
+                    }
+                    Err(e) => {
+                        warn!("Skipping unreadable queue item {}: {:?}", item.song_id, e);
+                        continue;
+                    }
+                }
+            }
+            queue.song_queue.push(item.song_id);
+        }
+
+        let details = details.unwrap_or_default();
+        queue.current_index =
+            (details.current_index.max(0) as usize).min(queue.song_queue.len().saturating_sub(1));
+
+        Ok(QueueState {
+            queue,
+            repeat: details.repeat_mode.into(),
+            shuffle: ShuffleState {
+                enabled: details.shuffle,
+                avoid_same_artist: details.avoid_same_artist,
+                original_order: serde_json::from_str(&details.original_order).unwrap_or_default(),
+            },
+            history: serde_json::from_str(&details.history).unwrap_or_default(),
+            autoplay: details.autoplay,
+        })
+    }
+
+    /// Persists the queue along with its details. Songs before position from are
+    /// unchanged and kept as they are, the rest are rewritten
+    #[tracing::instrument(level = "debug", skip(self, state))]
+    pub fn save_queue_state(&self, state: &QueueState, from: usize) -> Result<()> {
+        let mut conn = self.pool.get().unwrap();
+        let items = state
+            .queue
+            .song_queue
+            .iter()
+            .enumerate()
+            .skip(from)
+            .filter_map(|(position, song_id)| {
+                let song = state.queue.data.get(song_id)?;
+                Some(QueueItem {
+                    position: position as i32,
+                    song_id: song_id.clone(),
+                    song: serde_json::to_string(song).ok()?,
+                })
+            })
+            .collect::<Vec<_>>();
+
+        conn.transaction::<_, MoosyncError, _>(|conn| {
+            delete(play_queue)
+                .filter(schema::play_queue::position.ge(from as i32))
+                .execute(conn)?;
+            for chunk in items.chunks(500) {
+                insert_into(play_queue).values(chunk).execute(conn)?;
+            }
+            Self::upsert_queue_details(conn, state)?;
+            Ok(())
+        })?;
+
+        trace!("Saved {} queued songs from position {}", items.len(), from);
+        Ok(())
+    }
+
+    /// Persists only the current index and modes, for changes which don't touch the songs
+    #[tracing::instrument(level = "debug", skip(self, state))]
+    pub fn save_queue_details(&self, state: &QueueState) -> Result<()> {
+        let mut conn = self.pool.get().unwrap();
+        Self::upsert_queue_details(&mut conn, state)
+    }
+
+    fn upsert_queue_details(
+        conn: &mut PooledConnection<ConnectionManager<LoggingConnection<SqliteConnection>>>,
+        state: &QueueState,
+    ) -> Result<()> {
+        let details = QueryableQueueState {
+            id: 0,
+            current_index: state.queue.current_index as i32,
+            repeat_mode: state.repeat.into(),
+            shuffle: state.shuffle.enabled,
+            avoid_same_artist: state.shuffle.avoid_same_artist,
+            original_order: serde_json::to_string(&state.shuffle.original_order)?,
+            history: serde_json::to_string(&state.history)?,
+            autoplay: state.autoplay,
+        };
+        insert_into(queue_state)
+            .values(&details)
+            .on_conflict(schema::queue_state::id)
+            .do_update()
+            .set(&details)
+            .execute(conn)?;
+        Ok(())
+    }
+
+    /// Creates or replaces a saved queue. Returns its id
+    #[tracing::instrument(level = "debug", skip(self, saved))]
+    pub fn upsert_saved_queue(&self, mut saved: SavedQueue) -> Result<String> {
+        if saved.id.is_empty() {
+            saved.id = Uuid::new_v4().to_string();
+        }
+        if saved.name.trim().is_empty() {
+            saved.name = "Saved queue".to_string();
+        }
+        saved.updated_at = SystemTime::now().duration_since(UNIX_EPOCH)?.as_millis() as i64;
+
+        let row = QueryableSavedQueue {
+            id: saved.id.clone(),
+            name: saved.name,
+            songs: serde_json::to_string(&saved.songs)?,
+            current_index: saved.current_index as i32,
+            position: saved.position,
+            repeat_mode: saved.repeat.into(),
+            updated_at: saved.updated_at,
+        };
+        insert_into(saved_queues)
+            .values(&row)
+            .on_conflict(schema::saved_queues::id)
+            .do_update()
+            .set(&row)
+            .execute(&mut self.pool.get().unwrap())?;
+        Ok(saved.id)
+    }
+
+    fn parse_saved_queue(row: QueryableSavedQueue) -> Result<SavedQueue> {
+        Ok(SavedQueue {
+            id: row.id,
+            name: row.name,
+            songs: serde_json::from_str(&row.songs)?,
+            current_index: row.current_index.max(0) as usize,
+            position: row.position,
+            repeat: row.repeat_mode.into(),
+            updated_at: row.updated_at,
+        })
+    }
+
+    /// Saved queues, most recently updated first
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub fn get_saved_queues(&self) -> Result<Vec<SavedQueue>> {
+        let rows = QueryDsl::order(saved_queues, schema::saved_queues::updated_at.desc())
+            .load::<QueryableSavedQueue>(&mut self.pool.get().unwrap())?;
+        Ok(rows
+            .into_iter()
+            .filter_map(|row| {
+                let id = row.id.clone();
+                match Self::parse_saved_queue(row) {
+                    Ok(saved) => Some(saved),
+                    Err(e) => {
+                        warn!("Skipping unreadable saved queue {}: {:?}", id, e);
+                        None
+                    }
+                }
+            })
+            .collect())
+    }
+
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub fn get_saved_queue(&self, id: String) -> Result<Option<SavedQueue>> {
+        let row = QueryDsl::filter(saved_queues, schema::saved_queues::id.eq(id))
+            .first::<QueryableSavedQueue>(&mut self.pool.get().unwrap())
+            .optional()?;
+        row.map(Self::parse_saved_queue).transpose()
+    }
+
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub fn remove_saved_queue(&self, id: String) -> Result<()> {
+        delete(QueryDsl::filter(
+            saved_queues,
+            schema::saved_queues::id.eq(id),
+        ))
+        .execute(&mut self.pool.get().unwrap())?;
+        Ok(())
+    }
+
+    /// Appends songs to a saved queue, creating it with the given name if it doesn't exist yet
+    #[tracing::instrument(level = "debug", skip(self, songs))]
+    pub fn add_to_saved_queue(&self, id: String, name: String, songs: Vec<Song>) -> Result<()> {
+        let mut saved = self.get_saved_queue(id.clone())?.unwrap_or(SavedQueue {
+            id,
+            name,
+            ..Default::default()
+        });
+        saved.songs.extend(songs);
+        self.upsert_saved_queue(saved)?;
+        Ok(())
+    }
+
+    /// Creates a playlist with the songs of a saved queue. Returns the id of the playlist
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub fn saved_queue_to_playlist(&self, id: String) -> Result<String> {
+        let Some(saved) = self.get_saved_queue(id.clone())? else {
+            return Err(format!("Saved queue {} not found", id).into());
+        };
+        let playlist_id = self.create_playlist(QueryablePlaylist {
+            playlist_name: saved.name,
+            ..Default::default()
+        })?;
+        self.add_to_playlist(playlist_id.clone(), saved.songs)?;
+        Ok(playlist_id)
+    }
+
+    #[tracing::instrument(level = "debug", skip(self, track))]
+    pub fn add_pending_scrobble(
+        &self,
+        service: ScrobbleService,
+        track: &ScrobbleTrack,
+    ) -> Result<()> {
+        let mut conn = self.pool.get().unwrap();
+        insert_into(pending_scrobbles)
+            .values(NewPendingScrobble {
+                service: service.key().to_string(),
+                track: serde_json::to_string(track)?,
+            })
+            .execute(&mut conn)?;
+        Ok(())
+    }
+
+    /// Listens waiting to be submitted to the service, oldest first.
+    /// Rows which can't be read anymore are deleted so that they don't block the queue.
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub fn get_pending_scrobbles(
+        &self,
+        service: ScrobbleService,
+        limit: usize,
+    ) -> Result<Vec<(i32, ScrobbleTrack)>> {
+        let mut conn = self.pool.get().unwrap();
+        loop {
+            let rows: Vec<PendingScrobble> = QueryDsl::filter(
+                pending_scrobbles,
+                schema::pending_scrobbles::service.eq(service.key()),
+            )
+            .order(schema::pending_scrobbles::id.asc())
+            .limit(limit as i64)
+            .load(&mut conn)?;
+
+            let mut unreadable = vec![];
+            let tracks = rows
+                .into_iter()
+                .filter_map(|row| match serde_json::from_str(&row.track) {
+                    Ok(track) => Some((row.id, track)),
+                    Err(e) => {
+                        warn!("Removing unreadable scrobble {}: {:?}", row.id, e);
+                        unreadable.push(row.id);
+                        None
+                    }
+                })
+                .collect::<Vec<_>>();
+
+            if unreadable.is_empty() {
+                return Ok(tracks);
+            }
+            delete(QueryDsl::filter(
+                pending_scrobbles,
+                schema::pending_scrobbles::id.eq_any(unreadable),
+            ))
+            .execute(&mut conn)?;
+
+            // A batch of only unreadable rows would look like an empty queue
+            if !tracks.is_empty() {
+                return Ok(tracks);
+            }
+        }
+    }
+
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub fn remove_pending_scrobbles(&self, ids: &[i32]) -> Result<()> {
+        let mut conn = self.pool.get().unwrap();
+        delete(QueryDsl::filter(
+            pending_scrobbles,
+            schema::pending_scrobbles::id.eq_any(ids),
+        ))
+        .execute(&mut conn)?;
+        Ok(())
+    }
+
+    /// Counts a failed submission and drops listens which failed too many times
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub fn increment_scrobble_attempts(&self, ids: &[i32], max_attempts: i32) -> Result<()> {
+        let mut conn = self.pool.get().unwrap();
+        conn.transaction::<_, MoosyncError, _>(|conn| {
+            update(QueryDsl::filter(
+                pending_scrobbles,
+                schema::pending_scrobbles::id.eq_any(ids),
+            ))
+            .set(schema::pending_scrobbles::attempts.eq(schema::pending_scrobbles::attempts + 1))
+            .execute(conn)?;
+
+            let dropped = delete(QueryDsl::filter(
+                pending_scrobbles,
+                schema::pending_scrobbles::attempts.ge(max_attempts),
+            ))
+            .execute(conn)?;
+            if dropped > 0 {
+                warn!(
+                    "Dropped {} scrobbles after {} attempts",
+                    dropped, max_attempts
+                );
+            }
+            Ok(())
+        })
+    }
+
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub fn set_resume_point(&self, id: String, position: f64) -> Result<()> {
+        let mut conn = self.pool.get().unwrap();
+        let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_millis() as i64;
+        let values = (
+            schema::resume_points::song_id.eq(id),
+            schema::resume_points::position.eq(position),
+            schema::resume_points::updated_at.eq(now),
+        );
+        insert_into(resume_points)
+            .values(values)
+            .on_conflict(schema::resume_points::song_id)
+            .do_update()
+            .set((
+                schema::resume_points::position.eq(position),
+                schema::resume_points::updated_at.eq(now),
+            ))
+            .execute(&mut conn)?;
+        Ok(())
+    }
+
+    /// Position in seconds at which the song was left off, if any
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub fn get_resume_point(&self, id: String) -> Result<Option<f64>> {
+        let mut conn = self.pool.get().unwrap();
+        let position = QueryDsl::select(resume_points, schema::resume_points::position)
+            .filter(schema::resume_points::song_id.eq(id))
+            .first::<f64>(&mut conn)
+            .optional()?;
+        Ok(position)
+    }
+
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub fn remove_resume_point(&self, id: String) -> Result<()> {
+        let mut conn = self.pool.get().unwrap();
+        delete(QueryDsl::filter(
+            resume_points,
+            schema::resume_points::song_id.eq(id),
+        ))
+        .execute(&mut conn)?;
+        Ok(())
+    }
+
+    /// Library songs similar to the seeds, best matches first. Shared artists
+    /// weigh the most, then shared genres and songs released around the same years.
+    #[tracing::instrument(level = "debug", skip(self, seeds, exclude))]
+    pub fn get_similar_songs(
+        &self,
+        seeds: &[Song],
+        exclude: &HashSet<String>,
+        limit: usize,
+    ) -> Result<Vec<Song>> {
+        let mut conn = self.pool.get().unwrap();
+
+        let artist_ids = seeds
+            .iter()
+            .flat_map(|s| s.artists.iter().flatten())
+            .filter_map(|a| a.artist_id.clone())
+            .collect::<HashSet<_>>();
+        let genre_ids = seeds
+            .iter()
+            .flat_map(|s| s.genre.iter().flatten())
+            .filter_map(|g| g.genre_id.clone())
+            .collect::<HashSet<_>>();
+        let seed_years = seeds
+            .iter()
+            .filter_map(|s| parse_year(s.song.year.as_deref()))
+            .collect::<HashSet<_>>();
+
+        let mut scores: HashMap<String, u32> = HashMap::new();
+        let artist_data: Vec<ArtistBridge> = QueryDsl::filter(
+            artist_bridge,
+            schema::artist_bridge::artist.eq_any(artist_ids),
+        )
+        .load(&mut conn)?;
+        for song in artist_data.into_iter().filter_map(|v| v.song) {
+            *scores.entry(song).or_default() += 3;
+        }
+
+        let genre_data: Vec<GenreBridge> =
+            QueryDsl::filter(genre_bridge, schema::genre_bridge::genre.eq_any(genre_ids))
+                .load(&mut conn)?;
+        for song in genre_data.into_iter().filter_map(|v| v.song) {
+            *scores.entry(song).or_default() += 2;
+        }
+
+        let mut candidates: Vec<QueryableSong> = QueryDsl::filter(
+            allsongs,
+            _id.eq_any(
+                scores
+                    .keys()
+                    .filter(|id| !exclude.contains(*id))
+                    .cloned()
+                    .collect::<Vec<_>>(),
+            ),
+        )
+        .load(&mut conn)?;
+
+        let era = seed_years
+            .iter()
+            .flat_map(|y| (y - SIMILAR_ERA_YEARS)..=(y + SIMILAR_ERA_YEARS))
+            .collect::<HashSet<_>>();
+        let mut era_songs: Vec<QueryableSong> = vec![];
+        if !era.is_empty() {
+            let mut predicate = schema::allsongs::table.into_boxed();
+            for year in era {
+                predicate = predicate.or_filter(schema::allsongs::year.like(format!("{}%", year)));
+            }
+            era_songs = predicate.limit((limit * 4) as i64).load(&mut conn)?;
+        }
+        for song in era_songs {
+            if let Some(id) = &song._id {
+                if !scores.contains_key(id) && !exclude.contains(id) {
+                    scores.insert(id.clone(), 0);
+                    candidates.push(song);
+                }
+            }
+        }
+
+        let score = |song: &QueryableSong| {
+            let mut score = song
+                ._id
+                .as_ref()
+                .and_then(|id| scores.get(id))
+                .copied()
+                .unwrap_or_default();
+            if let Some(year) = parse_year(song.year.as_deref()) {
+                if seed_years
+                    .iter()
+                    .any(|y| (y - year).abs() <= SIMILAR_ERA_YEARS)
+                {
+                    score += 1;
+                }
+            }
+            score
+        };
+        candidates.sort_by_cached_key(|s| std::cmp::Reverse(score(s)));
+        candidates.truncate(limit);
+
+        trace!("Found {} similar songs", candidates.len());
+        candidates
+            .into_iter()
+            .map(|s| self.get_song_from_queryable(&mut conn, s))
+            .collect()
+    }
+
     #[tracing::instrument(level = "debug", skip(self))]
     pub fn export_playlist(&self, playlist_id: String) -> Result<String> {
         let mut conn = self.pool.get().unwrap();
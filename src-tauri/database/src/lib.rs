@@ -17,6 +17,7 @@
 pub mod cache;
 pub mod database;
 pub mod migrations;
+pub mod radio;
 
 #[cfg(test)]
 mod test;
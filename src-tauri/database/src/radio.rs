@@ -0,0 +1,145 @@
+// Moosync
+// Copyright (C) 2024, 2025  Moosync <support@moosync.app>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::collections::BTreeMap;
+
+use types::{errors::Result, radio::QueryableStation};
+
+fn is_stream_url(line: &str) -> bool {
+    let line = line.to_lowercase();
+    line.starts_with("http://") || line.starts_with("https://")
+}
+
+// Extracts key="value" pairs from an extended M3U #EXTINF line
+fn m3u_attribute(info: &str, key: &str) -> Option<String> {
+    let needle = format!("{}=\"", key);
+    let start = info.find(&needle)? + needle.len();
+    let end = info[start..].find('"')? + start;
+    let value = info[start..end].trim();
+    if value.is_empty() {
+        return None;
+    }
+    Some(value.to_string())
+}
+
+// Title follows the last comma which isn't inside a quoted attribute
+fn m3u_title(info: &str) -> Option<String> {
+    let mut in_quotes = false;
+    let mut split = None;
+    for (i, c) in info.char_indices() {
+        match c {
+            '"' => in_quotes = !in_quotes,
+            ',' if !in_quotes => split = Some(i),
+            _ => {}
+        }
+    }
+    let title = info[split? + 1..].trim();
+    if title.is_empty() {
+        return None;
+    }
+    Some(title.to_string())
+}
+
+fn parse_pls(content: &str) -> Vec<QueryableStation> {
+    let mut entries: BTreeMap<u32, QueryableStation> = BTreeMap::new();
+    for line in content.lines() {
+        let Some((key, value)) = line.trim().split_once('=') else {
+            continue;
+        };
+        let key = key.trim().to_lowercase();
+        let value = value.trim();
+
+        let (field, index) = if let Some(index) = key.strip_prefix("file") {
+            ("file", index)
+        } else if let Some(index) = key.strip_prefix("title") {
+            ("title", index)
+        } else {
+            continue;
+        };
+
+        let Ok(index) = index.parse::<u32>() else {
+            continue;
+        };
+        let entry = entries.entry(index).or_default();
+        match field {
+            "file" => entry.station_url = value.to_string(),
+            _ => entry.station_name = value.to_string(),
+        }
+    }
+
+    entries
+        .into_values()
+        .filter(|s| is_stream_url(&s.station_url))
+        .collect()
+}
+
+fn parse_m3u(content: &str) -> Vec<QueryableStation> {
+    let mut stations = vec![];
+    let mut pending: Option<QueryableStation> = None;
+    for line in content.lines() {
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        if let Some(info) = line.strip_prefix("#EXTINF:") {
+            pending = Some(QueryableStation {
+                station_name: m3u_title(info).unwrap_or_default(),
+                cover: m3u_attribute(info, "tvg-logo").or(m3u_attribute(info, "logo")),
+                genre: m3u_attribute(info, "group-title"),
+                ..Default::default()
+            });
+            continue;
+        }
+
+        if line.starts_with('#') || !is_stream_url(line) {
+            continue;
+        }
+
+        let mut station = pending.take().unwrap_or_default();
+        station.station_url = line.to_string();
+        stations.push(station);
+    }
+    stations
+}
+
+/// Parses PLS or (extended) M3U station lists. Entries without a title are named after their url.
+#[tracing::instrument(level = "debug", skip(content))]
+pub fn parse_station_list(content: &str) -> Result<Vec<QueryableStation>> {
+    let content = content.trim_start_matches('\u{feff}');
+    let is_pls = content
+        .lines()
+        .find(|l| !l.trim().is_empty())
+        .is_some_and(|l| l.trim().eq_ignore_ascii_case("[playlist]"));
+
+    let mut stations = if is_pls {
+        parse_pls(content)
+    } else {
+        parse_m3u(content)
+    };
+
+    if stations.is_empty() {
+        return Err("No stations found in the station list".into());
+    }
+
+    for station in stations.iter_mut() {
+        if station.station_name.is_empty() {
+            station.station_name = station.station_url.clone();
+        }
+    }
+
+    Ok(stations)
+}
@@ -16,12 +16,16 @@
 
 use std::{env::temp_dir, fs, path::PathBuf};
 
-use crate::database::Database;
+use crate::{database::Database, radio::parse_station_list};
 use types::{
     entities::{
         GetEntityOptions, QueryableAlbum, QueryableArtist, QueryableGenre, QueryablePlaylist,
     },
+    queue::{Queue, QueueState, SavedQueue, ShuffleState},
+    radio::QueryableStation,
+    scrobble::{ScrobbleService, ScrobbleTrack},
     songs::{GetSongOptions, QueryableSong, SearchableSong, Song, SongType},
+    ui::player_details::RepeatModes,
 };
 use uuid::Uuid;
 
@@ -663,3 +667,392 @@ fn test_analytics() {
 
     cleanup(&db_path);
 }
+
+// Test station management
+#[test]
+fn test_stations() {
+    let db_path = get_test_db_path();
+    let db = Database::new(db_path.clone());
+
+    let added = db
+        .add_stations(vec![
+            QueryableStation {
+                station_name: "Jazz FM".to_string(),
+                station_url: "http://radio.example.com/jazz".to_string(),
+                ..Default::default()
+            },
+            QueryableStation {
+                station_name: "Duplicate".to_string(),
+                station_url: "http://radio.example.com/jazz".to_string(),
+                ..Default::default()
+            },
+        ])
+        .unwrap();
+
+    // Duplicate urls are skipped
+    assert_eq!(added.len(), 1);
+    assert!(added[0].station_id.is_some());
+    assert!(added[0].date_added.is_some());
+
+    let mut station = added[0].clone();
+    station.genre = Some("Jazz".to_string());
+    db.update_station(station.clone()).unwrap();
+
+    let stations = db.get_stations().unwrap();
+    assert_eq!(stations, vec![station.clone()]);
+
+    db.remove_station(station.station_id.clone().unwrap())
+        .unwrap();
+    assert!(db.get_stations().unwrap().is_empty());
+
+    cleanup(&db_path);
+}
+
+// Test parsing PLS station lists
+#[test]
+fn test_parse_pls_station_list() {
+    let content = "[playlist]
+File1=http://radio.example.com/stream1
+Title1=First Station
+Length1=-1
+File2=https://radio.example.com/stream2
+NumberOfEntries=2
+Version=2
+";
+
+    let stations = parse_station_list(content).unwrap();
+    assert_eq!(stations.len(), 2);
+    assert_eq!(stations[0].station_name, "First Station");
+    assert_eq!(stations[0].station_url, "http://radio.example.com/stream1");
+    // Untitled entries are named after their url
+    assert_eq!(
+        stations[1].station_name,
+        "https://radio.example.com/stream2"
+    );
+}
+
+// Test parsing extended M3U station lists
+#[test]
+fn test_parse_m3u_station_list() {
+    let content = "#EXTM3U
+#EXTINF:-1 tvg-logo=\"http://img.example.com/a,b.png\" group-title=\"Rock\",Rock Radio
+http://radio.example.com/rock
+# Comment
+/local/file.mp3
+http://radio.example.com/plain
+";
+
+    let stations = parse_station_list(content).unwrap();
+    assert_eq!(stations.len(), 2);
+    assert_eq!(stations[0].station_name, "Rock Radio");
+    assert_eq!(stations[0].genre.as_deref(), Some("Rock"));
+    assert_eq!(
+        stations[0].cover.as_deref(),
+        Some("http://img.example.com/a,b.png")
+    );
+    assert_eq!(stations[1].station_url, "http://radio.example.com/plain");
+
+    assert!(parse_station_list("not a station list").is_err());
+}
+
+// Test importing a station list into the database
+#[test]
+fn test_import_stations() {
+    let db_path = get_test_db_path();
+    let db = Database::new(db_path.clone());
+
+    let content = "#EXTM3U
+#EXTINF:-1,Station A
+http://radio.example.com/a
+#EXTINF:-1,Station B
+http://radio.example.com/b
+";
+
+    assert_eq!(db.import_stations(content.to_string()).unwrap().len(), 2);
+    // Importing the same list again doesn't create duplicates
+    assert!(db.import_stations(content.to_string()).unwrap().is_empty());
+    assert_eq!(db.get_stations().unwrap().len(), 2);
+
+    cleanup(&db_path);
+}
+
+// Test persisting the play queue
+#[test]
+fn test_queue_state() {
+    let db_path = get_test_db_path();
+    let db = Database::new(db_path.clone());
+
+    // Nothing saved yet
+    assert_eq!(db.get_queue_state().unwrap(), QueueState::default());
+
+    let mut song_a = create_test_song("Song A", "/test/a.mp3");
+    song_a.song._id = Some("a".to_string());
+    let mut song_b = create_test_song("Song B", "/test/b.mp3");
+    song_b.song._id = Some("b".to_string());
+
+    let mut state = QueueState {
+        queue: Queue {
+            song_queue: vec!["a".to_string(), "b".to_string(), "a".to_string()],
+            current_index: 1,
+            data: [("a".to_string(), song_a), ("b".to_string(), song_b)]
+                .into_iter()
+                .collect(),
+        },
+        repeat: RepeatModes::Loop,
+        shuffle: ShuffleState {
+            enabled: true,
+            avoid_same_artist: true,
+            original_order: vec!["b".to_string(), "a".to_string(), "a".to_string()],
+        },
+        history: vec!["a".to_string(), "b".to_string()],
+        autoplay: true,
+    };
+    db.save_queue_state(&state, 0).unwrap();
+    assert_eq!(db.get_queue_state().unwrap(), state);
+
+    state.queue.current_index = 2;
+    state.repeat = RepeatModes::Once;
+    state.history.push("a".to_string());
+    db.save_queue_details(&state).unwrap();
+    assert_eq!(db.get_queue_state().unwrap(), state);
+
+    // Only songs after the unchanged ones are rewritten
+    let mut song_c = create_test_song("Song C", "/test/c.mp3");
+    song_c.song._id = Some("c".to_string());
+    state.queue.song_queue.push("c".to_string());
+    state.queue.data.insert("c".to_string(), song_c);
+    db.save_queue_state(&state, 3).unwrap();
+    assert_eq!(db.get_queue_state().unwrap(), state);
+
+    state.queue.song_queue.remove(1);
+    db.save_queue_state(&state, 1).unwrap();
+    state.queue.data.remove("b");
+    assert_eq!(db.get_queue_state().unwrap(), state);
+
+    state.queue.song_queue.truncate(1);
+    state.queue.data.remove("c");
+    state.queue.current_index = 0;
+    state.shuffle = ShuffleState::default();
+    db.save_queue_state(&state, 1).unwrap();
+    assert_eq!(db.get_queue_state().unwrap(), state);
+
+    cleanup(&db_path);
+}
+
+// Test finding songs similar to a seed for autoplay
+#[test]
+fn test_get_similar_songs() {
+    let db_path = get_test_db_path();
+    let db = Database::new(db_path.clone());
+
+    let song = |title: &str, artist: &str, genre: &str, year: &str| {
+        let mut song = create_test_song(title, &format!("/test/{}.mp3", title));
+        song.song.year = Some(year.to_string());
+        song.artists = Some(vec![QueryableArtist {
+            artist_name: Some(artist.to_string()),
+            ..Default::default()
+        }]);
+        song.genre = Some(vec![QueryableGenre {
+            genre_name: Some(genre.to_string()),
+            ..Default::default()
+        }]);
+        song
+    };
+
+    let songs = db
+        .insert_songs(vec![
+            song("seed", "Artist A", "Rock", "2001"),
+            song("same_artist", "Artist A", "Pop", "1990"),
+            song("same_genre", "Artist B", "Rock", "1980"),
+            song("same_era", "Artist C", "Jazz", "2002-01-01"),
+            song("unrelated", "Artist D", "Metal", "1970"),
+            song("played", "Artist A", "Rock", "2001"),
+        ])
+        .unwrap();
+
+    let exclude = [&songs[0], &songs[5]]
+        .iter()
+        .filter_map(|s| s.song._id.clone())
+        .collect();
+    let similar = db.get_similar_songs(&songs[..1], &exclude, 10).unwrap();
+    let titles = similar
+        .iter()
+        .filter_map(|s| s.song.title.as_deref())
+        .collect::<Vec<_>>();
+    assert_eq!(titles, vec!["same_artist", "same_genre", "same_era"]);
+
+    let similar = db.get_similar_songs(&songs[..1], &exclude, 1).unwrap();
+    assert_eq!(similar.len(), 1);
+
+    cleanup(&db_path);
+}
+
+#[test]
+fn test_pending_scrobbles() {
+    let db_path = get_test_db_path();
+    let db = Database::new(db_path.clone());
+
+    let track = |title: &str, timestamp: i64| ScrobbleTrack {
+        title: title.to_string(),
+        artists: vec!["Artist".to_string()],
+        album: Some("Album".to_string()),
+        duration: Some(200.0),
+        timestamp,
+        ..Default::default()
+    };
+
+    db.add_pending_scrobble(ScrobbleService::LastFm, &track("first", 1))
+        .unwrap();
+    db.add_pending_scrobble(ScrobbleService::LastFm, &track("second", 2))
+        .unwrap();
+    db.add_pending_scrobble(ScrobbleService::ListenBrainz, &track("other", 3))
+        .unwrap();
+
+    // Only listens of the requested service, oldest first
+    let pending = db
+        .get_pending_scrobbles(ScrobbleService::LastFm, 10)
+        .unwrap();
+    assert_eq!(
+        pending.iter().map(|(_, t)| t.clone()).collect::<Vec<_>>(),
+        vec![track("first", 1), track("second", 2)]
+    );
+    let pending_limited = db
+        .get_pending_scrobbles(ScrobbleService::LastFm, 1)
+        .unwrap();
+    assert_eq!(pending_limited.len(), 1);
+
+    // Dropped once the max attempts are reached
+    let first_id = pending[0].0;
+    db.increment_scrobble_attempts(&[first_id], 2).unwrap();
+    assert_eq!(
+        db.get_pending_scrobbles(ScrobbleService::LastFm, 10)
+            .unwrap()
+            .len(),
+        2
+    );
+    db.increment_scrobble_attempts(&[first_id], 2).unwrap();
+    let pending = db
+        .get_pending_scrobbles(ScrobbleService::LastFm, 10)
+        .unwrap();
+    assert_eq!(pending.len(), 1);
+    assert_eq!(pending[0].1.title, "second");
+
+    db.remove_pending_scrobbles(&[pending[0].0]).unwrap();
+    assert!(db
+        .get_pending_scrobbles(ScrobbleService::LastFm, 10)
+        .unwrap()
+        .is_empty());
+    assert_eq!(
+        db.get_pending_scrobbles(ScrobbleService::ListenBrainz, 10)
+            .unwrap()
+            .len(),
+        1
+    );
+
+    cleanup(&db_path);
+}
+
+#[test]
+fn test_resume_points() {
+    let db_path = get_test_db_path();
+    let db = Database::new(db_path.clone());
+
+    assert_eq!(db.get_resume_point("missing".into()).unwrap(), None);
+
+    // Non library songs can have resume points too
+    db.set_resume_point("youtube:abc".into(), 120.5).unwrap();
+    assert_eq!(
+        db.get_resume_point("youtube:abc".into()).unwrap(),
+        Some(120.5)
+    );
+
+    // Updated in place
+    db.set_resume_point("youtube:abc".into(), 300.0).unwrap();
+    assert_eq!(
+        db.get_resume_point("youtube:abc".into()).unwrap(),
+        Some(300.0)
+    );
+
+    db.remove_resume_point("youtube:abc".into()).unwrap();
+    assert_eq!(db.get_resume_point("youtube:abc".into()).unwrap(), None);
+
+    // Removed along with the song
+    let inserted = db
+        .insert_songs(vec![create_test_song("Podcast", "/path/podcast.mp3")])
+        .unwrap();
+    let id = inserted[0].song._id.clone().unwrap();
+    db.set_resume_point(id.clone(), 1800.0).unwrap();
+    db.remove_songs(vec![id.clone()]).unwrap();
+    assert_eq!(db.get_resume_point(id).unwrap(), None);
+
+    cleanup(&db_path);
+}
+
+#[test]
+fn test_saved_queues() {
+    let db_path = get_test_db_path();
+    let db = Database::new(db_path.clone());
+
+    let mut song_a = create_test_song("Song A", "/test/a.mp3");
+    song_a.song._id = Some("a".to_string());
+    let mut song_b = create_test_song("Song B", "/test/b.mp3");
+    song_b.song._id = Some("b".to_string());
+
+    let id = db
+        .upsert_saved_queue(SavedQueue {
+            name: "Evening".to_string(),
+            songs: vec![song_a.clone(), song_b.clone()],
+            current_index: 1,
+            position: 42.5,
+            repeat: RepeatModes::Loop,
+            ..Default::default()
+        })
+        .unwrap();
+    assert!(!id.is_empty());
+
+    let saved = db.get_saved_queue(id.clone()).unwrap().unwrap();
+    assert_eq!(saved.name, "Evening");
+    assert_eq!(saved.songs, vec![song_a.clone(), song_b.clone()]);
+    assert_eq!(saved.current_index, 1);
+    assert_eq!(saved.position, 42.5);
+    assert_eq!(saved.repeat, RepeatModes::Loop);
+
+    // Saving with the same id replaces the snapshot
+    db.upsert_saved_queue(SavedQueue {
+        id: id.clone(),
+        name: "Evening".to_string(),
+        songs: vec![song_b.clone()],
+        ..Default::default()
+    })
+    .unwrap();
+    let saved = db.get_saved_queues().unwrap();
+    assert_eq!(saved.len(), 1);
+    assert_eq!(saved[0].songs, vec![song_b.clone()]);
+
+    // Appending creates the queue when missing
+    db.add_to_saved_queue("later".into(), "Play later".into(), vec![song_a.clone()])
+        .unwrap();
+    db.add_to_saved_queue("later".into(), "Play later".into(), vec![song_b.clone()])
+        .unwrap();
+    let later = db.get_saved_queue("later".into()).unwrap().unwrap();
+    assert_eq!(later.name, "Play later");
+    assert_eq!(later.songs, vec![song_a, song_b]);
+
+    let playlist_id = db.saved_queue_to_playlist("later".into()).unwrap();
+    let songs = db
+        .get_songs_by_options(GetSongOptions {
+            playlist: Some(QueryablePlaylist {
+                playlist_id: Some(playlist_id),
+                ..Default::default()
+            }),
+            ..Default::default()
+        })
+        .unwrap();
+    assert_eq!(songs.len(), 2);
+
+    db.remove_saved_queue(id.clone()).unwrap();
+    assert!(db.get_saved_queue(id).unwrap().is_none());
+    assert!(db.saved_queue_to_playlist("missing".into()).is_err());
+
+    cleanup(&db_path);
+}
@@ -0,0 +1,202 @@
+// Moosync
+// Copyright (C) 2024, 2025  Moosync <support@moosync.app>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Typed wrappers around the commands an extension can send to Moosync.
+//!
+//! Commands needing a capability must have it declared in the extension manifest
+//! and granted by the user, otherwise the host aborts the running call.
+
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::Value;
+use types::{
+    entities::{GetEntityOptions, QueryablePlaylist},
+    errors::{MoosyncError, Result},
+    extensions::MainCommand,
+    songs::{GetSongOptions, Song},
+    ui::{
+        extensions::{AddToPlaylistRequest, ExtensionEventKind, PreferenceData},
+        player_details::PlayerState,
+    },
+};
+
+use crate::host::{with_host, HashType};
+
+fn request<T: DeserializeOwned>(command: MainCommand) -> Result<T> {
+    let reply = with_host(|host| host.send_main_command(&command))?;
+    let Some(reply) = reply else {
+        return Err(format!("Host did not reply to {:?}", command).into());
+    };
+    serde_json::from_value(reply)
+        .map_err(|e| MoosyncError::String(format!("Failed to parse reply to {:?}: {}", command, e)))
+}
+
+fn preference(key: &str, value: Option<Value>) -> PreferenceData {
+    PreferenceData {
+        key: key.to_string(),
+        value,
+        default_value: None,
+    }
+}
+
+fn preference_value<T: DeserializeOwned>(data: PreferenceData) -> Result<Option<T>> {
+    match data.value {
+        None | Some(Value::Null) => Ok(None),
+        Some(value) => Ok(Some(serde_json::from_value(value)?)),
+    }
+}
+
+/// Requires the `libraryRead` capability
+pub fn get_songs(options: GetSongOptions) -> Result<Vec<Song>> {
+    request(MainCommand::GetSong(options))
+}
+
+/// Requires the `libraryRead` capability. The shape of the reply depends on the requested entity
+pub fn get_entity(options: GetEntityOptions) -> Result<Value> {
+    request(MainCommand::GetEntity(options))
+}
+
+pub fn get_current_song() -> Result<Option<Song>> {
+    request(MainCommand::GetCurrentSong())
+}
+
+pub fn get_player_state() -> Result<PlayerState> {
+    request(MainCommand::GetPlayerState())
+}
+
+pub fn get_volume() -> Result<f64> {
+    request(MainCommand::GetVolume())
+}
+
+/// Position of the current song in seconds
+pub fn get_time() -> Result<f64> {
+    request(MainCommand::GetTime())
+}
+
+pub fn get_queue() -> Result<Value> {
+    request(MainCommand::GetQueue())
+}
+
+/// Requires the `preferences` capability. Keys are scoped to the extension by the host
+pub fn get_preference<T: DeserializeOwned>(key: &str) -> Result<Option<T>> {
+    let data: PreferenceData = request(MainCommand::GetPreference(preference(key, None)))?;
+    preference_value(data)
+}
+
+/// Requires the `preferences` capability
+pub fn set_preference(key: &str, value: impl Serialize) -> Result<bool> {
+    let value = serde_json::to_value(value)?;
+    request(MainCommand::SetPreference(preference(key, Some(value))))
+}
+
+/// Requires the `secureStorage` capability
+pub fn get_secure<T: DeserializeOwned>(key: &str) -> Result<Option<T>> {
+    let data: PreferenceData = request(MainCommand::GetSecure(preference(key, None)))?;
+    preference_value(data)
+}
+
+/// Requires the `secureStorage` capability
+pub fn set_secure(key: &str, value: impl Serialize) -> Result<bool> {
+    let value = serde_json::to_value(value)?;
+    request(MainCommand::SetSecure(preference(key, Some(value))))
+}
+
+/// Requires the `libraryWrite` capability. Returns the songs as they were stored
+pub fn add_songs(songs: Vec<Song>) -> Result<Vec<Song>> {
+    request(MainCommand::AddSongs(songs))
+}
+
+/// Requires the `libraryWrite` capability
+pub fn remove_song(song: Song) -> Result<bool> {
+    request(MainCommand::RemoveSong(song))
+}
+
+/// Requires the `libraryWrite` capability
+pub fn update_song(song: Song) -> Result<Song> {
+    request(MainCommand::UpdateSong(song))
+}
+
+/// Requires the `libraryWrite` capability. Returns the id of the new playlist
+pub fn add_playlist(playlist: QueryablePlaylist) -> Result<String> {
+    request(MainCommand::AddPlaylist(playlist))
+}
+
+/// Requires the `libraryWrite` capability
+pub fn add_to_playlist(playlist_id: &str, songs: Vec<Song>) -> Result<bool> {
+    request(MainCommand::AddToPlaylist(AddToPlaylistRequest {
+        playlist_id: playlist_id.to_string(),
+        songs,
+    }))
+}
+
+/// Requires the `openUrl` capability
+pub fn register_oauth(path: &str) -> Result<bool> {
+    request(MainCommand::RegisterOAuth(path.to_string()))
+}
+
+/// Requires the `openUrl` capability
+pub fn open_external_url(url: &str) -> Result<bool> {
+    request(MainCommand::OpenExternalUrl(url.to_string()))
+}
+
+/// Asks Moosync to fetch the accounts of this, or the given, extension again
+pub fn update_accounts(package_name: Option<String>) -> Result<bool> {
+    request(MainCommand::UpdateAccounts(package_name))
+}
+
+pub fn extensions_updated() -> Result<bool> {
+    request(MainCommand::ExtensionsUpdated())
+}
+
+/// Starts delivering these events. Once subscribed, events outside the subscription
+/// are no longer sent, even if the manifest didn't declare any `subscriptions`
+pub fn subscribe(events: &[ExtensionEventKind]) -> Result<bool> {
+    request(MainCommand::Subscribe(events.to_vec()))
+}
+
+pub fn unsubscribe(events: &[ExtensionEventKind]) -> Result<bool> {
+    request(MainCommand::Unsubscribe(events.to_vec()))
+}
+
+/// Seconds since the unix epoch, extensions have no clock of their own
+pub fn system_time() -> Result<u64> {
+    with_host(|host| host.system_time())
+}
+
+pub fn hash(hash_type: HashType, data: &[u8]) -> Result<Vec<u8>> {
+    with_host(|host| host.hash(hash_type, data))
+}
+
+/// Requires the `sockets` capability. Connects to a unix socket, or a named pipe on Windows
+pub fn open_socket(path: &str) -> Result<i64> {
+    let sock_id = with_host(|host| host.open_clientfd(path))?;
+    if sock_id < 0 {
+        return Err(format!("Failed to open socket {}", path).into());
+    }
+    Ok(sock_id)
+}
+
+pub fn write_socket(sock_id: i64, buf: &[u8]) -> Result<usize> {
+    let written = with_host(|host| host.write_sock(sock_id, buf))?;
+    if written < 0 {
+        return Err(format!("Failed to write to socket {}", sock_id).into());
+    }
+    Ok(written as usize)
+}
+
+/// Reads at most `read_len` bytes, the host caps a single read at 1024
+pub fn read_socket(sock_id: i64, read_len: u64) -> Result<Vec<u8>> {
+    with_host(|host| host.read_sock(sock_id, read_len))
+}
@@ -0,0 +1,57 @@
+// Moosync
+// Copyright (C) 2024, 2025  Moosync <support@moosync.app>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::sync::Once;
+
+use extism_pdk::Memory;
+
+use crate::{dispatch, Extension};
+
+static PANIC_HOOK: Once = Once::new();
+
+/// The host doesn't keep WASI stdout and stderr, so panics are sent through
+/// the extism log which ends up in the extension logs
+fn install_panic_hook() {
+    PANIC_HOOK.call_once(|| {
+        std::panic::set_hook(Box::new(|info| extism_pdk::error!("{}", info)));
+    });
+}
+
+fn fail(message: String) -> i32 {
+    if let Ok(memory) = Memory::from_bytes(&message) {
+        unsafe { extism_pdk::extism::error_set(memory.offset()) };
+    }
+    1
+}
+
+/// Body of every function exported by [`crate::register_extension`]
+pub fn call(extension: &mut dyn Extension, fn_name: &str) -> i32 {
+    install_panic_hook();
+
+    let input: Vec<u8> = match extism_pdk::input() {
+        Ok(input) => input,
+        Err(e) => return fail(e.to_string()),
+    };
+
+    match dispatch(extension, fn_name, &input) {
+        Ok(output) if output.is_empty() => 0,
+        Ok(output) => match extism_pdk::output(output) {
+            Ok(_) => 0,
+            Err(e) => fail(e.to_string()),
+        },
+        Err(e) => fail(e.to_string()),
+    }
+}
@@ -0,0 +1,355 @@
+// Moosync
+// Copyright (C) 2024, 2025  Moosync <support@moosync.app>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::Value;
+use types::{
+    entities::{QueryableAlbum, QueryableArtist, QueryablePlaylist},
+    errors::{MoosyncError, Result},
+    songs::Song,
+    ui::{
+        extensions::{
+            AccountLoginArgs, ContextMenuReturnType, CustomRequestReturnType,
+            ExtensionAccountDetail, ExtensionProviderScope, PlaybackDetailsReturnType,
+            PlaylistAndSongsReturnType, PlaylistReturnType, PreferenceArgs,
+            RecommendationsReturnType, SearchReturnType, SongReturnType,
+            SongsWithPageTokenReturnType,
+        },
+        player_details::PlayerState,
+    },
+};
+
+macro_rules! wrapper_names {
+    ($($name:ident),*) => {
+        /// Functions the host calls on an extension, other than `entry` and `onStopped`
+        pub const WRAPPERS: &[&str] = &[$(stringify!($name)),*];
+    };
+}
+
+crate::__with_wrappers!(wrapper_names);
+
+fn not_implemented<T>(name: &str) -> Result<T> {
+    Err(format!("{} is not implemented by this extension", name).into())
+}
+
+/// Everything Moosync may ask of an extension.
+///
+/// Requests for data default to an empty answer, so the extension is simply skipped when
+/// Moosync combines the answers of all extensions. Only requests which Moosync sends to
+/// the extension owning a song or account default to an error.
+/// Events default to doing nothing. Player and library events are only delivered if the
+/// extension is subscribed to them, through `subscriptions` in its manifest or
+/// [`crate::api::subscribe`]. Seeks, volume and queue changes arrive batched, only the
+/// latest of a burst is delivered.
+///
+/// The host only waits a limited time for every call and disables extensions which
+/// keep failing, so long running work should be avoided.
+pub trait Extension {
+    /// Called once after the extension is loaded
+    fn on_started(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    /// Called before the extension is unloaded
+    fn on_stopped(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    /// Features of Moosync this extension provides, e.g. search or lyrics
+    fn get_provider_scopes(&mut self) -> Result<Vec<ExtensionProviderScope>> {
+        Ok(vec![])
+    }
+
+    fn get_accounts(&mut self) -> Result<Vec<ExtensionAccountDetail>> {
+        Ok(vec![])
+    }
+
+    /// Logs into, or out of, one of the accounts returned by [`Extension::get_accounts`].
+    /// Returns the url to open for the login, if any
+    fn perform_account_login(&mut self, args: AccountLoginArgs) -> Result<String> {
+        let _ = args;
+        not_implemented("perform_account_login")
+    }
+
+    fn get_playlists(&mut self) -> Result<PlaylistReturnType> {
+        Ok(PlaylistReturnType { playlists: vec![] })
+    }
+
+    fn get_playlist_content(
+        &mut self,
+        playlist_id: String,
+        next_page_token: Option<String>,
+    ) -> Result<SongsWithPageTokenReturnType> {
+        let _ = (playlist_id, next_page_token);
+        Ok(SongsWithPageTokenReturnType {
+            songs: vec![],
+            next_page_token: None,
+        })
+    }
+
+    /// Called with the code when a url registered through [`crate::api::register_oauth`] is opened
+    fn oauth_callback(&mut self, code: String) -> Result<()> {
+        let _ = code;
+        Ok(())
+    }
+
+    fn on_queue_changed(&mut self, queue: Value) -> Result<()> {
+        let _ = queue;
+        Ok(())
+    }
+
+    fn on_seeked(&mut self, time: f64) -> Result<()> {
+        let _ = time;
+        Ok(())
+    }
+
+    /// The new volume isn't sent, use [`crate::api::get_volume`]
+    fn on_volume_changed(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    fn on_player_state_changed(&mut self, state: PlayerState) -> Result<()> {
+        let _ = state;
+        Ok(())
+    }
+
+    /// The new song isn't sent, use [`crate::api::get_current_song`]
+    fn on_song_changed(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    /// Called when one of the preferences declared in the manifest changes
+    fn on_preferences_changed(&mut self, args: PreferenceArgs) -> Result<()> {
+        let _ = args;
+        Ok(())
+    }
+
+    /// Only sent to the extension which provided the song
+    fn get_playback_details(&mut self, song: Song) -> Result<PlaybackDetailsReturnType> {
+        let _ = song;
+        not_implemented("get_playback_details")
+    }
+
+    fn handle_custom_request(&mut self, url: String) -> Result<CustomRequestReturnType> {
+        let _ = url;
+        Ok(CustomRequestReturnType {
+            mime_type: None,
+            data: None,
+            redirect_url: None,
+        })
+    }
+
+    fn get_song_from_url(&mut self, url: String) -> Result<SongReturnType> {
+        let _ = url;
+        Ok(SongReturnType { song: None })
+    }
+
+    fn get_playlist_from_url(&mut self, url: String) -> Result<PlaylistAndSongsReturnType> {
+        let _ = url;
+        Ok(PlaylistAndSongsReturnType {
+            playlist: None,
+            songs: None,
+        })
+    }
+
+    fn search(&mut self, term: String) -> Result<SearchReturnType> {
+        let _ = term;
+        Ok(SearchReturnType {
+            songs: vec![],
+            playlists: vec![],
+            artists: vec![],
+            albums: vec![],
+        })
+    }
+
+    fn get_recommendations(&mut self) -> Result<RecommendationsReturnType> {
+        Ok(RecommendationsReturnType { songs: vec![] })
+    }
+
+    /// Empty lyrics let another extension answer
+    fn get_lyrics(&mut self, song: Song) -> Result<String> {
+        let _ = song;
+        Ok(String::new())
+    }
+
+    fn get_artist_songs(
+        &mut self,
+        artist: QueryableArtist,
+        next_page_token: Option<String>,
+    ) -> Result<SongsWithPageTokenReturnType> {
+        let _ = (artist, next_page_token);
+        Ok(SongsWithPageTokenReturnType {
+            songs: vec![],
+            next_page_token: None,
+        })
+    }
+
+    fn get_album_songs(
+        &mut self,
+        album: QueryableAlbum,
+        next_page_token: Option<String>,
+    ) -> Result<SongsWithPageTokenReturnType> {
+        let _ = (album, next_page_token);
+        Ok(SongsWithPageTokenReturnType {
+            songs: vec![],
+            next_page_token: None,
+        })
+    }
+
+    fn on_song_added(&mut self, songs: Vec<Song>) -> Result<()> {
+        let _ = songs;
+        Ok(())
+    }
+
+    fn on_song_removed(&mut self, songs: Vec<Song>) -> Result<()> {
+        let _ = songs;
+        Ok(())
+    }
+
+    fn on_playlist_added(&mut self, playlists: Vec<QueryablePlaylist>) -> Result<()> {
+        let _ = playlists;
+        Ok(())
+    }
+
+    fn on_playlist_removed(&mut self, playlists: Vec<QueryablePlaylist>) -> Result<()> {
+        let _ = playlists;
+        Ok(())
+    }
+
+    fn get_song_from_id(&mut self, id: String) -> Result<SongReturnType> {
+        let _ = id;
+        Ok(SongReturnType { song: None })
+    }
+
+    /// Only sent to the extension which provided the song. The song itself isn't sent
+    fn get_remote_url(&mut self) -> Result<String> {
+        not_implemented("get_remote_url")
+    }
+
+    fn scrobble(&mut self, song: Song) -> Result<()> {
+        let _ = song;
+        Ok(())
+    }
+
+    fn get_song_context_menu(&mut self, songs: Vec<Song>) -> Result<Vec<ContextMenuReturnType>> {
+        let _ = songs;
+        Ok(vec![])
+    }
+
+    fn get_playlist_context_menu(
+        &mut self,
+        playlist: QueryablePlaylist,
+    ) -> Result<Vec<ContextMenuReturnType>> {
+        let _ = playlist;
+        Ok(vec![])
+    }
+
+    /// Called with the action id of a context menu entry the user clicked
+    fn on_context_menu_action(&mut self, action_id: String) -> Result<()> {
+        let _ = action_id;
+        Ok(())
+    }
+}
+
+fn parse<T: DeserializeOwned>(fn_name: &str, input: &[u8]) -> Result<T> {
+    serde_json::from_slice(input)
+        .map_err(|e| MoosyncError::String(format!("Invalid input for {}: {}", fn_name, e)))
+}
+
+fn reply<T: Serialize>(value: Result<T>) -> Result<Value> {
+    Ok(serde_json::to_value(value?)?)
+}
+
+fn event(value: Result<()>) -> Result<Value> {
+    value.map(|_| Value::Null)
+}
+
+/// Decodes the input of an exported function, calls the extension and encodes its reply.
+/// Inputs are read the way `ExtensionCommand::to_plugin_call` writes them.
+/// Returns nothing for `entry` and `onStopped`, JSON for everything else.
+pub fn dispatch(extension: &mut dyn Extension, fn_name: &str, input: &[u8]) -> Result<Vec<u8>> {
+    let output = match fn_name {
+        "entry" => return extension.on_started().map(|_| vec![]),
+        "onStopped" => return extension.on_stopped().map(|_| vec![]),
+        "get_provider_scopes_wrapper" => reply(extension.get_provider_scopes()),
+        "get_accounts_wrapper" => reply(extension.get_accounts()),
+        "perform_account_login_wrapper" => {
+            reply(extension.perform_account_login(parse(fn_name, input)?))
+        }
+        "get_playlists_wrapper" => reply(extension.get_playlists()),
+        "get_playlist_content_wrapper" => {
+            let (playlist_id, next_page_token) = parse(fn_name, input)?;
+            reply(extension.get_playlist_content(playlist_id, next_page_token))
+        }
+        "oauth_callback_wrapper" => event(extension.oauth_callback(parse(fn_name, input)?)),
+        "on_queue_changed_wrapper" => event(extension.on_queue_changed(parse(fn_name, input)?)),
+        "on_seeked_wrapper" => event(extension.on_seeked(parse(fn_name, input)?)),
+        "on_volume_changed_wrapper" => event(extension.on_volume_changed()),
+        "on_player_state_changed_wrapper" => {
+            event(extension.on_player_state_changed(parse(fn_name, input)?))
+        }
+        "on_song_changed_wrapper" => event(extension.on_song_changed()),
+        "on_preferences_changed_wrapper" => {
+            event(extension.on_preferences_changed(parse(fn_name, input)?))
+        }
+        "get_playback_details_wrapper" => {
+            reply(extension.get_playback_details(parse(fn_name, input)?))
+        }
+        "handle_custom_request_wrapper" => {
+            reply(extension.handle_custom_request(parse(fn_name, input)?))
+        }
+        "get_song_from_url_wrapper" => reply(extension.get_song_from_url(parse(fn_name, input)?)),
+        "get_playlist_from_url_wrapper" => {
+            reply(extension.get_playlist_from_url(parse(fn_name, input)?))
+        }
+        "search_wrapper" => reply(extension.search(parse(fn_name, input)?)),
+        "get_recommendations_wrapper" => reply(extension.get_recommendations()),
+        "get_lyrics_wrapper" => {
+            // The song is sent wrapped in an array
+            let [song]: [Song; 1] = parse(fn_name, input)?;
+            reply(extension.get_lyrics(song))
+        }
+        "get_artist_songs_wrapper" => {
+            let (artist, next_page_token) = parse(fn_name, input)?;
+            reply(extension.get_artist_songs(artist, next_page_token))
+        }
+        "get_album_songs_wrapper" => {
+            let (album, next_page_token) = parse(fn_name, input)?;
+            reply(extension.get_album_songs(album, next_page_token))
+        }
+        "on_song_added_wrapper" => event(extension.on_song_added(parse(fn_name, input)?)),
+        "on_song_removed_wrapper" => event(extension.on_song_removed(parse(fn_name, input)?)),
+        "on_playlist_added_wrapper" => event(extension.on_playlist_added(parse(fn_name, input)?)),
+        "on_playlist_removed_wrapper" => {
+            event(extension.on_playlist_removed(parse(fn_name, input)?))
+        }
+        "get_song_from_id_wrapper" => reply(extension.get_song_from_id(parse(fn_name, input)?)),
+        "get_remote_url_wrapper" => reply(extension.get_remote_url()),
+        "scrobble_wrapper" => event(extension.scrobble(parse(fn_name, input)?)),
+        "get_song_context_menu_wrapper" => {
+            reply(extension.get_song_context_menu(parse(fn_name, input)?))
+        }
+        "get_playlist_context_menu_wrapper" => {
+            reply(extension.get_playlist_context_menu(parse(fn_name, input)?))
+        }
+        "on_context_menu_action_wrapper" => {
+            event(extension.on_context_menu_action(parse(fn_name, input)?))
+        }
+        _ => Err(format!("Unknown extension function {}", fn_name).into()),
+    }?;
+
+    Ok(serde_json::to_vec(&output)?)
+}
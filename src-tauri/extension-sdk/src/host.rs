@@ -0,0 +1,152 @@
+// Moosync
+// Copyright (C) 2024, 2025  Moosync <support@moosync.app>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::{cell::RefCell, rc::Rc};
+
+use serde_json::Value;
+use types::{errors::Result, extensions::MainCommand};
+
+/// Digests the host can compute through `hash`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HashType {
+    Sha1,
+    Sha256,
+    Sha512,
+}
+
+impl HashType {
+    /// Name the host expects, anything it doesn't know falls back to SHA1
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            HashType::Sha1 => "SHA1",
+            HashType::Sha256 => "SHA256",
+            HashType::Sha512 => "SHA512",
+        }
+    }
+}
+
+/// Functions Moosync exposes to extensions under the `extism:host/user` namespace.
+///
+/// Every argument and return value crosses the boundary as an extism memory handle:
+/// commands as JSON, strings as UTF-8, integers as little endian bytes.
+pub trait Host {
+    /// Sends an externally tagged [`MainCommand`], e.g. `{"GetVolume": []}`.
+    /// The reply is the untagged `MainCommandResponse` JSON, or nothing if the command has no reply.
+    /// Commands needing a capability the user didn't grant abort the running call.
+    fn send_main_command(&self, command: &MainCommand) -> Result<Option<Value>>;
+
+    /// Seconds since the unix epoch
+    fn system_time(&self) -> Result<u64>;
+
+    /// Connects to a unix socket or named pipe, returns its id or -1 on failure
+    fn open_clientfd(&self, path: &str) -> Result<i64>;
+
+    /// Writes the whole buffer, returns the number of bytes written or -1 on failure
+    fn write_sock(&self, sock_id: i64, buf: &[u8]) -> Result<i64>;
+
+    /// Reads at most `read_len` bytes, capped at 1024. Returns nothing on failure
+    fn read_sock(&self, sock_id: i64, read_len: u64) -> Result<Vec<u8>>;
+
+    fn hash(&self, hash_type: HashType, data: &[u8]) -> Result<Vec<u8>>;
+}
+
+thread_local! {
+    static CURRENT_HOST: RefCell<Option<Rc<dyn Host>>> = const { RefCell::new(None) };
+}
+
+/// Replaces the host used by [`crate::api`], `None` restores the default.
+/// Only useful in tests, extensions running inside Moosync talk to the real host.
+pub fn set_host(host: Option<Rc<dyn Host>>) {
+    CURRENT_HOST.with_borrow_mut(|current| *current = host);
+}
+
+pub(crate) fn with_host<T>(f: impl FnOnce(&dyn Host) -> Result<T>) -> Result<T> {
+    let host = CURRENT_HOST.with_borrow(|current| current.clone());
+    match host {
+        Some(host) => f(host.as_ref()),
+        None => default_host(f),
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+fn default_host<T>(f: impl FnOnce(&dyn Host) -> Result<T>) -> Result<T> {
+    f(&extism::ExtismHost)
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn default_host<T>(_: impl FnOnce(&dyn Host) -> Result<T>) -> Result<T> {
+    Err("No host is available outside of wasm, use testing::MockHost".into())
+}
+
+#[cfg(target_arch = "wasm32")]
+mod extism {
+    use extism_pdk::host_fn;
+    use serde_json::Value;
+    use types::{
+        errors::{MoosyncError, Result},
+        extensions::MainCommand,
+    };
+
+    use super::{HashType, Host};
+
+    #[host_fn]
+    extern "ExtismHost" {
+        fn send_main_command(command: Vec<u8>) -> Vec<u8>;
+        fn system_time() -> u64;
+        fn open_clientfd(path: String) -> i64;
+        fn write_sock(sock_id: i64, buf: Vec<u8>) -> i64;
+        fn read_sock(sock_id: i64, read_len: u64) -> Vec<u8>;
+        fn hash(hash_type: String, data: Vec<u8>) -> Vec<u8>;
+    }
+
+    fn host_error(e: extism_pdk::Error) -> MoosyncError {
+        MoosyncError::String(format!("Host call failed: {}", e))
+    }
+
+    pub(super) struct ExtismHost;
+
+    impl Host for ExtismHost {
+        fn send_main_command(&self, command: &MainCommand) -> Result<Option<Value>> {
+            let command = serde_json::to_vec(command)?;
+            let reply = unsafe { send_main_command(command) }.map_err(host_error)?;
+            // Commands without a reply come back as an empty buffer
+            if reply.is_empty() {
+                return Ok(None);
+            }
+            Ok(Some(serde_json::from_slice(&reply)?))
+        }
+
+        fn system_time(&self) -> Result<u64> {
+            unsafe { system_time() }.map_err(host_error)
+        }
+
+        fn open_clientfd(&self, path: &str) -> Result<i64> {
+            unsafe { open_clientfd(path.to_string()) }.map_err(host_error)
+        }
+
+        fn write_sock(&self, sock_id: i64, buf: &[u8]) -> Result<i64> {
+            unsafe { write_sock(sock_id, buf.to_vec()) }.map_err(host_error)
+        }
+
+        fn read_sock(&self, sock_id: i64, read_len: u64) -> Result<Vec<u8>> {
+            unsafe { read_sock(sock_id, read_len) }.map_err(host_error)
+        }
+
+        fn hash(&self, hash_type: HashType, data: &[u8]) -> Result<Vec<u8>> {
+            unsafe { hash(hash_type.as_str().to_string(), data.to_vec()) }.map_err(host_error)
+        }
+    }
+}
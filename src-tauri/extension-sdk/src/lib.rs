@@ -0,0 +1,139 @@
+// Moosync
+// Copyright (C) 2024, 2025  Moosync <support@moosync.app>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Write Moosync extensions in Rust.
+//!
+//! Extensions are extism plugins built for `wasm32-unknown-unknown` or `wasm32-wasip1`
+//! as a `cdylib`. Implement [`Extension`] and export it with [`register_extension!`]:
+//!
+//! ```ignore
+//! use moosync_extension_sdk::{
+//!     register_extension,
+//!     types::{errors::Result, songs::Song},
+//!     Extension,
+//! };
+//!
+//! #[derive(Default)]
+//! struct Lyrics;
+//!
+//! impl Extension for Lyrics {
+//!     fn get_lyrics(&mut self, song: Song) -> Result<String> {
+//!         Ok(format!("Lyrics of {:?}", song.song.title))
+//!     }
+//! }
+//!
+//! register_extension!(Lyrics::default());
+//! ```
+//!
+//! Moosync talks to the extension through exported functions, see [`extension::WRAPPERS`].
+//! Their inputs and outputs are JSON and match the types in [`types::extensions`].
+//! The extension talks back through the host functions behind [`api`].
+//! Output written to stdout or stderr is discarded, log through extism instead.
+//! Panic messages are logged for you.
+//! [`testing::TestHarness`] runs an extension natively against a mock host.
+
+pub mod api;
+pub mod extension;
+pub mod host;
+pub mod testing;
+
+#[cfg(target_arch = "wasm32")]
+#[doc(hidden)]
+pub mod export;
+
+pub use extension::{dispatch, Extension};
+pub use types;
+
+#[cfg(test)]
+mod test;
+
+/// Exports `entry`, `onStopped` and every wrapper function Moosync calls.
+/// Takes an expression which builds the extension, evaluated on the first call.
+#[macro_export]
+macro_rules! register_extension {
+    ($init:expr) => {
+        #[cfg(target_arch = "wasm32")]
+        mod __moosync_extension {
+            use super::*;
+
+            ::std::thread_local! {
+                static EXTENSION: ::std::cell::RefCell<::std::boxed::Box<dyn $crate::Extension>> =
+                    ::std::cell::RefCell::new(::std::boxed::Box::new($init));
+            }
+
+            $crate::__export_functions!(entry, onStopped);
+            $crate::__with_wrappers!($crate::__export_functions);
+        }
+    };
+}
+
+/// Calls the given macro with every wrapper function, so the exports of
+/// [`register_extension!`] and [`extension::WRAPPERS`] come from one list
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __with_wrappers {
+    ($($callback:tt)*) => {
+        $($callback)*! {
+            get_provider_scopes_wrapper,
+            get_accounts_wrapper,
+            perform_account_login_wrapper,
+            get_playlists_wrapper,
+            get_playlist_content_wrapper,
+            oauth_callback_wrapper,
+            on_queue_changed_wrapper,
+            on_seeked_wrapper,
+            on_volume_changed_wrapper,
+            on_player_state_changed_wrapper,
+            on_song_changed_wrapper,
+            on_preferences_changed_wrapper,
+            get_playback_details_wrapper,
+            handle_custom_request_wrapper,
+            get_song_from_url_wrapper,
+            get_playlist_from_url_wrapper,
+            search_wrapper,
+            get_recommendations_wrapper,
+            get_lyrics_wrapper,
+            get_artist_songs_wrapper,
+            get_album_songs_wrapper,
+            on_song_added_wrapper,
+            on_song_removed_wrapper,
+            on_playlist_added_wrapper,
+            on_playlist_removed_wrapper,
+            get_song_from_id_wrapper,
+            get_remote_url_wrapper,
+            scrobble_wrapper,
+            get_song_context_menu_wrapper,
+            get_playlist_context_menu_wrapper,
+            on_context_menu_action_wrapper
+        }
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __export_functions {
+    ($($name:ident),*) => {
+        $(
+            #[allow(non_snake_case)]
+            #[no_mangle]
+            pub extern "C" fn $name() -> i32 {
+                EXTENSION.with_borrow_mut(|extension| {
+                    $crate::export::call(extension.as_mut(), stringify!($name))
+                })
+            }
+        )*
+    };
+}
@@ -0,0 +1,230 @@
+// Moosync
+// Copyright (C) 2024, 2025  Moosync <support@moosync.app>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::collections::HashSet;
+
+use types::{
+    entities::{QueryableAlbum, QueryableArtist, QueryablePlaylist},
+    errors::Result,
+    extensions::{
+        ExtensionCommand, ExtensionCommandResponse, ExtensionExtraEventResponse, MainCommand,
+    },
+    songs::Song,
+    ui::{
+        extensions::{
+            AccountLoginArgs, ExtensionEventKind, ExtensionExtraEvent, ExtensionExtraEventArgs,
+            ExtensionProviderScope, PackageNameArgs, PreferenceArgs, SearchReturnType,
+        },
+        player_details::PlayerState,
+    },
+};
+
+use crate::{api, dispatch, extension::WRAPPERS, testing::TestHarness, Extension};
+
+#[derive(Default)]
+struct LyricsExtension {
+    started: bool,
+}
+
+impl Extension for LyricsExtension {
+    fn on_started(&mut self) -> Result<()> {
+        self.started = api::set_preference("enabled", true)?;
+        Ok(())
+    }
+
+    fn get_provider_scopes(&mut self) -> Result<Vec<ExtensionProviderScope>> {
+        Ok(vec![ExtensionProviderScope::Lyrics])
+    }
+
+    fn get_lyrics(&mut self, song: Song) -> Result<String> {
+        let volume = api::get_volume()?;
+        Ok(format!(
+            "{} at {}",
+            song.song.title.unwrap_or_default(),
+            volume
+        ))
+    }
+}
+
+fn song(title: &str) -> Song {
+    let mut song = Song::default();
+    song.song.title = Some(title.to_string());
+    song
+}
+
+#[test]
+fn test_start_sends_commands() {
+    let mut harness = TestHarness::new("moosync.test", LyricsExtension::default());
+    harness.host().reply("SetPreference", true).unwrap();
+
+    harness.start().unwrap();
+    assert!(harness.extension().started);
+
+    let commands = harness.host().commands();
+    assert_eq!(commands.len(), 1);
+    let MainCommand::SetPreference(data) = &commands[0] else {
+        panic!("Expected SetPreference, got {:?}", commands[0]);
+    };
+    assert_eq!(data.key, "enabled");
+    assert_eq!(data.value, Some(serde_json::json!(true)));
+}
+
+#[test]
+fn test_missing_reply_fails_call() {
+    let mut harness = TestHarness::new("moosync.test", LyricsExtension::default());
+    assert!(harness.start().is_err());
+}
+
+#[test]
+fn test_responses_parse_like_host() {
+    let mut harness = TestHarness::new("moosync.test", LyricsExtension::default());
+    harness.host().reply("GetVolume", 50.0).unwrap();
+
+    assert_eq!(
+        harness.get_provider_scopes().unwrap(),
+        ExtensionCommandResponse::GetProviderScopes(vec![ExtensionProviderScope::Lyrics])
+    );
+
+    let response = harness
+        .event(ExtensionExtraEvent::RequestedLyrics([song("Title")]))
+        .unwrap();
+    assert_eq!(
+        response,
+        ExtensionExtraEventResponse::RequestedLyrics("Title at 50".to_string())
+    );
+}
+
+#[test]
+fn test_default_events() {
+    let mut harness = TestHarness::new("moosync.test", LyricsExtension::default());
+
+    let response = harness
+        .event(ExtensionExtraEvent::RequestedSearchResult([
+            "term".to_string()
+        ]))
+        .unwrap();
+    assert_eq!(
+        response,
+        ExtensionExtraEventResponse::RequestedSearchResult(SearchReturnType {
+            songs: vec![],
+            playlists: vec![],
+            artists: vec![],
+            albums: vec![],
+        })
+    );
+
+    let response = harness.event(ExtensionExtraEvent::Seeked([10.0])).unwrap();
+    assert_eq!(response, ExtensionExtraEventResponse::Seeked);
+    assert!(harness.get_accounts().is_ok());
+}
+
+#[test]
+fn test_every_wrapper_is_dispatched() {
+    let mut extension = LyricsExtension::default();
+    for fn_name in WRAPPERS {
+        let res = dispatch(&mut extension, fn_name, &[]);
+        if let Err(e) = res {
+            assert!(
+                !e.to_string().starts_with("Unknown extension function"),
+                "{} is not dispatched",
+                fn_name
+            );
+        }
+    }
+    assert!(dispatch(&mut extension, "missing_wrapper", &[]).is_err());
+}
+
+// Test that the host calls exactly the functions the SDK exports
+#[test]
+fn test_wrappers_match_plugin_calls() {
+    let package_name = || "moosync.test".to_string();
+    let events = vec![
+        ExtensionExtraEvent::RequestedPlaylists([false]),
+        ExtensionExtraEvent::RequestedPlaylistSongs("id".into(), false, None),
+        ExtensionExtraEvent::OauthCallback(["code".into()]),
+        ExtensionExtraEvent::SongQueueChanged([serde_json::json!({})]),
+        ExtensionExtraEvent::Seeked([1.0]),
+        ExtensionExtraEvent::VolumeChanged([1.0]),
+        ExtensionExtraEvent::PlayerStateChanged([PlayerState::default()]),
+        ExtensionExtraEvent::SongChanged([None]),
+        ExtensionExtraEvent::PreferenceChanged([PreferenceArgs {
+            key: "key".into(),
+            value: serde_json::json!(true),
+        }]),
+        ExtensionExtraEvent::PlaybackDetailsRequested([song("song")]),
+        ExtensionExtraEvent::CustomRequest(["url".into()]),
+        ExtensionExtraEvent::RequestedSongFromURL("url".into(), false),
+        ExtensionExtraEvent::RequestedPlaylistFromURL("url".into(), false),
+        ExtensionExtraEvent::RequestedSearchResult(["term".into()]),
+        ExtensionExtraEvent::RequestedRecommendations,
+        ExtensionExtraEvent::RequestedLyrics([song("song")]),
+        ExtensionExtraEvent::RequestedArtistSongs(QueryableArtist::default(), None),
+        ExtensionExtraEvent::RequestedAlbumSongs(QueryableAlbum::default(), None),
+        ExtensionExtraEvent::SongAdded([vec![song("song")]]),
+        ExtensionExtraEvent::SongRemoved([vec![song("song")]]),
+        ExtensionExtraEvent::PlaylistAdded([vec![QueryablePlaylist::default()]]),
+        ExtensionExtraEvent::PlaylistRemoved([vec![QueryablePlaylist::default()]]),
+        ExtensionExtraEvent::RequestedSongFromId(["id".into()]),
+        ExtensionExtraEvent::GetRemoteURL([song("song")]),
+        ExtensionExtraEvent::Scrobble([song("song")]),
+        ExtensionExtraEvent::RequestedSongContextMenu([vec![song("song")]]),
+        ExtensionExtraEvent::RequestedPlaylistContextMenu([QueryablePlaylist::default()]),
+        ExtensionExtraEvent::ContextMenuAction(["action".into()]),
+    ];
+
+    let mut commands = vec![
+        ExtensionCommand::GetProviderScopes(PackageNameArgs {
+            package_name: package_name(),
+        }),
+        ExtensionCommand::GetAccounts(PackageNameArgs {
+            package_name: package_name(),
+        }),
+        ExtensionCommand::PerformAccountLogin(AccountLoginArgs {
+            package_name: package_name(),
+            account_id: "account".into(),
+            login_status: true,
+        }),
+    ];
+    commands.extend(events.into_iter().map(|data| {
+        ExtensionCommand::ExtraExtensionEvent(ExtensionExtraEventArgs {
+            data,
+            package_name: package_name(),
+        })
+    }));
+
+    let called = commands
+        .iter()
+        .map(|command| command.to_plugin_call().1)
+        .collect::<HashSet<_>>();
+    let exported = WRAPPERS.iter().copied().collect::<HashSet<_>>();
+    assert_eq!(exported.len(), WRAPPERS.len(), "WRAPPERS has duplicates");
+    assert_eq!(called, exported);
+}
+
+#[test]
+fn test_runtime_subscriptions() {
+    let harness = TestHarness::new("moosync.test", LyricsExtension::default());
+    assert_eq!(harness.host().subscriptions(), None);
+
+    assert!(
+        api::subscribe(&[ExtensionEventKind::SongChanged, ExtensionEventKind::Seeked]).unwrap()
+    );
+    assert!(api::unsubscribe(&[ExtensionEventKind::Seeked]).unwrap());
+    assert_eq!(
+        harness.host().subscriptions(),
+        Some(vec![ExtensionEventKind::SongChanged])
+    );
+}
@@ -0,0 +1,282 @@
+// Moosync
+// Copyright (C) 2024, 2025  Moosync <support@moosync.app>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Runs an extension natively against a mock host, without Moosync or a wasm runtime.
+
+use std::{
+    cell::{Cell, RefCell},
+    collections::{BTreeSet, HashMap, VecDeque},
+    rc::Rc,
+};
+
+use serde::Serialize;
+use serde_json::Value;
+use types::{
+    errors::{MoosyncError, Result},
+    extensions::{
+        ExtensionCommand, ExtensionCommandResponse, ExtensionExtraEventResponse, MainCommand,
+    },
+    ui::extensions::{
+        ExtensionEventKind, ExtensionExtraEvent, ExtensionExtraEventArgs, PackageNameArgs,
+    },
+};
+
+use crate::{
+    dispatch,
+    host::{set_host, HashType, Host},
+    Extension,
+};
+
+/// Name of the variant a command is serialized under, e.g. `GetVolume`
+pub fn command_name(command: &MainCommand) -> String {
+    match serde_json::to_value(command) {
+        Ok(Value::Object(map)) => map.keys().next().cloned().unwrap_or_default(),
+        Ok(Value::String(name)) => name,
+        _ => String::new(),
+    }
+}
+
+#[derive(Default)]
+struct MockSocket {
+    path: String,
+    written: Vec<u8>,
+    readable: VecDeque<u8>,
+}
+
+type Hasher = Box<dyn Fn(HashType, &[u8]) -> Vec<u8>>;
+
+/// Host which answers commands with canned replies and records everything sent to it
+#[derive(Default)]
+pub struct MockHost {
+    replies: RefCell<HashMap<String, Value>>,
+    commands: RefCell<Vec<MainCommand>>,
+    time: Cell<u64>,
+    socket_paths: RefCell<HashMap<String, Vec<u8>>>,
+    sockets: RefCell<Vec<MockSocket>>,
+    hasher: RefCell<Option<Hasher>>,
+    subscriptions: RefCell<Option<BTreeSet<ExtensionEventKind>>>,
+}
+
+impl MockHost {
+    /// Answers every command named `command`, e.g. `GetVolume`, with `reply`.
+    /// Commands without a reply make the call fail like they would inside Moosync
+    pub fn reply(&self, command: &str, reply: impl Serialize) -> Result<()> {
+        let reply = serde_json::to_value(reply)?;
+        self.replies.borrow_mut().insert(command.to_string(), reply);
+        Ok(())
+    }
+
+    /// Commands the extension sent so far, oldest first
+    pub fn commands(&self) -> Vec<MainCommand> {
+        self.commands.borrow().clone()
+    }
+
+    pub fn set_time(&self, time: u64) {
+        self.time.set(time);
+    }
+
+    /// Lets the extension open a socket at `path` and read `data` from it
+    pub fn add_socket(&self, path: &str, data: &[u8]) {
+        self.socket_paths
+            .borrow_mut()
+            .insert(path.to_string(), data.to_vec());
+    }
+
+    /// Everything written to the sockets opened at `path`
+    pub fn written(&self, path: &str) -> Vec<u8> {
+        self.sockets
+            .borrow()
+            .iter()
+            .filter(|s| s.path == path)
+            .flat_map(|s| s.written.clone())
+            .collect()
+    }
+
+    /// Computes the digests returned by `hash`, which fails if none is set
+    pub fn set_hasher(&self, hasher: impl Fn(HashType, &[u8]) -> Vec<u8> + 'static) {
+        *self.hasher.borrow_mut() = Some(Box::new(hasher));
+    }
+
+    /// Events the extension subscribed to at runtime, None if it never changed them
+    pub fn subscriptions(&self) -> Option<Vec<ExtensionEventKind>> {
+        self.subscriptions
+            .borrow()
+            .as_ref()
+            .map(|events| events.iter().copied().collect())
+    }
+}
+
+impl Host for MockHost {
+    fn send_main_command(&self, command: &MainCommand) -> Result<Option<Value>> {
+        self.commands.borrow_mut().push(command.clone());
+        // Subscriptions are answered by the runner inside Moosync too
+        match command {
+            MainCommand::Subscribe(events) => {
+                let mut subscriptions = self.subscriptions.borrow_mut();
+                subscriptions
+                    .get_or_insert_with(Default::default)
+                    .extend(events.iter().copied());
+                return Ok(Some(Value::Bool(true)));
+            }
+            MainCommand::Unsubscribe(events) => {
+                let mut subscriptions = self.subscriptions.borrow_mut();
+                subscriptions
+                    .get_or_insert_with(|| ExtensionEventKind::ALL.into_iter().collect())
+                    .retain(|e| !events.contains(e));
+                return Ok(Some(Value::Bool(true)));
+            }
+            _ => {}
+        }
+        let name = command_name(command);
+        match self.replies.borrow().get(&name) {
+            Some(reply) => Ok(Some(reply.clone())),
+            None => Err(format!("MockHost has no reply for {}", name).into()),
+        }
+    }
+
+    fn system_time(&self) -> Result<u64> {
+        Ok(self.time.get())
+    }
+
+    fn open_clientfd(&self, path: &str) -> Result<i64> {
+        let Some(data) = self.socket_paths.borrow().get(path).cloned() else {
+            return Ok(-1);
+        };
+        let mut sockets = self.sockets.borrow_mut();
+        sockets.push(MockSocket {
+            path: path.to_string(),
+            written: vec![],
+            readable: data.into(),
+        });
+        Ok((sockets.len() - 1) as i64)
+    }
+
+    fn write_sock(&self, sock_id: i64, buf: &[u8]) -> Result<i64> {
+        let mut sockets = self.sockets.borrow_mut();
+        let Some(socket) = sockets.get_mut(sock_id as usize) else {
+            return Ok(-1);
+        };
+        socket.written.extend_from_slice(buf);
+        Ok(buf.len() as i64)
+    }
+
+    fn read_sock(&self, sock_id: i64, read_len: u64) -> Result<Vec<u8>> {
+        let mut sockets = self.sockets.borrow_mut();
+        let Some(socket) = sockets.get_mut(sock_id as usize) else {
+            return Ok(vec![]);
+        };
+        let read_len = if read_len == 0 || read_len > 1024 {
+            1024
+        } else {
+            read_len as usize
+        };
+        let read_len = read_len.min(socket.readable.len());
+        Ok(socket.readable.drain(..read_len).collect())
+    }
+
+    fn hash(&self, hash_type: HashType, data: &[u8]) -> Result<Vec<u8>> {
+        match self.hasher.borrow().as_ref() {
+            Some(hasher) => Ok(hasher(hash_type, data)),
+            None => Err("MockHost has no hasher, see MockHost::set_hasher".into()),
+        }
+    }
+}
+
+/// Calls an extension the way Moosync does, with a [`MockHost`] answering its commands.
+///
+/// The mock host is installed for the current thread until the harness is dropped,
+/// so tests using a harness shouldn't share a thread.
+pub struct TestHarness<E: Extension> {
+    extension: E,
+    host: Rc<MockHost>,
+    package_name: String,
+}
+
+impl<E: Extension> TestHarness<E> {
+    pub fn new(package_name: &str, extension: E) -> Self {
+        let host = Rc::new(MockHost::default());
+        set_host(Some(host.clone()));
+        Self {
+            extension,
+            host,
+            package_name: package_name.to_string(),
+        }
+    }
+
+    pub fn host(&self) -> &MockHost {
+        &self.host
+    }
+
+    pub fn extension(&mut self) -> &mut E {
+        &mut self.extension
+    }
+
+    /// Calls `entry`
+    pub fn start(&mut self) -> Result<()> {
+        dispatch(&mut self.extension, "entry", &[]).map(|_| ())
+    }
+
+    /// Calls `onStopped`
+    pub fn stop(&mut self) -> Result<()> {
+        dispatch(&mut self.extension, "onStopped", &[]).map(|_| ())
+    }
+
+    /// Encodes the command, calls the extension and parses its reply exactly like the host
+    pub fn send(&mut self, command: ExtensionCommand) -> Result<ExtensionCommandResponse> {
+        let (_, fn_name, input) = command.to_plugin_call();
+        let output = dispatch(&mut self.extension, fn_name, &input)?;
+        let value = if output.is_empty() {
+            Value::Null
+        } else {
+            serde_json::from_slice(&output)?
+        };
+        command.parse_response(value)
+    }
+
+    pub fn get_provider_scopes(&mut self) -> Result<ExtensionCommandResponse> {
+        self.send(ExtensionCommand::GetProviderScopes(PackageNameArgs {
+            package_name: self.package_name.clone(),
+        }))
+    }
+
+    pub fn get_accounts(&mut self) -> Result<ExtensionCommandResponse> {
+        self.send(ExtensionCommand::GetAccounts(PackageNameArgs {
+            package_name: self.package_name.clone(),
+        }))
+    }
+
+    pub fn event(&mut self, event: ExtensionExtraEvent) -> Result<ExtensionExtraEventResponse> {
+        let response = self.send(ExtensionCommand::ExtraExtensionEvent(
+            ExtensionExtraEventArgs {
+                data: event,
+                package_name: self.package_name.clone(),
+            },
+        ))?;
+        match response {
+            ExtensionCommandResponse::ExtraExtensionEvent(response) => Ok(*response),
+            response => Err(MoosyncError::String(format!(
+                "Unexpected response {:?}",
+                response
+            ))),
+        }
+    }
+}
+
+impl<E: Extension> Drop for TestHarness<E> {
+    fn drop(&mut self) {
+        set_host(None);
+    }
+}
@@ -15,14 +15,17 @@
 // along with this program.  If not, see <http://www.gnu.org/licenses/>.
 
 use std::{
-    collections::{BTreeMap, HashMap},
+    collections::{BTreeMap, HashMap, HashSet},
     env, fs,
     io::{Read, Write},
-    path::PathBuf,
+    path::{Path, PathBuf},
     process,
     str::FromStr,
-    sync::Arc,
-    thread,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    thread::{self, JoinHandle},
     time::{Duration, Instant, SystemTime, UNIX_EPOCH},
 };
 
@@ -40,24 +43,71 @@ use interprocess::local_socket::{
 use regex::{Captures, Regex};
 use serde_json::Value;
 use tokio::sync::{
-    mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender},
-    Mutex,
+    mpsc::{UnboundedReceiver, UnboundedSender},
+    oneshot, Mutex,
+};
+use tracing::{debug, error, info, Level};
+use types::{
+    entities::QueryablePlaylist,
+    errors::{MoosyncError, Result as MoosyncResult},
+    extensions::ExtensionExtraEventResponse,
+    songs::Song,
 };
-use tracing::{debug, error, info};
-use types::{errors::Result as MoosyncResult, extensions::ExtensionExtraEventResponse};
 use types::{
     extensions::{
         sanitize_album, sanitize_artist, sanitize_playlist, sanitize_song, ExtensionCommand,
         ExtensionCommandResponse, ExtensionManifest, GenericExtensionHostRequest, MainCommand,
         MainCommandResponse, RunnerCommand, RunnerCommandResp,
     },
-    ui::extensions::ExtensionDetail,
+    preferences::PreferenceUIData,
+    ui::extensions::{
+        extension_preference_key, ExtensionCapability, ExtensionDetail, ExtensionDisabledEvent,
+        ExtensionPermissions, ExtensionSubscriptionInfo, LyricsReturnType,
+    },
 };
 
+use crate::{
+    logs::{self, ExtensionLogs},
+    settings,
+    subscriptions::ExtensionSubscriptions,
+};
+
+#[cfg(test)]
+mod test;
+
+/// How long broadcast requests wait for each extension to reply
+const EXTENSION_RESPONSE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Size of a single WASM memory page in bytes
+const WASM_PAGE_SIZE: u64 = 65536;
+
+/// Resource limits applied to every extension
+#[derive(Debug, Clone, Copy)]
+pub struct ExtensionLimits {
+    /// Longest a single call into an extension, or a host reply to an extension, may take
+    pub call_timeout: Duration,
+    /// Most memory an extension may allocate, in bytes
+    pub memory_max: u64,
+    /// Consecutive crashes or timeouts after which an extension is disabled. 0 never disables
+    pub max_failures: u32,
+}
+
+impl Default for ExtensionLimits {
+    fn default() -> Self {
+        Self {
+            call_timeout: Duration::from_secs(30),
+            memory_max: 256 * 1024 * 1024,
+            max_failures: 3,
+        }
+    }
+}
+
 // Ext handler inner
 pub type MainCommandReplySender = UnboundedSender<ExtensionCommandResponse>;
 pub type ExtCommandSender = UnboundedSender<GenericExtensionHostRequest<MainCommand>>;
-pub type ExtCommandReplySender = UnboundedSender<GenericExtensionHostRequest<MainCommandResponse>>;
+pub type ExtCommandReplySender =
+    std::sync::mpsc::Sender<GenericExtensionHostRequest<MainCommandResponse>>;
+pub type ExtensionDisabledSender = UnboundedSender<ExtensionDisabledEvent>;
 
 // Outer handler
 
@@ -66,18 +116,60 @@ pub type ExtCommandReceiver = UnboundedReceiver<GenericExtensionHostRequest<Main
 struct MainCommandUserData {
     reply_map: Arc<std::sync::Mutex<HashMap<String, ExtCommandReplySender>>>,
     ext_command_tx: ExtCommandSender,
+    timeout: Duration,
+    package_name: String,
+    capabilities: HashSet<ExtensionCapability>,
+    subscriptions: ExtensionSubscriptions,
+    shutting_down: Arc<AtomicBool>,
+}
+
+/// Extensions only see the preferences and secure values stored under their own package
+fn scope_preference(command: &mut MainCommand, package_name: &str) {
+    if let MainCommand::GetPreference(data)
+    | MainCommand::SetPreference(data)
+    | MainCommand::GetSecure(data)
+    | MainCommand::SetSecure(data) = command
+    {
+        data.key = extension_preference_key(package_name, &data.key);
+    }
 }
 
 host_fn!(send_main_command(user_data: MainCommandUserData; command: MainCommand) -> Option<Value> {
+    let mut command = command;
     let user_data = user_data.get()?;
     let user_data = user_data.lock().unwrap();
     tracing::debug!("Got extension command {:?}", command);
+    // Answered here, the rest of the app doesn't know about subscriptions
+    match &command {
+        MainCommand::Subscribe(events) => {
+            user_data.subscriptions.subscribe(&user_data.package_name, events);
+            return Ok(serde_json::to_value(MainCommandResponse::Subscribe(true)).ok())
+        }
+        MainCommand::Unsubscribe(events) => {
+            user_data.subscriptions.unsubscribe(&user_data.package_name, events);
+            return Ok(serde_json::to_value(MainCommandResponse::Unsubscribe(true)).ok())
+        }
+        _ => {}
+    }
+    if let Some(capability) = command.required_capability() {
+        if !user_data.capabilities.contains(&capability) {
+            tracing::warn!("Extension {} was denied {:?}", user_data.package_name, command);
+            return Err(Error::msg(format!("Permission {:?} was not granted", capability)))
+        }
+    }
+    scope_preference(&mut command, &user_data.package_name);
+
     match command.to_request() {
         Ok(request) => {
             let reply_map = user_data.reply_map.clone();
-            let (tx, mut rx) = unbounded_channel();
+            let (tx, rx) = std::sync::mpsc::channel();
             {
                 let mut reply_map = reply_map.lock().unwrap();
+                // Checked under the lock so a shutdown can't miss this sender
+                if user_data.shutting_down.load(Ordering::Relaxed) {
+                    tracing::debug!("Rejecting {:?} during shutdown", command);
+                    return Err(Error::msg("The app is shutting down"))
+                }
                 reply_map.insert(request.channel.clone(), tx);
             }
 
@@ -86,15 +178,21 @@ host_fn!(send_main_command(user_data: MainCommandUserData; command: MainCommand)
             ext_command_tx.send(request.clone()).unwrap();
 
             tracing::trace!("waiting on response for {:?}", command);
-            if let Some(resp) = block_on(rx.recv()) {
-                {
-                    let mut reply_map = reply_map.lock().unwrap();
-                    reply_map.remove(&request.channel);
+            let resp = rx.recv_timeout(user_data.timeout);
+            {
+                let mut reply_map = reply_map.lock().unwrap();
+                reply_map.remove(&request.channel);
+            }
+
+            match resp {
+                Ok(resp) => {
+                    tracing::debug!("Got response for {:?}: {:?}", command, resp);
+                    return Ok(resp.data)
+                }
+                Err(e) => {
+                    tracing::error!("Failed to receive response for {:?}: {:?}", command, e);
+                    return Err(Error::msg(format!("Failed to receive response: {}", e)))
                 }
-                tracing::debug!("Got response for {:?}: {:?}", command, resp);
-                return Ok(resp.data)
-            } else {
-                return Err(Error::msg("Failed to receive response"))
             }
         }
         Err(e) => {
@@ -188,7 +286,7 @@ host_fn!(write_sock(user_data: SocketUserData; sock_id: i64, buf: Vec<u8>) -> i6
             return Ok(-1);
         } else {
             info!("Wrote all");
-            return Ok(-1);
+            return Ok(buf.len() as i64);
         }
     }
 
@@ -245,6 +343,18 @@ host_fn!(hash(hash_type: String, data: Vec<u8>) -> Vec<u8> {
     return Ok(buf);
 });
 
+/// Whether the call crashed the plugin, ran out of time or exhausted its memory
+fn is_plugin_fault(e: &Error) -> bool {
+    if e.downcast_ref::<wasmtime::Trap>().is_some() {
+        return true;
+    }
+    // Extism reports its own timeouts and failed allocations as plain messages
+    e.chain().any(|cause| {
+        let cause = cause.to_string().to_lowercase();
+        cause == "timeout" || cause.contains("out of memory")
+    })
+}
+
 #[derive(Debug, Clone)]
 struct Extension {
     plugin: Option<Arc<Mutex<Plugin>>>,
@@ -254,7 +364,19 @@ struct Extension {
     author: Option<String>,
     version: String,
     path: PathBuf,
+    manifest_path: PathBuf,
+    // Whether the plugin is loaded and its entry has returned
     active: bool,
+    // Whether the user wants the extension to run
+    enabled: bool,
+    // Crashes or timeouts since the last successful call
+    failures: u32,
+    disabled_reason: Option<String>,
+    // Requested permissions the user hasn't approved yet
+    pending_permissions: Option<ExtensionPermissions>,
+    preferences: Vec<PreferenceUIData>,
+    // Loaded in place from a development directory instead of the extensions folder
+    dev: bool,
 }
 
 impl From<&Extension> for ExtensionDetail {
@@ -266,37 +388,212 @@ impl From<&Extension> for ExtensionDetail {
             desc: None,
             author: val.author.clone(),
             version: val.version.clone(),
-            has_started: true,
+            has_started: val.plugin.is_some(),
             entry: val.path.clone().to_str().unwrap().to_string(),
-            preferences: vec![],
+            preferences: val.preferences.clone(),
             extension_path: val.path.clone().to_str().unwrap().to_string(),
             extension_icon: Some(val.icon.clone()),
             active: val.active,
+            enabled: val.enabled,
+            disabled_reason: val.disabled_reason.clone(),
+            pending_permissions: val.pending_permissions.clone(),
+            dev: val.dev,
         }
     }
 }
 
+/// Counts consecutive crashes and timeouts and disables extensions which keep failing
+#[derive(Debug, Clone)]
+struct FailureTracker {
+    extensions_map: Arc<Mutex<HashMap<String, Extension>>>,
+    max_failures: u32,
+    disabled_tx: ExtensionDisabledSender,
+    ext_command_tx: ExtCommandSender,
+    logs: ExtensionLogs,
+}
+
+impl FailureTracker {
+    fn record_success(&self, package_name: &str) {
+        let mut extensions_map = block_on(self.extensions_map.lock());
+        if let Some(ext) = extensions_map.get_mut(package_name) {
+            ext.failures = 0;
+        }
+    }
+
+    #[tracing::instrument(level = "debug", skip(self))]
+    fn record_failure(&self, package_name: &str, reason: String) {
+        self.logs.push(package_name, Level::ERROR, reason.clone());
+        let plugin = {
+            let mut extensions_map = block_on(self.extensions_map.lock());
+            let Some(ext) = extensions_map.get_mut(package_name) else {
+                return;
+            };
+            ext.failures += 1;
+            if !ext.enabled || self.max_failures == 0 || ext.failures < self.max_failures {
+                return;
+            }
+
+            ext.enabled = false;
+            ext.active = false;
+            ext.disabled_reason = Some(reason.clone());
+            ext.plugin.take()
+        };
+
+        // A plugin which keeps crashing can't be trusted to run onStopped
+        drop(plugin);
+        tracing::warn!(
+            "Disabled extension {} after {} failures: {}",
+            package_name,
+            self.max_failures,
+            reason
+        );
+
+        let _ = self.disabled_tx.send(ExtensionDisabledEvent {
+            package_name: package_name.to_string(),
+            reason,
+        });
+        let _ = self
+            .ext_command_tx
+            .send(MainCommand::ExtensionsUpdated().to_request().unwrap());
+    }
+}
+
 #[derive(Debug)]
 pub(crate) struct ExtensionHandlerInner {
     extensions_path: String,
     ext_command_tx: ExtCommandSender,
     extensions_map: Arc<Mutex<HashMap<String, Extension>>>,
     reply_map: Arc<std::sync::Mutex<HashMap<String, ExtCommandReplySender>>>,
+    disabled: HashSet<String>,
+    limits: ExtensionLimits,
+    disabled_tx: ExtensionDisabledSender,
+    granted: HashMap<String, ExtensionPermissions>,
+    // Grant requested permissions to extensions without a stored grant,
+    // which were installed before permissions were stored
+    trust_installed: bool,
+    // Unpacked extension directories loaded in place
+    dev_paths: Vec<PathBuf>,
+    logs: ExtensionLogs,
+    subscriptions: ExtensionSubscriptions,
+    shutting_down: Arc<AtomicBool>,
 }
 
 impl ExtensionHandlerInner {
-    #[tracing::instrument(level = "debug", skip(ext_command_tx))]
+    #[tracing::instrument(level = "debug", skip(ext_command_tx, disabled_tx, logs))]
     pub fn new(
         extensions_path: &PathBuf,
         cache_path: &PathBuf,
         ext_command_tx: ExtCommandSender,
+        disabled_tx: ExtensionDisabledSender,
+        logs: ExtensionLogs,
     ) -> Self {
         Self {
             extensions_path: extensions_path.to_string_lossy().to_string(),
             ext_command_tx,
             extensions_map: Default::default(),
             reply_map: Arc::new(std::sync::Mutex::new(HashMap::new())),
+            disabled: Default::default(),
+            limits: Default::default(),
+            disabled_tx,
+            granted: Default::default(),
+            trust_installed: false,
+            dev_paths: Default::default(),
+            logs,
+            subscriptions: Default::default(),
+            shutting_down: Default::default(),
+        }
+    }
+
+    /// Makes running extensions fail their main commands instead of waiting on replies
+    /// which won't come anymore
+    pub(crate) fn begin_shutdown(&self) {
+        self.shutting_down.store(true, Ordering::Relaxed);
+        // Dropping the reply senders wakes up commands which are already waiting
+        self.reply_map.lock().unwrap().clear();
+    }
+
+    pub(crate) fn is_shutting_down(&self) -> bool {
+        self.shutting_down.load(Ordering::Relaxed)
+    }
+
+    /// Passing None treats every extension found by the next [Self::spawn_extensions] as approved
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub(crate) fn set_granted_permissions(
+        &mut self,
+        granted: Option<HashMap<String, ExtensionPermissions>>,
+    ) {
+        self.trust_installed = granted.is_none();
+        self.granted = granted.unwrap_or_default();
+    }
+
+    pub(crate) fn get_granted_permissions(&self) -> HashMap<String, ExtensionPermissions> {
+        self.granted.clone()
+    }
+
+    /// Returns the requested permissions if the user hasn't approved all of them
+    #[tracing::instrument(level = "debug", skip(self, manifest))]
+    fn missing_permissions(
+        &mut self,
+        manifest: &ExtensionManifest,
+    ) -> Option<ExtensionPermissions> {
+        let requested = manifest.requested_permissions();
+        if let Some(granted) = self.granted.get(&manifest.name) {
+            if granted.covers(&requested) {
+                return None;
+            }
+        } else if self.trust_installed {
+            self.granted.insert(manifest.name.clone(), requested);
+            return None;
+        }
+        Some(requested)
+    }
+
+    /// Stores the pending permissions of the extension as granted and starts it if enabled
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub(crate) async fn grant_permissions(&mut self, package_name: String) -> MoosyncResult<()> {
+        let (permissions, enabled, manifest_path) = {
+            let mut extensions_map = self.extensions_map.lock().await;
+            let Some(ext) = extensions_map.get_mut(&package_name) else {
+                return Err(format!("Extension {} not found", package_name).into());
+            };
+            let Some(permissions) = ext.pending_permissions.take() else {
+                return Ok(());
+            };
+            (permissions, ext.enabled, ext.manifest_path.clone())
+        };
+
+        tracing::info!("Granted {:?} to {}", permissions, package_name);
+        self.granted.insert(package_name, permissions);
+        if enabled {
+            if let Some(manifest) = Self::read_manifest(&manifest_path) {
+                self.start_extension(manifest);
+            }
         }
+
+        self.send_extensions_updated();
+        Ok(())
+    }
+
+    /// New limits apply to extensions started after this call
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub(crate) fn set_limits(&mut self, limits: ExtensionLimits) {
+        self.limits = limits;
+    }
+
+    fn failure_tracker(&self) -> FailureTracker {
+        FailureTracker {
+            extensions_map: self.extensions_map.clone(),
+            max_failures: self.limits.max_failures,
+            disabled_tx: self.disabled_tx.clone(),
+            ext_command_tx: self.ext_command_tx.clone(),
+            logs: self.logs.clone(),
+        }
+    }
+
+    /// Extensions in this list are registered but not started by [Self::spawn_extensions]
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub(crate) fn set_disabled_extensions(&mut self, disabled: Vec<String>) {
+        self.disabled = disabled.into_iter().collect();
     }
 
     #[tracing::instrument(level = "debug", skip(self))]
@@ -323,31 +620,83 @@ impl ExtensionHandlerInner {
                 }
             }
         }
+
+        for dev_path in &self.dev_paths {
+            let manifest_path = dev_path.join("package.json");
+            if manifest_path.is_file() {
+                package_json_paths.push(manifest_path);
+            } else {
+                tracing::warn!("No extension manifest found in {:?}", dev_path);
+            }
+        }
         package_json_paths
     }
 
+    fn is_dev_manifest(&self, manifest_path: &Path) -> bool {
+        manifest_path
+            .parent()
+            .is_some_and(|dir| self.dev_paths.iter().any(|d| d == dir))
+    }
+
+    /// Replaces the unpacked extensions loaded in place. Extensions from directories
+    /// which are no longer listed are unloaded and new ones are started.
     #[tracing::instrument(level = "debug", skip(self))]
-    async fn find_extensions(&self) -> Vec<ExtensionManifest> {
+    pub(crate) async fn set_dev_paths(&mut self, dev_paths: Vec<PathBuf>) {
+        self.dev_paths = dev_paths;
+        let removed = {
+            let extensions_map = self.extensions_map.lock().await;
+            extensions_map
+                .values()
+                .filter(|e| e.dev && !self.is_dev_manifest(&e.manifest_path))
+                .map(|e| e.package_name.clone())
+                .collect::<Vec<_>>()
+        };
+        for package_name in removed {
+            self.remove_extension(&package_name).await;
+        }
+        self.spawn_extensions().await;
+    }
+
+    /// Package names and wasm entries of the extensions loaded from development directories
+    pub(crate) async fn dev_entries(&self) -> Vec<(String, PathBuf)> {
+        let extensions_map = self.extensions_map.lock().await;
+        extensions_map
+            .values()
+            .filter(|e| e.dev)
+            .map(|e| (e.package_name.clone(), e.path.clone()))
+            .collect()
+    }
+
+    #[tracing::instrument(level = "debug")]
+    fn read_manifest(manifest_path: &Path) -> Option<ExtensionManifest> {
+        let contents = fs::read(manifest_path).ok()?;
+        match serde_json::from_slice::<ExtensionManifest>(&contents) {
+            Ok(mut manifest) => {
+                manifest.extension_entry = manifest_path
+                    .parent()
+                    .unwrap()
+                    .join(manifest.extension_entry);
+                if manifest.extension_entry.extension().unwrap_or_default() == "wasm"
+                    && manifest.extension_entry.exists()
+                {
+                    return Some(manifest);
+                }
+            }
+            Err(e) => tracing::error!("Error parsing manifest: {:?}", e),
+        }
+        None
+    }
+
+    #[tracing::instrument(level = "debug", skip(self))]
+    async fn find_extensions(&self) -> Vec<(PathBuf, ExtensionManifest)> {
         let manifests = self.find_extension_manifests();
         let mut parsed_manifests = vec![];
 
         let extensions_map = self.extensions_map.lock().await;
         for manifest_path in manifests {
-            if let Ok(contents) = fs::read(manifest_path.clone()) {
-                match serde_json::from_slice::<ExtensionManifest>(&contents) {
-                    Ok(mut manifest) => {
-                        manifest.extension_entry = manifest_path
-                            .parent()
-                            .unwrap()
-                            .join(manifest.extension_entry);
-                        if !extensions_map.contains_key(&manifest.name)
-                            && manifest.extension_entry.extension().unwrap() == "wasm"
-                            && manifest.extension_entry.exists()
-                        {
-                            parsed_manifests.push(manifest);
-                        }
-                    }
-                    Err(e) => tracing::error!("Error parsing manifest: {:?}", e),
+            if let Some(manifest) = Self::read_manifest(&manifest_path) {
+                if !extensions_map.contains_key(&manifest.name) {
+                    parsed_manifests.push((manifest_path, manifest));
                 }
             }
         }
@@ -355,7 +704,14 @@ impl ExtensionHandlerInner {
         parsed_manifests
     }
 
-    fn get_empty_extension(manifest: ExtensionManifest) -> Extension {
+    fn get_empty_extension(
+        manifest: ExtensionManifest,
+        manifest_path: PathBuf,
+        enabled: bool,
+        pending_permissions: Option<ExtensionPermissions>,
+        dev: bool,
+    ) -> Extension {
+        let preferences = settings::validate_preferences(&manifest.name, &manifest.preferences);
         Extension {
             plugin: None,
             name: manifest.display_name,
@@ -364,7 +720,14 @@ impl ExtensionHandlerInner {
             author: manifest.author,
             version: manifest.version,
             path: manifest.extension_entry.clone(),
+            manifest_path,
             active: false,
+            enabled,
+            failures: 0,
+            disabled_reason: None,
+            pending_permissions,
+            preferences,
+            dev,
         }
     }
 
@@ -373,9 +736,14 @@ impl ExtensionHandlerInner {
         manifest: ExtensionManifest,
         reply_map: Arc<std::sync::Mutex<HashMap<String, ExtCommandReplySender>>>,
         ext_command_tx: ExtCommandSender,
-    ) -> Arc<Mutex<Plugin>> {
+        limits: ExtensionLimits,
+        subscriptions: ExtensionSubscriptions,
+        shutting_down: Arc<AtomicBool>,
+    ) -> MoosyncResult<Arc<Mutex<Plugin>>> {
         let url = Wasm::file(manifest.extension_entry.clone());
         let mut plugin_manifest = Manifest::new([url]);
+        // Only started once the user approved these
+        let requested = manifest.requested_permissions();
         if let Some(permissions) = manifest.permissions {
             let re = Regex::new(r"\{([A-Z_][A-Z0-9_]*)\}").unwrap();
             let mut allowed_paths = HashMap::new();
@@ -403,14 +771,31 @@ impl ExtensionHandlerInner {
                 .with_config_key("pid", format!("{}", process::id()));
         }
 
+        // Calls running past the timeout are interrupted and return an error
+        plugin_manifest = plugin_manifest
+            .with_timeout(limits.call_timeout)
+            .with_memory_max((limits.memory_max / WASM_PAGE_SIZE).max(1) as u32);
+
         let user_data = UserData::new(MainCommandUserData {
             reply_map,
             ext_command_tx,
+            timeout: limits.call_timeout,
+            package_name: manifest.name.clone(),
+            capabilities: requested.capabilities.iter().copied().collect(),
+            subscriptions,
+            shutting_down,
         });
 
         let sock_data = UserData::new(SocketUserData {
             socks: vec![],
-            allowed_paths: plugin_manifest.allowed_paths.clone(),
+            allowed_paths: if requested
+                .capabilities
+                .contains(&ExtensionCapability::Sockets)
+            {
+                plugin_manifest.allowed_paths.clone()
+            } else {
+                None
+            },
         });
 
         let plugin_builder = PluginBuilder::new(plugin_manifest)
@@ -466,56 +851,129 @@ impl ExtensionHandlerInner {
             plugin_builder = plugin_builder.with_cache_config(cache_path);
         }
 
-        let plugin = plugin_builder.build().unwrap();
+        let plugin = plugin_builder
+            .build()
+            .map_err(|e| format!("Failed to load {:?}: {}", manifest.extension_entry, e))?;
 
-        Arc::new(Mutex::new(plugin))
+        Ok(Arc::new(Mutex::new(plugin)))
     }
 
+    /// Loads the plugin and calls its entry on a separate thread.
+    /// The returned receiver resolves to whether the extension is now running.
     #[tracing::instrument(level = "debug", skip(self))]
-    async fn spawn_extensions(&mut self) {
-        let manifests = self.find_extensions().await;
-        for manifest in manifests {
-            let package_name = manifest.name.clone();
-            let extension_map = self.extensions_map.clone();
-            let reply_map = self.reply_map.clone();
-            let ext_command_tx = self.ext_command_tx.clone();
-
+    fn start_extension(&self, manifest: ExtensionManifest) -> oneshot::Receiver<bool> {
+        let package_name = manifest.name.clone();
+        let version = manifest.version.clone();
+        let extension_map = self.extensions_map.clone();
+        let reply_map = self.reply_map.clone();
+        let ext_command_tx = self.ext_command_tx.clone();
+        let limits = self.limits;
+        let tracker = self.failure_tracker();
+        let subscriptions = self.subscriptions.clone();
+        let shutting_down = self.shutting_down.clone();
+        let (started_tx, started_rx) = oneshot::channel();
+
+        // Before the entry runs, which may change them
+        subscriptions.register(&package_name, manifest.subscriptions.clone());
+
+        thread::spawn(move || {
+            let _span = logs::extension_span(&package_name).entered();
+            let plugin_mutex = match Self::spawn_extension(
+                manifest,
+                reply_map,
+                ext_command_tx.clone(),
+                limits,
+                subscriptions.clone(),
+                shutting_down,
+            ) {
+                Ok(plugin_mutex) => plugin_mutex,
+                Err(e) => {
+                    tracing::error!("Failed to load extension {}: {:?}", package_name, e);
+                    tracker.record_failure(&package_name, e.to_string());
+                    subscriptions.remove(&package_name);
+                    let _ = started_tx.send(false);
+                    return;
+                }
+            };
             {
-                let mut extensions_map = extension_map.lock().await;
-                extensions_map.insert(
-                    package_name.clone(),
-                    Self::get_empty_extension(manifest.clone()),
-                );
-                ext_command_tx
-                    .send(MainCommand::ExtensionsUpdated().to_request().unwrap())
-                    .unwrap();
-            }
-
-            thread::spawn(move || {
-                let plugin_mutex =
-                    Self::spawn_extension(manifest, reply_map, ext_command_tx.clone());
-                {
-                    let mut plugin = block_on(plugin_mutex.lock());
-
-                    tracing::trace!("Callign entry");
-                    plugin.call::<(), ()>("entry", ()).unwrap();
+                let mut plugin = block_on(plugin_mutex.lock());
+
+                tracing::trace!("Callign entry");
+                if let Err(e) = plugin.call::<(), ()>("entry", ()) {
+                    tracing::error!("Failed to start extension {}: {:?}", package_name, e);
+                    tracker.record_failure(&package_name, e.to_string());
+                    subscriptions.remove(&package_name);
+                    let _ = started_tx.send(false);
+                    return;
                 }
-                {
-                    let mut extensions_map = block_on(extension_map.lock());
-                    if let Some(ext) = extensions_map.get_mut(&package_name) {
+            }
+            {
+                let mut extensions_map = block_on(extension_map.lock());
+                match extensions_map.get_mut(&package_name) {
+                    Some(ext) if ext.enabled && ext.plugin.is_none() && ext.version == version => {
                         ext.plugin = Some(plugin_mutex);
                         ext.active = true;
-                    } else {
-                        panic!("Where the fuck did the extension disappear");
+                    }
+                    _ => {
+                        // Disabled, removed, replaced or started again while the entry was running
+                        drop(extensions_map);
+                        Self::stop_plugin(&package_name, plugin_mutex);
+                        let _ = started_tx.send(false);
+                        return;
                     }
                 }
+            }
 
-                ext_command_tx
-                    .send(MainCommand::ExtensionsUpdated().to_request().unwrap())
-                    .unwrap();
-            });
+            let _ = started_tx.send(true);
+            ext_command_tx
+                .send(MainCommand::ExtensionsUpdated().to_request().unwrap())
+                .unwrap();
+        });
+
+        started_rx
+    }
+
+    /// Gives the extension a chance to clean up. The plugin and its memory are
+    /// freed once the last in-flight call holding it returns.
+    #[tracing::instrument(level = "debug", skip(plugin))]
+    fn stop_plugin(package_name: &str, plugin: Arc<Mutex<Plugin>>) {
+        let _span = logs::extension_span(package_name).entered();
+        let mut plugin = block_on(plugin.lock());
+        if plugin.function_exists("onStopped") {
+            if let Err(e) = plugin.call::<(), ()>("onStopped", ()) {
+                tracing::error!("Extension {} failed to stop: {:?}", package_name, e);
+            }
         }
+    }
+
+    /// Unloads the plugins of the matching extensions, or all of them if the package name is empty.
+    /// onStopped may send main commands, so it is called on separate threads which
+    /// don't need this handler to be unlocked.
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub(crate) async fn stop_extensions(&mut self, package_name: String) -> Vec<JoinHandle<()>> {
+        let plugins = {
+            let mut extensions_map = self.extensions_map.lock().await;
+            extensions_map
+                .values_mut()
+                .filter(|e| package_name.is_empty() || e.package_name == package_name)
+                .filter_map(|e| {
+                    e.active = false;
+                    // Registered again from the manifest when it starts
+                    self.subscriptions.remove(&e.package_name);
+                    e.plugin.take().map(|p| (e.package_name.clone(), p))
+                })
+                .collect::<Vec<_>>()
+        };
 
+        plugins
+            .into_iter()
+            .map(|(package_name, plugin)| {
+                thread::spawn(move || Self::stop_plugin(&package_name, plugin))
+            })
+            .collect()
+    }
+
+    fn send_extensions_updated(&self) {
         if let Err(e) = self
             .ext_command_tx
             .send(MainCommand::ExtensionsUpdated().to_request().unwrap())
@@ -524,6 +982,135 @@ impl ExtensionHandlerInner {
         }
     }
 
+    #[tracing::instrument(level = "debug", skip(self))]
+    async fn spawn_extensions(&mut self) {
+        let manifests = self.find_extensions().await;
+        for (manifest_path, manifest) in manifests {
+            let enabled = !self.disabled.contains(&manifest.name);
+            let pending = self.missing_permissions(&manifest);
+            let can_start = enabled && pending.is_none();
+            let dev = self.is_dev_manifest(&manifest_path);
+            {
+                let mut extensions_map = self.extensions_map.lock().await;
+                extensions_map.insert(
+                    manifest.name.clone(),
+                    Self::get_empty_extension(
+                        manifest.clone(),
+                        manifest_path,
+                        enabled,
+                        pending,
+                        dev,
+                    ),
+                );
+            }
+            self.send_extensions_updated();
+
+            if can_start {
+                self.start_extension(manifest);
+            }
+        }
+        // Extensions installed from now on have to be approved
+        self.trust_installed = false;
+
+        self.send_extensions_updated();
+    }
+
+    /// Flips whether the extension runs and returns the new state
+    #[tracing::instrument(level = "debug", skip(self))]
+    async fn toggle_extension(&mut self, package_name: String) -> MoosyncResult<bool> {
+        let (enabled, pending, manifest_path) = {
+            let mut extensions_map = self.extensions_map.lock().await;
+            let Some(ext) = extensions_map.get_mut(&package_name) else {
+                return Err(format!("Extension {} not found", package_name).into());
+            };
+            ext.enabled = !ext.enabled;
+            if ext.enabled {
+                ext.failures = 0;
+                ext.disabled_reason = None;
+            }
+            (
+                ext.enabled,
+                ext.pending_permissions.is_some(),
+                ext.manifest_path.clone(),
+            )
+        };
+
+        if enabled {
+            self.disabled.remove(&package_name);
+            if !pending {
+                if let Some(manifest) = Self::read_manifest(&manifest_path) {
+                    self.start_extension(manifest);
+                }
+            }
+        } else {
+            self.disabled.insert(package_name.clone());
+            self.stop_extensions(package_name).await;
+        }
+
+        self.send_extensions_updated();
+        Ok(enabled)
+    }
+
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub(crate) async fn has_extension(&self, package_name: &String) -> bool {
+        self.extensions_map.lock().await.contains_key(package_name)
+    }
+
+    /// Stops the extension and starts it again from the files on disk.
+    /// Returns a receiver for the start result if the extension is enabled.
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub(crate) async fn reload_extension(
+        &mut self,
+        package_name: String,
+    ) -> MoosyncResult<Option<oneshot::Receiver<bool>>> {
+        if package_name.is_empty() {
+            return Err("No extension specified".into());
+        }
+        let Some(ext) = self.get_extensions(package_name.clone()).await.pop() else {
+            return Err(format!("Extension {} not found", package_name).into());
+        };
+
+        self.stop_extensions(package_name.clone()).await;
+
+        let manifest = Self::read_manifest(&ext.manifest_path);
+        // Updates asking for more than was granted wait for approval
+        let pending = manifest
+            .as_ref()
+            .and_then(|manifest| self.missing_permissions(manifest));
+        let can_start = ext.enabled && pending.is_none();
+        {
+            let mut extensions_map = self.extensions_map.lock().await;
+            match &manifest {
+                Some(manifest) if manifest.name == package_name => {
+                    extensions_map.insert(
+                        package_name.clone(),
+                        Self::get_empty_extension(
+                            manifest.clone(),
+                            ext.manifest_path.clone(),
+                            ext.enabled,
+                            pending,
+                            ext.dev,
+                        ),
+                    );
+                }
+                _ => {
+                    tracing::warn!("Extension {} is no longer on disk", package_name);
+                    extensions_map.remove(&package_name);
+                }
+            }
+        }
+
+        let mut started = None;
+        if let Some(manifest) = manifest {
+            if can_start && manifest.name == package_name {
+                started = Some(self.start_extension(manifest));
+            }
+        }
+
+        self.send_extensions_updated();
+        Ok(started)
+    }
+
     #[tracing::instrument(level = "debug", skip(self))]
     async fn get_extensions(&self, package_name: String) -> Vec<Extension> {
         let mut plugins = vec![];
@@ -652,12 +1239,277 @@ impl ExtensionHandlerInner {
                         _context_menu_return_type,
                     ) => {}
                     ExtensionExtraEventResponse::ContextMenuAction => {}
+                    ExtensionExtraEventResponse::RequestedLyricsFrom(_) => {}
                 }
             }
             ExtensionCommandResponse::Empty => {}
         }
     }
 
+    /// Calls the plugin and parses its reply
+    #[tracing::instrument(level = "debug", skip(extension, args, tracker))]
+    fn call_extension(
+        extension: &Extension,
+        command: &ExtensionCommand,
+        fn_name: &'static str,
+        args: Vec<u8>,
+        tracker: &FailureTracker,
+    ) -> MoosyncResult<ExtensionCommandResponse> {
+        let Some(plugin) = &extension.plugin else {
+            return Err(format!("Extension {} is not running", extension.package_name).into());
+        };
+
+        let res = {
+            let _span = logs::extension_span(&extension.package_name).entered();
+            let mut plugin = block_on(plugin.lock());
+            // Extensions built with older SDKs don't handle every event
+            if !plugin.function_exists(fn_name) {
+                tracing::debug!(
+                    "Extension {} does not export {}",
+                    extension.package_name,
+                    fn_name
+                );
+                return Ok(ExtensionCommandResponse::Empty);
+            }
+            plugin.call::<_, Value>(fn_name, args)
+        };
+        let res = match res {
+            Ok(res) => {
+                tracker.record_success(&extension.package_name);
+                res
+            }
+            Err(e) => {
+                // Errors returned by the extension itself are answers, not crashes
+                if is_plugin_fault(&e) {
+                    tracker.record_failure(&extension.package_name, e.to_string());
+                }
+                return Err(MoosyncError::String(format!(
+                    "Extension {} responsed with error: {:?}",
+                    extension.package_name, e
+                )));
+            }
+        };
+
+        let mut parsed_response = command.parse_response(res.clone()).map_err(|e| {
+            MoosyncError::String(format!(
+                "Failed to parse response from extension {} {:?}: {:?}",
+                extension.package_name, e, res
+            ))
+        })?;
+        Self::sanitize_response(&mut parsed_response, extension.package_name.clone());
+        Ok(parsed_response)
+    }
+
+    /// Marks which extension produced the entries of a response
+    fn tag_response(response: &mut ExtensionCommandResponse, package_name: &str) {
+        let ExtensionCommandResponse::ExtraExtensionEvent(resp) = response else {
+            return;
+        };
+
+        // Same key the host registers extension providers under
+        let provider = format!("extension:{}", package_name);
+        let tag_song = |song: &mut Song| {
+            if song.song.provider_extension.is_none() {
+                song.song.provider_extension = Some(provider.clone());
+            }
+        };
+        let tag_playlist = |playlist: &mut QueryablePlaylist| {
+            if playlist.extension.is_none() {
+                playlist.extension = Some(provider.clone());
+            }
+        };
+        match resp.as_mut() {
+            ExtensionExtraEventResponse::RequestedSearchResult(result) => {
+                result.songs.iter_mut().for_each(tag_song);
+                result.playlists.iter_mut().for_each(tag_playlist);
+            }
+            ExtensionExtraEventResponse::RequestedRecommendations(result) => {
+                result.songs.iter_mut().for_each(tag_song)
+            }
+            ExtensionExtraEventResponse::RequestedPlaylists(result) => {
+                result.playlists.iter_mut().for_each(tag_playlist)
+            }
+            ExtensionExtraEventResponse::RequestedPlaylistSongs(result)
+            | ExtensionExtraEventResponse::RequestedArtistSongs(result)
+            | ExtensionExtraEventResponse::RequestedAlbumSongs(result) => {
+                result.songs.iter_mut().for_each(tag_song)
+            }
+            ExtensionExtraEventResponse::RequestedSongFromURL(result)
+            | ExtensionExtraEventResponse::RequestedSongFromId(result) => {
+                result.song.iter_mut().for_each(tag_song)
+            }
+            ExtensionExtraEventResponse::RequestedPlaylistFromURL(result) => {
+                result.playlist.iter_mut().for_each(tag_playlist);
+                result.songs.iter_mut().flatten().for_each(tag_song);
+            }
+            ExtensionExtraEventResponse::RequestedSongContextMenu(items)
+            | ExtensionExtraEventResponse::RequestedPlaylistContextMenu(items) => {
+                for item in items {
+                    item.package_name
+                        .get_or_insert_with(|| package_name.to_string());
+                }
+            }
+            // A plain string can't say where it came from
+            ExtensionExtraEventResponse::RequestedLyrics(lyrics) => {
+                **resp = ExtensionExtraEventResponse::RequestedLyricsFrom(LyricsReturnType {
+                    lyrics: std::mem::take(lyrics),
+                    package_name: package_name.to_string(),
+                });
+            }
+            _ => {}
+        }
+    }
+
+    fn merge_extra_event(
+        merged: &mut ExtensionExtraEventResponse,
+        response: ExtensionExtraEventResponse,
+    ) {
+        match (merged, response) {
+            (
+                ExtensionExtraEventResponse::RequestedSearchResult(merged),
+                ExtensionExtraEventResponse::RequestedSearchResult(response),
+            ) => {
+                merged.songs.extend(response.songs);
+                merged.playlists.extend(response.playlists);
+                merged.artists.extend(response.artists);
+                merged.albums.extend(response.albums);
+            }
+            (
+                ExtensionExtraEventResponse::RequestedRecommendations(merged),
+                ExtensionExtraEventResponse::RequestedRecommendations(response),
+            ) => merged.songs.extend(response.songs),
+            (
+                ExtensionExtraEventResponse::RequestedPlaylists(merged),
+                ExtensionExtraEventResponse::RequestedPlaylists(response),
+            ) => merged.playlists.extend(response.playlists),
+            (
+                ExtensionExtraEventResponse::RequestedSongContextMenu(merged),
+                ExtensionExtraEventResponse::RequestedSongContextMenu(response),
+            )
+            | (
+                ExtensionExtraEventResponse::RequestedPlaylistContextMenu(merged),
+                ExtensionExtraEventResponse::RequestedPlaylistContextMenu(response),
+            ) => {
+                for item in response {
+                    if !merged.contains(&item) {
+                        merged.push(item);
+                    }
+                }
+            }
+            (
+                ExtensionExtraEventResponse::RequestedLyricsFrom(merged),
+                ExtensionExtraEventResponse::RequestedLyricsFrom(response),
+            ) => {
+                if merged.lyrics.trim().is_empty() {
+                    *merged = response;
+                }
+            }
+            // Nothing worth merging, the first reply wins
+            _ => {}
+        }
+    }
+
+    /// Combines the replies of several extensions into one response.
+    /// Replies are merged in package name order so the result doesn't depend on timing.
+    #[tracing::instrument(level = "debug", skip(responses))]
+    fn merge_responses(
+        mut responses: Vec<(String, ExtensionCommandResponse)>,
+    ) -> ExtensionCommandResponse {
+        responses.sort_by(|a, b| a.0.cmp(&b.0));
+
+        let mut merged = ExtensionCommandResponse::Empty;
+        for (package_name, mut response) in responses {
+            Self::tag_response(&mut response, &package_name);
+            merged = match (merged, response) {
+                (merged, ExtensionCommandResponse::Empty) => merged,
+                (ExtensionCommandResponse::Empty, response) => response,
+                (
+                    ExtensionCommandResponse::GetProviderScopes(mut merged),
+                    ExtensionCommandResponse::GetProviderScopes(response),
+                ) => {
+                    for scope in response {
+                        if !merged.contains(&scope) {
+                            merged.push(scope);
+                        }
+                    }
+                    ExtensionCommandResponse::GetProviderScopes(merged)
+                }
+                (
+                    ExtensionCommandResponse::GetAccounts(mut merged),
+                    ExtensionCommandResponse::GetAccounts(response),
+                ) => {
+                    merged.extend(response);
+                    ExtensionCommandResponse::GetAccounts(merged)
+                }
+                (
+                    ExtensionCommandResponse::ExtraExtensionEvent(mut merged),
+                    ExtensionCommandResponse::ExtraExtensionEvent(response),
+                ) => {
+                    Self::merge_extra_event(merged.as_mut(), *response);
+                    ExtensionCommandResponse::ExtraExtensionEvent(merged)
+                }
+                (merged, _) => merged,
+            };
+        }
+        merged
+    }
+
+    /// Sends the command to every running extension and replies with the merged
+    /// responses of those which answered in time
+    #[tracing::instrument(level = "debug", skip(self, extensions, args, tx))]
+    fn execute_broadcast(
+        &self,
+        command: &ExtensionCommand,
+        extensions: Vec<Extension>,
+        fn_name: &'static str,
+        args: Vec<u8>,
+        tx: MainCommandReplySender,
+    ) {
+        let extensions = extensions
+            .into_iter()
+            .filter(|e| e.active && e.plugin.is_some())
+            .collect::<Vec<_>>();
+        let expected = extensions.len();
+
+        let (resp_tx, resp_rx) = std::sync::mpsc::channel();
+        for extension in extensions {
+            let command = command.clone();
+            let args = args.clone();
+            let resp_tx = resp_tx.clone();
+            let tracker = self.failure_tracker();
+            thread::spawn(move || {
+                let resp = Self::call_extension(&extension, &command, fn_name, args, &tracker)
+                    .unwrap_or_else(|e| {
+                        tracing::error!("{:?}", e);
+                        ExtensionCommandResponse::Empty
+                    });
+                let _ = resp_tx.send((extension.package_name, resp));
+            });
+        }
+        drop(resp_tx);
+
+        thread::spawn(move || {
+            let deadline = Instant::now() + EXTENSION_RESPONSE_TIMEOUT;
+            let mut responses = Vec::with_capacity(expected);
+            while responses.len() < expected {
+                let timeout = deadline.saturating_duration_since(Instant::now());
+                match resp_rx.recv_timeout(timeout) {
+                    Ok(resp) => responses.push(resp),
+                    Err(_) => {
+                        tracing::warn!(
+                            "Only {} of {} extensions replied to {}",
+                            responses.len(),
+                            expected,
+                            fn_name
+                        );
+                        break;
+                    }
+                }
+            }
+            let _ = tx.send(Self::merge_responses(responses));
+        });
+    }
+
     #[tracing::instrument(level = "debug", skip(self))]
     async fn execute_command(
         &mut self,
@@ -665,71 +1517,55 @@ impl ExtensionHandlerInner {
         tx: MainCommandReplySender,
     ) -> MoosyncResult<()> {
         let (package_name, fn_name, args) = command.to_plugin_call();
-        let plugins = self.get_extensions(package_name.clone()).await;
+        let mut plugins = self.get_extensions(package_name.clone()).await;
+
+        // Notifications only go to the running extensions subscribed to them
+        if let ExtensionCommand::ExtraExtensionEvent(event) = command {
+            if let Some(kind) = event.data.subscription_kind() {
+                plugins.retain(|e| e.active && self.subscriptions.accepts(&e.package_name, kind));
+            }
+        }
 
-        let plugins_len = plugins.len();
+        // Replies to requests without a package name are always merged, so their
+        // entries say which extension they came from
+        if package_name.is_empty() || plugins.len() > 1 {
+            self.execute_broadcast(command, plugins, fn_name, args, tx);
+            return Ok(());
+        }
 
         for extension in plugins {
             let command = command.clone();
             let args = args.clone();
-            let extension = extension.clone();
-            let package_name = package_name.clone();
             let tx = tx.clone();
+            let tracker = self.failure_tracker();
             thread::spawn(move || {
                 if !extension.active {
                     return;
                 }
 
-                if let Some(plugin) = &extension.plugin {
-                    let mut plugin = block_on(plugin.lock());
-                    let res = plugin.call::<_, Value>(fn_name, args.clone());
-                    match res {
-                        Ok(res) => match command.parse_response(res.clone()) {
-                            Ok(mut parsed_response) => {
-                                Self::sanitize_response(&mut parsed_response, package_name.clone());
-                                if plugins_len == 1 {
-                                    let _ = tx.send(parsed_response);
-                                }
-                            }
-                            Err(e) => {
-                                if plugins_len == 1 {
-                                    let _ = tx.send(ExtensionCommandResponse::Empty);
-                                    tracing::error!(
-                                        "Failed to parse response from extension {} {:?}: {:?}",
-                                        package_name,
-                                        e,
-                                        res
-                                    );
-                                }
-                            }
-                        },
-                        Err(e) => {
-                            if plugins_len == 1 {
-                                let _ = tx.send(ExtensionCommandResponse::Empty);
-                                tracing::error!(
-                                    "Extension {} responsed with error: {:?}",
-                                    extension.package_name,
-                                    e
-                                );
-                            }
-                        }
-                    }
-                }
+                let resp = Self::call_extension(&extension, &command, fn_name, args, &tracker)
+                    .unwrap_or_else(|e| {
+                        tracing::error!("{:?}", e);
+                        ExtensionCommandResponse::Empty
+                    });
+                let _ = tx.send(resp);
             });
         }
-
-        if plugins_len > 1 {
-            let _ = tx.send(ExtensionCommandResponse::Empty);
-        }
         Ok(())
     }
 
     #[tracing::instrument(level = "debug", skip(self))]
     async fn remove_extension(&mut self, package_name: &String) {
+        self.stop_extensions(package_name.clone()).await;
+        self.granted.remove(package_name);
         let mut extensions_map = self.extensions_map.lock().await;
         extensions_map.remove(package_name);
     }
 
+    pub(crate) fn get_subscriptions(&self) -> Vec<ExtensionSubscriptionInfo> {
+        self.subscriptions.get()
+    }
+
     #[tracing::instrument(level = "debug", skip(self))]
     pub async fn handle_extension_command(
         &mut self,
@@ -766,13 +1602,20 @@ impl ExtensionHandlerInner {
                     .first()
                     .map(|e| e.icon.clone()),
             ),
-            RunnerCommand::ToggleExtensionStatus(_) => todo!(),
+            RunnerCommand::ToggleExtensionStatus(p) => {
+                RunnerCommandResp::ExtensionStatus(self.toggle_extension(p.package_name).await?)
+            }
             RunnerCommand::RemoveExtension(p) => {
                 self.remove_extension(&p.package_name).await;
                 RunnerCommandResp::Empty()
             }
+            RunnerCommand::ReloadExtension(p) => {
+                self.reload_extension(p.package_name).await?;
+                RunnerCommandResp::Empty()
+            }
             RunnerCommand::StopProcess => {
-                todo!()
+                self.stop_extensions(String::new()).await;
+                RunnerCommandResp::Empty()
             }
             RunnerCommand::GetDisplayName(p) => RunnerCommandResp::ExtensionIcon(
                 self.get_extensions(p.package_name)
@@ -796,7 +1639,10 @@ impl ExtensionHandlerInner {
         tracing::trace!("Inside reply {:?} {:?}", reply_map, resp);
         if let Some(tx) = reply_map.get(&resp.channel) {
             tracing::trace!("Handling as reply");
-            tx.send(resp.clone()).unwrap();
+            // The extension may have stopped waiting after a timeout
+            if let Err(e) = tx.send(resp.clone()) {
+                tracing::warn!("Reply arrived after the request timed out: {:?}", e);
+            }
             return Ok(());
         }
 
@@ -0,0 +1,598 @@
+// Moosync
+// Copyright (C) 2024, 2025  Moosync <support@moosync.app>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    path::PathBuf,
+    time::Duration,
+};
+
+use extism::Error;
+use futures::executor::block_on;
+use tokio::sync::mpsc::unbounded_channel;
+use types::{
+    entities::QueryablePlaylist,
+    extensions::{
+        ExtensionCommandResponse, ExtensionExtraEventResponse, ExtensionManifest, MainCommand,
+    },
+    songs::Song,
+    ui::extensions::{
+        extension_preference_key, ContextMenuReturnType, ExtensionCapability, ExtensionPermissions,
+        ExtensionProviderScope, PlaylistReturnType, PreferenceData, SearchReturnType,
+        SongsWithPageTokenReturnType,
+    },
+};
+
+use super::{is_plugin_fault, scope_preference, Extension, ExtensionHandlerInner, FailureTracker};
+use crate::logs::ExtensionLogs;
+
+fn new_inner() -> ExtensionHandlerInner {
+    let (ext_command_tx, _) = unbounded_channel();
+    let (disabled_tx, _) = unbounded_channel();
+    let path = PathBuf::from("moosync-test-extensions");
+    ExtensionHandlerInner::new(
+        &path,
+        &path,
+        ext_command_tx,
+        disabled_tx,
+        ExtensionLogs::default(),
+    )
+}
+
+// Test that a shutdown wakes up main commands waiting on a reply
+#[test]
+fn test_begin_shutdown_drops_pending_replies() {
+    let inner = new_inner();
+    let (tx, rx) = std::sync::mpsc::channel();
+    inner
+        .reply_map
+        .lock()
+        .unwrap()
+        .insert("channel".to_string(), tx);
+
+    assert!(!inner.is_shutting_down());
+    inner.begin_shutdown();
+
+    assert!(inner.is_shutting_down());
+    assert!(inner.reply_map.lock().unwrap().is_empty());
+    assert_eq!(
+        rx.recv_timeout(Duration::from_secs(1)),
+        Err(std::sync::mpsc::RecvTimeoutError::Disconnected)
+    );
+}
+
+fn song(title: &str) -> Song {
+    let mut song = Song::default();
+    song.song.title = Some(title.to_string());
+    song
+}
+
+fn playlist(name: &str) -> QueryablePlaylist {
+    QueryablePlaylist {
+        playlist_name: name.to_string(),
+        ..Default::default()
+    }
+}
+
+fn menu_item(action_id: &str) -> ContextMenuReturnType {
+    ContextMenuReturnType {
+        name: action_id.to_string(),
+        icon: String::new(),
+        action_id: action_id.to_string(),
+        package_name: None,
+    }
+}
+
+fn search_result(songs: Vec<Song>, playlists: Vec<QueryablePlaylist>) -> ExtensionCommandResponse {
+    ExtensionCommandResponse::ExtraExtensionEvent(Box::new(
+        ExtensionExtraEventResponse::RequestedSearchResult(SearchReturnType {
+            songs,
+            playlists,
+            artists: vec![],
+            albums: vec![],
+        }),
+    ))
+}
+
+fn extra_event(response: ExtensionCommandResponse) -> ExtensionExtraEventResponse {
+    match response {
+        ExtensionCommandResponse::ExtraExtensionEvent(resp) => *resp,
+        _ => panic!("Expected an extra event response"),
+    }
+}
+
+// Test that search results are concatenated in package name order
+#[test]
+fn test_merge_responses_search() {
+    let merged = ExtensionHandlerInner::merge_responses(vec![
+        ("b.ext".to_string(), search_result(vec![song("b")], vec![])),
+        (
+            "a.ext".to_string(),
+            search_result(vec![song("a")], vec![playlist("a")]),
+        ),
+        ("c.ext".to_string(), ExtensionCommandResponse::Empty),
+    ]);
+
+    let ExtensionExtraEventResponse::RequestedSearchResult(result) = extra_event(merged) else {
+        panic!("Expected a search result");
+    };
+    let titles = result
+        .songs
+        .iter()
+        .map(|s| s.song.title.clone().unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(titles, vec!["a", "b"]);
+    assert_eq!(result.playlists.len(), 1);
+}
+
+// Test that only empty replies merge into an empty response
+#[test]
+fn test_merge_responses_empty() {
+    let merged = ExtensionHandlerInner::merge_responses(vec![
+        ("a.ext".to_string(), ExtensionCommandResponse::Empty),
+        ("b.ext".to_string(), ExtensionCommandResponse::Empty),
+    ]);
+    assert!(matches!(merged, ExtensionCommandResponse::Empty));
+    assert!(matches!(
+        ExtensionHandlerInner::merge_responses(vec![]),
+        ExtensionCommandResponse::Empty
+    ));
+}
+
+// Test that provider scopes are unioned
+#[test]
+fn test_merge_responses_provider_scopes() {
+    let merged = ExtensionHandlerInner::merge_responses(vec![
+        (
+            "a.ext".to_string(),
+            ExtensionCommandResponse::GetProviderScopes(vec![
+                ExtensionProviderScope::Search,
+                ExtensionProviderScope::Lyrics,
+            ]),
+        ),
+        (
+            "b.ext".to_string(),
+            ExtensionCommandResponse::GetProviderScopes(vec![ExtensionProviderScope::Search]),
+        ),
+    ]);
+
+    let ExtensionCommandResponse::GetProviderScopes(scopes) = merged else {
+        panic!("Expected provider scopes");
+    };
+    assert_eq!(
+        scopes,
+        vec![
+            ExtensionProviderScope::Search,
+            ExtensionProviderScope::Lyrics
+        ]
+    );
+}
+
+// Test that the first non-empty lyrics win and keep their source
+#[test]
+fn test_merge_responses_lyrics() {
+    let lyrics = |text: &str| {
+        ExtensionCommandResponse::ExtraExtensionEvent(Box::new(
+            ExtensionExtraEventResponse::RequestedLyrics(text.to_string()),
+        ))
+    };
+    let merged = ExtensionHandlerInner::merge_responses(vec![
+        ("c.ext".to_string(), lyrics("from c")),
+        ("a.ext".to_string(), lyrics("  ")),
+        ("b.ext".to_string(), lyrics("from b")),
+    ]);
+
+    let ExtensionExtraEventResponse::RequestedLyricsFrom(result) = extra_event(merged) else {
+        panic!("Expected lyrics");
+    };
+    assert_eq!(result.lyrics, "from b");
+    assert_eq!(result.package_name, "b.ext");
+}
+
+// Test that context menu entries are unioned without duplicates
+#[test]
+fn test_merge_extra_event_context_menu() {
+    let mut merged =
+        ExtensionExtraEventResponse::RequestedSongContextMenu(vec![menu_item("a"), menu_item("b")]);
+    ExtensionHandlerInner::merge_extra_event(
+        &mut merged,
+        ExtensionExtraEventResponse::RequestedSongContextMenu(vec![menu_item("b"), menu_item("c")]),
+    );
+
+    let ExtensionExtraEventResponse::RequestedSongContextMenu(items) = merged else {
+        panic!("Expected context menu entries");
+    };
+    let actions = items
+        .iter()
+        .map(|i| i.action_id.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(actions, vec!["a", "b", "c"]);
+}
+
+// Test that replies of a different kind are ignored
+#[test]
+fn test_merge_extra_event_mismatch() {
+    let mut merged = ExtensionExtraEventResponse::RequestedPlaylists(PlaylistReturnType {
+        playlists: vec![playlist("a")],
+    });
+    ExtensionHandlerInner::merge_extra_event(
+        &mut merged,
+        ExtensionExtraEventResponse::RequestedLyrics("lyrics".to_string()),
+    );
+    ExtensionHandlerInner::merge_extra_event(
+        &mut merged,
+        ExtensionExtraEventResponse::RequestedPlaylists(PlaylistReturnType {
+            playlists: vec![playlist("b")],
+        }),
+    );
+
+    let ExtensionExtraEventResponse::RequestedPlaylists(result) = merged else {
+        panic!("Expected playlists");
+    };
+    assert_eq!(result.playlists.len(), 2);
+}
+
+// Test that songs and playlists are tagged with the provider of the extension
+#[test]
+fn test_tag_response_entities() {
+    let mut tagged_song = song("tagged");
+    tagged_song.song.provider_extension = Some("extension:other".to_string());
+    let mut response = search_result(vec![song("a"), tagged_song], vec![playlist("a")]);
+
+    ExtensionHandlerInner::tag_response(&mut response, "a.ext");
+
+    let ExtensionExtraEventResponse::RequestedSearchResult(result) = extra_event(response) else {
+        panic!("Expected a search result");
+    };
+    assert_eq!(
+        result.songs[0].song.provider_extension.as_deref(),
+        Some("extension:a.ext")
+    );
+    assert_eq!(
+        result.songs[1].song.provider_extension.as_deref(),
+        Some("extension:other")
+    );
+    assert_eq!(
+        result.playlists[0].extension.as_deref(),
+        Some("extension:a.ext")
+    );
+}
+
+// Test that playlists and artist songs are tagged too
+#[test]
+fn test_tag_response_playlists_and_artist_songs() {
+    let mut playlists = ExtensionCommandResponse::ExtraExtensionEvent(Box::new(
+        ExtensionExtraEventResponse::RequestedPlaylists(PlaylistReturnType {
+            playlists: vec![playlist("a")],
+        }),
+    ));
+    ExtensionHandlerInner::tag_response(&mut playlists, "a.ext");
+    let ExtensionExtraEventResponse::RequestedPlaylists(result) = extra_event(playlists) else {
+        panic!("Expected playlists");
+    };
+    assert_eq!(
+        result.playlists[0].extension.as_deref(),
+        Some("extension:a.ext")
+    );
+
+    let mut songs = ExtensionCommandResponse::ExtraExtensionEvent(Box::new(
+        ExtensionExtraEventResponse::RequestedArtistSongs(SongsWithPageTokenReturnType {
+            songs: vec![song("a")],
+            next_page_token: None,
+        }),
+    ));
+    ExtensionHandlerInner::tag_response(&mut songs, "a.ext");
+    let ExtensionExtraEventResponse::RequestedArtistSongs(result) = extra_event(songs) else {
+        panic!("Expected artist songs");
+    };
+    assert_eq!(
+        result.songs[0].song.provider_extension.as_deref(),
+        Some("extension:a.ext")
+    );
+}
+
+// Test that context menu entries keep the extension which should handle them
+#[test]
+fn test_tag_response_context_menu() {
+    let mut other = menu_item("b");
+    other.package_name = Some("other.ext".to_string());
+    let mut response = ExtensionCommandResponse::ExtraExtensionEvent(Box::new(
+        ExtensionExtraEventResponse::RequestedPlaylistContextMenu(vec![menu_item("a"), other]),
+    ));
+
+    ExtensionHandlerInner::tag_response(&mut response, "a.ext");
+
+    let ExtensionExtraEventResponse::RequestedPlaylistContextMenu(items) = extra_event(response)
+    else {
+        panic!("Expected context menu entries");
+    };
+    assert_eq!(items[0].package_name.as_deref(), Some("a.ext"));
+    assert_eq!(items[1].package_name.as_deref(), Some("other.ext"));
+}
+
+fn new_tracker(inner: &ExtensionHandlerInner, package_name: &str) -> FailureTracker {
+    block_on(inner.extensions_map.lock()).insert(
+        package_name.to_string(),
+        Extension {
+            plugin: None,
+            package_name: package_name.to_string(),
+            name: package_name.to_string(),
+            icon: String::new(),
+            author: None,
+            version: "1.0.0".to_string(),
+            path: PathBuf::new(),
+            manifest_path: PathBuf::new(),
+            active: true,
+            enabled: true,
+            failures: 0,
+            disabled_reason: None,
+            pending_permissions: None,
+            preferences: vec![],
+            dev: false,
+        },
+    );
+    inner.failure_tracker()
+}
+
+fn extension(inner: &ExtensionHandlerInner, package_name: &str) -> Extension {
+    block_on(inner.extensions_map.lock())
+        .get(package_name)
+        .cloned()
+        .unwrap()
+}
+
+// Test that traps, timeouts and memory exhaustion count as crashes
+#[test]
+fn test_is_plugin_fault() {
+    assert!(is_plugin_fault(&Error::from(
+        wasmtime::Trap::UnreachableCodeReached
+    )));
+    assert!(is_plugin_fault(&Error::from(wasmtime::Trap::Interrupt)));
+    assert!(is_plugin_fault(&Error::msg("timeout")));
+    assert!(is_plugin_fault(
+        &Error::msg("Out of memory").context("Failed to allocate input")
+    ));
+}
+
+// Test that errors returned by the extension don't count as crashes
+#[test]
+fn test_is_plugin_fault_extension_error() {
+    assert!(!is_plugin_fault(&Error::msg("Song not found")));
+    assert!(!is_plugin_fault(&Error::msg(
+        "Request timeout while fetching lyrics"
+    )));
+}
+
+// Test that an extension is disabled once it failed too often in a row
+#[test]
+fn test_failure_tracker_disables() {
+    let mut inner = new_inner();
+    inner.limits.max_failures = 2;
+    let tracker = new_tracker(&inner, "crashy.ext");
+
+    tracker.record_failure("crashy.ext", "trap".to_string());
+    let ext = extension(&inner, "crashy.ext");
+    assert_eq!(ext.failures, 1);
+    assert!(ext.enabled);
+
+    tracker.record_failure("crashy.ext", "trap".to_string());
+    let ext = extension(&inner, "crashy.ext");
+    assert!(!ext.enabled);
+    assert!(!ext.active);
+    assert_eq!(ext.disabled_reason.as_deref(), Some("trap"));
+}
+
+// Test that a successful call resets the failure count
+#[test]
+fn test_failure_tracker_success_resets() {
+    let mut inner = new_inner();
+    inner.limits.max_failures = 2;
+    let tracker = new_tracker(&inner, "flaky.ext");
+
+    tracker.record_failure("flaky.ext", "trap".to_string());
+    tracker.record_success("flaky.ext");
+    tracker.record_failure("flaky.ext", "trap".to_string());
+
+    let ext = extension(&inner, "flaky.ext");
+    assert_eq!(ext.failures, 1);
+    assert!(ext.enabled);
+}
+
+// Test that a zero limit never disables extensions
+#[test]
+fn test_failure_tracker_unlimited() {
+    let mut inner = new_inner();
+    inner.limits.max_failures = 0;
+    let tracker = new_tracker(&inner, "crashy.ext");
+
+    for _ in 0..10 {
+        tracker.record_failure("crashy.ext", "trap".to_string());
+    }
+    assert!(extension(&inner, "crashy.ext").enabled);
+}
+
+fn manifest(permissions: serde_json::Value) -> ExtensionManifest {
+    let mut manifest = serde_json::json!({
+        "moosyncExtension": true,
+        "displayName": "Test",
+        "extensionEntry": "ext.wasm",
+        "name": "moosync.test",
+        "version": "1.0.0",
+        "icon": "",
+    });
+    if !permissions.is_null() {
+        manifest["permissions"] = permissions;
+    }
+    serde_json::from_value(manifest).unwrap()
+}
+
+// Test that granted permissions cover only what they include
+#[test]
+fn test_permissions_covers() {
+    let granted = ExtensionPermissions {
+        hosts: vec!["example.com".to_string()],
+        paths: BTreeMap::from([("/music".to_string(), PathBuf::from("/music"))]),
+        capabilities: vec![ExtensionCapability::LibraryRead],
+    };
+
+    assert!(granted.covers(&ExtensionPermissions::default()));
+    assert!(granted.covers(&granted.clone()));
+    assert!(!granted.covers(&ExtensionPermissions {
+        hosts: vec!["other.com".to_string()],
+        ..Default::default()
+    }));
+    assert!(!granted.covers(&ExtensionPermissions {
+        paths: BTreeMap::from([("/music".to_string(), PathBuf::from("/elsewhere"))]),
+        ..Default::default()
+    }));
+    assert!(!granted.covers(&ExtensionPermissions {
+        capabilities: vec![ExtensionCapability::LibraryWrite],
+        ..Default::default()
+    }));
+}
+
+// Test that old manifests without capabilities ask for all of them
+#[test]
+fn test_requested_permissions_defaults() {
+    let requested = manifest(serde_json::Value::Null).requested_permissions();
+    assert_eq!(requested.capabilities, ExtensionCapability::ALL.to_vec());
+    assert!(requested.hosts.is_empty());
+
+    let requested =
+        manifest(serde_json::json!({ "hosts": [], "paths": {} })).requested_permissions();
+    assert_eq!(requested.capabilities, ExtensionCapability::ALL.to_vec());
+}
+
+// Test that declared capabilities are sorted and deduplicated
+#[test]
+fn test_requested_permissions_declared() {
+    let requested = manifest(serde_json::json!({
+        "hosts": ["example.com"],
+        "paths": {},
+        "capabilities": ["preferences", "libraryRead", "preferences"],
+    }))
+    .requested_permissions();
+
+    assert_eq!(
+        requested.capabilities,
+        vec![
+            ExtensionCapability::LibraryRead,
+            ExtensionCapability::Preferences
+        ]
+    );
+    assert_eq!(requested.hosts, vec!["example.com".to_string()]);
+}
+
+// Test which commands need which capability
+#[test]
+fn test_required_capability() {
+    assert_eq!(
+        MainCommand::GetSong(Default::default()).required_capability(),
+        Some(ExtensionCapability::LibraryRead)
+    );
+    assert_eq!(
+        MainCommand::AddSongs(vec![]).required_capability(),
+        Some(ExtensionCapability::LibraryWrite)
+    );
+    assert_eq!(
+        MainCommand::SetPreference(Default::default()).required_capability(),
+        Some(ExtensionCapability::Preferences)
+    );
+    assert_eq!(
+        MainCommand::GetSecure(Default::default()).required_capability(),
+        Some(ExtensionCapability::SecureStorage)
+    );
+    assert_eq!(
+        MainCommand::OpenExternalUrl(String::new()).required_capability(),
+        Some(ExtensionCapability::OpenUrl)
+    );
+    assert_eq!(MainCommand::GetCurrentSong().required_capability(), None);
+    assert_eq!(MainCommand::Subscribe(vec![]).required_capability(), None);
+}
+
+// Test that only approved permissions let an extension start
+#[test]
+fn test_missing_permissions() {
+    let mut inner = new_inner();
+    let manifest = manifest(serde_json::json!({
+        "hosts": [],
+        "paths": {},
+        "capabilities": ["libraryRead"],
+    }));
+
+    inner.set_granted_permissions(Some(HashMap::new()));
+    assert!(inner.missing_permissions(&manifest).is_some());
+
+    inner.set_granted_permissions(Some(HashMap::from([(
+        "moosync.test".to_string(),
+        ExtensionPermissions {
+            capabilities: ExtensionCapability::ALL.to_vec(),
+            ..Default::default()
+        },
+    )])));
+    assert!(inner.missing_permissions(&manifest).is_none());
+
+    // Extensions installed before grants were stored are trusted once
+    inner.set_granted_permissions(None);
+    assert!(inner.missing_permissions(&manifest).is_none());
+    assert!(inner.get_granted_permissions().contains_key("moosync.test"));
+}
+
+fn scoped_key(command: MainCommand, package_name: &str) -> String {
+    let mut command = command;
+    scope_preference(&mut command, package_name);
+    match command {
+        MainCommand::GetPreference(data)
+        | MainCommand::SetPreference(data)
+        | MainCommand::GetSecure(data)
+        | MainCommand::SetSecure(data) => data.key,
+        _ => unreachable!(),
+    }
+}
+
+// Test that one extension can't reach the preferences or secure values of another
+#[test]
+fn test_scope_preference_isolates_extensions() {
+    let data = || PreferenceData {
+        key: "token".into(),
+        ..Default::default()
+    };
+
+    let stored = scoped_key(MainCommand::SetSecure(data()), "moosync.first");
+    assert_eq!(stored, extension_preference_key("moosync.first", "token"));
+    assert_eq!(
+        scoped_key(MainCommand::GetSecure(data()), "moosync.first"),
+        stored
+    );
+    assert_ne!(
+        scoped_key(MainCommand::GetSecure(data()), "moosync.second"),
+        stored
+    );
+    assert_ne!(
+        scoped_key(MainCommand::GetPreference(data()), "moosync.second"),
+        stored
+    );
+
+    // Asking for the full key of another extension stays inside its own scope
+    let foreign = PreferenceData {
+        key: stored.clone(),
+        ..Default::default()
+    };
+    assert_ne!(
+        scoped_key(MainCommand::GetSecure(foreign), "moosync.second"),
+        stored
+    );
+}
@@ -15,23 +15,33 @@
 // along with this program.  If not, see <http://www.gnu.org/licenses/>.
 
 use std::{
-    collections::HashMap,
+    collections::{HashMap, HashSet},
     fs::{self, File},
     io::Write,
-    path::PathBuf,
+    path::{Path, PathBuf},
     str::FromStr,
-    sync::Arc,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc, Arc,
+    },
     thread,
+    time::Duration,
 };
 
+use crypto::{digest::Digest, sha2::Sha256};
 use ext_runner::{ExtCommandReceiver, ExtensionHandlerInner};
 use fs_extra::dir::CopyOptions;
 use futures::lock::Mutex;
 use futures::{executor::block_on, StreamExt};
+use notify::{Event, RecursiveMode, Watcher};
+use semver::Version;
 use serde_json::Value;
 use tokio::{
     select,
-    sync::mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender},
+    sync::{
+        mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender},
+        oneshot,
+    },
 };
 use types::{
     errors::{MoosyncError, Result},
@@ -40,13 +50,25 @@ use types::{
         MainCommand, MainCommandResponse, RunnerCommand, RunnerCommandResp,
     },
     ui::extensions::{
-        AccountLoginArgs, ExtensionAccountDetail, ExtensionDetail, ExtensionExtraEventArgs,
-        ExtensionProviderScope, FetchedExtensionManifest, PackageNameArgs,
+        AccountLoginArgs, ExtensionAccountDetail, ExtensionDetail, ExtensionDisabledEvent,
+        ExtensionEventKind, ExtensionExtraEvent, ExtensionExtraEventArgs, ExtensionLogEntry,
+        ExtensionPermissions, ExtensionProviderScope, ExtensionSubscriptionInfo,
+        FetchedExtensionManifest, PackageNameArgs,
     },
 };
 use zip_extensions::zip_extract;
 
 mod ext_runner;
+mod logs;
+mod settings;
+mod signing;
+mod subscriptions;
+
+#[cfg(test)]
+mod test;
+
+pub use ext_runner::ExtensionLimits;
+pub use logs::ExtensionLogs;
 
 pub type UiRequestSender = UnboundedSender<GenericExtensionHostRequest<MainCommand>>;
 pub type UiRequestReceiver = UnboundedReceiver<GenericExtensionHostRequest<MainCommand>>;
@@ -54,36 +76,80 @@ pub type UiRequestReceiver = UnboundedReceiver<GenericExtensionHostRequest<MainC
 pub type UiReplySender = UnboundedSender<GenericExtensionHostRequest<MainCommandResponse>>;
 pub type UiReplyReceiver = UnboundedReceiver<GenericExtensionHostRequest<MainCommandResponse>>;
 
+pub type ExtensionDisabledReceiver = UnboundedReceiver<ExtensionDisabledEvent>;
+
+/// How long extensions get to run onStopped when the app exits
+const STOP_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// How long an updated extension gets to start before it is rolled back
+const LOAD_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// How long a changed dev extension has to stay untouched before it is reloaded
+const DEV_RELOAD_DEBOUNCE: Duration = Duration::from_millis(500);
+
+/// How long bursts of high frequency events are collected before the latest one is sent
+const EVENT_BATCH_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Resolves the directory of a path so watcher events compare equal to the
+/// entries. The file itself may not exist while a build replaces it
+fn canonical_entry(path: &Path) -> PathBuf {
+    match (path.parent(), path.file_name()) {
+        (Some(dir), Some(name)) => fs::canonicalize(dir)
+            .map(|dir| dir.join(name))
+            .unwrap_or_else(|_| path.to_path_buf()),
+        _ => path.to_path_buf(),
+    }
+}
+
 pub struct ExtensionHandler {
     pub extensions_dir: PathBuf,
     pub tmp_dir: PathBuf,
     inner: Arc<Mutex<ExtensionHandlerInner>>,
+    updates: Mutex<Vec<FetchedExtensionManifest>>,
+    allow_unsigned: AtomicBool,
+    logs: ExtensionLogs,
+    dev_watcher: std::sync::Mutex<Option<Box<dyn Watcher + Send>>>,
+    pending_events: Arc<std::sync::Mutex<HashMap<ExtensionEventKind, ExtensionExtraEvent>>>,
 }
 
 impl ExtensionHandler {
-    #[tracing::instrument(level = "debug", skip(extensions_dir, tmp_dir))]
+    #[tracing::instrument(level = "debug", skip(extensions_dir, tmp_dir, logs))]
     pub fn new(
         extensions_dir: PathBuf,
         tmp_dir: PathBuf,
         cache_dir: PathBuf,
-    ) -> (Self, UiRequestReceiver, UiReplySender) {
+        logs: ExtensionLogs,
+    ) -> (
+        Self,
+        UiRequestReceiver,
+        UiReplySender,
+        ExtensionDisabledReceiver,
+    ) {
         let (ext_command_tx, ext_command_rx) = unbounded_channel();
         let (ui_request_tx, ui_request_rx) = unbounded_channel();
         let (ui_reply_tx, ui_reply_rx) = unbounded_channel();
+        let (disabled_tx, disabled_rx) = unbounded_channel();
 
         let ret = Self {
             inner: Arc::new(Mutex::new(ExtensionHandlerInner::new(
                 &extensions_dir,
                 &cache_dir,
                 ext_command_tx,
+                disabled_tx,
+                logs.clone(),
             ))),
             extensions_dir,
             tmp_dir,
+            updates: Default::default(),
+            allow_unsigned: Default::default(),
+            logs,
+            dev_watcher: Default::default(),
+            pending_events: Default::default(),
         };
 
         ret.listen_ext_reply_and_command(ext_command_rx, ui_request_tx, ui_reply_rx);
 
-        (ret, ui_request_rx, ui_reply_tx)
+        (ret, ui_request_rx, ui_reply_tx, disabled_rx)
     }
 
     fn listen_ext_reply_and_command(
@@ -99,17 +165,21 @@ impl ExtensionHandler {
                     tracing::trace!("handling ext commands");
                     select! {
                         resp = ext_command_rx.recv() => {
-                            if let Some(resp) = resp {
-                                tracing::trace!("Got ext command {:?}", resp);
-                                ui_request_tx.send(resp).unwrap();
+                            let Some(resp) = resp else {
+                                break;
+                            };
+                            tracing::trace!("Got ext command {:?}", resp);
+                            if ui_request_tx.send(resp).is_err() {
+                                break;
                             }
                         }
                         resp = ui_reply_rx.recv() => {
-                            if let Some(resp) = resp {
-                                tracing::trace!("Got ui reply {:?} {:?}", resp, inner);
-                                let inner = inner.lock().await;
-                                inner.handle_main_command_reply(&resp).unwrap();
-                            }
+                            let Some(resp) = resp else {
+                                break;
+                            };
+                            tracing::trace!("Got ui reply {:?} {:?}", resp, inner);
+                            let inner = inner.lock().await;
+                            inner.handle_main_command_reply(&resp).unwrap();
                         }
                     }
                 }
@@ -130,15 +200,83 @@ impl ExtensionHandler {
         Err(MoosyncError::String("No extension found".into()))
     }
 
-    #[tracing::instrument(level = "debug", skip(self, version))]
-    fn get_ext_version(&self, version: String) -> Result<u64> {
-        Ok(u64::from_str(
-            &version.split('.').collect::<Vec<&str>>().join(""),
-        )?)
+    #[tracing::instrument(level = "debug", skip(self))]
+    fn parse_version(&self, version: &str) -> Result<Version> {
+        let version = version.trim().trim_start_matches('v');
+        if let Ok(parsed) = Version::parse(version) {
+            return Ok(parsed);
+        }
+
+        // Allow versions like "1.2" which leave out the patch number
+        let (core, rest) = match version.find(['-', '+']) {
+            Some(i) => version.split_at(i),
+            None => (version, ""),
+        };
+        let mut parts = core.split('.').collect::<Vec<_>>();
+        while parts.len() < 3 {
+            parts.push("0");
+        }
+        Version::parse(&format!("{}{}", parts.join("."), rest))
+            .map_err(|e| format!("Invalid extension version {}: {}", version, e).into())
+    }
+
+    /// Developer override which lets unsigned packages be installed from files
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub fn set_allow_unsigned(&self, allow_unsigned: bool) {
+        self.allow_unsigned.store(allow_unsigned, Ordering::Relaxed);
+    }
+
+    fn keyring_path(&self) -> PathBuf {
+        self.extensions_dir.with_file_name(signing::KEYRING_FILE)
     }
 
+    /// Trusts packages signed with the given base64 ed25519 public key
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub fn trust_extension_publisher(&self, publisher: String, public_key: String) -> Result<()> {
+        signing::trust_publisher(&self.keyring_path(), &publisher, &public_key)
+    }
+
+    /// Makes sure an unpacked package is signed by a trusted publisher
+    #[tracing::instrument(level = "debug", skip(self))]
+    fn verify_package(&self, dir: &Path, local: bool) -> Result<()> {
+        let keyring = signing::load_keyring(&self.keyring_path());
+        let verification = signing::verify_package(dir, &keyring)?;
+        let allow_unsigned = local && self.allow_unsigned.load(Ordering::Relaxed);
+
+        match verification {
+            signing::Verification::Trusted(publisher) => {
+                tracing::info!("Extension package signed by {}", publisher);
+                Ok(())
+            }
+            _ if allow_unsigned => {
+                tracing::warn!(
+                    "Installing {:?} package because unsigned extensions are allowed",
+                    verification
+                );
+                Ok(())
+            }
+            signing::Verification::Untrusted => {
+                Err("Extension package is signed by a publisher which is not trusted".into())
+            }
+            signing::Verification::Unsigned => Err("Extension package is not signed".into()),
+        }
+    }
+
+    fn backup_path(&self, package_name: &str) -> PathBuf {
+        self.extensions_dir
+            .with_file_name("extensions_backup")
+            .join(package_name)
+    }
+
+    /// Returns the package name of the installed extension
     #[tracing::instrument(level = "debug", skip(self, ext_path))]
-    pub async fn install_extension(&self, ext_path: String) -> Result<()> {
+    pub async fn install_extension(&self, ext_path: String) -> Result<String> {
+        self.install_package(ext_path, true).await
+    }
+
+    /// Unpacks and verifies the package. Only local packages may skip signing
+    #[tracing::instrument(level = "debug", skip(self, ext_path))]
+    async fn install_package(&self, ext_path: String, local: bool) -> Result<String> {
         tracing::debug!("ext path {}", ext_path);
         let ext_path =
             PathBuf::from_str(&ext_path).map_err(|e| MoosyncError::String(e.to_string()))?;
@@ -149,6 +287,19 @@ impl ExtensionHandler {
 
         zip_extract(&ext_path, &tmp_dir)?;
 
+        let ret = self.install_unpacked(&tmp_dir, local).await;
+        if tmp_dir.exists() {
+            let _ = fs::remove_dir_all(&tmp_dir);
+        }
+        ret
+    }
+
+    /// Moves the unpacked package in place. An update keeps the previous version
+    /// as a backup, which is restored if anything fails
+    #[tracing::instrument(level = "debug", skip(self, tmp_dir))]
+    async fn install_unpacked(&self, tmp_dir: &Path, local: bool) -> Result<String> {
+        self.verify_package(tmp_dir, local)?;
+
         let package_manifest: ExtensionManifest =
             serde_json::from_slice(&fs::read(tmp_dir.join("package.json"))?)?;
 
@@ -158,52 +309,149 @@ impl ExtensionHandler {
             ));
         }
 
-        let ext_extract_path = self.extensions_dir.join(package_manifest.name.clone());
+        let package_name = package_manifest.name.clone();
+        let ext_extract_path = self.extensions_dir.join(package_name.clone());
+        let backup_path = self.backup_path(&package_name);
 
-        match self.get_extension_version(ext_extract_path.clone()) {
+        let is_update = match self.get_extension_version(ext_extract_path.clone()) {
             Ok(version) => {
-                let old_version = self.get_ext_version(version)?;
-                let new_version = self.get_ext_version(package_manifest.version)?;
+                let old_version = self.parse_version(&version)?;
+                let new_version = self.parse_version(&package_manifest.version)?;
 
-                if new_version > old_version {
-                    fs::remove_dir_all(ext_extract_path.clone())?;
-                } else {
+                if new_version <= old_version {
                     return Err(MoosyncError::String(format!(
                         "Duplicate extension {}. Can not install",
                         package_manifest.name
                     )));
                 }
+
+                // Keep the old version around until the new one has started
+                if backup_path.exists() {
+                    fs::remove_dir_all(&backup_path)?;
+                }
+                fs::create_dir_all(backup_path.parent().unwrap())?;
+                fs::rename(&ext_extract_path, &backup_path)?;
+                true
             }
             Err(_) => {
                 let _ = fs::remove_dir_all(ext_extract_path.clone());
+                false
+            }
+        };
+
+        if let Err(e) = Self::move_package(tmp_dir, &ext_extract_path) {
+            if is_update {
+                Self::restore_backup(&package_name, &ext_extract_path, &backup_path);
+            }
+            return Err(e);
+        }
+
+        let is_loaded = {
+            let inner = self.inner.lock().await;
+            inner.has_extension(&package_name).await
+        };
+        if !is_update || !is_loaded {
+            if let Err(e) = self.find_new_extensions().await {
+                if is_update {
+                    Self::restore_backup(&package_name, &ext_extract_path, &backup_path);
+                }
+                return Err(e);
             }
+        } else if let Err(e) = self.reload_and_wait(package_name.clone()).await {
+            tracing::error!("Update of {} failed, rolling back: {:?}", package_name, e);
+            self.rollback_extension(package_name.clone(), &ext_extract_path, &backup_path)
+                .await?;
+            return Err(format!(
+                "Failed to start the new version of {}, restored the previous version",
+                package_name
+            )
+            .into());
         }
 
+        if backup_path.exists() {
+            if let Err(e) = fs::remove_dir_all(&backup_path) {
+                tracing::warn!("Failed to remove backup of {}: {:?}", package_name, e);
+            }
+        }
+
+        Ok(package_name)
+    }
+
+    #[tracing::instrument(level = "debug")]
+    fn move_package(tmp_dir: &Path, ext_path: &Path) -> Result<()> {
         let options = CopyOptions::default().overwrite(true);
-        let parent_dir = ext_extract_path.parent().unwrap();
-        tracing::debug!(
-            "Moving items from {:?} to {:?}",
-            tmp_dir.clone(),
-            parent_dir
-        );
+        let parent_dir = ext_path.parent().unwrap();
+        tracing::debug!("Moving items from {:?} to {:?}", tmp_dir, parent_dir);
         if !parent_dir.exists() {
             tracing::debug!("Creating dir {:?}", parent_dir);
             fs::create_dir_all(parent_dir)?;
         }
-        fs_extra::move_items(&[tmp_dir.clone()], parent_dir, &options)?;
+        fs_extra::move_items(&[tmp_dir], parent_dir, &options)?;
+
+        let moved_path = parent_dir.join(tmp_dir.file_name().unwrap());
+        tracing::debug!("Renaming {:?} to {:?}", moved_path, ext_path);
+        if let Err(e) = fs::rename(&moved_path, ext_path) {
+            let _ = fs::remove_dir_all(&moved_path);
+            return Err(e.into());
+        }
+        Ok(())
+    }
+
+    /// Puts the previous version back in place of a partially installed update
+    #[tracing::instrument(level = "debug")]
+    fn restore_backup(package_name: &str, ext_path: &Path, backup_path: &Path) -> bool {
+        if ext_path.exists() {
+            if let Err(e) = fs::remove_dir_all(ext_path) {
+                tracing::error!(
+                    "Failed to remove failed update of {}: {:?}",
+                    package_name,
+                    e
+                );
+                return false;
+            }
+        }
+        if let Err(e) = fs::rename(backup_path, ext_path) {
+            tracing::error!("Failed to restore backup of {}: {:?}", package_name, e);
+            return false;
+        }
+        true
+    }
+
+    /// Reloads the extension and waits for its entry to return
+    #[tracing::instrument(level = "debug", skip(self))]
+    async fn reload_and_wait(&self, package_name: String) -> Result<()> {
+        let started = {
+            let mut inner = self.inner.lock().await;
+            inner.reload_extension(package_name.clone()).await?
+        };
 
-        tracing::debug!(
-            "Renaming {:?} to {:?}",
-            parent_dir.join(tmp_dir.file_name().unwrap()),
-            parent_dir.join(package_manifest.name.clone())
-        );
-        fs::rename(
-            parent_dir.join(tmp_dir.file_name().unwrap()),
-            parent_dir.join(package_manifest.name),
-        )?;
+        // Disabled extensions aren't started
+        let Some(started) = started else {
+            return Ok(());
+        };
+
+        match tokio::time::timeout(LOAD_TIMEOUT, started).await {
+            Ok(Ok(true)) => Ok(()),
+            Ok(_) => Err(format!("Extension {} failed to start", package_name).into()),
+            Err(_) => Err(format!("Extension {} took too long to start", package_name).into()),
+        }
+    }
 
-        self.find_new_extensions().await.unwrap();
+    #[tracing::instrument(level = "debug", skip(self))]
+    async fn rollback_extension(
+        &self,
+        package_name: String,
+        ext_path: &Path,
+        backup_path: &Path,
+    ) -> Result<()> {
+        if !Self::restore_backup(&package_name, ext_path, backup_path) {
+            return Err(
+                format!("Failed to restore the previous version of {}", package_name).into(),
+            );
+        }
 
+        let mut inner = self.inner.lock().await;
+        inner.reload_extension(package_name).await?;
         Ok(())
     }
 
@@ -232,18 +480,53 @@ impl ExtensionHandler {
 
         tracing::info!("parsed url {}. Saving at {:?}", parsed_url, file_path);
 
+        // Only the developer override lets packages without a checksum through
+        if fetched_ext.sha256.is_none() {
+            if !self.allow_unsigned.load(Ordering::Relaxed) {
+                return Err(format!(
+                    "{} was not published with a checksum",
+                    fetched_ext.package_name
+                )
+                .into());
+            }
+            tracing::warn!(
+                "Downloading {} without a checksum because unsigned extensions are allowed",
+                fetched_ext.package_name
+            );
+        }
+
         let mut stream = reqwest::get(parsed_url).await?.bytes_stream();
         let mut file = File::create(file_path.clone())?;
+        let mut hasher = Sha256::new();
 
         while let Some(chunk_result) = stream.next().await {
             let chunk = chunk_result?;
+            hasher.input(&chunk);
             file.write_all(&chunk)?;
         }
+        drop(file);
 
         tracing::info!("Wrote file");
 
-        self.install_extension(file_path.to_string_lossy().to_string())
-            .await?;
+        let digest = hasher.result_str();
+        let matches = match &fetched_ext.sha256 {
+            Some(expected) => digest.eq_ignore_ascii_case(expected.trim()),
+            None => true,
+        };
+        if !matches {
+            let _ = fs::remove_file(&file_path);
+            return Err(format!(
+                "Download of {} does not match its published checksum",
+                fetched_ext.package_name
+            )
+            .into());
+        }
+
+        let res = self
+            .install_package(file_path.to_string_lossy().to_string(), false)
+            .await;
+        let _ = fs::remove_file(&file_path);
+        res?;
 
         Ok(())
     }
@@ -264,6 +547,192 @@ impl ExtensionHandler {
         Ok(())
     }
 
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub async fn set_disabled_extensions(&self, disabled: Vec<String>) {
+        let mut inner = self.inner.lock().await;
+        inner.set_disabled_extensions(disabled);
+    }
+
+    /// Pass None if no permissions were ever stored, which grants every installed
+    /// extension what it asks for
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub async fn set_granted_permissions(
+        &self,
+        granted: Option<HashMap<String, ExtensionPermissions>>,
+    ) {
+        let mut inner = self.inner.lock().await;
+        inner.set_granted_permissions(granted);
+    }
+
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub async fn get_granted_permissions(&self) -> HashMap<String, ExtensionPermissions> {
+        let inner = self.inner.lock().await;
+        inner.get_granted_permissions()
+    }
+
+    /// Approves the permissions the extension is waiting on and starts it
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub async fn grant_extension_permissions(&self, package_name: String) -> Result<()> {
+        let mut inner = self.inner.lock().await;
+        inner.grant_permissions(package_name).await
+    }
+
+    /// Loads the unpacked extensions in these directories in place and reloads them
+    /// whenever their wasm entry changes. An empty list unloads all of them.
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub async fn set_dev_extensions(&self, dev_paths: Vec<PathBuf>) {
+        let entries = {
+            let mut inner = self.inner.lock().await;
+            inner.set_dev_paths(dev_paths).await;
+            inner.dev_entries().await
+        };
+        self.watch_dev_extensions(entries);
+    }
+
+    #[tracing::instrument(level = "debug", skip(self))]
+    fn watch_dev_extensions(&self, entries: Vec<(String, PathBuf)>) {
+        let entries = entries
+            .into_iter()
+            .map(|(package_name, entry)| (package_name, canonical_entry(&entry)))
+            .collect::<Vec<_>>();
+        let mut dev_watcher = self.dev_watcher.lock().unwrap();
+        // Dropping the old watcher also ends its reload thread
+        *dev_watcher = None;
+        if entries.is_empty() {
+            return;
+        }
+
+        let (changed_tx, changed_rx) = mpsc::channel::<PathBuf>();
+        let watcher = notify::recommended_watcher(move |ev: notify::Result<Event>| {
+            if let Ok(ev) = ev {
+                if ev.kind.is_modify() || ev.kind.is_create() {
+                    for path in ev.paths {
+                        let _ = changed_tx.send(path);
+                    }
+                }
+            }
+        });
+        let mut watcher = match watcher {
+            Ok(watcher) => watcher,
+            Err(e) => {
+                tracing::error!("Failed to watch dev extensions: {:?}", e);
+                return;
+            }
+        };
+
+        for (package_name, entry) in &entries {
+            // Build tools often replace the file, which would end a watch on the file itself
+            let Some(dir) = entry.parent() else {
+                continue;
+            };
+            if let Err(e) = watcher.watch(dir, RecursiveMode::NonRecursive) {
+                tracing::error!("Failed to watch {:?} of {}: {:?}", dir, package_name, e);
+            }
+        }
+
+        let inner = self.inner.clone();
+        let logs = self.logs.clone();
+        thread::spawn(move || {
+            while let Ok(path) = changed_rx.recv() {
+                // Wait until the build stops writing before reloading
+                let mut changed = HashSet::new();
+                let mut next = Some(path);
+                while let Some(path) = next {
+                    let path = canonical_entry(&path);
+                    changed.extend(
+                        entries
+                            .iter()
+                            .filter(|(_, entry)| *entry == path)
+                            .map(|(package_name, _)| package_name.clone()),
+                    );
+                    next = changed_rx.recv_timeout(DEV_RELOAD_DEBOUNCE).ok();
+                }
+
+                for package_name in changed {
+                    tracing::info!("Reloading dev extension {}", package_name);
+                    logs.push(
+                        &package_name,
+                        tracing::Level::INFO,
+                        "Entry changed, reloading".into(),
+                    );
+                    let mut inner = block_on(inner.lock());
+                    if let Err(e) = block_on(inner.reload_extension(package_name.clone())) {
+                        tracing::error!("Failed to reload {}: {:?}", package_name, e);
+                    }
+                }
+            }
+        });
+
+        *dev_watcher = Some(Box::new(watcher));
+    }
+
+    /// Buffered logs of the extension, only the entries after `after` if given
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub fn get_extension_logs(
+        &self,
+        package_name: String,
+        after: Option<u64>,
+    ) -> Result<Vec<ExtensionLogEntry>> {
+        Ok(self.logs.get(&package_name, after))
+    }
+
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub fn clear_extension_logs(&self, package_name: String) -> Result<()> {
+        self.logs.clear(&package_name);
+        Ok(())
+    }
+
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub async fn set_extension_limits(&self, limits: ExtensionLimits) {
+        let mut inner = self.inner.lock().await;
+        inner.set_limits(limits);
+    }
+
+    /// Enables or disables the extension and returns whether it is now enabled
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub async fn toggle_extension_status(&self, args: PackageNameArgs) -> Result<bool> {
+        let mut inner = self.inner.lock().await;
+        let ret = inner
+            .handle_runner_command(RunnerCommand::ToggleExtensionStatus(args))
+            .await?;
+        if let RunnerCommandResp::ExtensionStatus(enabled) = ret {
+            return Ok(enabled);
+        }
+        Err("Failed to toggle extension".into())
+    }
+
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub async fn reload_extension(&self, args: PackageNameArgs) -> Result<()> {
+        let mut inner = self.inner.lock().await;
+        inner
+            .handle_runner_command(RunnerCommand::ReloadExtension(args))
+            .await?;
+        Ok(())
+    }
+
+    /// Stops all extensions and waits a while for their onStopped callbacks
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub async fn stop_extensions(&self) -> Result<()> {
+        let handles = {
+            let mut inner = self.inner.lock().await;
+            inner.begin_shutdown();
+            inner.stop_extensions(String::new()).await
+        };
+
+        let (done_tx, done_rx) = oneshot::channel();
+        thread::spawn(move || {
+            for handle in handles {
+                let _ = handle.join();
+            }
+            let _ = done_tx.send(());
+        });
+
+        if tokio::time::timeout(STOP_TIMEOUT, done_rx).await.is_err() {
+            tracing::warn!("Extensions took too long to stop");
+        }
+        Ok(())
+    }
+
     #[tracing::instrument(level = "debug", skip(self))]
     pub async fn get_installed_extensions(&self) -> Result<Vec<ExtensionDetail>> {
         let mut inner = self.inner.lock().await;
@@ -315,6 +784,13 @@ impl ExtensionHandler {
 
     pub async fn send_extra_event(&self, args: ExtensionExtraEventArgs) -> Result<Value> {
         let package_name = args.package_name.clone();
+        if package_name.is_empty() {
+            if let Some(kind) = args.data.subscription_kind().filter(|k| k.is_batched()) {
+                self.batch_event(kind, args.data);
+                return Ok(Value::Null);
+            }
+        }
+
         let resp = self
             .send_extension_command(
                 ExtensionCommand::ExtraExtensionEvent(args),
@@ -333,6 +809,64 @@ impl ExtensionHandler {
         }
     }
 
+    /// Keeps only the latest event of a burst and sends it once the burst is over
+    fn batch_event(&self, kind: ExtensionEventKind, data: ExtensionExtraEvent) {
+        let first = {
+            let mut pending = self.pending_events.lock().unwrap();
+            pending.insert(kind, data).is_none()
+        };
+        if !first {
+            tracing::trace!("Replaced pending {:?} event", kind);
+            return;
+        }
+
+        let pending = self.pending_events.clone();
+        let inner = self.inner.clone();
+        thread::spawn(move || {
+            thread::sleep(EVENT_BATCH_INTERVAL);
+            let Some(data) = pending.lock().unwrap().remove(&kind) else {
+                return;
+            };
+
+            let command = ExtensionCommand::ExtraExtensionEvent(ExtensionExtraEventArgs {
+                data,
+                package_name: String::new(),
+            });
+            // Nobody waits for the replies to notifications
+            let (tx, _rx) = unbounded_channel();
+            let mut inner = block_on(inner.lock());
+            if let Err(e) = block_on(inner.handle_extension_command(&command, tx)) {
+                tracing::error!("Failed to send batched {:?} event: {:?}", kind, e);
+            }
+        });
+    }
+
+    /// Events each extension is subscribed to and how many it received
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub async fn get_extension_subscriptions(&self) -> Result<Vec<ExtensionSubscriptionInfo>> {
+        let inner = self.inner.lock().await;
+        Ok(inner.get_subscriptions())
+    }
+
+    /// Sends the event to every extension and waits for their combined reply
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub async fn broadcast_extra_event(&self, data: ExtensionExtraEvent) -> Result<Value> {
+        let resp = self
+            .send_extension_command(
+                ExtensionCommand::ExtraExtensionEvent(ExtensionExtraEventArgs {
+                    data,
+                    package_name: String::new(),
+                }),
+                true,
+            )
+            .await?;
+
+        if let ExtensionCommandResponse::ExtraExtensionEvent(resp) = resp {
+            return Ok(serde_json::to_value(resp)?);
+        }
+        Ok(Value::Null)
+    }
+
     pub async fn get_provider_scopes(
         &self,
         package_name: PackageNameArgs,
@@ -373,6 +907,64 @@ impl ExtensionHandler {
         Ok(String::new())
     }
 
+    /// Compares the installed extensions with the latest published versions.
+    /// Pre-releases are only offered to extensions which are already on one.
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub async fn check_extension_updates(&self) -> Result<Vec<FetchedExtensionManifest>> {
+        let installed = self.get_installed_extensions().await?;
+        let available = self.get_extension_manifest().await?;
+
+        let updates = available
+            .into_iter()
+            .filter(|fetched| {
+                installed.iter().any(|ext| {
+                    if ext.package_name != fetched.package_name {
+                        return false;
+                    }
+                    match (
+                        self.parse_version(&fetched.version),
+                        self.parse_version(&ext.version),
+                    ) {
+                        (Ok(new), Ok(old)) => {
+                            new > old && (new.pre.is_empty() || !old.pre.is_empty())
+                        }
+                        _ => false,
+                    }
+                })
+            })
+            .collect::<Vec<_>>();
+
+        *self.updates.lock().await = updates.clone();
+        Ok(updates)
+    }
+
+    /// Updates found by the last call to [Self::check_extension_updates]
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub async fn get_extension_updates(&self) -> Result<Vec<FetchedExtensionManifest>> {
+        Ok(self.updates.lock().await.clone())
+    }
+
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub async fn update_extension(&self, package_name: String) -> Result<()> {
+        let update = self
+            .updates
+            .lock()
+            .await
+            .iter()
+            .find(|u| u.package_name == package_name)
+            .cloned();
+        let Some(update) = update else {
+            return Err(format!("No update available for {}", package_name).into());
+        };
+
+        self.download_extension(update).await?;
+        self.updates
+            .lock()
+            .await
+            .retain(|u| u.package_name != package_name);
+        Ok(())
+    }
+
     #[tracing::instrument(level = "debug", skip(self))]
     pub async fn get_extension_manifest(&self) -> Result<Vec<FetchedExtensionManifest>> {
         #[derive(serde::Deserialize, Debug, Clone)]
@@ -393,6 +985,7 @@ impl ExtensionHandler {
             version: String,
             icon: Option<String>,
             _permissions: HashMap<String, Value>,
+            sha256: Option<String>,
         }
 
         tracing::info!("Getting extension manifest");
@@ -429,6 +1022,7 @@ impl ExtensionHandler {
                             description: None,
                             url: asset.browser_download_url.clone(),
                             version: manifest.version,
+                            sha256: manifest.sha256,
                         })
                     }
                 }
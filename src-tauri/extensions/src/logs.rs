@@ -0,0 +1,179 @@
+// Moosync
+// Copyright (C) 2024, 2025  Moosync <support@moosync.app>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::{
+    collections::{HashMap, VecDeque},
+    fmt,
+    sync::{Arc, Mutex},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use tracing::{
+    field::{Field, Visit},
+    span, Event, Level, Span, Subscriber,
+};
+use tracing_subscriber::{filter, layer::Context, registry::LookupSpan, Layer};
+use types::ui::extensions::ExtensionLogEntry;
+
+#[cfg(test)]
+mod test;
+
+/// Plugin calls run inside this span so their logs can be traced back to the extension
+const EXTENSION_SPAN: &str = "extension_call";
+
+/// Entries kept per extension, older ones are dropped
+const LOG_CAPACITY: usize = 1000;
+
+pub(crate) fn extension_span(package_name: &str) -> Span {
+    tracing::info_span!(EXTENSION_SPAN, package_name = package_name)
+}
+
+#[derive(Debug, Default)]
+struct LogBuffers {
+    next_id: u64,
+    buffers: HashMap<String, VecDeque<ExtensionLogEntry>>,
+}
+
+/// Ring buffers holding the recent logs of every extension
+#[derive(Debug, Default, Clone)]
+pub struct ExtensionLogs {
+    inner: Arc<Mutex<LogBuffers>>,
+}
+
+impl ExtensionLogs {
+    pub(crate) fn push(&self, package_name: &str, level: Level, message: String) {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_millis() as u64)
+            .unwrap_or_default();
+
+        let mut inner = self.inner.lock().unwrap();
+        let id = inner.next_id;
+        inner.next_id += 1;
+
+        let buffer = inner.buffers.entry(package_name.to_string()).or_default();
+        if buffer.len() >= LOG_CAPACITY {
+            buffer.pop_front();
+        }
+        buffer.push_back(ExtensionLogEntry {
+            id,
+            package_name: package_name.to_string(),
+            level: level.to_string(),
+            message,
+            timestamp,
+        });
+    }
+
+    /// Returns the buffered entries of the extension, only those newer than `after` if given
+    pub fn get(&self, package_name: &str, after: Option<u64>) -> Vec<ExtensionLogEntry> {
+        let inner = self.inner.lock().unwrap();
+        inner
+            .buffers
+            .get(package_name)
+            .map(|buffer| {
+                buffer
+                    .iter()
+                    .filter(|e| after.is_none_or(|after| e.id > after))
+                    .cloned()
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    pub fn clear(&self, package_name: &str) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.buffers.remove(package_name);
+    }
+
+    /// Layer which captures the logs extensions write through extism while they are called
+    pub fn layer<S>(&self) -> impl Layer<S>
+    where
+        S: Subscriber + for<'a> LookupSpan<'a>,
+    {
+        ExtensionLogLayer { logs: self.clone() }.with_filter(filter::filter_fn(|metadata| {
+            (metadata.is_span() && metadata.name() == EXTENSION_SPAN)
+                || metadata.target().starts_with("extism")
+        }))
+    }
+}
+
+struct PackageName(String);
+
+#[derive(Default)]
+struct PackageNameVisitor(Option<String>);
+
+impl Visit for PackageNameVisitor {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        if field.name() == "package_name" {
+            self.0 = Some(value.to_string());
+        }
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        if field.name() == "package_name" {
+            self.0 = Some(format!("{:?}", value));
+        }
+    }
+}
+
+#[derive(Default)]
+struct MessageVisitor(String);
+
+impl Visit for MessageVisitor {
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        if field.name() == "message" {
+            self.0 = format!("{:?}", value);
+        }
+    }
+}
+
+struct ExtensionLogLayer {
+    logs: ExtensionLogs,
+}
+
+impl<S> Layer<S> for ExtensionLogLayer
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_new_span(&self, attrs: &span::Attributes<'_>, id: &span::Id, ctx: Context<'_, S>) {
+        if attrs.metadata().name() != EXTENSION_SPAN {
+            return;
+        }
+
+        let mut visitor = PackageNameVisitor::default();
+        attrs.record(&mut visitor);
+        if let (Some(package_name), Some(span)) = (visitor.0, ctx.span(id)) {
+            span.extensions_mut().insert(PackageName(package_name));
+        }
+    }
+
+    fn on_event(&self, event: &Event<'_>, ctx: Context<'_, S>) {
+        let Some(scope) = ctx.event_scope(event) else {
+            return;
+        };
+        let Some(package_name) = scope
+            .into_iter()
+            .find_map(|span| span.extensions().get::<PackageName>().map(|p| p.0.clone()))
+        else {
+            return;
+        };
+
+        let mut visitor = MessageVisitor::default();
+        event.record(&mut visitor);
+        self.logs
+            .push(&package_name, *event.metadata().level(), visitor.0);
+    }
+}
@@ -0,0 +1,87 @@
+// Moosync
+// Copyright (C) 2024, 2025  Moosync <support@moosync.app>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use tracing::Level;
+
+use super::{ExtensionLogs, LOG_CAPACITY};
+
+// Test that only the newest entries are kept once the buffer is full
+#[test]
+fn test_capacity() {
+    let logs = ExtensionLogs::default();
+    for i in 0..LOG_CAPACITY + 10 {
+        logs.push("moosync.test", Level::INFO, format!("{}", i));
+    }
+
+    let entries = logs.get("moosync.test", None);
+    assert_eq!(entries.len(), LOG_CAPACITY);
+    assert_eq!(entries.first().unwrap().message, "10");
+    assert_eq!(
+        entries.last().unwrap().message,
+        format!("{}", LOG_CAPACITY + 9)
+    );
+}
+
+// Test that only entries newer than the given id are returned
+#[test]
+fn test_after() {
+    let logs = ExtensionLogs::default();
+    for i in 0..5 {
+        logs.push("moosync.test", Level::INFO, format!("{}", i));
+    }
+
+    let entries = logs.get("moosync.test", None);
+    let after = logs.get("moosync.test", Some(entries[2].id));
+    assert_eq!(
+        after.iter().map(|e| e.message.as_str()).collect::<Vec<_>>(),
+        vec!["3", "4"]
+    );
+    assert!(logs.get("moosync.test", Some(entries[4].id)).is_empty());
+}
+
+// Test that ids keep increasing across extensions so `after` stays valid
+#[test]
+fn test_ids_across_extensions() {
+    let logs = ExtensionLogs::default();
+    logs.push("moosync.first", Level::INFO, "first".into());
+    logs.push("moosync.second", Level::WARN, "second".into());
+    logs.push("moosync.first", Level::ERROR, "third".into());
+
+    let first = logs.get("moosync.first", None);
+    let second = logs.get("moosync.second", None);
+    assert_eq!(first.len(), 2);
+    assert_eq!(second.len(), 1);
+    assert!(first[0].id < second[0].id && second[0].id < first[1].id);
+    assert_eq!(
+        logs.get("moosync.first", Some(second[0].id))[0].message,
+        "third"
+    );
+    assert_eq!(first[1].level, Level::ERROR.to_string());
+}
+
+// Test that clearing only drops the logs of that extension
+#[test]
+fn test_clear() {
+    let logs = ExtensionLogs::default();
+    logs.push("moosync.first", Level::INFO, "first".into());
+    logs.push("moosync.second", Level::INFO, "second".into());
+
+    logs.clear("moosync.first");
+
+    assert!(logs.get("moosync.first", None).is_empty());
+    assert_eq!(logs.get("moosync.second", None).len(), 1);
+    assert!(logs.get("moosync.missing", None).is_empty());
+}
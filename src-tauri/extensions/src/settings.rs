@@ -0,0 +1,126 @@
+// Moosync
+// Copyright (C) 2024, 2025  Moosync <support@moosync.app>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::collections::HashSet;
+
+use serde_json::Value;
+use types::{
+    errors::{MoosyncError, Result},
+    preferences::{InputType, PreferenceTypes, PreferenceUIData},
+    ui::extensions::extension_preference_key,
+};
+
+#[cfg(test)]
+mod test;
+
+fn is_valid_key(key: &str) -> bool {
+    !key.is_empty()
+        && key
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+}
+
+/// Checks the default value against the type of the preference
+fn is_valid_default(pref: &PreferenceUIData, default: &Value) -> bool {
+    let item_keys = || {
+        pref.items
+            .iter()
+            .flatten()
+            .map(|i| i.key.as_str())
+            .collect::<HashSet<_>>()
+    };
+    match pref._type {
+        PreferenceTypes::EditText => match pref.input_type {
+            Some(InputType::Number) => default.is_number(),
+            _ => default.is_string(),
+        },
+        PreferenceTypes::FilePicker => default.is_string(),
+        PreferenceTypes::Dropdown => default
+            .as_str()
+            .is_some_and(|key| item_keys().contains(key)),
+        PreferenceTypes::CheckboxGroup => default.as_array().is_some_and(|keys| {
+            let item_keys = item_keys();
+            keys.iter()
+                .all(|key| key.as_str().is_some_and(|key| item_keys.contains(key)))
+        }),
+        _ => false,
+    }
+}
+
+#[tracing::instrument(level = "debug", skip(raw))]
+fn validate_preference(package_name: &str, raw: &Value) -> Result<PreferenceUIData> {
+    let mut pref: PreferenceUIData = serde_json::from_value(raw.clone())?;
+    if !is_valid_key(&pref.key) {
+        return Err(format!("Invalid preference key {:?}", pref.key).into());
+    }
+
+    match pref._type {
+        PreferenceTypes::EditText => {
+            if pref.input_type.is_none() {
+                pref.input_type = Some(InputType::Text);
+            }
+        }
+        PreferenceTypes::FilePicker => {}
+        PreferenceTypes::CheckboxGroup | PreferenceTypes::Dropdown => {
+            let items = pref.items.as_deref().unwrap_or_default();
+            if items.is_empty() {
+                return Err(format!("Preference {} has no items", pref.key).into());
+            }
+            let mut seen = HashSet::new();
+            if !items
+                .iter()
+                .all(|i| is_valid_key(&i.key) && seen.insert(i.key.as_str()))
+            {
+                return Err(format!("Preference {} has invalid item keys", pref.key).into());
+            }
+        }
+        _ => {
+            return Err(MoosyncError::String(format!(
+                "Preference {} has unsupported type {:?}",
+                pref.key, pref._type
+            )))
+        }
+    }
+
+    if let Some(default) = &pref.default {
+        if !is_valid_default(&pref, default) {
+            tracing::warn!("Ignoring invalid default of preference {}", pref.key);
+            pref.default = None;
+        }
+    }
+
+    pref.key = extension_preference_key(package_name, &pref.key);
+    Ok(pref)
+}
+
+/// Keeps the valid preferences declared by an extension, with keys scoped to its package
+#[tracing::instrument(level = "debug", skip(raw))]
+pub(crate) fn validate_preferences(package_name: &str, raw: &[Value]) -> Vec<PreferenceUIData> {
+    let mut keys = HashSet::new();
+    raw.iter()
+        .filter_map(|raw| match validate_preference(package_name, raw) {
+            Ok(pref) if keys.insert(pref.key.clone()) => Some(pref),
+            Ok(pref) => {
+                tracing::warn!("Extension {} declares {} twice", package_name, pref.key);
+                None
+            }
+            Err(e) => {
+                tracing::warn!("Ignoring preference of extension {}: {:?}", package_name, e);
+                None
+            }
+        })
+        .collect()
+}
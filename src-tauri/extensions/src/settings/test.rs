@@ -0,0 +1,146 @@
+// Moosync
+// Copyright (C) 2024, 2025  Moosync <support@moosync.app>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use serde_json::{json, Value};
+use types::{
+    preferences::{InputType, PreferenceTypes},
+    ui::extensions::extension_preference_key,
+};
+
+use super::validate_preferences;
+
+const PACKAGE: &str = "moosync.test";
+
+fn edit_text(key: &str, default: Value) -> Value {
+    json!({
+        "type": "EditText",
+        "title": "Title",
+        "key": key,
+        "description": "Description",
+        "default": default,
+    })
+}
+
+fn dropdown(key: &str, items: &[&str], default: Value) -> Value {
+    json!({
+        "type": "Dropdown",
+        "title": "Title",
+        "key": key,
+        "description": "Description",
+        "items": items
+            .iter()
+            .map(|key| json!({ "title": key, "key": key }))
+            .collect::<Vec<_>>(),
+        "default": default,
+    })
+}
+
+// Test that valid preferences are kept with keys scoped to the package
+#[test]
+fn test_validate_scopes_keys() {
+    let prefs = validate_preferences(
+        PACKAGE,
+        &[
+            edit_text("token", json!("abc")),
+            dropdown("quality", &["low", "high"], json!("high")),
+        ],
+    );
+
+    assert_eq!(prefs.len(), 2);
+    assert_eq!(prefs[0].key, extension_preference_key(PACKAGE, "token"));
+    assert_eq!(prefs[1].key, extension_preference_key(PACKAGE, "quality"));
+    assert!(matches!(prefs[0].input_type, Some(InputType::Text)));
+    assert_eq!(prefs[1]._type, PreferenceTypes::Dropdown);
+}
+
+// Test that scoped keys of similar package names don't collide
+#[test]
+fn test_preference_key_collisions() {
+    assert_ne!(
+        extension_preference_key("moosync.test", "key"),
+        extension_preference_key("moosync_test", "key")
+    );
+    assert_ne!(
+        extension_preference_key("moosync.test", "key"),
+        extension_preference_key("moosync%2Etest", "key")
+    );
+}
+
+// Test that invalid keys and unsupported types are dropped
+#[test]
+fn test_validate_rejects_invalid() {
+    let prefs = validate_preferences(
+        PACKAGE,
+        &[
+            edit_text("", json!("abc")),
+            edit_text("nested.key", json!("abc")),
+            json!({
+                "type": "ThemeSelector",
+                "title": "Title",
+                "key": "theme",
+                "description": "Description",
+            }),
+            json!({ "key": "missing_fields" }),
+            dropdown("empty", &[], Value::Null),
+            dropdown("duplicate_items", &["a", "a"], Value::Null),
+        ],
+    );
+
+    assert!(prefs.is_empty());
+}
+
+// Test that a preference declared twice is only kept once
+#[test]
+fn test_validate_duplicate_keys() {
+    let prefs = validate_preferences(
+        PACKAGE,
+        &[
+            edit_text("token", json!("abc")),
+            edit_text("token", json!("def")),
+        ],
+    );
+
+    assert_eq!(prefs.len(), 1);
+    assert_eq!(prefs[0].default, Some(json!("abc")));
+}
+
+// Test that defaults which don't match the preference are dropped
+#[test]
+fn test_validate_defaults() {
+    let mut number = edit_text("count", json!("ten"));
+    number["inputType"] = json!("number");
+    let checkboxes = json!({
+        "type": "CheckboxGroup",
+        "title": "Title",
+        "key": "sources",
+        "description": "Description",
+        "items": [{ "title": "A", "key": "a" }, { "title": "B", "key": "b" }],
+        "default": ["a", "c"],
+    });
+
+    let prefs = validate_preferences(
+        PACKAGE,
+        &[
+            number,
+            dropdown("quality", &["low", "high"], json!("medium")),
+            checkboxes,
+            edit_text("token", json!(1)),
+        ],
+    );
+
+    assert_eq!(prefs.len(), 4);
+    assert!(prefs.iter().all(|p| p.default.is_none()));
+}
@@ -0,0 +1,191 @@
+// Moosync
+// Copyright (C) 2024, 2025  Moosync <support@moosync.app>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use crypto::{digest::Digest, ed25519, sha2::Sha256};
+use serde::Deserialize;
+use types::errors::Result;
+
+#[cfg(test)]
+mod test;
+
+/// Detached signature shipped at the root of an extension package
+pub(crate) const SIGNATURE_FILE: &str = "signature.json";
+
+/// Publisher names mapped to their base64 ed25519 public keys
+pub(crate) const KEYRING_FILE: &str = "trusted_publishers.json";
+
+/// Publishers trusted out of the box. Keys added by the user are merged on top
+const DEFAULT_KEYRING: &str = include_str!("trusted_publishers.json");
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct PackageSignature {
+    /// Base64 ed25519 public key of the publisher
+    public_key: String,
+    /// Hex SHA-256 of the package contents, see [package_digest]
+    digest: String,
+    /// Base64 ed25519 signature of the digest
+    signature: String,
+}
+
+#[derive(Debug, PartialEq)]
+pub(crate) enum Verification {
+    Trusted(String),
+    Untrusted,
+    Unsigned,
+}
+
+fn collect_files(root: &Path, dir: &Path, files: &mut Vec<PathBuf>) -> Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            collect_files(root, &path, files)?;
+        } else if path != root.join(SIGNATURE_FILE) {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+/// Hashes every file of the unpacked package except the signature, in path order,
+/// so the digest doesn't depend on how the archive was built
+#[tracing::instrument(level = "debug")]
+pub(crate) fn package_digest(dir: &Path) -> Result<String> {
+    let mut files = vec![];
+    collect_files(dir, dir, &mut files)?;
+
+    let mut entries = files
+        .into_iter()
+        .map(|path| {
+            let relative = path
+                .strip_prefix(dir)
+                .unwrap_or(&path)
+                .to_string_lossy()
+                .replace('\\', "/");
+            (relative, path)
+        })
+        .collect::<Vec<_>>();
+    entries.sort();
+
+    let mut hasher = Sha256::new();
+    for (relative, path) in entries {
+        let contents = fs::read(path)?;
+        hasher.input(relative.as_bytes());
+        hasher.input(&[0]);
+        hasher.input(&(contents.len() as u64).to_le_bytes());
+        hasher.input(&contents);
+    }
+    Ok(hasher.result_str())
+}
+
+fn default_keyring() -> HashMap<String, String> {
+    serde_json::from_str(DEFAULT_KEYRING).unwrap_or_else(|e| {
+        tracing::error!("Failed to parse default trusted publishers: {:?}", e);
+        HashMap::new()
+    })
+}
+
+fn read_keyring(path: &Path) -> HashMap<String, String> {
+    let Ok(contents) = fs::read(path) else {
+        return HashMap::new();
+    };
+    serde_json::from_slice(&contents).unwrap_or_else(|e| {
+        tracing::error!("Failed to parse trusted publishers {:?}: {:?}", path, e);
+        HashMap::new()
+    })
+}
+
+/// Default publishers along with the ones trusted by the user
+#[tracing::instrument(level = "debug")]
+pub(crate) fn load_keyring(path: &Path) -> HashMap<String, String> {
+    let mut keyring = default_keyring();
+    keyring.extend(read_keyring(path));
+    keyring
+}
+
+/// Adds a publisher to the user keyring, replacing any key it had before
+#[tracing::instrument(level = "debug")]
+pub(crate) fn trust_publisher(path: &Path, publisher: &str, public_key: &str) -> Result<()> {
+    let publisher = publisher.trim();
+    let public_key = public_key.trim();
+    if publisher.is_empty() {
+        return Err("Publisher name can not be empty".into());
+    }
+
+    let decoded = STANDARD
+        .decode(public_key)
+        .map_err(|e| format!("Publisher key is not valid base64: {}", e))?;
+    if decoded.len() != 32 {
+        return Err("Publisher key must be a 32 byte ed25519 public key".into());
+    }
+
+    let mut keyring = read_keyring(path);
+    keyring.insert(publisher.to_string(), public_key.to_string());
+
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(path, serde_json::to_vec_pretty(&keyring)?)?;
+    Ok(())
+}
+
+/// Checks the signature of an unpacked package. Packages which were changed
+/// after signing are always an error.
+#[tracing::instrument(level = "debug", skip(keyring))]
+pub(crate) fn verify_package(
+    dir: &Path,
+    keyring: &HashMap<String, String>,
+) -> Result<Verification> {
+    let signature_path = dir.join(SIGNATURE_FILE);
+    if !signature_path.exists() {
+        return Ok(Verification::Unsigned);
+    }
+
+    let signature: PackageSignature = serde_json::from_slice(&fs::read(signature_path)?)
+        .map_err(|e| format!("Extension signature is malformed: {}", e))?;
+
+    let digest = package_digest(dir)?;
+    if !digest.eq_ignore_ascii_case(&signature.digest) {
+        return Err("Extension package was modified after it was signed".into());
+    }
+
+    let public_key = STANDARD
+        .decode(&signature.public_key)
+        .map_err(|e| format!("Extension signature has an invalid key: {}", e))?;
+    let signature_bytes = STANDARD
+        .decode(&signature.signature)
+        .map_err(|e| format!("Extension signature is malformed: {}", e))?;
+    if public_key.len() != 32 || signature_bytes.len() != 64 {
+        return Err("Extension signature is malformed".into());
+    }
+
+    if !ed25519::verify(signature.digest.as_bytes(), &public_key, &signature_bytes) {
+        return Err("Extension signature does not match its contents".into());
+    }
+
+    Ok(keyring
+        .iter()
+        .find(|(_, key)| key.trim() == signature.public_key.trim())
+        .map(|(publisher, _)| Verification::Trusted(publisher.clone()))
+        .unwrap_or(Verification::Untrusted))
+}
@@ -0,0 +1,208 @@
+// Moosync
+// Copyright (C) 2024, 2025  Moosync <support@moosync.app>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::{
+    collections::HashMap,
+    env::temp_dir,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use crypto::ed25519;
+use serde_json::json;
+
+use super::{
+    load_keyring, package_digest, trust_publisher, verify_package, Verification, SIGNATURE_FILE,
+};
+
+fn test_dir(name: &str) -> PathBuf {
+    let dir = temp_dir().join(format!("moosync-test-signing-{}", name));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn package(name: &str) -> PathBuf {
+    let dir = test_dir(name);
+    fs::write(dir.join("package.json"), r#"{"name":"test"}"#).unwrap();
+    fs::create_dir_all(dir.join("dist")).unwrap();
+    fs::write(dir.join("dist/ext.wasm"), [0u8, 1, 2, 3]).unwrap();
+    dir
+}
+
+fn keypair(seed: u8) -> ([u8; 64], [u8; 32]) {
+    ed25519::keypair(&[seed; 32])
+}
+
+fn sign(dir: &Path, secret: &[u8; 64], public: &[u8; 32]) {
+    let digest = package_digest(dir).unwrap();
+    let signature = ed25519::signature(digest.as_bytes(), secret);
+    fs::write(
+        dir.join(SIGNATURE_FILE),
+        json!({
+            "publicKey": STANDARD.encode(public),
+            "digest": digest,
+            "signature": STANDARD.encode(signature),
+        })
+        .to_string(),
+    )
+    .unwrap();
+}
+
+fn keyring(publisher: &str, public: &[u8; 32]) -> HashMap<String, String> {
+    HashMap::from([(publisher.to_string(), STANDARD.encode(public))])
+}
+
+// Test that the digest doesn't depend on file order and skips the signature
+#[test]
+fn test_package_digest_is_stable() {
+    let first = package("digest-first");
+    let second = test_dir("digest-second");
+    fs::create_dir_all(second.join("dist")).unwrap();
+    fs::write(second.join("dist/ext.wasm"), [0u8, 1, 2, 3]).unwrap();
+    fs::write(second.join("package.json"), r#"{"name":"test"}"#).unwrap();
+
+    let digest = package_digest(&first).unwrap();
+    assert_eq!(digest, package_digest(&second).unwrap());
+
+    fs::write(first.join(SIGNATURE_FILE), "{}").unwrap();
+    assert_eq!(digest, package_digest(&first).unwrap());
+}
+
+// Test that moving contents between files changes the digest
+#[test]
+fn test_package_digest_includes_paths() {
+    let first = test_dir("digest-paths-first");
+    fs::write(first.join("a"), "ab").unwrap();
+    fs::write(first.join("b"), "").unwrap();
+
+    let second = test_dir("digest-paths-second");
+    fs::write(second.join("a"), "a").unwrap();
+    fs::write(second.join("b"), "b").unwrap();
+
+    assert_ne!(
+        package_digest(&first).unwrap(),
+        package_digest(&second).unwrap()
+    );
+}
+
+// Test that a package signed by a known publisher is trusted
+#[test]
+fn test_verify_trusted() {
+    let dir = package("trusted");
+    let (secret, public) = keypair(1);
+    sign(&dir, &secret, &public);
+
+    assert_eq!(
+        verify_package(&dir, &keyring("Publisher", &public)).unwrap(),
+        Verification::Trusted("Publisher".into())
+    );
+}
+
+// Test that a valid signature from an unknown key is untrusted
+#[test]
+fn test_verify_untrusted() {
+    let dir = package("untrusted");
+    let (secret, public) = keypair(1);
+    let (_, other) = keypair(2);
+    sign(&dir, &secret, &public);
+
+    assert_eq!(
+        verify_package(&dir, &keyring("Publisher", &other)).unwrap(),
+        Verification::Untrusted
+    );
+}
+
+// Test that a package without a signature is reported as unsigned
+#[test]
+fn test_verify_unsigned() {
+    let dir = package("unsigned");
+    assert_eq!(
+        verify_package(&dir, &HashMap::new()).unwrap(),
+        Verification::Unsigned
+    );
+}
+
+// Test that changing a file after signing fails verification
+#[test]
+fn test_verify_tampered() {
+    let dir = package("tampered");
+    let (secret, public) = keypair(1);
+    sign(&dir, &secret, &public);
+    fs::write(dir.join("dist/ext.wasm"), [3u8, 2, 1, 0]).unwrap();
+
+    assert!(verify_package(&dir, &keyring("Publisher", &public)).is_err());
+}
+
+// Test that adding a file after signing fails verification
+#[test]
+fn test_verify_added_file() {
+    let dir = package("added-file");
+    let (secret, public) = keypair(1);
+    sign(&dir, &secret, &public);
+    fs::write(dir.join("dist/extra.js"), "").unwrap();
+
+    assert!(verify_package(&dir, &keyring("Publisher", &public)).is_err());
+}
+
+// Test that a signature made with another key than the one it claims fails
+#[test]
+fn test_verify_wrong_key() {
+    let dir = package("wrong-key");
+    let (secret, _) = keypair(1);
+    let (_, public) = keypair(2);
+    sign(&dir, &secret, &public);
+
+    assert!(verify_package(&dir, &keyring("Publisher", &public)).is_err());
+}
+
+// Test that the default publishers are always part of the keyring
+#[test]
+fn test_load_keyring_includes_defaults() {
+    let dir = test_dir("keyring-defaults");
+    let keyring = load_keyring(&dir.join("trusted_publishers.json"));
+    assert!(keyring.contains_key("Moosync"));
+    for key in keyring.values() {
+        assert_eq!(STANDARD.decode(key).unwrap().len(), 32);
+    }
+}
+
+// Test that trusted publishers are saved and merged with the defaults
+#[test]
+fn test_trust_publisher() {
+    let dir = test_dir("trust-publisher");
+    let path = dir.join("trusted_publishers.json");
+    let (_, public) = keypair(1);
+
+    trust_publisher(&path, "Publisher", &STANDARD.encode(public)).unwrap();
+
+    let keyring = load_keyring(&path);
+    assert_eq!(keyring.get("Publisher"), Some(&STANDARD.encode(public)));
+    assert!(keyring.contains_key("Moosync"));
+}
+
+// Test that invalid publisher keys are rejected
+#[test]
+fn test_trust_publisher_invalid_key() {
+    let dir = test_dir("trust-invalid");
+    let path = dir.join("trusted_publishers.json");
+
+    assert!(trust_publisher(&path, "Publisher", "not base64!").is_err());
+    assert!(trust_publisher(&path, "Publisher", &STANDARD.encode([0u8; 16])).is_err());
+    assert!(trust_publisher(&path, "", &STANDARD.encode([0u8; 32])).is_err());
+    assert!(!path.exists());
+}
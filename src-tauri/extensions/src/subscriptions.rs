@@ -0,0 +1,126 @@
+// Moosync
+// Copyright (C) 2024, 2025  Moosync <support@moosync.app>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::{
+    collections::{BTreeSet, HashMap},
+    sync::{Arc, Mutex},
+};
+
+use types::ui::extensions::{ExtensionEventKind, ExtensionSubscriptionInfo};
+
+#[cfg(test)]
+mod test;
+
+#[derive(Debug, Default)]
+struct Subscription {
+    // None until the extension declares what it wants, it receives every event till then
+    events: Option<BTreeSet<ExtensionEventKind>>,
+    delivered: u64,
+    skipped: u64,
+}
+
+impl Subscription {
+    fn events(&self) -> BTreeSet<ExtensionEventKind> {
+        self.events
+            .clone()
+            .unwrap_or_else(|| ExtensionEventKind::ALL.into_iter().collect())
+    }
+}
+
+/// Events each extension is subscribed to, declared in its manifest or changed at runtime
+#[derive(Debug, Default, Clone)]
+pub(crate) struct ExtensionSubscriptions {
+    inner: Arc<Mutex<HashMap<String, Subscription>>>,
+}
+
+impl ExtensionSubscriptions {
+    /// Resets the subscriptions of the extension to the ones from its manifest
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub(crate) fn register(&self, package_name: &str, declared: Option<Vec<ExtensionEventKind>>) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.insert(
+            package_name.to_string(),
+            Subscription {
+                events: declared.map(|events| events.into_iter().collect()),
+                ..Default::default()
+            },
+        );
+    }
+
+    pub(crate) fn remove(&self, package_name: &str) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.remove(package_name);
+    }
+
+    /// Only running extensions are registered, late calls from stopped ones are ignored
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub(crate) fn subscribe(&self, package_name: &str, events: &[ExtensionEventKind]) {
+        let mut inner = self.inner.lock().unwrap();
+        let Some(subscription) = inner.get_mut(package_name) else {
+            return;
+        };
+        // Subscribing opts out of receiving everything
+        let mut subscribed = subscription.events.take().unwrap_or_default();
+        subscribed.extend(events.iter().copied());
+        subscription.events = Some(subscribed);
+    }
+
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub(crate) fn unsubscribe(&self, package_name: &str, events: &[ExtensionEventKind]) {
+        let mut inner = self.inner.lock().unwrap();
+        let Some(subscription) = inner.get_mut(package_name) else {
+            return;
+        };
+        let mut subscribed = subscription.events();
+        subscribed.retain(|e| !events.contains(e));
+        subscription.events = Some(subscribed);
+    }
+
+    /// Whether the event should be sent to the extension, counted for [Self::get].
+    /// Extensions which aren't registered aren't running and get nothing
+    pub(crate) fn accepts(&self, package_name: &str, kind: ExtensionEventKind) -> bool {
+        let mut inner = self.inner.lock().unwrap();
+        let Some(subscription) = inner.get_mut(package_name) else {
+            return false;
+        };
+        let accepted = subscription
+            .events
+            .as_ref()
+            .is_none_or(|events| events.contains(&kind));
+        if accepted {
+            subscription.delivered += 1;
+        } else {
+            subscription.skipped += 1;
+        }
+        accepted
+    }
+
+    pub(crate) fn get(&self) -> Vec<ExtensionSubscriptionInfo> {
+        let inner = self.inner.lock().unwrap();
+        let mut ret = inner
+            .iter()
+            .map(|(package_name, subscription)| ExtensionSubscriptionInfo {
+                package_name: package_name.clone(),
+                events: subscription.events().into_iter().collect(),
+                declared: subscription.events.is_some(),
+                delivered: subscription.delivered,
+                skipped: subscription.skipped,
+            })
+            .collect::<Vec<_>>();
+        ret.sort_by(|a, b| a.package_name.cmp(&b.package_name));
+        ret
+    }
+}
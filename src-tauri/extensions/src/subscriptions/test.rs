@@ -0,0 +1,116 @@
+// Moosync
+// Copyright (C) 2024, 2025  Moosync <support@moosync.app>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use types::ui::extensions::ExtensionEventKind;
+
+use super::ExtensionSubscriptions;
+
+const PACKAGE: &str = "moosync.test";
+
+// Test that extensions without declared subscriptions receive everything
+#[test]
+fn test_undeclared_accepts_all() {
+    let subscriptions = ExtensionSubscriptions::default();
+    subscriptions.register(PACKAGE, None);
+
+    for kind in ExtensionEventKind::ALL {
+        assert!(subscriptions.accepts(PACKAGE, kind));
+    }
+
+    let info = subscriptions.get();
+    assert_eq!(info.len(), 1);
+    assert!(!info[0].declared);
+    assert_eq!(info[0].events.len(), ExtensionEventKind::ALL.len());
+    assert_eq!(info[0].delivered, ExtensionEventKind::ALL.len() as u64);
+}
+
+// Test that declared subscriptions filter events and count the skipped ones
+#[test]
+fn test_declared_filters() {
+    let subscriptions = ExtensionSubscriptions::default();
+    subscriptions.register(PACKAGE, Some(vec![ExtensionEventKind::SongChanged]));
+
+    assert!(subscriptions.accepts(PACKAGE, ExtensionEventKind::SongChanged));
+    assert!(!subscriptions.accepts(PACKAGE, ExtensionEventKind::Seeked));
+    assert!(!subscriptions.accepts(PACKAGE, ExtensionEventKind::VolumeChanged));
+
+    let info = subscriptions.get();
+    assert!(info[0].declared);
+    assert_eq!(info[0].events, vec![ExtensionEventKind::SongChanged]);
+    assert_eq!(info[0].delivered, 1);
+    assert_eq!(info[0].skipped, 2);
+}
+
+// Test that subscribing at runtime opts out of receiving everything
+#[test]
+fn test_subscribe() {
+    let subscriptions = ExtensionSubscriptions::default();
+    subscriptions.register(PACKAGE, None);
+
+    subscriptions.subscribe(PACKAGE, &[ExtensionEventKind::Seeked]);
+
+    assert!(subscriptions.accepts(PACKAGE, ExtensionEventKind::Seeked));
+    assert!(!subscriptions.accepts(PACKAGE, ExtensionEventKind::SongChanged));
+}
+
+// Test that unsubscribing without declared subscriptions keeps every other event
+#[test]
+fn test_unsubscribe() {
+    let subscriptions = ExtensionSubscriptions::default();
+    subscriptions.register(PACKAGE, None);
+
+    subscriptions.unsubscribe(PACKAGE, &[ExtensionEventKind::Seeked]);
+
+    assert!(!subscriptions.accepts(PACKAGE, ExtensionEventKind::Seeked));
+    assert!(subscriptions.accepts(PACKAGE, ExtensionEventKind::SongChanged));
+    assert_eq!(
+        subscriptions.get()[0].events.len(),
+        ExtensionEventKind::ALL.len() - 1
+    );
+}
+
+// Test that extensions which aren't registered get nothing and aren't added
+#[test]
+fn test_unregistered() {
+    let subscriptions = ExtensionSubscriptions::default();
+
+    assert!(!subscriptions.accepts(PACKAGE, ExtensionEventKind::SongChanged));
+    subscriptions.subscribe(PACKAGE, &[ExtensionEventKind::SongChanged]);
+    subscriptions.unsubscribe(PACKAGE, &[ExtensionEventKind::Seeked]);
+
+    assert!(subscriptions.get().is_empty());
+}
+
+// Test that removed extensions are forgotten and registering resets their counts
+#[test]
+fn test_remove_and_register() {
+    let subscriptions = ExtensionSubscriptions::default();
+    subscriptions.register(PACKAGE, None);
+    subscriptions.register("moosync.other", None);
+    subscriptions.subscribe(PACKAGE, &[ExtensionEventKind::Seeked]);
+    subscriptions.accepts(PACKAGE, ExtensionEventKind::Seeked);
+
+    subscriptions.remove(PACKAGE);
+    let info = subscriptions.get();
+    assert_eq!(info.len(), 1);
+    assert_eq!(info[0].package_name, "moosync.other");
+
+    subscriptions.register(PACKAGE, None);
+    let info = subscriptions.get();
+    assert_eq!(info[0].package_name, PACKAGE);
+    assert!(!info[0].declared);
+    assert_eq!(info[0].delivered, 0);
+}
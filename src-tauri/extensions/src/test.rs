@@ -0,0 +1,286 @@
+// Moosync
+// Copyright (C) 2024, 2025  Moosync <support@moosync.app>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::{
+    env::temp_dir,
+    fs,
+    path::PathBuf,
+    time::{Duration, Instant},
+};
+
+use types::ui::extensions::{ExtensionEventKind, ExtensionExtraEvent, FetchedExtensionManifest};
+
+use crate::{canonical_entry, ExtensionHandler, ExtensionLogs, EVENT_BATCH_INTERVAL, STOP_TIMEOUT};
+
+fn test_dir(name: &str) -> PathBuf {
+    let dir = temp_dir().join(format!("moosync-test-extensions-{}", name));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn new_handler(name: &str) -> ExtensionHandler {
+    let dir = test_dir(name);
+    let (handler, _, _, _) = ExtensionHandler::new(
+        dir.join("extensions"),
+        dir.join("tmp"),
+        dir.join("cache"),
+        ExtensionLogs::default(),
+    );
+    handler
+}
+
+// Test that stopping without running extensions returns right away
+#[tokio::test]
+async fn test_stop_extensions_without_extensions() {
+    let handler = new_handler("stop-empty");
+
+    let start = Instant::now();
+    handler.stop_extensions().await.unwrap();
+
+    assert!(start.elapsed() < STOP_TIMEOUT);
+    assert!(handler.inner.lock().await.is_shutting_down());
+}
+
+// Test that stopping twice, as an exit after a restart might, is harmless
+#[tokio::test]
+async fn test_stop_extensions_twice() {
+    let handler = new_handler("stop-twice");
+
+    handler.stop_extensions().await.unwrap();
+    tokio::time::timeout(Duration::from_secs(1), handler.stop_extensions())
+        .await
+        .unwrap()
+        .unwrap();
+}
+
+// Test that versions compare numerically, not as strings
+#[test]
+fn test_parse_version_ordering() {
+    let handler = new_handler("version-ordering");
+
+    let old = handler.parse_version("1.9.9").unwrap();
+    let new = handler.parse_version("1.10.0").unwrap();
+    assert!(old < new);
+    assert_eq!(
+        handler.parse_version("v1.10.0").unwrap(),
+        handler.parse_version(" 1.10.0 ").unwrap()
+    );
+}
+
+// Test that a pre-release sorts before its release
+#[test]
+fn test_parse_version_pre_release() {
+    let handler = new_handler("version-pre-release");
+
+    let beta = handler.parse_version("2.0.0-beta.1").unwrap();
+    let release = handler.parse_version("2.0.0").unwrap();
+    assert!(beta < release);
+    assert!(handler.parse_version("1.9.9").unwrap() < beta);
+    assert_eq!(handler.parse_version("2.0-beta.1").unwrap(), beta);
+}
+
+// Test that a missing patch number is filled in
+#[test]
+fn test_parse_version_short() {
+    let handler = new_handler("version-short");
+
+    assert_eq!(
+        handler.parse_version("1.2").unwrap(),
+        handler.parse_version("1.2.0").unwrap()
+    );
+    assert_eq!(
+        handler.parse_version("1").unwrap(),
+        handler.parse_version("1.0.0").unwrap()
+    );
+    assert!(handler.parse_version("1.2").unwrap() < handler.parse_version("1.2.1").unwrap());
+}
+
+// Test that garbage isn't accepted as a version
+#[test]
+fn test_parse_version_invalid() {
+    let handler = new_handler("version-invalid");
+
+    assert!(handler.parse_version("").is_err());
+    assert!(handler.parse_version("latest").is_err());
+    assert!(handler.parse_version("1.2.3.4").is_err());
+}
+
+// Test that a failed update puts the previous version back
+#[test]
+fn test_restore_backup() {
+    let dir = test_dir("restore-backup");
+    let ext_path = dir.join("extensions").join("ext");
+    let backup_path = dir.join("extensions_backup").join("ext");
+    fs::create_dir_all(&ext_path).unwrap();
+    fs::create_dir_all(&backup_path).unwrap();
+    fs::write(ext_path.join("package.json"), "new").unwrap();
+    fs::write(backup_path.join("package.json"), "old").unwrap();
+
+    assert!(ExtensionHandler::restore_backup(
+        "ext",
+        &ext_path,
+        &backup_path
+    ));
+    assert_eq!(
+        fs::read_to_string(ext_path.join("package.json")).unwrap(),
+        "old"
+    );
+    assert!(!backup_path.exists());
+}
+
+// Test that the backup is restored when the update never got moved in place
+#[test]
+fn test_restore_backup_without_update() {
+    let dir = test_dir("restore-backup-missing");
+    let ext_path = dir.join("extensions").join("ext");
+    let backup_path = dir.join("extensions_backup").join("ext");
+    fs::create_dir_all(ext_path.parent().unwrap()).unwrap();
+    fs::create_dir_all(&backup_path).unwrap();
+    fs::write(backup_path.join("package.json"), "old").unwrap();
+
+    assert!(ExtensionHandler::restore_backup(
+        "ext",
+        &ext_path,
+        &backup_path
+    ));
+    assert!(ext_path.join("package.json").exists());
+}
+
+// Test that an unpacked package is moved to its extension directory
+#[test]
+fn test_move_package() {
+    let dir = test_dir("move-package");
+    let tmp_dir = dir.join("tmp").join("moosync_ext_test");
+    let ext_path = dir.join("extensions").join("ext");
+    fs::create_dir_all(&tmp_dir).unwrap();
+    fs::write(tmp_dir.join("package.json"), "{}").unwrap();
+
+    ExtensionHandler::move_package(&tmp_dir, &ext_path).unwrap();
+
+    assert!(ext_path.join("package.json").exists());
+    assert!(!tmp_dir.exists());
+}
+
+// Test that differently written paths to a dev entry compare equal
+#[test]
+fn test_canonical_entry() {
+    let dir = test_dir("canonical-entry");
+    fs::create_dir_all(dir.join("dist")).unwrap();
+    fs::write(dir.join("dist").join("ext.wasm"), "").unwrap();
+
+    assert_eq!(
+        canonical_entry(&dir.join("dist").join("..").join("dist").join("ext.wasm")),
+        canonical_entry(&dir.join("dist").join("ext.wasm"))
+    );
+    // The entry may be missing while it is rebuilt
+    assert_eq!(
+        canonical_entry(&dir.join("dist").join("missing.wasm")),
+        fs::canonicalize(dir.join("dist"))
+            .unwrap()
+            .join("missing.wasm")
+    );
+}
+
+// Test that symlinked dev directories match the events of the real directory
+#[cfg(unix)]
+#[test]
+fn test_canonical_entry_symlink() {
+    let dir = test_dir("canonical-entry-symlink");
+    fs::create_dir_all(dir.join("dist")).unwrap();
+    std::os::unix::fs::symlink(dir.join("dist"), dir.join("link")).unwrap();
+
+    assert_eq!(
+        canonical_entry(&dir.join("link").join("ext.wasm")),
+        canonical_entry(&dir.join("dist").join("ext.wasm"))
+    );
+}
+
+// Test that a burst of events only keeps the latest one until it is sent
+#[test]
+fn test_batch_event_keeps_latest() {
+    let handler = new_handler("batch-latest");
+
+    handler.batch_event(
+        ExtensionEventKind::Seeked,
+        ExtensionExtraEvent::Seeked([1.0]),
+    );
+    handler.batch_event(
+        ExtensionEventKind::Seeked,
+        ExtensionExtraEvent::Seeked([2.0]),
+    );
+    handler.batch_event(
+        ExtensionEventKind::VolumeChanged,
+        ExtensionExtraEvent::VolumeChanged([50.0]),
+    );
+
+    {
+        let pending = handler.pending_events.lock().unwrap();
+        assert_eq!(pending.len(), 2);
+        assert!(matches!(
+            pending.get(&ExtensionEventKind::Seeked),
+            Some(ExtensionExtraEvent::Seeked([time])) if *time == 2.0
+        ));
+    }
+
+    let start = Instant::now();
+    while !handler.pending_events.lock().unwrap().is_empty() {
+        assert!(start.elapsed() < EVENT_BATCH_INTERVAL * 20);
+        std::thread::sleep(Duration::from_millis(10));
+    }
+}
+
+// Test that an event arriving after a batch was sent starts a new batch
+#[test]
+fn test_batch_event_after_send() {
+    let handler = new_handler("batch-after-send");
+
+    handler.batch_event(
+        ExtensionEventKind::Seeked,
+        ExtensionExtraEvent::Seeked([1.0]),
+    );
+    std::thread::sleep(EVENT_BATCH_INTERVAL * 4);
+    assert!(handler.pending_events.lock().unwrap().is_empty());
+
+    handler.batch_event(
+        ExtensionEventKind::Seeked,
+        ExtensionExtraEvent::Seeked([2.0]),
+    );
+    assert!(handler
+        .pending_events
+        .lock()
+        .unwrap()
+        .contains_key(&ExtensionEventKind::Seeked));
+}
+
+// Test that a store entry without a checksum is rejected before downloading
+#[tokio::test]
+async fn test_download_extension_requires_checksum() {
+    let handler = new_handler("download-no-checksum");
+    let manifest = FetchedExtensionManifest {
+        name: "Test".into(),
+        package_name: "moosync.test.nochecksum".into(),
+        logo: None,
+        description: None,
+        // Never reached, the checksum is checked first
+        url: "http://127.0.0.1:9/ext.msox".into(),
+        version: "1.0.0".into(),
+        sha256: None,
+    };
+
+    let err = handler.download_extension(manifest).await.unwrap_err();
+    assert!(err.to_string().contains("checksum"));
+}
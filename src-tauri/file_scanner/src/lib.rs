@@ -24,6 +24,7 @@ pub use scanner::{ScanState, ScannerHolder};
 mod song_scanner;
 mod types;
 mod utils;
+pub use utils::scan_file;
 
 #[cfg(test)]
 mod tests;
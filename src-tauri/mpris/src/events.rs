@@ -0,0 +1,91 @@
+// Moosync
+// Copyright (C) 2024, 2025  Moosync <support@moosync.app>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::time::Duration;
+
+use types::ui::player_details::RepeatModes;
+
+/// Events sent by the OS media controls.
+#[derive(Clone, PartialEq, Debug)]
+pub enum MediaControlEvent {
+    Play,
+    Pause,
+    Toggle,
+    Next,
+    Previous,
+    Stop,
+
+    /// Seek forward or backward by an undetermined amount.
+    Seek(SeekDirection),
+    /// Seek forward or backward by a certain amount.
+    SeekBy(SeekDirection, Duration),
+    /// Set the position/progress of the currently playing media item.
+    SetPosition(MediaPosition),
+    /// Sets the volume, from 0.0 to 1.0.
+    SetVolume(f64),
+    /// Open the URI in the media player.
+    OpenUri(String),
+
+    /// Change the repeat mode of the queue.
+    SetLoopStatus(RepeatModes),
+    /// Turn shuffle on or off.
+    SetShuffle(bool),
+    /// Play the song at this index of the queue.
+    GoTo(usize),
+
+    /// Bring the media player's user interface to the front using any appropriate mechanism available.
+    Raise,
+    /// Shut down the media player.
+    Quit,
+}
+
+/// An instant in a media item.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct MediaPosition(pub Duration);
+
+/// The direction to seek in.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum SeekDirection {
+    Forward,
+    Backward,
+}
+
+#[cfg(any(target_os = "windows", target_os = "macos"))]
+impl From<souvlaki::MediaControlEvent> for MediaControlEvent {
+    fn from(value: souvlaki::MediaControlEvent) -> Self {
+        let direction = |dir: souvlaki::SeekDirection| match dir {
+            souvlaki::SeekDirection::Forward => SeekDirection::Forward,
+            souvlaki::SeekDirection::Backward => SeekDirection::Backward,
+        };
+        match value {
+            souvlaki::MediaControlEvent::Play => Self::Play,
+            souvlaki::MediaControlEvent::Pause => Self::Pause,
+            souvlaki::MediaControlEvent::Toggle => Self::Toggle,
+            souvlaki::MediaControlEvent::Next => Self::Next,
+            souvlaki::MediaControlEvent::Previous => Self::Previous,
+            souvlaki::MediaControlEvent::Stop => Self::Stop,
+            souvlaki::MediaControlEvent::Seek(dir) => Self::Seek(direction(dir)),
+            souvlaki::MediaControlEvent::SeekBy(dir, by) => Self::SeekBy(direction(dir), by),
+            souvlaki::MediaControlEvent::SetPosition(pos) => {
+                Self::SetPosition(MediaPosition(pos.0))
+            }
+            souvlaki::MediaControlEvent::SetVolume(volume) => Self::SetVolume(volume),
+            souvlaki::MediaControlEvent::OpenUri(uri) => Self::OpenUri(uri),
+            souvlaki::MediaControlEvent::Raise => Self::Raise,
+            souvlaki::MediaControlEvent::Quit => Self::Quit,
+        }
+    }
+}
@@ -14,14 +14,24 @@
 // You should have received a copy of the GNU General Public License
 // along with this program.  If not, see <http://www.gnu.org/licenses/>.
 
-#[cfg(not(any(target_os = "android", target_os = "ios")))]
+mod events;
+
+pub use events::{MediaControlEvent, MediaPosition, SeekDirection};
+
+#[cfg(any(target_os = "windows", target_os = "macos"))]
 mod mpris;
 
-#[cfg(not(any(target_os = "android", target_os = "ios")))]
-pub use mpris::{MediaControlEvent, MprisHolder};
+#[cfg(any(target_os = "windows", target_os = "macos"))]
+pub use mpris::MprisHolder;
+
+#[cfg(target_os = "linux")]
+mod mpris_linux;
+
+#[cfg(target_os = "linux")]
+pub use mpris_linux::MprisHolder;
 
 #[cfg(target_os = "android")]
 pub mod mpris_android;
 
 #[cfg(target_os = "android")]
-pub use mpris_android::{MediaControlEvent, MprisHolder};
+pub use mpris_android::MprisHolder;
@@ -14,7 +14,6 @@
 // You should have received a copy of the GNU General Public License
 // along with this program.  If not, see <http://www.gnu.org/licenses/>.
 
-pub use souvlaki::MediaControlEvent;
 use std::{
     sync::{
         mpsc::{self, Receiver},
@@ -26,10 +25,12 @@ use std::{
 use souvlaki::{MediaControls, MediaMetadata, MediaPlayback, MediaPosition, PlatformConfig};
 use types::{
     errors::{MoosyncError, Result},
-    mpris::MprisPlayerDetails,
+    mpris::{MprisPlayerDetails, MprisQueueDetails},
     ui::player_details::PlayerState,
 };
 
+use crate::events::MediaControlEvent;
+
 pub struct MprisHolder {
     controls: Mutex<MediaControls>,
     pub event_rx: Arc<Mutex<Receiver<MediaControlEvent>>>,
@@ -64,7 +65,7 @@ impl MprisHolder {
         let (event_tx, event_rx) = mpsc::channel();
         controls
             .attach(move |event| {
-                event_tx.send(event).unwrap();
+                event_tx.send(event.into()).unwrap();
             })
             .map_err(|e| MoosyncError::String(format!("{:?}", e)))?;
 
@@ -147,6 +148,17 @@ impl MprisHolder {
         self.set_playback_state(last_state)?;
         Ok(())
     }
+
+    // Volume, repeat, shuffle and the queue are only exposed through MPRIS on linux
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub fn set_volume(&self, _volume: f64) -> Result<()> {
+        Ok(())
+    }
+
+    #[tracing::instrument(level = "debug", skip(self, _details))]
+    pub fn set_queue_details(&self, _details: MprisQueueDetails) -> Result<()> {
+        Ok(())
+    }
 }
 
 #[cfg(target_os = "windows")]
@@ -0,0 +1,694 @@
+// Moosync
+// Copyright (C) 2024, 2025  Moosync <support@moosync.app>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::{
+    collections::HashMap,
+    sync::{
+        mpsc::{self, Receiver, Sender},
+        Arc, Mutex,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender};
+use types::{
+    errors::{MoosyncError, Result},
+    mpris::{MprisPlayerDetails, MprisQueueDetails},
+    ui::player_details::{PlayerState, RepeatModes},
+};
+use zbus::{
+    dbus_interface, fdo,
+    zvariant::{ObjectPath, OwnedObjectPath, OwnedValue, Value},
+    Connection, ConnectionBuilder, SignalContext,
+};
+
+use crate::events::{MediaControlEvent, MediaPosition, SeekDirection};
+
+#[cfg(test)]
+mod test;
+
+const BUS_NAME: &str = "org.mpris.MediaPlayer2.moosync";
+const MPRIS_PATH: &str = "/org/mpris/MediaPlayer2";
+const TRACK_PATH: &str = "/app/moosync/track";
+const NO_TRACK: &str = "/org/mpris/MediaPlayer2/TrackList/NoTrack";
+
+/// Position changes larger than this are announced as seeks
+const SEEK_THRESHOLD: Duration = Duration::from_secs(2);
+
+struct PlayerData {
+    metadata: Option<MprisPlayerDetails>,
+    state: PlayerState,
+    position: Duration,
+    position_updated: Instant,
+    volume: f64,
+    queue: MprisQueueDetails,
+}
+
+impl PlayerData {
+    // Position is only reported every few seconds, clients expect it to keep moving
+    fn current_position(&self) -> Duration {
+        if self.state == PlayerState::Playing {
+            self.position + self.position_updated.elapsed()
+        } else {
+            self.position
+        }
+    }
+
+    fn set_position(&mut self, position: Duration) {
+        self.position = position;
+        self.position_updated = Instant::now();
+    }
+
+    fn current_track(&self) -> OwnedObjectPath {
+        if self.queue.tracks.is_empty() {
+            no_track()
+        } else {
+            track_path(self.queue.current_index)
+        }
+    }
+
+    fn track_ids(&self) -> Vec<OwnedObjectPath> {
+        (0..self.queue.tracks.len())
+            .map(|i| track_path(self.queue.current_index + i))
+            .collect()
+    }
+}
+
+type SharedData = Arc<Mutex<PlayerData>>;
+
+// Changes which have to be signalled on the bus
+#[derive(Debug, Clone, Copy)]
+enum Update {
+    Metadata,
+    PlaybackStatus,
+    Seeked(i64),
+    Volume,
+    Queue,
+}
+
+fn track_path(index: usize) -> OwnedObjectPath {
+    ObjectPath::try_from(format!("{}/{}", TRACK_PATH, index))
+        .unwrap()
+        .into()
+}
+
+fn no_track() -> OwnedObjectPath {
+    ObjectPath::from_static_str_unchecked(NO_TRACK).into()
+}
+
+fn parse_track_path(path: &str) -> Option<usize> {
+    path.strip_prefix(TRACK_PATH)?
+        .strip_prefix('/')?
+        .parse()
+        .ok()
+}
+
+fn metadata_map(
+    details: &MprisPlayerDetails,
+    track_id: OwnedObjectPath,
+) -> HashMap<String, OwnedValue> {
+    let mut map = HashMap::new();
+    map.insert(
+        "mpris:trackid".to_string(),
+        Value::from(track_id.into_inner()).into(),
+    );
+    if let Some(duration) = details.duration.filter(|d| *d > 0f64) {
+        map.insert(
+            "mpris:length".to_string(),
+            Value::from((duration * 1_000_000f64) as i64).into(),
+        );
+    }
+    if let Some(thumbnail) = &details.thumbnail {
+        let url = if thumbnail.contains("://") {
+            thumbnail.clone()
+        } else {
+            format!("file://{}", thumbnail)
+        };
+        map.insert("mpris:artUrl".to_string(), Value::from(url).into());
+    }
+    if let Some(title) = &details.title {
+        map.insert("xesam:title".to_string(), Value::from(title.clone()).into());
+    }
+    if let Some(album) = &details.album_name {
+        map.insert("xesam:album".to_string(), Value::from(album.clone()).into());
+    }
+    if let Some(artist) = &details.artist_name {
+        map.insert(
+            "xesam:artist".to_string(),
+            Value::from(vec![artist.clone()]).into(),
+        );
+    }
+    if let Some(album_artist) = &details.album_artist {
+        map.insert(
+            "xesam:albumArtist".to_string(),
+            Value::from(vec![album_artist.clone()]).into(),
+        );
+    }
+    if let Some(genres) = &details.genres {
+        map.insert(
+            "xesam:genre".to_string(),
+            Value::from(genres.clone()).into(),
+        );
+    }
+    map
+}
+
+fn loop_status(repeat: RepeatModes) -> &'static str {
+    match repeat {
+        RepeatModes::None => "None",
+        RepeatModes::Once | RepeatModes::Loop => "Track",
+    }
+}
+
+fn send_event(events: &Sender<MediaControlEvent>, event: MediaControlEvent) {
+    if let Err(e) = events.send(event) {
+        tracing::error!("Failed to send media control event: {:?}", e);
+    }
+}
+
+struct Root {
+    events: Sender<MediaControlEvent>,
+}
+
+#[dbus_interface(name = "org.mpris.MediaPlayer2")]
+impl Root {
+    fn raise(&self) {
+        send_event(&self.events, MediaControlEvent::Raise);
+    }
+
+    fn quit(&self) {
+        send_event(&self.events, MediaControlEvent::Quit);
+    }
+
+    #[dbus_interface(property)]
+    fn can_quit(&self) -> bool {
+        true
+    }
+
+    #[dbus_interface(property)]
+    fn can_raise(&self) -> bool {
+        true
+    }
+
+    #[dbus_interface(property)]
+    fn has_track_list(&self) -> bool {
+        true
+    }
+
+    #[dbus_interface(property)]
+    fn identity(&self) -> String {
+        "Moosync".into()
+    }
+
+    #[dbus_interface(property)]
+    fn desktop_entry(&self) -> String {
+        "moosync".into()
+    }
+
+    #[dbus_interface(property)]
+    fn supported_uri_schemes(&self) -> Vec<String> {
+        vec![
+            "file".into(),
+            "http".into(),
+            "https".into(),
+            "moosync".into(),
+        ]
+    }
+
+    #[dbus_interface(property)]
+    fn supported_mime_types(&self) -> Vec<String> {
+        [
+            "audio/mpeg",
+            "audio/flac",
+            "audio/ogg",
+            "audio/wav",
+            "audio/aac",
+            "audio/mp4",
+            "audio/webm",
+        ]
+        .into_iter()
+        .map(String::from)
+        .collect()
+    }
+}
+
+struct Player {
+    data: SharedData,
+    events: Sender<MediaControlEvent>,
+}
+
+#[dbus_interface(name = "org.mpris.MediaPlayer2.Player")]
+impl Player {
+    fn next(&self) {
+        send_event(&self.events, MediaControlEvent::Next);
+    }
+
+    fn previous(&self) {
+        send_event(&self.events, MediaControlEvent::Previous);
+    }
+
+    fn pause(&self) {
+        send_event(&self.events, MediaControlEvent::Pause);
+    }
+
+    fn play_pause(&self) {
+        send_event(&self.events, MediaControlEvent::Toggle);
+    }
+
+    fn stop(&self) {
+        send_event(&self.events, MediaControlEvent::Stop);
+    }
+
+    fn play(&self) {
+        send_event(&self.events, MediaControlEvent::Play);
+    }
+
+    /// Offset is in microseconds, negative values seek backwards
+    fn seek(&self, offset: i64) {
+        let direction = if offset < 0 {
+            SeekDirection::Backward
+        } else {
+            SeekDirection::Forward
+        };
+        send_event(
+            &self.events,
+            MediaControlEvent::SeekBy(direction, Duration::from_micros(offset.unsigned_abs())),
+        );
+    }
+
+    fn set_position(&self, track_id: ObjectPath<'_>, position: i64) {
+        let data = self.data.lock().unwrap();
+        // Requests for a track which isn't playing anymore are ignored
+        if track_id.as_str() != data.current_track().as_str() || position < 0 {
+            return;
+        }
+        let position = Duration::from_micros(position as u64);
+        let duration = data
+            .metadata
+            .as_ref()
+            .and_then(|m| m.duration)
+            .unwrap_or_default();
+        if position.as_secs_f64() > duration {
+            return;
+        }
+        drop(data);
+
+        send_event(
+            &self.events,
+            MediaControlEvent::SetPosition(MediaPosition(position)),
+        );
+    }
+
+    fn open_uri(&self, uri: String) {
+        send_event(&self.events, MediaControlEvent::OpenUri(uri));
+    }
+
+    #[dbus_interface(signal)]
+    async fn seeked(ctxt: &SignalContext<'_>, position: i64) -> zbus::Result<()>;
+
+    #[dbus_interface(property)]
+    fn playback_status(&self) -> String {
+        match self.data.lock().unwrap().state {
+            PlayerState::Playing => "Playing",
+            PlayerState::Paused | PlayerState::Loading => "Paused",
+            PlayerState::Stopped => "Stopped",
+        }
+        .into()
+    }
+
+    #[dbus_interface(property)]
+    fn loop_status(&self) -> String {
+        loop_status(self.data.lock().unwrap().queue.repeat).into()
+    }
+
+    #[dbus_interface(property)]
+    fn set_loop_status(&mut self, value: String) {
+        let repeat = match value.as_str() {
+            "Track" => RepeatModes::Loop,
+            "None" | "Playlist" => RepeatModes::None,
+            _ => {
+                tracing::warn!("Unknown loop status {}", value);
+                return;
+            }
+        };
+        send_event(&self.events, MediaControlEvent::SetLoopStatus(repeat));
+    }
+
+    #[dbus_interface(property)]
+    fn rate(&self) -> f64 {
+        1f64
+    }
+
+    #[dbus_interface(property)]
+    fn set_rate(&mut self, _value: f64) {}
+
+    #[dbus_interface(property)]
+    fn shuffle(&self) -> bool {
+        self.data.lock().unwrap().queue.shuffle
+    }
+
+    #[dbus_interface(property)]
+    fn set_shuffle(&mut self, value: bool) {
+        send_event(&self.events, MediaControlEvent::SetShuffle(value));
+    }
+
+    #[dbus_interface(property)]
+    fn metadata(&self) -> HashMap<String, OwnedValue> {
+        let data = self.data.lock().unwrap();
+        data.metadata
+            .as_ref()
+            .map(|m| metadata_map(m, data.current_track()))
+            .unwrap_or_default()
+    }
+
+    #[dbus_interface(property)]
+    fn volume(&self) -> f64 {
+        self.data.lock().unwrap().volume
+    }
+
+    #[dbus_interface(property)]
+    fn set_volume(&mut self, value: f64) {
+        send_event(
+            &self.events,
+            MediaControlEvent::SetVolume(value.clamp(0f64, 1f64)),
+        );
+    }
+
+    #[dbus_interface(property)]
+    fn position(&self) -> i64 {
+        self.data.lock().unwrap().current_position().as_micros() as i64
+    }
+
+    #[dbus_interface(property)]
+    fn minimum_rate(&self) -> f64 {
+        1f64
+    }
+
+    #[dbus_interface(property)]
+    fn maximum_rate(&self) -> f64 {
+        1f64
+    }
+
+    #[dbus_interface(property)]
+    fn can_go_next(&self) -> bool {
+        self.data.lock().unwrap().queue.can_go_next
+    }
+
+    #[dbus_interface(property)]
+    fn can_go_previous(&self) -> bool {
+        self.data.lock().unwrap().queue.can_go_previous
+    }
+
+    #[dbus_interface(property)]
+    fn can_play(&self) -> bool {
+        self.data.lock().unwrap().metadata.is_some()
+    }
+
+    #[dbus_interface(property)]
+    fn can_pause(&self) -> bool {
+        true
+    }
+
+    #[dbus_interface(property)]
+    fn can_seek(&self) -> bool {
+        self.data
+            .lock()
+            .unwrap()
+            .metadata
+            .as_ref()
+            .and_then(|m| m.duration)
+            .is_some_and(|d| d > 0f64)
+    }
+
+    #[dbus_interface(property)]
+    fn can_control(&self) -> bool {
+        true
+    }
+}
+
+struct TrackList {
+    data: SharedData,
+    events: Sender<MediaControlEvent>,
+}
+
+#[dbus_interface(name = "org.mpris.MediaPlayer2.TrackList")]
+impl TrackList {
+    fn get_tracks_metadata(
+        &self,
+        track_ids: Vec<OwnedObjectPath>,
+    ) -> Vec<HashMap<String, OwnedValue>> {
+        let data = self.data.lock().unwrap();
+        track_ids
+            .into_iter()
+            .filter_map(|track_id| {
+                let index = parse_track_path(track_id.as_str())?;
+                let details = data
+                    .queue
+                    .tracks
+                    .get(index.checked_sub(data.queue.current_index)?)?;
+                Some(metadata_map(details, track_id))
+            })
+            .collect()
+    }
+
+    fn add_track(
+        &self,
+        _uri: String,
+        _after_track: OwnedObjectPath,
+        _set_as_current: bool,
+    ) -> fdo::Result<()> {
+        Err(fdo::Error::NotSupported(
+            "The track list can't be edited".into(),
+        ))
+    }
+
+    fn remove_track(&self, _track_id: OwnedObjectPath) -> fdo::Result<()> {
+        Err(fdo::Error::NotSupported(
+            "The track list can't be edited".into(),
+        ))
+    }
+
+    fn go_to(&self, track_id: OwnedObjectPath) {
+        if let Some(index) = parse_track_path(track_id.as_str()) {
+            send_event(&self.events, MediaControlEvent::GoTo(index));
+        }
+    }
+
+    #[dbus_interface(signal)]
+    async fn track_list_replaced(
+        ctxt: &SignalContext<'_>,
+        tracks: Vec<OwnedObjectPath>,
+        current_track: OwnedObjectPath,
+    ) -> zbus::Result<()>;
+
+    #[dbus_interface(property)]
+    fn tracks(&self) -> Vec<OwnedObjectPath> {
+        self.data.lock().unwrap().track_ids()
+    }
+
+    #[dbus_interface(property)]
+    fn can_edit_tracks(&self) -> bool {
+        false
+    }
+}
+
+async fn build_connection(
+    data: SharedData,
+    events: Sender<MediaControlEvent>,
+) -> zbus::Result<Connection> {
+    ConnectionBuilder::session()?
+        .name(BUS_NAME)?
+        .serve_at(
+            MPRIS_PATH,
+            Root {
+                events: events.clone(),
+            },
+        )?
+        .serve_at(
+            MPRIS_PATH,
+            Player {
+                data: data.clone(),
+                events: events.clone(),
+            },
+        )?
+        .serve_at(MPRIS_PATH, TrackList { data, events })?
+        .build()
+        .await
+}
+
+async fn signal_updates(
+    connection: &Connection,
+    data: &SharedData,
+    mut update_rx: UnboundedReceiver<Update>,
+) -> zbus::Result<()> {
+    let object_server = connection.object_server();
+    let player = object_server.interface::<_, Player>(MPRIS_PATH).await?;
+    let ctxt = player.signal_context();
+
+    while let Some(update) = update_rx.recv().await {
+        let iface = player.get().await;
+        let res = match update {
+            Update::Metadata => {
+                iface.metadata_changed(ctxt).await?;
+                iface.can_play_changed(ctxt).await?;
+                iface.can_seek_changed(ctxt).await
+            }
+            Update::PlaybackStatus => iface.playback_status_changed(ctxt).await,
+            Update::Seeked(position) => Player::seeked(ctxt, position).await,
+            Update::Volume => iface.volume_changed(ctxt).await,
+            Update::Queue => {
+                iface.loop_status_changed(ctxt).await?;
+                iface.shuffle_changed(ctxt).await?;
+                iface.can_go_next_changed(ctxt).await?;
+                iface.can_go_previous_changed(ctxt).await?;
+
+                let (tracks, current_track) = {
+                    let data = data.lock().unwrap();
+                    (data.track_ids(), data.current_track())
+                };
+                TrackList::track_list_replaced(ctxt, tracks, current_track).await
+            }
+        };
+
+        if let Err(e) = res {
+            tracing::error!("Failed to signal mpris update {:?}: {:?}", update, e);
+        }
+    }
+    Ok(())
+}
+
+/// Exposes the player over MPRIS2 on the session bus
+pub struct MprisHolder {
+    pub event_rx: Arc<Mutex<Receiver<MediaControlEvent>>>,
+    data: SharedData,
+    update_tx: UnboundedSender<Update>,
+}
+
+impl MprisHolder {
+    #[tracing::instrument(level = "debug", skip())]
+    pub fn new() -> Result<MprisHolder> {
+        let (event_tx, event_rx) = mpsc::channel();
+        let (update_tx, update_rx) = unbounded_channel();
+        let (ready_tx, ready_rx) = mpsc::channel::<std::result::Result<(), String>>();
+
+        let data = Arc::new(Mutex::new(PlayerData {
+            metadata: None,
+            state: PlayerState::Stopped,
+            position: Duration::ZERO,
+            position_updated: Instant::now(),
+            volume: 1f64,
+            queue: MprisQueueDetails::default(),
+        }));
+
+        let server_data = data.clone();
+        thread::spawn(move || {
+            let runtime = match tokio::runtime::Builder::new_current_thread()
+                .enable_all()
+                .build()
+            {
+                Ok(runtime) => runtime,
+                Err(e) => {
+                    let _ = ready_tx.send(Err(e.to_string()));
+                    return;
+                }
+            };
+
+            runtime.block_on(async move {
+                let connection = match build_connection(server_data.clone(), event_tx).await {
+                    Ok(connection) => {
+                        let _ = ready_tx.send(Ok(()));
+                        connection
+                    }
+                    Err(e) => {
+                        let _ = ready_tx.send(Err(e.to_string()));
+                        return;
+                    }
+                };
+
+                if let Err(e) = signal_updates(&connection, &server_data, update_rx).await {
+                    tracing::error!("MPRIS server stopped: {:?}", e);
+                }
+            });
+        });
+
+        ready_rx
+            .recv()
+            .map_err(|e| MoosyncError::String(format!("{:?}", e)))?
+            .map_err(MoosyncError::String)?;
+
+        Ok(MprisHolder {
+            event_rx: Arc::new(Mutex::new(event_rx)),
+            data,
+            update_tx,
+        })
+    }
+
+    fn send_update(&self, update: Update) -> Result<()> {
+        self.update_tx
+            .send(update)
+            .map_err(|e| MoosyncError::String(format!("{:?}", e)))
+    }
+
+    #[tracing::instrument(level = "debug", skip(self, metadata))]
+    pub fn set_metadata(&self, metadata: MprisPlayerDetails) -> Result<()> {
+        let mut data = self.data.lock().unwrap();
+        data.metadata = Some(metadata);
+        data.set_position(Duration::ZERO);
+        drop(data);
+
+        self.send_update(Update::Metadata)
+    }
+
+    #[tracing::instrument(level = "debug", skip(self, state))]
+    pub fn set_playback_state(&self, state: PlayerState) -> Result<()> {
+        let mut data = self.data.lock().unwrap();
+        let position = data.current_position();
+        data.set_position(position);
+        data.state = state;
+        drop(data);
+
+        self.send_update(Update::PlaybackStatus)
+    }
+
+    #[tracing::instrument(level = "debug", skip(self, duration))]
+    pub fn set_position(&self, duration: f64) -> Result<()> {
+        let position = Duration::from_secs_f64(duration.max(0f64));
+
+        let mut data = self.data.lock().unwrap();
+        let expected = data.current_position();
+        data.set_position(position);
+        drop(data);
+
+        // Clients keep the position moving on their own, only jumps have to be announced
+        if expected.abs_diff(position) > SEEK_THRESHOLD {
+            self.send_update(Update::Seeked(position.as_micros() as i64))?;
+        }
+        Ok(())
+    }
+
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub fn set_volume(&self, volume: f64) -> Result<()> {
+        self.data.lock().unwrap().volume = volume.clamp(0f64, 1f64);
+        self.send_update(Update::Volume)
+    }
+
+    #[tracing::instrument(level = "debug", skip(self, details))]
+    pub fn set_queue_details(&self, details: MprisQueueDetails) -> Result<()> {
+        self.data.lock().unwrap().queue = details;
+        self.send_update(Update::Queue)
+    }
+}
@@ -0,0 +1,108 @@
+// Moosync
+// Copyright (C) 2024, 2025  Moosync <support@moosync.app>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use types::{mpris::MprisPlayerDetails, ui::player_details::RepeatModes};
+use zbus::zvariant::Value;
+
+use super::{loop_status, metadata_map, no_track, parse_track_path, track_path, TRACK_PATH};
+
+fn details() -> MprisPlayerDetails {
+    MprisPlayerDetails {
+        id: Some("song".into()),
+        title: Some("Title".into()),
+        artist_name: Some("Artist".into()),
+        album_name: Some("Album".into()),
+        album_artist: Some("Album artist".into()),
+        genres: Some(vec!["Rock".into()]),
+        duration: Some(180f64),
+        thumbnail: Some("/tmp/cover.png".into()),
+    }
+}
+
+// Test reading the queue index back from a track path
+#[test]
+fn test_parse_track_path() {
+    assert_eq!(parse_track_path(track_path(0).as_str()), Some(0));
+    assert_eq!(parse_track_path(track_path(42).as_str()), Some(42));
+    assert_eq!(parse_track_path(&format!("{}/7", TRACK_PATH)), Some(7));
+}
+
+// Test rejecting paths which aren't queue tracks
+#[test]
+fn test_parse_track_path_invalid() {
+    assert_eq!(parse_track_path(no_track().as_str()), None);
+    assert_eq!(parse_track_path(TRACK_PATH), None);
+    assert_eq!(parse_track_path(&format!("{}/", TRACK_PATH)), None);
+    assert_eq!(parse_track_path(&format!("{}/-1", TRACK_PATH)), None);
+    assert_eq!(parse_track_path(&format!("{}/abc", TRACK_PATH)), None);
+    assert_eq!(parse_track_path(&format!("{}1", TRACK_PATH)), None);
+    assert_eq!(parse_track_path("/org/mpris/MediaPlayer2/1"), None);
+}
+
+// Test converting song details to MPRIS metadata
+#[test]
+fn test_metadata_map() {
+    let map = metadata_map(&details(), track_path(3));
+
+    assert_eq!(
+        *map["mpris:trackid"],
+        Value::from(track_path(3).into_inner())
+    );
+    assert_eq!(*map["mpris:length"], Value::from(180_000_000i64));
+    assert_eq!(*map["mpris:artUrl"], Value::from("file:///tmp/cover.png"));
+    assert_eq!(*map["xesam:title"], Value::from("Title"));
+    assert_eq!(*map["xesam:album"], Value::from("Album"));
+    assert_eq!(
+        *map["xesam:artist"],
+        Value::from(vec!["Artist".to_string()])
+    );
+    assert_eq!(
+        *map["xesam:albumArtist"],
+        Value::from(vec!["Album artist".to_string()])
+    );
+    assert_eq!(*map["xesam:genre"], Value::from(vec!["Rock".to_string()]));
+}
+
+// Test leaving out missing fields and unknown durations
+#[test]
+fn test_metadata_map_missing_fields() {
+    let details = MprisPlayerDetails {
+        id: None,
+        title: None,
+        artist_name: None,
+        album_name: None,
+        album_artist: None,
+        genres: None,
+        duration: Some(0f64),
+        thumbnail: Some("https://example.com/cover.png".into()),
+    };
+    let map = metadata_map(&details, no_track());
+
+    assert_eq!(map.len(), 2);
+    assert!(map.contains_key("mpris:trackid"));
+    assert_eq!(
+        *map["mpris:artUrl"],
+        Value::from("https://example.com/cover.png")
+    );
+}
+
+// Test mapping repeat modes to MPRIS loop statuses
+#[test]
+fn test_loop_status() {
+    assert_eq!(loop_status(RepeatModes::None), "None");
+    assert_eq!(loop_status(RepeatModes::Once), "Track");
+    assert_eq!(loop_status(RepeatModes::Loop), "Track");
+}
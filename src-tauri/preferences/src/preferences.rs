@@ -218,10 +218,14 @@ impl PreferenceConfig {
         #[cfg(not(any(target_os = "android", target_os = "ios")))]
         {
             let data: String = self.load_selective(key.clone())?;
-            let mut split = data.split(':');
-            let nonce = split.next().unwrap();
-            let nonce = GenericArray::clone_from_slice(&hex::decode(nonce).unwrap()[0..12]);
-            let ciphertext = hex::decode(split.next().unwrap()).unwrap();
+            let invalid = || MoosyncError::String(format!("{} is not encrypted", key));
+            let (nonce, ciphertext) = data.split_once(':').ok_or_else(invalid)?;
+            let nonce = hex::decode(nonce).map_err(|_| invalid())?;
+            if nonce.len() < 12 {
+                return Err(invalid());
+            }
+            let nonce = GenericArray::clone_from_slice(&nonce[0..12]);
+            let ciphertext = hex::decode(ciphertext).map_err(|_| invalid())?;
 
             let secret = self.secret.lock().unwrap();
             let cipher = ChaCha20Poly1305::new(&secret);
@@ -207,6 +207,31 @@ fn test_secure_preferences() -> Result<()> {
     Ok(())
 }
 
+#[test]
+fn test_secure_preferences_plain_value() -> Result<()> {
+    let test_dir = setup_test_dir();
+
+    let prefs = PreferenceConfig::new(test_dir.clone())?;
+
+    // Values saved before a preference was moved to secure storage are plain text
+    prefs.save_selective("plain_key".to_string(), Some("not encrypted"))?;
+    prefs.save_selective("plain_colon_key".to_string(), Some("abc:xyz"))?;
+    prefs.save_selective("short_nonce_key".to_string(), Some("abcd:abcd"))?;
+
+    assert!(prefs.get_secure::<String>("plain_key".to_string()).is_err());
+    assert!(prefs.get_secure::<String>("plain_colon_key".to_string()).is_err());
+    assert!(prefs.get_secure::<String>("short_nonce_key".to_string()).is_err());
+    assert!(prefs.get_secure::<String>("missing_key".to_string()).is_err());
+
+    // The plain value can be replaced with an encrypted one
+    prefs.set_secure("plain_key".to_string(), Some("secret".to_string()))?;
+    let loaded: String = prefs.get_secure("plain_key".to_string())?;
+    assert_eq!(loaded, "secret");
+
+    cleanup_test_dir(test_dir);
+    Ok(())
+}
+
 #[test]
 fn test_update_preferences() -> Result<()> {
     let test_dir = setup_test_dir();
@@ -0,0 +1,554 @@
+// Moosync
+// Copyright (C) 2024, 2025  Moosync <support@moosync.app>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::{
+    collections::{hash_map::DefaultHasher, HashMap},
+    fs::{self, File, OpenOptions},
+    hash::{Hash, Hasher},
+    io::{self, Read, Seek, SeekFrom, Write},
+    ops::Range,
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex, MutexGuard,
+    },
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use serde::{Deserialize, Serialize};
+use stream_download::storage::StorageProvider;
+use tracing::{debug, error, info, warn};
+use types::{errors::Result, ui::player_details::AudioCacheStats};
+
+/// 1 GiB, used until the size preference is loaded
+pub const DEFAULT_CACHE_SIZE: u64 = 1024 * 1024 * 1024;
+const INDEX_FILE: &str = "index.json";
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct CacheIndex {
+    entries: HashMap<String, CacheEntry>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct CacheEntry {
+    file: String,
+    size: u64,
+    complete: bool,
+    last_access: u64,
+}
+
+#[derive(Debug, Default)]
+struct CacheState {
+    index: CacheIndex,
+    /// Open readers and writers per cache file. Files replaced while open are
+    /// only deleted once the last one is dropped.
+    in_use: HashMap<String, usize>,
+    hits: u64,
+    misses: u64,
+}
+
+#[derive(Debug)]
+struct CacheInner {
+    dir: PathBuf,
+    max_size: AtomicU64,
+    state: Mutex<CacheState>,
+}
+
+/// Size bounded LRU cache for audio streamed by the rodio player.
+/// Entries are keyed by song id (or url) and only fully downloaded
+/// entries are served back, partial downloads are dropped once nothing reads them.
+#[derive(Debug, Clone)]
+pub struct AudioCache {
+    inner: Arc<CacheInner>,
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+fn file_name(key: &str) -> String {
+    let mut hasher = DefaultHasher::new();
+    key.hash(&mut hasher);
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_nanos())
+        .unwrap_or_default();
+    format!("{:016x}-{}", hasher.finish(), nanos)
+}
+
+impl AudioCache {
+    #[tracing::instrument(level = "debug", skip())]
+    pub fn new(dir: PathBuf, max_size: u64) -> Result<Self> {
+        if !dir.exists() {
+            fs::create_dir_all(&dir)?;
+        }
+
+        let index_path = dir.join(INDEX_FILE);
+        let mut index: CacheIndex = match fs::read(&index_path) {
+            Ok(data) => serde_json::from_slice(&data).unwrap_or_else(|e| {
+                warn!("Failed to parse audio cache index, starting fresh: {:?}", e);
+                CacheIndex::default()
+            }),
+            Err(_) => CacheIndex::default(),
+        };
+
+        // Partial downloads can't be resumed across restarts
+        index.entries.retain(|key, entry| {
+            let path = dir.join(&entry.file);
+            if entry.complete && path.exists() {
+                return true;
+            }
+            debug!("Dropping stale cache entry {}", key);
+            let _ = fs::remove_file(path);
+            false
+        });
+
+        // Remove files left behind by entries which never made it into the index
+        if let Ok(read_dir) = fs::read_dir(&dir) {
+            for file in read_dir.flatten() {
+                let name = file.file_name().to_string_lossy().to_string();
+                if name != INDEX_FILE && !index.entries.values().any(|e| e.file == name) {
+                    let path = file.path();
+                    let res = if path.is_dir() {
+                        fs::remove_dir_all(path)
+                    } else {
+                        fs::remove_file(path)
+                    };
+                    if let Err(e) = res {
+                        debug!("Failed to remove orphaned cache file {}: {:?}", name, e);
+                    }
+                }
+            }
+        }
+
+        let cache = Self {
+            inner: Arc::new(CacheInner {
+                dir,
+                max_size: AtomicU64::new(max_size),
+                state: Mutex::new(CacheState {
+                    index,
+                    ..Default::default()
+                }),
+            }),
+        };
+
+        {
+            let mut state = cache.lock();
+            cache.evict(&mut state, None);
+            cache.save(&state);
+        }
+
+        Ok(cache)
+    }
+
+    fn lock(&self) -> MutexGuard<'_, CacheState> {
+        self.inner
+            .state
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn save(&self, state: &CacheState) {
+        let res = serde_json::to_vec(&state.index)
+            .map_err(io::Error::other)
+            .and_then(|data| fs::write(self.inner.dir.join(INDEX_FILE), data));
+        if let Err(e) = res {
+            error!("Failed to write audio cache index: {:?}", e);
+        }
+    }
+
+    fn remove_file(&self, file: &str) {
+        if let Err(e) = fs::remove_file(self.inner.dir.join(file)) {
+            warn!("Failed to remove cached file {}: {:?}", file, e);
+        }
+    }
+
+    /// Drops the entry from the index. Its file stays on disk while it is open.
+    fn remove_entry(&self, state: &mut CacheState, key: &str) {
+        if let Some(entry) = state.index.entries.remove(key) {
+            if !state.in_use.contains_key(&entry.file) {
+                self.remove_file(&entry.file);
+            }
+        }
+    }
+
+    /// Changes the maximum size, evicting entries if the cache doesn't fit anymore
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub fn set_max_size(&self, max_size: u64) {
+        self.inner.max_size.store(max_size, Ordering::Relaxed);
+        let mut state = self.lock();
+        self.evict(&mut state, None);
+        self.save(&state);
+    }
+
+    /// Removes least recently used entries until the cache fits in max_size.
+    /// Entries that are being read or written are never evicted.
+    fn evict(&self, state: &mut CacheState, keep: Option<&str>) {
+        let max_size = self.inner.max_size.load(Ordering::Relaxed);
+        loop {
+            let total: u64 = state.index.entries.values().map(|e| e.size).sum();
+            if total <= max_size {
+                return;
+            }
+
+            let victim = state
+                .index
+                .entries
+                .iter()
+                .filter(|(key, entry)| {
+                    Some(key.as_str()) != keep && !state.in_use.contains_key(&entry.file)
+                })
+                .min_by_key(|(_, entry)| entry.last_access)
+                .map(|(key, _)| key.clone());
+
+            match victim {
+                Some(key) => {
+                    info!("Evicting {} from audio cache", key);
+                    self.remove_entry(state, &key);
+                }
+                None => return,
+            }
+        }
+    }
+
+    fn acquire(&self, state: &mut CacheState, file: &str) -> InUseGuard {
+        *state.in_use.entry(file.to_string()).or_default() += 1;
+        InUseGuard {
+            cache: self.clone(),
+            file: file.to_string(),
+        }
+    }
+
+    fn release(&self, file: &str) {
+        let mut state = self.lock();
+        let remaining = match state.in_use.get_mut(file) {
+            Some(count) => {
+                *count -= 1;
+                *count
+            }
+            None => return,
+        };
+        if remaining > 0 {
+            return;
+        }
+        state.in_use.remove(file);
+
+        let owner = state
+            .index
+            .entries
+            .iter()
+            .find(|(_, entry)| entry.file == file)
+            .map(|(key, entry)| (key.clone(), entry.complete));
+        match owner {
+            Some((key, false)) => {
+                debug!("Dropping partial download for {}", key);
+                self.remove_entry(&mut state, &key);
+                self.save(&state);
+            }
+            Some(_) => {}
+            // Replaced or removed while it was open
+            None => self.remove_file(file),
+        }
+    }
+
+    /// Returns a reader over the cached file if the entry was fully downloaded
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub fn open(&self, key: &str) -> Option<CacheReader> {
+        let mut state = self.lock();
+        let entry = state.index.entries.get_mut(key)?;
+        if !entry.complete {
+            return None;
+        }
+
+        let path = self.inner.dir.join(&entry.file);
+        match File::open(&path) {
+            Ok(file) => {
+                entry.last_access = now();
+                let file_name = entry.file.clone();
+                state.hits += 1;
+                let guard = self.acquire(&mut state, &file_name);
+                self.save(&state);
+                Some(CacheReader {
+                    file,
+                    _guard: guard,
+                })
+            }
+            Err(e) => {
+                warn!("Cached file for {} is unreadable: {:?}", key, e);
+                self.remove_entry(&mut state, key);
+                self.save(&state);
+                None
+            }
+        }
+    }
+
+    /// Creates a storage provider which writes the stream into the cache.
+    /// The entry is served by [`AudioCache::open`] once every byte was downloaded.
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub fn storage(&self, key: &str) -> CacheStorageProvider {
+        let mut state = self.lock();
+        state.misses += 1;
+
+        self.remove_entry(&mut state, key);
+
+        let file = file_name(key);
+        state.index.entries.insert(
+            key.to_string(),
+            CacheEntry {
+                file: file.clone(),
+                size: 0,
+                complete: false,
+                last_access: now(),
+            },
+        );
+        self.save(&state);
+
+        CacheStorageProvider {
+            cache: self.clone(),
+            key: key.to_string(),
+            path: self.inner.dir.join(file),
+        }
+    }
+
+    fn mark_complete(&self, key: &str, file: &str, size: u64) {
+        let mut state = self.lock();
+        let Some(entry) = state.index.entries.get_mut(key) else {
+            return;
+        };
+
+        // A newer download for the same key replaced this one
+        if entry.file != file {
+            return;
+        }
+
+        info!("Finished caching {} ({} bytes)", key, size);
+        entry.complete = true;
+        entry.size = size;
+        entry.last_access = now();
+
+        self.evict(&mut state, Some(key));
+        self.save(&state);
+    }
+
+    /// Access times only have second precision, tests set them to order entries
+    #[cfg(test)]
+    pub(crate) fn set_last_access(&self, key: &str, last_access: u64) {
+        let mut state = self.lock();
+        if let Some(entry) = state.index.entries.get_mut(key) {
+            entry.last_access = last_access;
+        }
+    }
+
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub fn stats(&self) -> AudioCacheStats {
+        let state = self.lock();
+        AudioCacheStats {
+            entries: state.index.entries.values().filter(|e| e.complete).count(),
+            size: state.index.entries.values().map(|e| e.size).sum(),
+            max_size: self.inner.max_size.load(Ordering::Relaxed),
+            hits: state.hits,
+            misses: state.misses,
+        }
+    }
+
+    /// Removes every entry which isn't currently playing
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub fn clear(&self) -> Result<()> {
+        let mut state = self.lock();
+        let keys: Vec<String> = state
+            .index
+            .entries
+            .iter()
+            .filter(|(_, entry)| !state.in_use.contains_key(&entry.file))
+            .map(|(key, _)| key.clone())
+            .collect();
+        for key in keys {
+            self.remove_entry(&mut state, &key);
+        }
+        state.hits = 0;
+        state.misses = 0;
+        self.save(&state);
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+struct InUseGuard {
+    cache: AudioCache,
+    file: String,
+}
+
+impl Drop for InUseGuard {
+    fn drop(&mut self) {
+        self.cache.release(&self.file);
+    }
+}
+
+#[derive(Debug)]
+pub struct CacheReader {
+    file: File,
+    _guard: InUseGuard,
+}
+
+impl Read for CacheReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.file.read(buf)
+    }
+}
+
+impl Seek for CacheReader {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        self.file.seek(pos)
+    }
+}
+
+/// Writes the downloaded stream into the cache file and keeps track of
+/// which ranges were written, so that the entry is only marked complete
+/// once the whole content is on disk.
+#[derive(Debug)]
+pub struct CacheWriter {
+    file: File,
+    key: String,
+    file_name: String,
+    content_length: Option<u64>,
+    written: Vec<Range<u64>>,
+    pos: u64,
+    complete: bool,
+    guard: InUseGuard,
+}
+
+impl CacheWriter {
+    fn add_range(&mut self, range: Range<u64>) {
+        self.written.push(range);
+        self.written.sort_by_key(|r| r.start);
+
+        let mut merged: Vec<Range<u64>> = Vec::with_capacity(self.written.len());
+        for range in self.written.drain(..) {
+            match merged.last_mut() {
+                Some(last) if range.start <= last.end => last.end = last.end.max(range.end),
+                _ => merged.push(range),
+            }
+        }
+        self.written = merged;
+    }
+
+    fn check_complete(&mut self) {
+        if self.complete {
+            return;
+        }
+
+        if let Some(len) = self.content_length {
+            if let [range] = self.written.as_slice() {
+                if range.start == 0 && range.end >= len {
+                    self.complete = true;
+                    if let Err(e) = self.file.flush() {
+                        error!("Failed to flush cached file: {:?}", e);
+                        return;
+                    }
+                    self.guard
+                        .cache
+                        .mark_complete(&self.key, &self.file_name, len);
+                }
+            }
+        }
+    }
+}
+
+impl Write for CacheWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.file.write(buf)?;
+        if written > 0 {
+            let start = self.pos;
+            self.pos += written as u64;
+            self.add_range(start..self.pos);
+            self.check_complete();
+        }
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+impl Seek for CacheWriter {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        self.pos = self.file.seek(pos)?;
+        Ok(self.pos)
+    }
+}
+
+#[derive(Debug)]
+pub struct CacheStorageProvider {
+    cache: AudioCache,
+    key: String,
+    path: PathBuf,
+}
+
+impl StorageProvider for CacheStorageProvider {
+    type Reader = CacheReader;
+    type Writer = CacheWriter;
+
+    fn into_reader_writer(
+        self,
+        content_length: Option<u64>,
+    ) -> io::Result<(Self::Reader, Self::Writer)> {
+        let writer = OpenOptions::new()
+            .create(true)
+            .write(true)
+            .truncate(true)
+            .open(&self.path)?;
+        let reader = File::open(&self.path)?;
+
+        let file_name = self
+            .path
+            .file_name()
+            .map(|f| f.to_string_lossy().to_string())
+            .unwrap_or_default();
+
+        let (reader_guard, writer_guard) = {
+            let mut state = self.cache.lock();
+            (
+                self.cache.acquire(&mut state, &file_name),
+                self.cache.acquire(&mut state, &file_name),
+            )
+        };
+
+        Ok((
+            CacheReader {
+                file: reader,
+                _guard: reader_guard,
+            },
+            CacheWriter {
+                file: writer,
+                key: self.key,
+                file_name,
+                content_length,
+                written: vec![],
+                pos: 0,
+                complete: false,
+                guard: writer_guard,
+            },
+        ))
+    }
+
+    fn max_capacity(&self) -> Option<usize> {
+        None
+    }
+}
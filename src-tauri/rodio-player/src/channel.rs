@@ -0,0 +1,89 @@
+// Moosync
+// Copyright (C) 2024, 2025  Moosync <support@moosync.app>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::{
+    io::{self, Read, Seek, SeekFrom},
+    sync::{
+        mpsc::{Receiver, SyncSender},
+        Mutex,
+    },
+};
+
+/// Sends a chunk to the reader. Returns false once the reader was dropped
+pub fn push(tx: &SyncSender<io::Result<Vec<u8>>>, data: Vec<u8>) -> bool {
+    data.is_empty() || tx.send(Ok(data)).is_ok()
+}
+
+/// Reads chunks pushed by a producer thread or task. Reaches EOF once the
+/// sender is dropped. Only no-op seeks are supported.
+#[derive(Debug)]
+pub struct ChannelReader {
+    rx: Mutex<Receiver<io::Result<Vec<u8>>>>,
+    buf: Vec<u8>,
+    offset: usize,
+    pos: u64,
+}
+
+impl ChannelReader {
+    pub fn new(rx: Receiver<io::Result<Vec<u8>>>) -> Self {
+        Self {
+            rx: Mutex::new(rx),
+            buf: vec![],
+            offset: 0,
+            pos: 0,
+        }
+    }
+}
+
+impl Read for ChannelReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.offset >= self.buf.len() {
+            let next = self
+                .rx
+                .lock()
+                .map_err(|_| io::Error::other("Stream receiver poisoned"))?
+                .recv();
+            match next {
+                Ok(Ok(data)) => {
+                    self.buf = data;
+                    self.offset = 0;
+                }
+                Ok(Err(e)) => return Err(e),
+                // Producer is done
+                Err(_) => return Ok(0),
+            }
+        }
+
+        let len = buf.len().min(self.buf.len() - self.offset);
+        buf[..len].copy_from_slice(&self.buf[self.offset..self.offset + len]);
+        self.offset += len;
+        self.pos += len as u64;
+        Ok(len)
+    }
+}
+
+impl Seek for ChannelReader {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        match pos {
+            SeekFrom::Current(0) => Ok(self.pos),
+            SeekFrom::Start(pos) if pos == self.pos => Ok(self.pos),
+            _ => Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "Live streams can't be seeked",
+            )),
+        }
+    }
+}
@@ -0,0 +1,215 @@
+// Moosync
+// Copyright (C) 2024, 2025  Moosync <support@moosync.app>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::{io, sync::mpsc::sync_channel, thread};
+
+use futures::channel::oneshot;
+use reqwest::Url;
+use tracing::{debug, error, info};
+use types::errors::{MoosyncError, Result};
+
+use crate::channel::{push, ChannelReader};
+
+/// Chunks of audio waiting for the decoder
+const BUFFERED_CHUNKS: usize = 16;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum IcyState {
+    Audio(usize),
+    MetaLength,
+    Meta(usize),
+}
+
+/// Splits an Icecast/Shoutcast stream into audio and the metadata blocks
+/// interleaved every `metaint` bytes.
+#[derive(Debug)]
+pub struct IcyParser {
+    metaint: usize,
+    state: IcyState,
+    meta: Vec<u8>,
+}
+
+impl IcyParser {
+    pub fn new(metaint: usize) -> Self {
+        Self {
+            metaint,
+            state: IcyState::Audio(metaint),
+            meta: vec![],
+        }
+    }
+
+    /// Returns the audio bytes of data and every stream title completed by it
+    pub fn push(&mut self, mut data: &[u8]) -> (Vec<u8>, Vec<String>) {
+        let mut audio = Vec::with_capacity(data.len());
+        let mut titles = vec![];
+
+        while !data.is_empty() {
+            match self.state {
+                IcyState::Audio(remaining) => {
+                    let len = remaining.min(data.len());
+                    audio.extend_from_slice(&data[..len]);
+                    data = &data[len..];
+                    self.state = if remaining == len {
+                        IcyState::MetaLength
+                    } else {
+                        IcyState::Audio(remaining - len)
+                    };
+                }
+                IcyState::MetaLength => {
+                    let len = data[0] as usize * 16;
+                    data = &data[1..];
+                    self.state = if len == 0 {
+                        IcyState::Audio(self.metaint)
+                    } else {
+                        IcyState::Meta(len)
+                    };
+                }
+                IcyState::Meta(remaining) => {
+                    let len = remaining.min(data.len());
+                    self.meta.extend_from_slice(&data[..len]);
+                    data = &data[len..];
+                    if remaining == len {
+                        if let Some(title) = parse_stream_title(&self.meta) {
+                            titles.push(title);
+                        }
+                        self.meta.clear();
+                        self.state = IcyState::Audio(self.metaint);
+                    } else {
+                        self.state = IcyState::Meta(remaining - len);
+                    }
+                }
+            }
+        }
+
+        (audio, titles)
+    }
+}
+
+/// Extracts StreamTitle from a metadata block like `StreamTitle='Artist - Title';StreamUrl='';`
+pub fn parse_stream_title(meta: &[u8]) -> Option<String> {
+    let meta = String::from_utf8_lossy(meta);
+    let meta = meta.trim_end_matches('\0');
+
+    const KEY: &str = "StreamTitle='";
+    let start = meta.find(KEY)? + KEY.len();
+    let rest = &meta[start..];
+    // Titles may contain quotes themselves, the value ends at the quote before the separator
+    let end = rest
+        .find("';")
+        .or_else(|| rest.rfind('\''))
+        .unwrap_or(rest.len());
+    Some(rest[..end].trim().to_string())
+}
+
+/// Requests interleaved metadata from url. Returns None if the server doesn't
+/// support it, in which case the stream should be played as a regular file.
+/// Title changes are passed to on_title as they arrive.
+///
+/// The stream is read on its own thread so that a full buffer never blocks
+/// a runtime worker.
+#[tracing::instrument(level = "debug", skip(on_title))]
+pub async fn connect<F>(url: Url, on_title: F) -> Result<Option<ChannelReader>>
+where
+    F: Fn(String) + Send + 'static,
+{
+    let (tx, rx) = sync_channel(BUFFERED_CHUNKS);
+    let (probe_tx, probe_rx) = oneshot::channel::<Result<bool>>();
+    thread::Builder::new()
+        .name("icy-reader".into())
+        .spawn(move || {
+            let runtime = match tokio::runtime::Builder::new_current_thread()
+                .enable_all()
+                .build()
+            {
+                Ok(runtime) => runtime,
+                Err(e) => {
+                    let _ = probe_tx.send(Err(e.into()));
+                    return;
+                }
+            };
+
+            runtime.block_on(async move {
+                let (mut response, metaint) = match open(url).await {
+                    Ok(Some(res)) => {
+                        if probe_tx.send(Ok(true)).is_err() {
+                            return;
+                        }
+                        res
+                    }
+                    Ok(None) => {
+                        let _ = probe_tx.send(Ok(false));
+                        return;
+                    }
+                    Err(e) => {
+                        let _ = probe_tx.send(Err(e));
+                        return;
+                    }
+                };
+
+                info!("Parsing ICY metadata every {} bytes", metaint);
+                let mut parser = IcyParser::new(metaint);
+                let mut last_title: Option<String> = None;
+                loop {
+                    match response.chunk().await {
+                        Ok(Some(chunk)) => {
+                            let (audio, titles) = parser.push(&chunk);
+                            for title in titles {
+                                if last_title.as_ref() != Some(&title) {
+                                    debug!("Stream title changed: {}", title);
+                                    on_title(title.clone());
+                                    last_title = Some(title);
+                                }
+                            }
+                            if !push(&tx, audio) {
+                                debug!("ICY reader dropped");
+                                return;
+                            }
+                        }
+                        Ok(None) => return,
+                        Err(e) => {
+                            error!("ICY stream failed: {:?}", e);
+                            let _ = tx.send(Err(io::Error::other(e.to_string())));
+                            return;
+                        }
+                    }
+                }
+            });
+        })?;
+
+    let has_metadata = probe_rx
+        .await
+        .map_err(|_| MoosyncError::String("ICY reader stopped".into()))??;
+    Ok(has_metadata.then(|| ChannelReader::new(rx)))
+}
+
+/// Returns the response and its metadata interval if the server interleaves metadata
+async fn open(url: Url) -> Result<Option<(reqwest::Response, usize)>> {
+    let response = reqwest::Client::new()
+        .get(url)
+        .header("Icy-MetaData", "1")
+        .send()
+        .await?
+        .error_for_status()?;
+
+    let metaint = response
+        .headers()
+        .get("icy-metaint")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.trim().parse::<usize>().ok())
+        .filter(|v| *v > 0);
+
+    Ok(metaint.map(|metaint| (response, metaint)))
+}
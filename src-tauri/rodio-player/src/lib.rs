@@ -14,12 +14,17 @@
 // You should have received a copy of the GNU General Public License
 // along with this program.  If not, see <http://www.gnu.org/licenses/>.
 
+mod cache;
+mod channel;
+mod icy;
+mod segmented;
+
 use std::{
     fs,
     io::BufReader,
     path::PathBuf,
-    str::FromStr,
     sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
         mpsc::{channel, Receiver, Sender},
         Arc, Mutex,
     },
@@ -27,18 +32,41 @@ use std::{
     time::Duration,
 };
 
-use rodio::{Decoder, OutputStream, Sink};
-use stream_download::{storage::temp::TempStorageProvider, Settings, StreamDownload};
-use tracing::{debug, error, info, trace};
-use types::{errors::Result, ui::player_details::PlayerEvents};
+use cache::{AudioCache, DEFAULT_CACHE_SIZE};
+use rodio::{
+    cpal::traits::{DeviceTrait, HostTrait},
+    Decoder, OutputStream, Sink, Source as _,
+};
+use segmented::{SegmentReader, StreamKind};
+use stream_download::{Settings, StreamDownload};
+use tracing::{debug, error, info, trace, warn};
+use types::{
+    errors::{MoosyncError, Result},
+    songs::SongType,
+    ui::player_details::{AudioCacheStats, PlayerEvents, StreamMetadata},
+};
+
+/// How often the default output device is checked for being unplugged or switched,
+/// and opening it is retried while there is no output
+const DEVICE_POLL_INTERVAL: Duration = Duration::from_secs(2);
 
 pub struct RodioPlayer {
     tx: Sender<RodioCommand>,
     events_rx: Arc<Mutex<Receiver<PlayerEvents>>>,
+    cache: AudioCache,
 }
 
-enum RodioCommand {
-    SetSrc(String),
+/// Source the UI asked to play
+#[derive(Debug, Clone)]
+struct Source {
+    src: String,
+    cache_key: Option<String>,
+    song_type: Option<SongType>,
+}
+
+/// Commands which need an open output
+enum PlayerCommand {
+    SetSrc(Source),
     Play,
     Pause,
     Stop,
@@ -46,27 +74,95 @@ enum RodioCommand {
     Seek(u64),
 }
 
+enum RodioCommand {
+    Player(PlayerCommand),
+    /// Replaces the output, handled without a sink
+    Reinitialize,
+}
+
+/// Keeps the output stream alive for as long as the sink is in use.
+/// Dropping the stream silences every sink created from its handle.
+struct AudioOutput {
+    _stream: OutputStream,
+    sink: Arc<Sink>,
+}
+
 impl RodioPlayer {
     #[tracing::instrument(level = "debug", skip())]
-    pub fn new(cache_dir: PathBuf) -> Self {
+    pub fn new(cache_dir: PathBuf) -> Result<Self> {
         let (events_tx, events_rx) = channel::<PlayerEvents>();
-        let cache_dir = cache_dir.join("rodio");
-        if !cache_dir.exists() {
-            fs::create_dir(cache_dir.clone()).unwrap();
-        }
-        let tx = Self::initialize(events_tx, cache_dir);
-        Self {
+        let cache = AudioCache::new(cache_dir.join("rodio"), DEFAULT_CACHE_SIZE)?;
+        let tx = Self::initialize(events_tx, cache.clone());
+        Ok(Self {
             tx,
             events_rx: Arc::new(Mutex::new(events_rx)),
-        }
+            cache,
+        })
     }
 
-    async fn set_src(cache_dir: PathBuf, src: String, sink: &Arc<Sink>) -> Result<()> {
+    /// Appends source to the sink. Segment streams start at start seconds, the
+    /// decoder seeks every other source. Returns whether seeking has to load
+    /// source again, which is the case for segment streams that aren't live.
+    async fn set_src<F>(
+        cache: &AudioCache,
+        source: Source,
+        start: u64,
+        sink: &Arc<Sink>,
+        on_title: F,
+    ) -> Result<bool>
+    where
+        F: Fn(String) + Send + 'static,
+    {
+        let Source {
+            src,
+            cache_key,
+            song_type,
+        } = source;
+        if let Some(kind) = StreamKind::from_song_type(song_type, &src) {
+            trace!("Creating {:?} segment stream", kind);
+            let url = src
+                .parse()
+                .map_err(|e| MoosyncError::String(format!("Invalid url {}: {:?}", src, e)))?;
+            let (reader, skip) = SegmentReader::open(kind, url, Duration::from_secs(start)).await?;
+            let seek_by_reload = !reader.live();
+            let decoder = Decoder::new(reader)?;
+            sink.append(decoder.skip_duration(skip));
+            return Ok(seek_by_reload);
+        }
+
         if src.starts_with("http") {
+            let cache_key = cache_key.unwrap_or_else(|| src.clone());
+            if let Some(reader) = cache.open(&cache_key) {
+                trace!("Playing {} from cache", cache_key);
+                let decoder = Decoder::new(BufReader::new(reader))?;
+                sink.append(decoder);
+                return Ok(false);
+            }
+
+            let url: reqwest::Url = src
+                .parse()
+                .map_err(|e| MoosyncError::String(format!("Invalid url {}: {:?}", src, e)))?;
+
+            // Internet radios interleave now playing info with the audio and never end,
+            // so they are played directly instead of going through the cache.
+            // Only plain URL songs, which radio stations are stored as, are probed
+            if song_type == Some(SongType::URL) {
+                match icy::connect(url.clone(), on_title).await {
+                    Ok(Some(reader)) => {
+                        trace!("Playing ICY stream");
+                        let decoder = Decoder::new(reader)?;
+                        sink.append(decoder);
+                        return Ok(false);
+                    }
+                    Ok(None) => {}
+                    Err(e) => warn!("Failed to probe for ICY metadata: {:?}", e),
+                }
+            }
+
             trace!("Creating stream");
             match StreamDownload::new_http(
-                src.parse().unwrap(),
-                TempStorageProvider::new_in(cache_dir),
+                url,
+                cache.storage(&cache_key),
                 Settings::default()
                     .on_progress(move |_cl, state, _c| {
                         tracing::debug!("Progress: {}", state.current_position)
@@ -83,18 +179,18 @@ impl RodioPlayer {
                     sink.append(decoder);
                     trace!("decoder appended");
 
-                    Ok(())
+                    Ok(false)
                 }
                 Err(e) => Err(e.to_string().into()),
             }
         } else {
-            let path = PathBuf::from_str(src.as_str()).unwrap();
+            let path = PathBuf::from(src.as_str());
             if path.exists() {
                 let file = fs::File::open(path)?;
                 let reader = BufReader::new(file);
                 let decoder = Decoder::new(reader)?;
                 sink.append(decoder);
-                return Ok(());
+                return Ok(false);
             }
 
             Err("Failed to read src".into())
@@ -105,112 +201,267 @@ impl RodioPlayer {
         self.events_rx.clone()
     }
 
-    fn send_event(events_tx: Sender<PlayerEvents>, event: PlayerEvents) {
-        events_tx.send(event).unwrap();
+    fn send_event(events_tx: &Sender<PlayerEvents>, event: PlayerEvents) {
+        if let Err(e) = events_tx.send(event) {
+            warn!("Dropping rodio event, receiver is gone: {:?}", e.0);
+        }
+    }
+
+    fn try_open_output() -> Result<AudioOutput> {
+        let (stream, stream_handle) = OutputStream::try_default()?;
+        let sink = Sink::try_new(&stream_handle)?;
+        Ok(AudioOutput {
+            _stream: stream,
+            sink: Arc::new(sink),
+        })
     }
 
-    fn initialize(events_tx: Sender<PlayerEvents>, cache_dir: PathBuf) -> Sender<RodioCommand> {
+    /// Opens the default output device. Failures are only logged here, the device
+    /// watcher keeps retrying while `has_output` is false.
+    fn open_output(has_output: &AtomicBool) -> Option<AudioOutput> {
+        let output = match Self::try_open_output() {
+            Ok(output) => {
+                info!("Opened audio output");
+                Some(output)
+            }
+            Err(e) => {
+                warn!("Failed to open audio output: {:?}", e);
+                None
+            }
+        };
+        has_output.store(output.is_some(), Ordering::SeqCst);
+        output
+    }
+
+    fn default_output_name() -> Option<String> {
+        rodio::cpal::default_host()
+            .default_output_device()
+            .and_then(|d| d.name().ok())
+    }
+
+    /// Rodio doesn't report a lost output device, so the default device is polled
+    /// and the output reopened whenever it goes away or changes, or couldn't be opened
+    fn watch_output_device(tx: Sender<RodioCommand>, has_output: Arc<AtomicBool>) {
+        thread::spawn(move || {
+            let mut last = Self::default_output_name();
+            loop {
+                thread::sleep(DEVICE_POLL_INTERVAL);
+                let current = Self::default_output_name();
+                if current == last && has_output.load(Ordering::SeqCst) {
+                    continue;
+                }
+
+                if current != last {
+                    info!(
+                        "Default output device changed from {:?} to {:?}",
+                        last, current
+                    );
+                }
+                last = current;
+                if tx.send(RodioCommand::Reinitialize).is_err() {
+                    return;
+                }
+            }
+        });
+    }
+
+    /// Position and whether to play when the last src is loaded on a new output.
+    /// None once the sink ran out, a finished song isn't started again.
+    /// offset is where the sink's source started in the song.
+    fn resume_position(sink: Option<&Sink>, offset: u64) -> Option<(u64, bool)> {
+        match sink {
+            Some(sink) if sink.empty() => None,
+            Some(sink) => Some((offset + sink.get_pos().as_secs(), !sink.is_paused())),
+            None => Some((0, false)),
+        }
+    }
+
+    /// Drops the output stream, opens the default device again and resumes the last src.
+    /// A src which already ended isn't played again, one loaded while there was no
+    /// output is loaded paused.
+    fn reinitialize(
+        output: &mut Option<AudioOutput>,
+        has_output: &AtomicBool,
+        tx: &Sender<RodioCommand>,
+        generation: &AtomicU64,
+        last_src: &Option<Source>,
+        position_offset: u64,
+        volume: f32,
+    ) {
+        let resume =
+            Self::resume_position(output.as_ref().map(|o| o.sink.as_ref()), position_offset);
+        generation.fetch_add(1, Ordering::SeqCst);
+        drop(output.take());
+        *output = Self::open_output(has_output);
+
+        if let Some(output) = output.as_ref() {
+            output.sink.set_volume(volume);
+            if let (Some(source), Some((pos, play))) = (last_src, resume) {
+                Self::queue_reload(tx, source.clone(), pos, play);
+            }
+        }
+    }
+
+    fn initialize(events_tx: Sender<PlayerEvents>, cache: AudioCache) -> Sender<RodioCommand> {
         let (tx, rx) = channel::<RodioCommand>();
         let ret = tx.clone();
+        let has_output = Arc::new(AtomicBool::new(false));
+        Self::watch_output_device(tx.clone(), has_output.clone());
 
         thread::spawn(move || {
-            let (_stream, stream_handle) = OutputStream::try_default().unwrap();
-            let sink = Arc::new(rodio::Sink::try_new(&stream_handle).unwrap());
-
-            let runtime = tokio::runtime::Builder::new_multi_thread()
+            let runtime = match tokio::runtime::Builder::new_multi_thread()
                 .enable_all()
                 .build()
-                .unwrap();
+            {
+                Ok(runtime) => runtime,
+                Err(e) => {
+                    error!("Failed to create rodio runtime: {:?}", e);
+                    Self::send_event(&events_tx, PlayerEvents::Error(e.into()));
+                    return;
+                }
+            };
+
+            let mut output = Self::open_output(&has_output);
 
-            let events_tx = events_tx.clone();
             runtime.block_on(async move {
-                let last_src = Arc::new(Mutex::new(None));
+                // Bumped every time the sink gets a new source, so that stale
+                // "ended" watchers don't fire for a song that was replaced.
+                let generation = Arc::new(AtomicU64::new(0));
+                let mut last_src: Option<Source> = None;
+                let mut volume = 1f32;
+                // Segment streams are seeked by loading them again from the segment
+                // containing the position, their sink position then starts there
+                let mut seek_by_reload = false;
+                let mut position_offset = 0u64;
+
                 while let Ok(command) = rx.recv() {
-                    let sink = sink.clone();
+                    let command = match command {
+                        RodioCommand::Reinitialize => {
+                            Self::reinitialize(
+                                &mut output,
+                                &has_output,
+                                &tx,
+                                &generation,
+                                &last_src,
+                                position_offset,
+                                volume,
+                            );
+                            continue;
+                        }
+                        RodioCommand::Player(command) => command,
+                    };
 
-                    match command {
-                        RodioCommand::SetSrc(src) => {
-                            let last_src = last_src.clone();
-                            {
-                                let mut last_src = last_src.lock().unwrap();
-                                *last_src = Some(src.clone());
+                    // The device watcher reopens the output, the src is loaded then
+                    let Some(sink) = output.as_ref().map(|o| o.sink.clone()) else {
+                        match command {
+                            PlayerCommand::SetVolume(v) => volume = v,
+                            PlayerCommand::SetSrc(source) => {
+                                last_src = Some(source);
+                                Self::send_event(
+                                    &events_tx,
+                                    PlayerEvents::Error("No audio output device available".into()),
+                                );
                             }
+                            _ => debug!("Ignoring command without an audio output"),
+                        }
+                        continue;
+                    };
 
-                            sink.clear();
-                            Self::send_event(events_tx.clone(), PlayerEvents::TimeUpdate(0f64));
-                            Self::send_event(events_tx.clone(), PlayerEvents::Loading);
+                    match command {
+                        PlayerCommand::SetSrc(source) => {
+                            last_src = Some(source.clone());
+                            position_offset = 0;
+                            Self::send_event(&events_tx, PlayerEvents::TimeUpdate(0f64));
+                            Self::send_event(&events_tx, PlayerEvents::Loading);
 
-                            if let Err(err) =
-                                Self::set_src(cache_dir.clone(), src.clone(), &sink).await
+                            match Self::load_src(
+                                &cache,
+                                source,
+                                0,
+                                sink.clone(),
+                                &events_tx,
+                                &generation,
+                            )
+                            .await
                             {
-                                error!("Failed to set src: {:?}", err);
-                                Self::send_event(events_tx.clone(), PlayerEvents::Error(err))
-                            } else {
-                                debug!("Set src");
-                                let src_clone = src.clone();
-
-                                let events_tx = events_tx.clone();
-                                let sink = sink.clone();
-
-                                // Send ended event only if song hasn't changed yet
-                                thread::spawn(move || {
-                                    sink.sleep_until_end();
-                                    let last_src = last_src.clone();
-                                    let last_src = last_src.lock().unwrap();
-                                    if let Some(last_src) = last_src.clone() {
-                                        info!("last src={}, current src={}", last_src, src_clone);
-                                        if last_src == src_clone {
-                                            Self::send_event(
-                                                events_tx.clone(),
-                                                PlayerEvents::Ended,
-                                            );
-                                        }
-                                    }
-                                });
+                                Ok(reload) => {
+                                    debug!("Set src");
+                                    seek_by_reload = reload;
+                                }
+                                Err(err) => {
+                                    error!("Failed to set src: {:?}", err);
+                                    seek_by_reload = false;
+                                    Self::send_event(&events_tx, PlayerEvents::Error(err))
+                                }
                             }
                         }
-                        RodioCommand::Play => {
+                        PlayerCommand::Play => {
                             if !sink.empty() {
                                 sink.play();
-                                Self::send_event(events_tx.clone(), PlayerEvents::Play)
+                                Self::send_event(&events_tx, PlayerEvents::Play)
                             }
                         }
-                        RodioCommand::Pause => {
+                        PlayerCommand::Pause => {
                             if !sink.empty() {
                                 sink.pause();
-                                Self::send_event(events_tx.clone(), PlayerEvents::Pause)
+                                Self::send_event(&events_tx, PlayerEvents::Pause)
                             }
                         }
-                        RodioCommand::Stop => {
+                        PlayerCommand::Stop => {
                             if !sink.empty() {
                                 sink.stop();
                                 sink.clear();
-                                Self::send_event(events_tx.clone(), PlayerEvents::Pause)
+                                Self::send_event(&events_tx, PlayerEvents::Pause)
                             }
                         }
-                        RodioCommand::SetVolume(volume) => {
-                            if !sink.empty() {
-                                sink.set_volume(volume);
+                        PlayerCommand::SetVolume(v) => {
+                            volume = v;
+                            sink.set_volume(v);
+                        }
+                        PlayerCommand::Seek(pos) if seek_by_reload && !sink.empty() => {
+                            let Some(source) = last_src.clone() else {
+                                continue;
+                            };
+                            let play = !sink.is_paused();
+                            match Self::load_src(
+                                &cache,
+                                source,
+                                pos,
+                                sink.clone(),
+                                &events_tx,
+                                &generation,
+                            )
+                            .await
+                            {
+                                Ok(_) => {
+                                    position_offset = pos;
+                                    if play {
+                                        sink.play();
+                                    }
+                                    Self::send_event(
+                                        &events_tx,
+                                        PlayerEvents::TimeUpdate(pos as f64),
+                                    )
+                                }
+                                Err(err) => {
+                                    error!("Failed to seek: {:?}", err);
+                                    Self::send_event(&events_tx, PlayerEvents::Error(err))
+                                }
                             }
                         }
-                        RodioCommand::Seek(pos) => {
+                        PlayerCommand::Seek(pos) => {
                             if !sink.empty() {
                                 if let Err(err) = sink.try_seek(Duration::from_secs(pos)) {
-                                    error!("Failed to seek: {:?}", err)
+                                    error!("Failed to seek: {:?}", err);
+                                    Self::send_event(&events_tx, PlayerEvents::Error(err.into()));
                                 } else {
                                     Self::send_event(
-                                        events_tx.clone(),
+                                        &events_tx,
                                         PlayerEvents::TimeUpdate(pos as f64),
                                     )
                                 }
-                            } else {
-                                let last_src = last_src.clone();
-                                let last_src = last_src.lock().unwrap();
-                                if let Some(last_src) = last_src.clone() {
-                                    tx.send(RodioCommand::SetSrc(last_src.clone())).unwrap();
-                                    tx.send(RodioCommand::Seek(pos)).unwrap();
-                                    tx.send(RodioCommand::Play).unwrap();
-                                }
+                            } else if let Some(source) = &last_src {
+                                Self::queue_reload(&tx, source.clone(), pos, true);
                             }
                         }
                     }
@@ -221,47 +472,145 @@ impl RodioPlayer {
         ret
     }
 
+    /// Replaces what the sink is playing with source, starting at start seconds, and
+    /// sends Ended once it finishes unless another src was loaded meanwhile.
+    /// Returns whether seeking has to load source again.
+    async fn load_src(
+        cache: &AudioCache,
+        source: Source,
+        start: u64,
+        sink: Arc<Sink>,
+        events_tx: &Sender<PlayerEvents>,
+        generation: &Arc<AtomicU64>,
+    ) -> Result<bool> {
+        let current_gen = generation.fetch_add(1, Ordering::SeqCst) + 1;
+        sink.clear();
+
+        let on_title = {
+            let events_tx = events_tx.clone();
+            let generation = generation.clone();
+            move |title: String| {
+                if generation.load(Ordering::SeqCst) == current_gen {
+                    Self::send_event(
+                        &events_tx,
+                        PlayerEvents::MetadataChanged(StreamMetadata::from_stream_title(&title)),
+                    );
+                }
+            }
+        };
+
+        let seek_by_reload = Self::set_src(cache, source, start, &sink, on_title).await?;
+
+        let events_tx = events_tx.clone();
+        let generation = generation.clone();
+        // Send ended event only if song hasn't changed yet
+        thread::spawn(move || {
+            sink.sleep_until_end();
+            let latest_gen = generation.load(Ordering::SeqCst);
+            info!(
+                "Sink drained, generation={} current={}",
+                current_gen, latest_gen
+            );
+            if latest_gen == current_gen {
+                Self::send_event(&events_tx, PlayerEvents::Ended);
+            }
+        });
+        Ok(seek_by_reload)
+    }
+
+    /// Loads the src again and seeks to pos through the command loop itself
+    fn queue_reload(tx: &Sender<RodioCommand>, source: Source, pos: u64, play: bool) {
+        let mut commands = vec![PlayerCommand::SetSrc(source), PlayerCommand::Seek(pos)];
+        if play {
+            commands.push(PlayerCommand::Play);
+        }
+        for command in commands {
+            if let Err(e) = tx.send(RodioCommand::Player(command)) {
+                error!("Failed to queue reload command: {:?}", e);
+                return;
+            }
+        }
+    }
+
+    fn send_command(&self, command: RodioCommand) -> Result<()> {
+        self.tx
+            .send(command)
+            .map_err(|_| MoosyncError::String("Rodio player is not running".into()))
+    }
+
     #[tracing::instrument(level = "debug", skip(self))]
-    pub async fn rodio_load(&self, src: String) -> Result<()> {
-        info!("Loading src={}", src);
-        self.tx.send(RodioCommand::SetSrc(src.clone())).unwrap();
-        Ok(())
+    pub async fn rodio_load(
+        &self,
+        src: String,
+        cache_key: Option<String>,
+        song_type: Option<SongType>,
+    ) -> Result<()> {
+        info!(
+            "Loading src={} cache_key={:?} song_type={:?}",
+            src, cache_key, song_type
+        );
+        self.send_command(RodioCommand::Player(PlayerCommand::SetSrc(Source {
+            src,
+            cache_key,
+            song_type,
+        })))
     }
 
     #[tracing::instrument(level = "debug", skip(self))]
     pub async fn rodio_play(&self) -> Result<()> {
-        self.tx.send(RodioCommand::Play).unwrap();
-        Ok(())
+        self.send_command(RodioCommand::Player(PlayerCommand::Play))
     }
 
     #[tracing::instrument(level = "debug", skip(self))]
     pub async fn rodio_pause(&self) -> Result<()> {
-        self.tx.send(RodioCommand::Pause).unwrap();
-        Ok(())
+        self.send_command(RodioCommand::Player(PlayerCommand::Pause))
     }
 
     #[tracing::instrument(level = "debug", skip(self))]
     pub async fn rodio_stop(&self) -> Result<()> {
-        self.tx.send(RodioCommand::Stop).unwrap();
-        Ok(())
+        self.send_command(RodioCommand::Player(PlayerCommand::Stop))
     }
 
     #[tracing::instrument(level = "debug", skip(self))]
     pub async fn rodio_seek(&self, pos: f64) -> Result<()> {
-        self.tx
-            .send(RodioCommand::Seek(pos.abs().round() as u64))
-            .unwrap();
-        Ok(())
+        self.send_command(RodioCommand::Player(PlayerCommand::Seek(
+            pos.abs().round() as u64
+        )))
     }
 
     #[tracing::instrument(level = "debug", skip(self))]
     pub async fn rodio_set_volume(&self, volume: f32) -> Result<()> {
-        self.tx.send(RodioCommand::SetVolume(volume)).unwrap();
-        Ok(())
+        self.send_command(RodioCommand::Player(PlayerCommand::SetVolume(volume)))
     }
 
     #[tracing::instrument(level = "debug", skip(self))]
     pub async fn rodio_get_volume(&self) -> Result<f32> {
         Ok(0f32)
     }
+
+    /// Drops the current output stream and opens the default device again,
+    /// resuming the last src from where it was
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub async fn rodio_reinitialize(&self) -> Result<()> {
+        self.send_command(RodioCommand::Reinitialize)
+    }
+
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub async fn rodio_cache_stats(&self) -> Result<AudioCacheStats> {
+        Ok(self.cache.stats())
+    }
+
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub async fn rodio_clear_cache(&self) -> Result<()> {
+        self.cache.clear()
+    }
+
+    /// Sets the maximum size of the audio cache in bytes
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub fn set_cache_size(&self, bytes: u64) {
+        self.cache.set_max_size(bytes);
+    }
 }
+
+#[cfg(test)]
+mod test;
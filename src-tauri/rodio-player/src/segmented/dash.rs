@@ -0,0 +1,489 @@
+// Moosync
+// Copyright (C) 2024, 2025  Moosync <support@moosync.app>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::{
+    collections::HashMap,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use quick_xml::{
+    events::{BytesStart, Event},
+    Reader,
+};
+use reqwest::Url;
+use tracing::debug;
+use types::errors::{MoosyncError, Result};
+
+use super::{join_url, Segment, SegmentList};
+
+/// Number of segments behind the live edge which are listed for dynamic manifests
+const LIVE_WINDOW: u64 = 10;
+
+#[derive(Debug, Default, Clone)]
+struct Node {
+    name: String,
+    attrs: HashMap<String, String>,
+    children: Vec<Node>,
+    text: String,
+}
+
+impl Node {
+    fn attr(&self, key: &str) -> Option<&str> {
+        self.attrs.get(key).map(|v| v.as_str())
+    }
+
+    fn child(&self, name: &str) -> Option<&Node> {
+        self.children.iter().find(|c| c.name == name)
+    }
+
+    fn children<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a Node> {
+        self.children.iter().filter(move |c| c.name == name)
+    }
+}
+
+fn xml_error<E: std::fmt::Display>(e: E) -> MoosyncError {
+    MoosyncError::String(format!("Failed to parse MPD: {}", e))
+}
+
+fn node_from(start: &BytesStart) -> Result<Node> {
+    let mut node = Node {
+        name: String::from_utf8_lossy(start.local_name().as_ref()).to_string(),
+        ..Default::default()
+    };
+    for attr in start.attributes() {
+        let attr = attr.map_err(xml_error)?;
+        let key = String::from_utf8_lossy(attr.key.local_name().as_ref()).to_string();
+        let value = attr.unescape_value().map_err(xml_error)?.to_string();
+        node.attrs.insert(key, value);
+    }
+    Ok(node)
+}
+
+fn parse_xml(content: &str) -> Result<Node> {
+    let mut reader = Reader::from_str(content);
+    reader.config_mut().trim_text(true);
+
+    let mut stack = vec![Node::default()];
+    loop {
+        match reader.read_event().map_err(xml_error)? {
+            Event::Start(start) => stack.push(node_from(&start)?),
+            Event::Empty(start) => {
+                let node = node_from(&start)?;
+                if let Some(parent) = stack.last_mut() {
+                    parent.children.push(node);
+                }
+            }
+            Event::End(_) => {
+                let node = stack.pop();
+                match (node, stack.last_mut()) {
+                    (Some(node), Some(parent)) => parent.children.push(node),
+                    _ => return Err(xml_error("unbalanced tags")),
+                }
+            }
+            Event::Text(text) => {
+                if let Some(node) = stack.last_mut() {
+                    node.text.push_str(&text.unescape().map_err(xml_error)?);
+                }
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+
+    stack
+        .pop()
+        .and_then(|root| root.children.into_iter().find(|c| c.name == "MPD"))
+        .ok_or_else(|| xml_error("missing MPD element"))
+}
+
+/// Parses an ISO 8601 duration such as PT1H2M3.5S
+pub fn parse_duration(value: &str) -> Option<Duration> {
+    let value = value.trim().strip_prefix('P')?;
+    let (date, time) = value.split_once('T').unwrap_or((value, ""));
+
+    let mut secs = 0f64;
+    let mut parse_part = |part: &str, units: &[(char, f64)]| -> Option<()> {
+        let mut number = String::new();
+        for c in part.chars() {
+            if c.is_ascii_digit() || c == '.' {
+                number.push(c);
+                continue;
+            }
+            let (_, multiplier) = units.iter().find(|(u, _)| *u == c)?;
+            secs += number.parse::<f64>().ok()? * multiplier;
+            number.clear();
+        }
+        Some(())
+    };
+
+    parse_part(
+        date,
+        &[
+            ('Y', 365f64 * 86400f64),
+            ('M', 30f64 * 86400f64),
+            ('W', 7f64 * 86400f64),
+            ('D', 86400f64),
+        ],
+    )?;
+    parse_part(time, &[('H', 3600f64), ('M', 60f64), ('S', 1f64)])?;
+
+    Some(Duration::from_secs_f64(secs))
+}
+
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = (if year >= 0 { year } else { year - 399 }) / 400;
+    let yoe = year - era * 400;
+    let doy = (153 * (if month > 2 { month - 3 } else { month + 9 }) + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146097 + doe - 719468
+}
+
+/// Parses an xs:dateTime such as 2024-01-01T10:00:00.5Z into seconds since the epoch
+pub fn parse_date_time(value: &str) -> Option<f64> {
+    let value = value.trim();
+    let (date, time) = value.split_once('T')?;
+
+    let mut date_parts = date.split('-').map(|p| p.parse::<i64>());
+    let year = date_parts.next()?.ok()?;
+    let month = date_parts.next()?.ok()?;
+    let day = date_parts.next()?.ok()?;
+
+    let (time, offset) = if let Some(time) = time.strip_suffix('Z') {
+        (time, 0f64)
+    } else if let Some(pos) = time.rfind(['+', '-']) {
+        let (time, offset) = time.split_at(pos);
+        let sign = if offset.starts_with('-') { -1f64 } else { 1f64 };
+        let (hours, minutes) = offset[1..].split_once(':').unwrap_or((&offset[1..], "0"));
+        let offset = hours.parse::<f64>().ok()? * 3600f64 + minutes.parse::<f64>().ok()? * 60f64;
+        (time, sign * offset)
+    } else {
+        (time, 0f64)
+    };
+
+    let mut time_parts = time.split(':');
+    let hours = time_parts.next()?.parse::<f64>().ok()?;
+    let minutes = time_parts.next()?.parse::<f64>().ok()?;
+    let seconds = time_parts.next().unwrap_or("0").parse::<f64>().ok()?;
+
+    let days = days_from_civil(year, month, day) as f64;
+    Some(days * 86400f64 + hours * 3600f64 + minutes * 60f64 + seconds - offset)
+}
+
+/// Replaces $RepresentationID$, $Number$, $Bandwidth$ and $Time$ identifiers,
+/// including printf style widths like $Number%05d$
+pub fn fill_template(template: &str, id: &str, bandwidth: u64, number: u64, time: u64) -> String {
+    let mut out = String::new();
+    for (i, part) in template.split('$').enumerate() {
+        if i % 2 == 0 {
+            out.push_str(part);
+            continue;
+        }
+
+        if part.is_empty() {
+            out.push('$');
+            continue;
+        }
+
+        let (identifier, format) = part.split_once('%').unwrap_or((part, ""));
+        let width = format
+            .trim_end_matches('d')
+            .trim_start_matches('0')
+            .parse::<usize>()
+            .unwrap_or_default();
+        let value = match identifier {
+            "RepresentationID" => {
+                out.push_str(id);
+                continue;
+            }
+            "Number" => number,
+            "Bandwidth" => bandwidth,
+            "Time" => time,
+            _ => {
+                out.push('$');
+                out.push_str(part);
+                out.push('$');
+                continue;
+            }
+        };
+        out.push_str(&format!("{:0width$}", value, width = width));
+    }
+    out
+}
+
+#[derive(Debug, Default, Clone)]
+struct SegmentTemplate {
+    media: Option<String>,
+    initialization: Option<String>,
+    start_number: Option<u64>,
+    timescale: Option<u64>,
+    duration: Option<u64>,
+    timeline: Option<Vec<(Option<u64>, u64, i64)>>,
+}
+
+impl SegmentTemplate {
+    fn from_node(node: &Node, parent: Option<&SegmentTemplate>) -> Self {
+        let parent = parent.cloned().unwrap_or_default();
+        let timeline = node.child("SegmentTimeline").map(|timeline| {
+            timeline
+                .children("S")
+                .map(|s| {
+                    (
+                        s.attr("t").and_then(|t| t.parse().ok()),
+                        s.attr("d").and_then(|d| d.parse().ok()).unwrap_or_default(),
+                        s.attr("r").and_then(|r| r.parse().ok()).unwrap_or_default(),
+                    )
+                })
+                .collect()
+        });
+
+        Self {
+            media: node.attr("media").map(|m| m.to_string()).or(parent.media),
+            initialization: node
+                .attr("initialization")
+                .map(|i| i.to_string())
+                .or(parent.initialization),
+            start_number: node
+                .attr("startNumber")
+                .and_then(|n| n.parse().ok())
+                .or(parent.start_number),
+            timescale: node
+                .attr("timescale")
+                .and_then(|t| t.parse().ok())
+                .or(parent.timescale),
+            duration: node
+                .attr("duration")
+                .and_then(|d| d.parse().ok())
+                .or(parent.duration),
+            timeline: timeline.or(parent.timeline),
+        }
+    }
+}
+
+fn is_audio(node: &Node) -> bool {
+    node.attr("contentType") == Some("audio")
+        || node
+            .attr("mimeType")
+            .is_some_and(|m| m.starts_with("audio"))
+}
+
+fn with_base_url(base: &Url, node: &Node) -> Result<Url> {
+    match node.child("BaseURL") {
+        Some(base_url) if !base_url.text.is_empty() => join_url(base, &base_url.text),
+        _ => Ok(base.clone()),
+    }
+}
+
+fn now() -> f64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs_f64())
+        .unwrap_or_default()
+}
+
+/// Parses the manifest and lists the segments of the best audio representation.
+/// Segment urls are resolved against url.
+pub fn parse_manifest(url: &Url, content: &str) -> Result<SegmentList> {
+    let mpd = parse_xml(content)?;
+    let live = mpd.attr("type") == Some("dynamic");
+
+    let period = mpd
+        .child("Period")
+        .ok_or_else(|| MoosyncError::String("MPD has no periods".into()))?;
+
+    let (adaptation_set, representation) = period
+        .children("AdaptationSet")
+        .flat_map(|set| set.children("Representation").map(move |rep| (set, rep)))
+        .filter(|(set, rep)| is_audio(set) || is_audio(rep))
+        .max_by_key(|(_, rep)| {
+            rep.attr("bandwidth")
+                .and_then(|b| b.parse::<u64>().ok())
+                .unwrap_or_default()
+        })
+        .ok_or_else(|| MoosyncError::String("MPD has no audio representation".into()))?;
+
+    debug!("Selected DASH representation {:?}", representation.attrs);
+
+    let base = with_base_url(url, &mpd)?;
+    let base = with_base_url(&base, period)?;
+    let base = with_base_url(&base, adaptation_set)?;
+    let base = with_base_url(&base, representation)?;
+
+    let id = representation.attr("id").unwrap_or_default();
+    let bandwidth = representation
+        .attr("bandwidth")
+        .and_then(|b| b.parse().ok())
+        .unwrap_or_default();
+
+    let presentation_duration = period
+        .attr("duration")
+        .or(mpd.attr("mediaPresentationDuration"))
+        .and_then(parse_duration);
+
+    let mut list = SegmentList {
+        url: url.clone(),
+        init: None,
+        segments: vec![],
+        live,
+        refresh: mpd
+            .attr("minimumUpdatePeriod")
+            .and_then(parse_duration)
+            .unwrap_or(Duration::from_secs(5))
+            .max(Duration::from_secs(1)),
+    };
+
+    let set_template = adaptation_set
+        .child("SegmentTemplate")
+        .map(|t| SegmentTemplate::from_node(t, None));
+    let template = match representation.child("SegmentTemplate") {
+        Some(t) => Some(SegmentTemplate::from_node(t, set_template.as_ref())),
+        None => set_template,
+    };
+
+    if let Some(template) = template {
+        let media = template
+            .media
+            .as_deref()
+            .ok_or_else(|| MoosyncError::String("SegmentTemplate without media".into()))?;
+        let start_number = template.start_number.unwrap_or(1);
+        let timescale = template.timescale.unwrap_or(1).max(1);
+
+        if let Some(init) = &template.initialization {
+            list.init = Some(join_url(&base, &fill_template(init, id, bandwidth, 0, 0))?);
+        }
+
+        if let Some(timeline) = &template.timeline {
+            let mut time = 0u64;
+            let mut number = start_number;
+            for (i, (t, d, r)) in timeline.iter().enumerate() {
+                if let Some(t) = t {
+                    time = *t;
+                }
+
+                let repeat = if *r >= 0 {
+                    *r as u64
+                } else {
+                    // Repeat until the next S element or until the end of the period
+                    let end = timeline
+                        .get(i + 1)
+                        .and_then(|(t, _, _)| *t)
+                        .or_else(|| {
+                            presentation_duration
+                                .map(|p| (p.as_secs_f64() * timescale as f64) as u64)
+                        })
+                        .unwrap_or(time + d);
+                    (end.saturating_sub(time) / (*d).max(1)).saturating_sub(1)
+                };
+
+                for _ in 0..=repeat {
+                    list.segments.push(Segment {
+                        url: join_url(&base, &fill_template(media, id, bandwidth, number, time))?,
+                        sequence: if media.contains("$Time") {
+                            time
+                        } else {
+                            number
+                        },
+                        duration: Some(*d as f64 / timescale as f64),
+                    });
+                    time += d;
+                    number += 1;
+                }
+            }
+        } else if let Some(duration) = template.duration.filter(|d| *d > 0) {
+            let segment_duration = duration as f64 / timescale as f64;
+            let numbers = if live {
+                let availability_start = mpd
+                    .attr("availabilityStartTime")
+                    .and_then(parse_date_time)
+                    .ok_or_else(|| {
+                        MoosyncError::String("Live MPD without availabilityStartTime".into())
+                    })?;
+                let period_start = period
+                    .attr("start")
+                    .and_then(parse_duration)
+                    .map(|d| d.as_secs_f64())
+                    .unwrap_or_default();
+                let elapsed = (now() - availability_start - period_start).max(0f64);
+                let available = (elapsed / segment_duration) as u64;
+                let latest = start_number + available.saturating_sub(1);
+                list.refresh = Duration::from_secs_f64(segment_duration.max(1f64));
+                latest.saturating_sub(LIVE_WINDOW).max(start_number)..=latest
+            } else {
+                let total = presentation_duration
+                    .ok_or_else(|| MoosyncError::String("MPD without duration".into()))?
+                    .as_secs_f64();
+                let count = (total / segment_duration).ceil() as u64;
+                start_number..=(start_number + count.max(1) - 1)
+            };
+
+            for number in numbers {
+                let time = (number - start_number) * duration;
+                list.segments.push(Segment {
+                    url: join_url(&base, &fill_template(media, id, bandwidth, number, time))?,
+                    sequence: number,
+                    duration: Some(segment_duration),
+                });
+            }
+        } else {
+            return Err("SegmentTemplate without duration or timeline".into());
+        }
+
+        return Ok(list);
+    }
+
+    if let Some(segment_list) = representation
+        .child("SegmentList")
+        .or(adaptation_set.child("SegmentList"))
+    {
+        if let Some(init) = segment_list
+            .child("Initialization")
+            .and_then(|i| i.attr("sourceURL"))
+        {
+            list.init = Some(join_url(&base, init)?);
+        }
+
+        for (i, segment) in segment_list.children("SegmentURL").enumerate() {
+            if let Some(media) = segment.attr("media") {
+                list.segments.push(Segment {
+                    url: join_url(&base, media)?,
+                    sequence: i as u64,
+                    duration: None,
+                });
+            }
+        }
+        return Ok(list);
+    }
+
+    // Single file representation
+    list.segments.push(Segment {
+        url: base,
+        sequence: 0,
+        duration: presentation_duration.map(|d| d.as_secs_f64()),
+    });
+    Ok(list)
+}
+
+pub async fn load(client: &reqwest::Client, url: &Url) -> Result<SegmentList> {
+    let content = client
+        .get(url.clone())
+        .send()
+        .await?
+        .error_for_status()?
+        .text()
+        .await?;
+
+    parse_manifest(url, &content)
+}
@@ -0,0 +1,246 @@
+// Moosync
+// Copyright (C) 2024, 2025  Moosync <support@moosync.app>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::time::Duration;
+
+use reqwest::Url;
+use tracing::debug;
+use types::errors::{MoosyncError, Result};
+
+use super::{join_url, Segment, SegmentList};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Variant {
+    pub uri: String,
+    pub bandwidth: u64,
+    pub codecs: Option<String>,
+    pub has_video: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Playlist {
+    Master(Vec<Variant>),
+    Media(SegmentList),
+}
+
+/// Parses the attribute list of a tag, eg. BANDWIDTH=1000,CODECS="mp4a.40.2"
+pub(crate) fn parse_attributes(list: &str) -> Vec<(String, String)> {
+    let mut attrs = vec![];
+    let mut chars = list.chars().peekable();
+    loop {
+        let key: String = chars
+            .by_ref()
+            .take_while(|c| *c != '=')
+            .collect::<String>()
+            .trim()
+            .to_string();
+        if key.is_empty() {
+            break;
+        }
+
+        let value: String = if chars.peek() == Some(&'"') {
+            chars.next();
+            let value = chars.by_ref().take_while(|c| *c != '"').collect();
+            // Skip until the next separator
+            for c in chars.by_ref() {
+                if c == ',' {
+                    break;
+                }
+            }
+            value
+        } else {
+            chars.by_ref().take_while(|c| *c != ',').collect()
+        };
+        attrs.push((key.to_uppercase(), value.trim().to_string()));
+    }
+    attrs
+}
+
+fn attribute<'a>(attrs: &'a [(String, String)], key: &str) -> Option<&'a str> {
+    attrs
+        .iter()
+        .find(|(k, _)| k == key)
+        .map(|(_, v)| v.as_str())
+}
+
+/// Parses a master or media playlist. Segment uris are resolved against base.
+pub fn parse_playlist(base: &Url, content: &str) -> Result<Playlist> {
+    let mut lines = content.lines().map(|l| l.trim()).filter(|l| !l.is_empty());
+
+    if lines.next() != Some("#EXTM3U") {
+        return Err("Not an m3u8 playlist".into());
+    }
+
+    let mut variants = vec![];
+    let mut pending_variant: Option<Variant> = None;
+    // Renditions don't carry a bandwidth, they get the one of the variants using their group
+    let mut renditions: Vec<(String, bool, Variant)> = vec![];
+    let mut group_bandwidths: Vec<(String, u64)> = vec![];
+
+    let mut list = SegmentList {
+        url: base.clone(),
+        init: None,
+        segments: vec![],
+        live: true,
+        refresh: Duration::from_secs(10),
+    };
+    let mut sequence = 0u64;
+    let mut pending_duration: Option<f64> = None;
+
+    for line in lines {
+        if let Some(tag) = line.strip_prefix('#') {
+            let (name, value) = tag.split_once(':').unwrap_or((tag, ""));
+            match name {
+                "EXT-X-STREAM-INF" => {
+                    let attrs = parse_attributes(value);
+                    let codecs = attribute(&attrs, "CODECS").map(|c| c.to_string());
+                    let bandwidth = attribute(&attrs, "BANDWIDTH")
+                        .and_then(|b| b.parse().ok())
+                        .unwrap_or_default();
+                    if let Some(group) = attribute(&attrs, "AUDIO") {
+                        group_bandwidths.push((group.to_string(), bandwidth));
+                    }
+                    pending_variant = Some(Variant {
+                        uri: String::new(),
+                        bandwidth,
+                        has_video: attribute(&attrs, "RESOLUTION").is_some()
+                            || codecs.as_ref().is_some_and(|c| {
+                                c.contains("avc") || c.contains("hvc") || c.contains("vp")
+                            }),
+                        codecs,
+                    });
+                }
+                "EXT-X-MEDIA" => {
+                    // Separate audio renditions are preferred over muxed variants
+                    let attrs = parse_attributes(value);
+                    if let (Some("AUDIO"), Some(uri)) =
+                        (attribute(&attrs, "TYPE"), attribute(&attrs, "URI"))
+                    {
+                        renditions.push((
+                            attribute(&attrs, "GROUP-ID")
+                                .unwrap_or_default()
+                                .to_string(),
+                            attribute(&attrs, "DEFAULT") == Some("YES"),
+                            Variant {
+                                uri: uri.to_string(),
+                                bandwidth: 0,
+                                codecs: None,
+                                has_video: false,
+                            },
+                        ));
+                    }
+                }
+                "EXT-X-TARGETDURATION" => {
+                    if let Ok(duration) = value.trim().parse::<f64>() {
+                        list.refresh = Duration::from_secs_f64(duration.max(1f64));
+                    }
+                }
+                "EXT-X-MEDIA-SEQUENCE" => {
+                    sequence = value.trim().parse().unwrap_or_default();
+                }
+                "EXT-X-ENDLIST" => list.live = false,
+                "EXT-X-PLAYLIST-TYPE" if value.trim() == "VOD" => list.live = false,
+                "EXT-X-MAP" => {
+                    let attrs = parse_attributes(value);
+                    if let Some(uri) = attribute(&attrs, "URI") {
+                        list.init = Some(join_url(base, uri)?);
+                    }
+                }
+                "EXT-X-KEY" => {
+                    let attrs = parse_attributes(value);
+                    if attribute(&attrs, "METHOD").is_some_and(|m| m != "NONE") {
+                        return Err("Encrypted HLS streams are not supported".into());
+                    }
+                }
+                "EXTINF" => {
+                    pending_duration = value.split(',').next().and_then(|d| d.trim().parse().ok());
+                }
+                _ => {}
+            }
+            continue;
+        }
+
+        if let Some(mut variant) = pending_variant.take() {
+            variant.uri = line.to_string();
+            variants.push(variant);
+            continue;
+        }
+
+        list.segments.push(Segment {
+            url: join_url(base, line)?,
+            sequence,
+            duration: pending_duration.take(),
+        });
+        sequence += 1;
+    }
+
+    for (group, default, mut rendition) in renditions.iter().cloned() {
+        // Other renditions in a group are alternate languages, only keep the default one
+        if !default && renditions.iter().any(|(g, d, _)| *g == group && *d) {
+            continue;
+        }
+        rendition.bandwidth = group_bandwidths
+            .iter()
+            .filter(|(g, _)| *g == group)
+            .map(|(_, bandwidth)| *bandwidth)
+            .max()
+            .unwrap_or_default();
+        variants.push(rendition);
+    }
+
+    if !variants.is_empty() {
+        return Ok(Playlist::Master(variants));
+    }
+
+    Ok(Playlist::Media(list))
+}
+
+/// Prefers the best audio only rendition, otherwise the cheapest muxed variant
+/// since only its audio gets decoded
+pub fn select_variant(variants: &[Variant]) -> Option<&Variant> {
+    variants
+        .iter()
+        .filter(|v| !v.has_video)
+        .max_by_key(|v| v.bandwidth)
+        .or_else(|| variants.iter().min_by_key(|v| v.bandwidth))
+}
+
+/// Fetches the playlist at url, following the master playlist if needed
+pub async fn load(client: &reqwest::Client, url: &Url) -> Result<SegmentList> {
+    let mut url = url.clone();
+    // Master playlists can't point to other master playlists, but guard against loops anyway
+    for _ in 0..2 {
+        let content = client
+            .get(url.clone())
+            .send()
+            .await?
+            .error_for_status()?
+            .text()
+            .await?;
+
+        match parse_playlist(&url, &content)? {
+            Playlist::Media(list) => return Ok(list),
+            Playlist::Master(variants) => {
+                let variant = select_variant(&variants)
+                    .ok_or_else(|| MoosyncError::String("No playable HLS variant".into()))?;
+                debug!("Selected HLS variant {:?}", variant);
+                url = join_url(&url, &variant.uri)?;
+            }
+        }
+    }
+
+    Err("Failed to resolve HLS media playlist".into())
+}
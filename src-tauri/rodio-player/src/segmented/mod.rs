@@ -0,0 +1,328 @@
+// Moosync
+// Copyright (C) 2024, 2025  Moosync <support@moosync.app>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+pub mod dash;
+pub mod hls;
+pub mod ts;
+
+use std::{
+    future::Future,
+    io::{self, Read, Seek, SeekFrom},
+    sync::mpsc::{sync_channel, SyncSender},
+    thread,
+    time::Duration,
+};
+
+use futures::channel::oneshot;
+use reqwest::Url;
+use tracing::{debug, error, info, warn};
+use types::{
+    errors::{MoosyncError, Result},
+    songs::SongType,
+};
+
+use crate::channel::{push, ChannelReader};
+use ts::TsDemuxer;
+
+/// Segments which are started from when joining a live stream
+const LIVE_EDGE_SEGMENTS: usize = 3;
+/// Fetched segments waiting for the decoder
+const BUFFERED_SEGMENTS: usize = 4;
+const FETCH_RETRIES: u32 = 3;
+const FETCH_RETRY_DELAY: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Segment {
+    pub url: Url,
+    pub sequence: u64,
+    pub duration: Option<f64>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SegmentList {
+    /// Playlist or manifest to reload for live streams
+    pub url: Url,
+    /// Initialization segment for fMP4
+    pub init: Option<Url>,
+    pub segments: Vec<Segment>,
+    pub live: bool,
+    pub refresh: Duration,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StreamKind {
+    Hls,
+    Dash,
+}
+
+impl StreamKind {
+    /// Stream type of the song, guessed from the url if the song type doesn't say
+    pub fn from_song_type(song_type: Option<SongType>, src: &str) -> Option<Self> {
+        match song_type {
+            Some(SongType::HLS) => Some(Self::Hls),
+            Some(SongType::DASH) => Some(Self::Dash),
+            _ => Self::detect(src),
+        }
+    }
+
+    /// Guesses the stream type from the url path
+    pub fn detect(src: &str) -> Option<Self> {
+        let url = Url::parse(src).ok()?;
+        let path = url.path().to_lowercase();
+        if path.ends_with(".m3u8") {
+            return Some(Self::Hls);
+        }
+        if path.ends_with(".mpd") {
+            return Some(Self::Dash);
+        }
+        None
+    }
+
+    async fn load(self, client: &reqwest::Client, url: &Url) -> Result<SegmentList> {
+        match self {
+            Self::Hls => hls::load(client, url).await,
+            Self::Dash => dash::load(client, url).await,
+        }
+    }
+}
+
+pub(crate) fn join_url(base: &Url, uri: &str) -> Result<Url> {
+    base.join(uri)
+        .map_err(|e| MoosyncError::String(format!("Invalid segment url {}: {:?}", uri, e)))
+}
+
+/// Segment to start fetching from to begin playback at start, and how far into
+/// that segment start is. Segments without a duration are started from.
+pub(crate) fn start_segment(segments: &[Segment], start: Duration) -> (usize, Duration) {
+    let mut elapsed = Duration::ZERO;
+    for (i, segment) in segments.iter().enumerate() {
+        let Some(duration) = segment
+            .duration
+            .and_then(|d| Duration::try_from_secs_f64(d).ok())
+        else {
+            return (i, start - elapsed);
+        };
+
+        if elapsed + duration > start {
+            return (i, start - elapsed);
+        }
+        elapsed += duration;
+    }
+    (segments.len(), Duration::ZERO)
+}
+
+/// Runs request until it succeeds, waiting twice as long after every failed attempt
+async fn retry<T, F, Fut>(url: &Url, request: F) -> Result<T>
+where
+    F: Fn() -> Fut,
+    Fut: Future<Output = Result<T>>,
+{
+    let mut delay = FETCH_RETRY_DELAY;
+    let mut last_err = None;
+    for attempt in 1..=FETCH_RETRIES {
+        match request().await {
+            Ok(res) => return Ok(res),
+            Err(e) => {
+                warn!("Failed to fetch {} (attempt {}): {:?}", url, attempt, e);
+                last_err = Some(e);
+                if attempt < FETCH_RETRIES {
+                    tokio::time::sleep(delay).await;
+                    delay *= 2;
+                }
+            }
+        }
+    }
+    Err(last_err.unwrap_or_else(|| format!("Failed to fetch {}", url).into()))
+}
+
+/// Continuous byte stream made of fetched segments. Transport stream segments
+/// are demuxed to their audio elementary stream before reaching the decoder.
+#[derive(Debug)]
+pub struct SegmentReader {
+    reader: ChannelReader,
+    live: bool,
+}
+
+impl SegmentReader {
+    /// Loads the playlist or manifest at url and spawns the fetcher, starting
+    /// from the segment which contains start. Also returns how much of that
+    /// segment has to be skipped to reach start. Live streams are always joined
+    /// at the live edge. Fetching stops once the reader is dropped.
+    #[tracing::instrument(level = "debug", skip())]
+    pub async fn open(kind: StreamKind, url: Url, start: Duration) -> Result<(Self, Duration)> {
+        let (tx, rx) = sync_channel(BUFFERED_SEGMENTS);
+        let (start_tx, start_rx) = oneshot::channel::<Result<(bool, Duration)>>();
+        thread::Builder::new()
+            .name("segment-fetcher".into())
+            .spawn(move || {
+                let runtime = match tokio::runtime::Builder::new_current_thread()
+                    .enable_all()
+                    .build()
+                {
+                    Ok(runtime) => runtime,
+                    Err(e) => {
+                        let _ = start_tx.send(Err(e.into()));
+                        return;
+                    }
+                };
+
+                runtime.block_on(async move {
+                    let client = reqwest::Client::new();
+                    let list = match Self::load(kind, &client, &url).await {
+                        Ok(list) => list,
+                        Err(e) => {
+                            let _ = start_tx.send(Err(e));
+                            return;
+                        }
+                    };
+
+                    let (first, skip) = if list.live {
+                        (0, Duration::ZERO)
+                    } else {
+                        start_segment(&list.segments, start)
+                    };
+                    if start_tx.send(Ok((list.live, skip))).is_err() {
+                        return;
+                    }
+
+                    let last_sequence = first.checked_sub(1).map(|i| list.segments[i].sequence);
+                    if let Err(e) = Self::fetch_loop(kind, &client, list, last_sequence, &tx).await
+                    {
+                        error!("Segment fetcher failed: {:?}", e);
+                        let _ = tx.send(Err(io::Error::other(e.to_string())));
+                    }
+                });
+            })?;
+
+        let (live, skip) = start_rx
+            .await
+            .map_err(|_| MoosyncError::String("Segment fetcher stopped".into()))??;
+        Ok((
+            Self {
+                reader: ChannelReader::new(rx),
+                live,
+            },
+            skip,
+        ))
+    }
+
+    /// Live streams can't be seeked
+    pub fn live(&self) -> bool {
+        self.live
+    }
+
+    async fn load(kind: StreamKind, client: &reqwest::Client, url: &Url) -> Result<SegmentList> {
+        retry(url, || kind.load(client, url)).await
+    }
+
+    async fn fetch(client: &reqwest::Client, url: &Url) -> Result<Vec<u8>> {
+        retry(url, || async move {
+            Ok::<_, MoosyncError>(
+                client
+                    .get(url.clone())
+                    .send()
+                    .await?
+                    .error_for_status()?
+                    .bytes()
+                    .await?
+                    .to_vec(),
+            )
+        })
+        .await
+    }
+
+    /// Fetches the segments of list after last_sequence, reloading the list
+    /// for as long as the stream is live
+    async fn fetch_loop(
+        kind: StreamKind,
+        client: &reqwest::Client,
+        mut list: SegmentList,
+        mut last_sequence: Option<u64>,
+        tx: &SyncSender<io::Result<Vec<u8>>>,
+    ) -> Result<()> {
+        let mut current_init: Option<Url> = None;
+        let mut demuxer: Option<TsDemuxer> = None;
+
+        loop {
+            let mut segments: Vec<&Segment> = list
+                .segments
+                .iter()
+                .filter(|s| last_sequence.is_none_or(|last| s.sequence > last))
+                .collect();
+
+            if last_sequence.is_none() && list.live && segments.len() > LIVE_EDGE_SEGMENTS {
+                segments.drain(..segments.len() - LIVE_EDGE_SEGMENTS);
+            }
+
+            if last_sequence
+                .is_some_and(|last| list.segments.first().is_some_and(|s| s.sequence > last + 1))
+            {
+                warn!("Fell behind the live playlist, some segments were skipped");
+            }
+
+            for segment in segments {
+                if list.init != current_init {
+                    if let Some(init) = &list.init {
+                        debug!("Fetching init segment {}", init);
+                        let data = Self::fetch(client, init).await?;
+                        if !push(tx, data) {
+                            return Ok(());
+                        }
+                    }
+                    current_init = list.init.clone();
+                }
+
+                debug!("Fetching segment {} ({})", segment.sequence, segment.url);
+                let data = Self::fetch(client, &segment.url).await?;
+
+                if demuxer.is_none() && ts::is_transport_stream(&data) {
+                    info!("Demuxing MPEG-TS segments");
+                    demuxer = Some(TsDemuxer::default());
+                }
+
+                let data = match demuxer.as_mut() {
+                    Some(demuxer) => demuxer.push(&data),
+                    None => data,
+                };
+
+                if !push(tx, data) {
+                    return Ok(());
+                }
+                last_sequence = Some(segment.sequence);
+            }
+
+            if !list.live {
+                debug!("Fetched every segment");
+                return Ok(());
+            }
+
+            tokio::time::sleep(list.refresh).await;
+            list = Self::load(kind, client, &list.url).await?;
+        }
+    }
+}
+
+impl Read for SegmentReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.reader.read(buf)
+    }
+}
+
+impl Seek for SegmentReader {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        self.reader.seek(pos)
+    }
+}
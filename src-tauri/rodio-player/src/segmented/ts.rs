@@ -0,0 +1,170 @@
+// Moosync
+// Copyright (C) 2024, 2025  Moosync <support@moosync.app>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use tracing::{debug, warn};
+
+pub const PACKET_SIZE: usize = 188;
+const SYNC_BYTE: u8 = 0x47;
+
+const STREAM_TYPE_MPEG1_AUDIO: u8 = 0x03;
+const STREAM_TYPE_MPEG2_AUDIO: u8 = 0x04;
+const STREAM_TYPE_ADTS_AAC: u8 = 0x0F;
+
+/// Checks whether the segment looks like an MPEG transport stream
+pub fn is_transport_stream(data: &[u8]) -> bool {
+    data.len() >= PACKET_SIZE
+        && data[0] == SYNC_BYTE
+        && (data.len() <= PACKET_SIZE || data[PACKET_SIZE] == SYNC_BYTE)
+}
+
+/// Minimal MPEG-TS demuxer which extracts the first audio elementary stream
+/// (ADTS AAC or MPEG audio) so that it can be fed to symphonia as a plain stream.
+/// State is kept across segments since HLS splits a single transport stream.
+#[derive(Debug, Default)]
+pub struct TsDemuxer {
+    pmt_pid: Option<u16>,
+    audio_pid: Option<u16>,
+    pending: Vec<u8>,
+}
+
+impl TsDemuxer {
+    pub fn push(&mut self, data: &[u8]) -> Vec<u8> {
+        let mut out = vec![];
+
+        let mut input = std::mem::take(&mut self.pending);
+        input.extend_from_slice(data);
+
+        let mut offset = 0;
+        while offset + PACKET_SIZE <= input.len() {
+            if input[offset] != SYNC_BYTE {
+                // Lost sync, look for the next sync byte
+                offset += 1;
+                continue;
+            }
+            self.handle_packet(&input[offset..offset + PACKET_SIZE], &mut out);
+            offset += PACKET_SIZE;
+        }
+
+        self.pending = input[offset..].to_vec();
+        out
+    }
+
+    fn handle_packet(&mut self, packet: &[u8], out: &mut Vec<u8>) {
+        let payload_unit_start = packet[1] & 0x40 != 0;
+        let pid = (((packet[1] & 0x1F) as u16) << 8) | packet[2] as u16;
+        let adaptation_field_control = (packet[3] >> 4) & 0x03;
+
+        if adaptation_field_control & 0x01 == 0 {
+            return;
+        }
+
+        let mut start = 4;
+        if adaptation_field_control & 0x02 != 0 {
+            start += 1 + packet[4] as usize;
+        }
+        if start >= PACKET_SIZE {
+            return;
+        }
+        let payload = &packet[start..];
+
+        if pid == 0 {
+            if payload_unit_start {
+                self.parse_pat(payload);
+            }
+        } else if Some(pid) == self.pmt_pid {
+            if payload_unit_start {
+                self.parse_pmt(payload);
+            }
+        } else if Some(pid) == self.audio_pid {
+            if payload_unit_start {
+                if let Some(data) = Self::strip_pes_header(payload) {
+                    out.extend_from_slice(data);
+                }
+            } else {
+                out.extend_from_slice(payload);
+            }
+        }
+    }
+
+    fn section(payload: &[u8]) -> Option<&[u8]> {
+        let pointer = *payload.first()? as usize;
+        let section = payload.get(1 + pointer..)?;
+        let section_length = ((*section.get(1)? as usize & 0x0F) << 8) | *section.get(2)? as usize;
+        // Excluding the CRC
+        section.get(..(3 + section_length).checked_sub(4)?)
+    }
+
+    fn parse_pat(&mut self, payload: &[u8]) {
+        let Some(section) = Self::section(payload) else {
+            return;
+        };
+
+        for program in section.get(8..).unwrap_or_default().chunks_exact(4) {
+            let program_number = ((program[0] as u16) << 8) | program[1] as u16;
+            if program_number != 0 {
+                let pid = (((program[2] & 0x1F) as u16) << 8) | program[3] as u16;
+                if self.pmt_pid != Some(pid) {
+                    debug!("Found PMT at pid {}", pid);
+                    self.pmt_pid = Some(pid);
+                }
+                return;
+            }
+        }
+    }
+
+    fn parse_pmt(&mut self, payload: &[u8]) {
+        let Some(section) = Self::section(payload) else {
+            return;
+        };
+        if section.len() < 12 {
+            return;
+        }
+
+        let program_info_length = ((section[10] as usize & 0x0F) << 8) | section[11] as usize;
+        let mut offset = 12 + program_info_length;
+        while offset + 5 <= section.len() {
+            let stream_type = section[offset];
+            let pid = (((section[offset + 1] & 0x1F) as u16) << 8) | section[offset + 2] as u16;
+            let es_info_length =
+                ((section[offset + 3] as usize & 0x0F) << 8) | section[offset + 4] as usize;
+
+            if matches!(
+                stream_type,
+                STREAM_TYPE_ADTS_AAC | STREAM_TYPE_MPEG1_AUDIO | STREAM_TYPE_MPEG2_AUDIO
+            ) {
+                if self.audio_pid != Some(pid) {
+                    debug!("Found audio stream type {} at pid {}", stream_type, pid);
+                    self.audio_pid = Some(pid);
+                }
+                return;
+            }
+
+            offset += 5 + es_info_length;
+        }
+
+        if self.audio_pid.is_none() {
+            warn!("Transport stream has no supported audio stream");
+        }
+    }
+
+    fn strip_pes_header(payload: &[u8]) -> Option<&[u8]> {
+        if payload.len() < 9 || payload[..3] != [0x00, 0x00, 0x01] {
+            return None;
+        }
+        let header_data_length = payload[8] as usize;
+        payload.get(9 + header_data_length..)
+    }
+}
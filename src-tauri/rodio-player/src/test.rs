@@ -0,0 +1,857 @@
+// Moosync
+// Copyright (C) 2024, 2025  Moosync <support@moosync.app>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::{
+    env::temp_dir,
+    fs,
+    io::{BufRead, BufReader, Read, Write},
+    net::TcpListener,
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    thread,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use futures::executor::block_on;
+use reqwest::Url;
+use stream_download::storage::StorageProvider;
+use types::songs::SongType;
+
+use crate::cache::AudioCache;
+use crate::icy::{parse_stream_title, IcyParser};
+use crate::segmented::{
+    dash::{fill_template, parse_date_time, parse_duration, parse_manifest},
+    hls::{parse_playlist, select_variant, Playlist},
+    start_segment,
+    ts::{TsDemuxer, PACKET_SIZE},
+    Segment, SegmentReader, StreamKind,
+};
+use crate::RodioPlayer;
+
+type Handler = Arc<dyn Fn(&str) -> Option<Vec<u8>> + Send + Sync>;
+
+// Serves fixture responses over plain HTTP/1.1 on a random local port
+fn serve(handler: Handler) -> Url {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            let Ok(mut stream) = stream else {
+                continue;
+            };
+            let handler = handler.clone();
+            thread::spawn(move || {
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut request_line = String::new();
+                reader.read_line(&mut request_line).unwrap();
+                loop {
+                    let mut line = String::new();
+                    if reader.read_line(&mut line).unwrap() == 0 || line == "\r\n" {
+                        break;
+                    }
+                }
+
+                let path = request_line.split(' ').nth(1).unwrap_or("/").to_string();
+                let response = match handler(&path) {
+                    Some(body) => {
+                        let mut res = format!(
+                            "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                            body.len()
+                        )
+                        .into_bytes();
+                        res.extend(body);
+                        res
+                    }
+                    None => {
+                        b"HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"
+                            .to_vec()
+                    }
+                };
+                let _ = stream.write_all(&response);
+            });
+        }
+    });
+    Url::parse(&format!("http://{}/", addr)).unwrap()
+}
+
+fn segment_data(sequence: u64) -> Vec<u8> {
+    format!("segment-{:03}|", sequence).into_bytes()
+}
+
+fn open_segments(kind: StreamKind, url: Url, start: u64) -> SegmentReader {
+    let (reader, skip) =
+        block_on(SegmentReader::open(kind, url, Duration::from_secs(start))).unwrap();
+    assert_eq!(skip, Duration::ZERO);
+    reader
+}
+
+fn read_exact_len(reader: &mut SegmentReader, len: usize) -> Vec<u8> {
+    let mut data = vec![0u8; len];
+    reader.read_exact(&mut data).unwrap();
+    data
+}
+
+#[test]
+fn test_parse_hls_media_playlist() {
+    let base = Url::parse("http://localhost/audio/playlist.m3u8").unwrap();
+    let content = "#EXTM3U\n\
+        #EXT-X-VERSION:7\n\
+        #EXT-X-TARGETDURATION:6\n\
+        #EXT-X-MEDIA-SEQUENCE:10\n\
+        #EXT-X-MAP:URI=\"init.mp4\"\n\
+        #EXTINF:6.0,\n\
+        seg10.m4s\n\
+        #EXTINF:5.5,\n\
+        http://cdn.example.com/seg11.m4s\n\
+        #EXT-X-ENDLIST\n";
+
+    let Playlist::Media(list) = parse_playlist(&base, content).unwrap() else {
+        panic!("Expected a media playlist");
+    };
+
+    assert!(!list.live);
+    assert_eq!(list.refresh, Duration::from_secs(6));
+    assert_eq!(
+        list.init.unwrap().as_str(),
+        "http://localhost/audio/init.mp4"
+    );
+    assert_eq!(list.segments.len(), 2);
+    assert_eq!(list.segments[0].sequence, 10);
+    assert_eq!(
+        list.segments[0].url.as_str(),
+        "http://localhost/audio/seg10.m4s"
+    );
+    assert_eq!(list.segments[1].sequence, 11);
+    assert_eq!(list.segments[1].duration, Some(5.5));
+    assert_eq!(
+        list.segments[1].url.as_str(),
+        "http://cdn.example.com/seg11.m4s"
+    );
+}
+
+#[test]
+fn test_parse_hls_master_playlist() {
+    let base = Url::parse("http://localhost/master.m3u8").unwrap();
+    let content = "#EXTM3U\n\
+        #EXT-X-STREAM-INF:BANDWIDTH=800000,RESOLUTION=640x360,CODECS=\"avc1.4d401e,mp4a.40.2\"\n\
+        video.m3u8\n\
+        #EXT-X-STREAM-INF:BANDWIDTH=64000,CODECS=\"mp4a.40.5\"\n\
+        audio_low.m3u8\n\
+        #EXT-X-STREAM-INF:BANDWIDTH=128000,CODECS=\"mp4a.40.2\"\n\
+        audio_high.m3u8\n";
+
+    let Playlist::Master(variants) = parse_playlist(&base, content).unwrap() else {
+        panic!("Expected a master playlist");
+    };
+
+    assert_eq!(variants.len(), 3);
+    assert!(variants[0].has_video);
+    assert_eq!(select_variant(&variants).unwrap().uri, "audio_high.m3u8");
+}
+
+#[test]
+fn test_hls_audio_renditions_use_group_bandwidth() {
+    let base = Url::parse("http://localhost/master.m3u8").unwrap();
+    let content = "#EXTM3U\n\
+        #EXT-X-MEDIA:TYPE=AUDIO,GROUP-ID=\"low\",NAME=\"English\",DEFAULT=YES,URI=\"low_en.m3u8\"\n\
+        #EXT-X-MEDIA:TYPE=AUDIO,GROUP-ID=\"high\",NAME=\"German\",DEFAULT=NO,URI=\"high_de.m3u8\"\n\
+        #EXT-X-MEDIA:TYPE=AUDIO,GROUP-ID=\"high\",NAME=\"English\",DEFAULT=YES,URI=\"high_en.m3u8\"\n\
+        #EXT-X-STREAM-INF:BANDWIDTH=2000000,RESOLUTION=1280x720,AUDIO=\"high\"\n\
+        video_high.m3u8\n\
+        #EXT-X-STREAM-INF:BANDWIDTH=500000,RESOLUTION=640x360,AUDIO=\"low\"\n\
+        video_low.m3u8\n";
+
+    let Playlist::Master(variants) = parse_playlist(&base, content).unwrap() else {
+        panic!("Expected a master playlist");
+    };
+
+    // Only the default rendition of each group is kept
+    assert_eq!(variants.len(), 4);
+    assert!(!variants.iter().any(|v| v.uri == "high_de.m3u8"));
+    let low = variants.iter().find(|v| v.uri == "low_en.m3u8").unwrap();
+    assert_eq!(low.bandwidth, 500000);
+    assert_eq!(select_variant(&variants).unwrap().uri, "high_en.m3u8");
+}
+
+#[test]
+fn test_hls_rejects_encrypted_playlist() {
+    let base = Url::parse("http://localhost/playlist.m3u8").unwrap();
+    let content = "#EXTM3U\n\
+        #EXT-X-KEY:METHOD=AES-128,URI=\"key.bin\"\n\
+        #EXTINF:6.0,\n\
+        seg0.ts\n";
+
+    assert!(parse_playlist(&base, content).is_err());
+}
+
+#[test]
+fn test_dash_helpers() {
+    assert_eq!(
+        fill_template("$RepresentationID$/seg-$Number%05d$.m4s", "a1", 0, 42, 0),
+        "a1/seg-00042.m4s"
+    );
+    assert_eq!(
+        fill_template("t$Time$-$Bandwidth$$$", "a1", 128000, 0, 9000),
+        "t9000-128000$"
+    );
+
+    assert_eq!(
+        parse_duration("PT1H2M3.5S"),
+        Some(Duration::from_secs_f64(3723.5))
+    );
+    assert_eq!(parse_duration("P1DT1S"), Some(Duration::from_secs(86401)));
+
+    assert_eq!(parse_date_time("1970-01-01T00:00:00Z"), Some(0f64));
+    assert_eq!(
+        parse_date_time("2024-01-01T01:00:00+01:00"),
+        Some(1704067200f64)
+    );
+}
+
+#[test]
+fn test_parse_dash_static_manifest() {
+    let url = Url::parse("http://localhost/stream/manifest.mpd").unwrap();
+    let content = r#"<?xml version="1.0" encoding="UTF-8"?>
+        <MPD xmlns="urn:mpeg:dash:schema:mpd:2011" type="static" mediaPresentationDuration="PT10S">
+          <Period>
+            <AdaptationSet contentType="video" mimeType="video/mp4">
+              <Representation id="v1" bandwidth="900000" />
+            </AdaptationSet>
+            <AdaptationSet contentType="audio" mimeType="audio/mp4">
+              <SegmentTemplate timescale="1000" duration="4000" startNumber="1"
+                initialization="$RepresentationID$/init.mp4" media="$RepresentationID$/$Number$.m4s" />
+              <Representation id="a-low" bandwidth="64000" />
+              <Representation id="a-high" bandwidth="128000" />
+            </AdaptationSet>
+          </Period>
+        </MPD>"#;
+
+    let list = parse_manifest(&url, content).unwrap();
+    assert!(!list.live);
+    assert_eq!(
+        list.init.unwrap().as_str(),
+        "http://localhost/stream/a-high/init.mp4"
+    );
+
+    let urls: Vec<&str> = list.segments.iter().map(|s| s.url.as_str()).collect();
+    assert_eq!(
+        urls,
+        vec![
+            "http://localhost/stream/a-high/1.m4s",
+            "http://localhost/stream/a-high/2.m4s",
+            "http://localhost/stream/a-high/3.m4s",
+        ]
+    );
+}
+
+#[test]
+fn test_parse_dash_segment_timeline() {
+    let url = Url::parse("http://localhost/live/manifest.mpd").unwrap();
+    let content = r#"<MPD type="dynamic" minimumUpdatePeriod="PT2S">
+          <Period start="PT0S">
+            <BaseURL>media/</BaseURL>
+            <AdaptationSet mimeType="audio/mp4">
+              <Representation id="aac" bandwidth="96000">
+                <SegmentTemplate timescale="48000" media="$Time$.m4s" initialization="init.m4s">
+                  <SegmentTimeline>
+                    <S t="96000" d="96000" r="2" />
+                    <S d="48000" />
+                  </SegmentTimeline>
+                </SegmentTemplate>
+              </Representation>
+            </AdaptationSet>
+          </Period>
+        </MPD>"#;
+
+    let list = parse_manifest(&url, content).unwrap();
+    assert!(list.live);
+    assert_eq!(list.refresh, Duration::from_secs(2));
+
+    let sequences: Vec<u64> = list.segments.iter().map(|s| s.sequence).collect();
+    assert_eq!(sequences, vec![96000, 192000, 288000, 384000]);
+    assert_eq!(
+        list.segments[3].url.as_str(),
+        "http://localhost/live/media/384000.m4s"
+    );
+}
+
+fn ts_packet(pid: u16, payload_unit_start: bool, payload: &[u8]) -> Vec<u8> {
+    let mut packet = vec![
+        0x47,
+        ((payload_unit_start as u8) << 6) | ((pid >> 8) as u8 & 0x1F),
+        pid as u8,
+    ];
+
+    let stuffing = PACKET_SIZE - 4 - payload.len();
+    if stuffing > 0 {
+        // Adaptation field followed by payload
+        packet.push(0x30);
+        packet.push((stuffing - 1) as u8);
+        if stuffing > 1 {
+            packet.push(0x00);
+            packet.extend(std::iter::repeat_n(0xFF, stuffing - 2));
+        }
+    } else {
+        packet.push(0x10);
+    }
+    packet.extend_from_slice(payload);
+    assert_eq!(packet.len(), PACKET_SIZE);
+    packet
+}
+
+fn transport_stream(audio: &[u8]) -> Vec<u8> {
+    // PAT pointing program 1 to PMT pid 0x1000
+    let pat = [
+        0x00, 0x00, 0xB0, 0x0D, 0x00, 0x01, 0xC1, 0x00, 0x00, 0x00, 0x01, 0xF0, 0x00, 0x00, 0x00,
+        0x00, 0x00,
+    ];
+    // PMT with a single ADTS AAC stream on pid 0x101
+    let pmt = [
+        0x00, 0x02, 0xB0, 0x12, 0x00, 0x01, 0xC1, 0x00, 0x00, 0xE1, 0x01, 0xF0, 0x00, 0x0F, 0xE1,
+        0x01, 0xF0, 0x00, 0x00, 0x00, 0x00, 0x00,
+    ];
+
+    let mut data = ts_packet(0, true, &pat);
+    data.extend(ts_packet(0x1000, true, &pmt));
+
+    let mut pes = vec![0x00, 0x00, 0x01, 0xC0, 0x00, 0x00, 0x80, 0x80, 0x05];
+    pes.extend([0x21, 0x00, 0x01, 0x00, 0x01]);
+    pes.extend_from_slice(audio);
+
+    for (i, chunk) in pes.chunks(PACKET_SIZE - 4).enumerate() {
+        data.extend(ts_packet(0x101, i == 0, chunk));
+    }
+    data
+}
+
+#[test]
+fn test_ts_demuxer_extracts_audio() {
+    let audio: Vec<u8> = (0..400u32).map(|i| (i % 251) as u8).collect();
+    let data = transport_stream(&audio);
+
+    let mut demuxer = TsDemuxer::default();
+    // Split at an odd offset to make sure partial packets carry over
+    let (first, second) = data.split_at(300);
+    let mut out = demuxer.push(first);
+    out.extend(demuxer.push(second));
+
+    assert_eq!(out, audio);
+}
+
+#[test]
+fn test_detect_stream_kind() {
+    assert_eq!(
+        StreamKind::detect("https://example.com/live/index.m3u8?token=1"),
+        Some(StreamKind::Hls)
+    );
+    assert_eq!(
+        StreamKind::detect("https://example.com/manifest.MPD"),
+        Some(StreamKind::Dash)
+    );
+    assert_eq!(StreamKind::detect("https://example.com/song.mp3"), None);
+    assert_eq!(StreamKind::detect("/home/user/song.m3u8.mp3"), None);
+}
+
+#[test]
+fn test_stream_kind_from_song_type() {
+    assert_eq!(
+        StreamKind::from_song_type(Some(SongType::HLS), "https://example.com/live?id=1"),
+        Some(StreamKind::Hls)
+    );
+    assert_eq!(
+        StreamKind::from_song_type(Some(SongType::DASH), "https://example.com/stream"),
+        Some(StreamKind::Dash)
+    );
+    assert_eq!(
+        StreamKind::from_song_type(Some(SongType::URL), "https://example.com/index.m3u8"),
+        Some(StreamKind::Hls)
+    );
+    assert_eq!(
+        StreamKind::from_song_type(None, "https://example.com/song.mp3"),
+        None
+    );
+}
+
+#[test]
+fn test_hls_vod_over_http() {
+    let base = serve(Arc::new(|path: &str| {
+        match path {
+        "/master.m3u8" => Some(
+            b"#EXTM3U\n#EXT-X-STREAM-INF:BANDWIDTH=128000,CODECS=\"mp4a.40.2\"\naudio/index.m3u8\n"
+                .to_vec(),
+        ),
+        "/audio/index.m3u8" => Some(
+            b"#EXTM3U\n#EXT-X-TARGETDURATION:1\n#EXT-X-MAP:URI=\"init.mp4\"\n#EXTINF:1,\n0.m4s\n#EXTINF:1,\n1.m4s\n#EXTINF:1,\n2.m4s\n#EXT-X-ENDLIST\n"
+                .to_vec(),
+        ),
+        "/audio/init.mp4" => Some(b"init|".to_vec()),
+        "/audio/0.m4s" => Some(segment_data(0)),
+        "/audio/1.m4s" => Some(segment_data(1)),
+        "/audio/2.m4s" => Some(segment_data(2)),
+        _ => None,
+    }
+    }));
+
+    let mut reader = open_segments(StreamKind::Hls, base.join("master.m3u8").unwrap(), 0);
+    let mut data = vec![];
+    reader.read_to_end(&mut data).unwrap();
+
+    let mut expected = b"init|".to_vec();
+    for i in 0..3 {
+        expected.extend(segment_data(i));
+    }
+    assert_eq!(data, expected);
+}
+
+#[test]
+fn test_hls_live_over_http() {
+    let requests = Arc::new(AtomicUsize::new(0));
+    let playlist_requests = requests.clone();
+    let base = serve(Arc::new(move |path: &str| {
+        if path == "/live.m3u8" {
+            // The playlist slides forward by 3 segments on every reload
+            let reload = playlist_requests.fetch_add(1, Ordering::SeqCst) as u64;
+            let first = reload * 3;
+            let mut playlist = format!(
+                "#EXTM3U\n#EXT-X-TARGETDURATION:1\n#EXT-X-MEDIA-SEQUENCE:{}\n",
+                first
+            );
+            for sequence in first..first + 5 {
+                playlist.push_str(&format!("#EXTINF:1,\nseg{}.ts\n", sequence));
+            }
+            return Some(playlist.into_bytes());
+        }
+
+        path.strip_prefix("/seg")
+            .and_then(|p| p.strip_suffix(".ts"))
+            .and_then(|p| p.parse().ok())
+            .map(segment_data)
+    }));
+
+    let mut reader = open_segments(StreamKind::Hls, base.join("live.m3u8").unwrap(), 0);
+    let segment_len = segment_data(0).len();
+
+    // Joins at the live edge and keeps following the playlist
+    let mut data = vec![];
+    for _ in 0..6 {
+        data.extend(read_exact_len(&mut reader, segment_len));
+    }
+
+    let mut expected = vec![];
+    for sequence in [2, 3, 4, 5, 6, 7] {
+        expected.extend(segment_data(sequence));
+    }
+    assert_eq!(data, expected);
+    assert!(requests.load(Ordering::SeqCst) >= 2);
+}
+
+#[test]
+fn test_dash_static_over_http() {
+    let base = serve(Arc::new(|path: &str| {
+        match path {
+        "/manifest.mpd" => Some(
+            br#"<MPD type="static" mediaPresentationDuration="PT3S"><Period><AdaptationSet mimeType="audio/mp4"><Representation id="a" bandwidth="1"><SegmentTemplate duration="1" initialization="$RepresentationID$-init.mp4" media="$RepresentationID$-$Number$.m4s" /></Representation></AdaptationSet></Period></MPD>"#
+                .to_vec(),
+        ),
+        "/a-init.mp4" => Some(b"init|".to_vec()),
+        "/a-1.m4s" => Some(segment_data(1)),
+        "/a-2.m4s" => Some(segment_data(2)),
+        "/a-3.m4s" => Some(segment_data(3)),
+        _ => None,
+    }
+    }));
+
+    let mut reader = open_segments(StreamKind::Dash, base.join("manifest.mpd").unwrap(), 0);
+    let mut data = vec![];
+    reader.read_to_end(&mut data).unwrap();
+
+    let mut expected = b"init|".to_vec();
+    for i in 1..=3 {
+        expected.extend(segment_data(i));
+    }
+    assert_eq!(data, expected);
+}
+
+#[test]
+fn test_missing_segment_reports_error() {
+    let base = serve(Arc::new(|path: &str| match path {
+        "/index.m3u8" => Some(
+            b"#EXTM3U\n#EXT-X-TARGETDURATION:1\n#EXTINF:1,\nmissing.ts\n#EXT-X-ENDLIST\n".to_vec(),
+        ),
+        _ => None,
+    }));
+
+    let mut reader = open_segments(StreamKind::Hls, base.join("index.m3u8").unwrap(), 0);
+    let mut data = vec![];
+    assert!(reader.read_to_end(&mut data).is_err());
+}
+
+#[test]
+fn test_start_segment() {
+    let segments: Vec<Segment> = [Some(4.0), Some(4.0), None, Some(4.0)]
+        .into_iter()
+        .enumerate()
+        .map(|(i, duration)| Segment {
+            url: Url::parse(&format!("http://localhost/{}.ts", i)).unwrap(),
+            sequence: i as u64,
+            duration,
+        })
+        .collect();
+
+    assert_eq!(
+        start_segment(&segments, Duration::ZERO),
+        (0, Duration::ZERO)
+    );
+    assert_eq!(
+        start_segment(&segments, Duration::from_secs(5)),
+        (1, Duration::from_secs(1))
+    );
+    // Segments without a duration are skipped by the decoder instead
+    assert_eq!(
+        start_segment(&segments, Duration::from_secs(10)),
+        (2, Duration::from_secs(2))
+    );
+}
+
+#[test]
+fn test_hls_vod_starts_at_segment() {
+    let base = serve(Arc::new(|path: &str| {
+        match path {
+        "/index.m3u8" => Some(
+            b"#EXTM3U\n#EXT-X-TARGETDURATION:4\n#EXT-X-MEDIA-SEQUENCE:5\n#EXT-X-MAP:URI=\"init.mp4\"\n#EXTINF:4,\n5.m4s\n#EXTINF:4,\n6.m4s\n#EXTINF:4,\n7.m4s\n#EXT-X-ENDLIST\n"
+                .to_vec(),
+        ),
+        "/init.mp4" => Some(b"init|".to_vec()),
+        "/5.m4s" => Some(segment_data(5)),
+        "/6.m4s" => Some(segment_data(6)),
+        "/7.m4s" => Some(segment_data(7)),
+        _ => None,
+    }
+    }));
+
+    let (mut reader, skip) = block_on(SegmentReader::open(
+        StreamKind::Hls,
+        base.join("index.m3u8").unwrap(),
+        Duration::from_secs(5),
+    ))
+    .unwrap();
+    assert!(!reader.live());
+    assert_eq!(skip, Duration::from_secs(1));
+
+    let mut data = vec![];
+    reader.read_to_end(&mut data).unwrap();
+
+    let mut expected = b"init|".to_vec();
+    expected.extend(segment_data(6));
+    expected.extend(segment_data(7));
+    assert_eq!(data, expected);
+}
+
+#[test]
+fn test_hls_live_retries_playlist_reload() {
+    let requests = Arc::new(AtomicUsize::new(0));
+    let playlist_requests = requests.clone();
+    let base = serve(Arc::new(move |path: &str| {
+        if path == "/live.m3u8" {
+            let request = playlist_requests.fetch_add(1, Ordering::SeqCst) as u64;
+            // The first reload fails
+            if request == 1 {
+                return None;
+            }
+            let first = request.saturating_sub(1);
+            return Some(
+                format!(
+                    "#EXTM3U\n#EXT-X-TARGETDURATION:1\n#EXT-X-MEDIA-SEQUENCE:{}\n#EXTINF:1,\nseg{}.ts\n",
+                    first, first
+                )
+                .into_bytes(),
+            );
+        }
+
+        path.strip_prefix("/seg")
+            .and_then(|p| p.strip_suffix(".ts"))
+            .and_then(|p| p.parse().ok())
+            .map(segment_data)
+    }));
+
+    let mut reader = open_segments(StreamKind::Hls, base.join("live.m3u8").unwrap(), 0);
+    assert!(reader.live());
+    let segment_len = segment_data(0).len();
+
+    let mut data = vec![];
+    for _ in 0..2 {
+        data.extend(read_exact_len(&mut reader, segment_len));
+    }
+
+    let mut expected = segment_data(0);
+    expected.extend(segment_data(1));
+    assert_eq!(data, expected);
+    assert!(requests.load(Ordering::SeqCst) >= 3);
+}
+
+// Interleaves metadata blocks into audio the same way Icecast does
+fn icy_stream(audio: &[u8], metaint: usize, titles: &[&str]) -> Vec<u8> {
+    let mut data = vec![];
+    for (i, chunk) in audio.chunks(metaint).enumerate() {
+        data.extend_from_slice(chunk);
+        if chunk.len() < metaint {
+            break;
+        }
+        match titles.get(i) {
+            Some(title) => {
+                let mut meta = format!("StreamTitle='{}';StreamUrl='';", title).into_bytes();
+                meta.resize(meta.len().div_ceil(16) * 16, 0);
+                data.push((meta.len() / 16) as u8);
+                data.extend(meta);
+            }
+            None => data.push(0),
+        }
+    }
+    data
+}
+
+#[test]
+fn test_icy_parser_splits_metadata() {
+    let audio: Vec<u8> = (0..100u8).collect();
+    let stream = icy_stream(&audio, 16, &["Artist - First", "", "It's - Second"]);
+
+    // Feed in small uneven chunks so blocks are split across pushes
+    let mut parser = IcyParser::new(16);
+    let mut parsed_audio = vec![];
+    let mut titles = vec![];
+    for chunk in stream.chunks(7) {
+        let (a, t) = parser.push(chunk);
+        parsed_audio.extend(a);
+        titles.extend(t);
+    }
+
+    assert_eq!(parsed_audio, audio);
+    assert_eq!(titles, vec!["Artist - First", "", "It's - Second"]);
+}
+
+#[test]
+fn test_parse_stream_title() {
+    assert_eq!(
+        parse_stream_title(b"StreamTitle='Artist - Title';StreamUrl='';\0\0"),
+        Some("Artist - Title".to_string())
+    );
+    assert_eq!(
+        parse_stream_title(b"StreamTitle='Don't Stop';"),
+        Some("Don't Stop".to_string())
+    );
+    assert_eq!(parse_stream_title(b"StreamUrl='http://example.com';"), None);
+}
+
+fn cache_dir() -> PathBuf {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_nanos();
+    temp_dir().join(format!(
+        "moosync_audio_cache_{}_{}",
+        std::process::id(),
+        nanos
+    ))
+}
+
+// Downloads data into the cache the way StreamDownload does
+fn fill_cache(cache: &AudioCache, key: &str, data: &[u8]) {
+    let (_reader, mut writer) = cache
+        .storage(key)
+        .into_reader_writer(Some(data.len() as u64))
+        .unwrap();
+    writer.write_all(data).unwrap();
+}
+
+fn read_cached(cache: &AudioCache, key: &str) -> Option<Vec<u8>> {
+    let mut reader = cache.open(key)?;
+    let mut data = vec![];
+    reader.read_to_end(&mut data).unwrap();
+    Some(data)
+}
+
+#[test]
+fn test_cache_serves_complete_entries() {
+    let dir = cache_dir();
+    let cache = AudioCache::new(dir.clone(), 1024).unwrap();
+
+    assert!(cache.open("song").is_none());
+    fill_cache(&cache, "song", b"audio data");
+    assert_eq!(read_cached(&cache, "song"), Some(b"audio data".to_vec()));
+
+    let stats = cache.stats();
+    assert_eq!(stats.entries, 1);
+    assert_eq!(stats.size, 10);
+    assert_eq!(stats.max_size, 1024);
+    assert_eq!(stats.hits, 1);
+    assert_eq!(stats.misses, 1);
+
+    // The index survives a restart
+    drop(cache);
+    let cache = AudioCache::new(dir.clone(), 1024).unwrap();
+    assert_eq!(read_cached(&cache, "song"), Some(b"audio data".to_vec()));
+
+    let _ = fs::remove_dir_all(dir);
+}
+
+#[test]
+fn test_cache_drops_partial_downloads() {
+    let dir = cache_dir();
+    let cache = AudioCache::new(dir.clone(), 1024).unwrap();
+
+    {
+        let (_reader, mut writer) = cache.storage("song").into_reader_writer(Some(10)).unwrap();
+        writer.write_all(b"audio").unwrap();
+    }
+
+    assert!(cache.open("song").is_none());
+    let stats = cache.stats();
+    assert_eq!(stats.entries, 0);
+    assert_eq!(stats.size, 0);
+    // Only the index is left on disk
+    assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+
+    let _ = fs::remove_dir_all(dir);
+}
+
+#[test]
+fn test_cache_evicts_least_recently_used() {
+    let dir = cache_dir();
+    let cache = AudioCache::new(dir.clone(), 10).unwrap();
+
+    fill_cache(&cache, "first", b"1111");
+    fill_cache(&cache, "second", b"2222");
+    cache.set_last_access("first", 1);
+    cache.set_last_access("second", 2);
+
+    fill_cache(&cache, "third", b"3333");
+    assert!(cache.open("first").is_none());
+    assert_eq!(cache.stats().size, 8);
+
+    // Entries being read are kept even if they are the oldest
+    cache.set_last_access("second", 1);
+    cache.set_last_access("third", 2);
+    let playing = cache.open("second").unwrap();
+    cache.set_last_access("second", 1);
+    fill_cache(&cache, "fourth", b"4444");
+    assert!(cache.open("third").is_none());
+    drop(playing);
+    assert!(cache.open("second").is_some());
+    assert!(cache.open("fourth").is_some());
+
+    // Entries bigger than the cache are kept until something else needs the space
+    fill_cache(&cache, "huge", b"0123456789abcdef");
+    let stats = cache.stats();
+    assert_eq!(stats.entries, 1);
+    assert_eq!(stats.size, 16);
+
+    let _ = fs::remove_dir_all(dir);
+}
+
+#[test]
+fn test_cache_clear_keeps_entries_in_use() {
+    let dir = cache_dir();
+    let cache = AudioCache::new(dir.clone(), 1024).unwrap();
+
+    fill_cache(&cache, "first", b"1111");
+    fill_cache(&cache, "second", b"2222");
+    let playing = cache.open("second").unwrap();
+
+    cache.clear().unwrap();
+    let stats = cache.stats();
+    assert_eq!(stats.entries, 1);
+    assert_eq!(stats.size, 4);
+    assert_eq!(stats.hits, 0);
+    assert_eq!(stats.misses, 0);
+    drop(playing);
+    assert!(cache.open("first").is_none());
+    assert!(cache.open("second").is_some());
+
+    let _ = fs::remove_dir_all(dir);
+}
+
+#[test]
+fn test_cache_redownload_keeps_open_reader() {
+    let dir = cache_dir();
+    let cache = AudioCache::new(dir.clone(), 1024).unwrap();
+
+    fill_cache(&cache, "song", b"old audio");
+    let mut playing = cache.open("song").unwrap();
+
+    fill_cache(&cache, "song", b"new audio");
+    let mut data = vec![];
+    playing.read_to_end(&mut data).unwrap();
+    assert_eq!(data, b"old audio");
+    // Index and both files
+    assert_eq!(fs::read_dir(&dir).unwrap().count(), 3);
+
+    drop(playing);
+    assert_eq!(fs::read_dir(&dir).unwrap().count(), 2);
+    assert_eq!(read_cached(&cache, "song"), Some(b"new audio".to_vec()));
+    assert_eq!(cache.stats().entries, 1);
+
+    let _ = fs::remove_dir_all(dir);
+}
+
+#[test]
+fn test_cache_set_max_size_evicts() {
+    let dir = cache_dir();
+    let cache = AudioCache::new(dir.clone(), 1024).unwrap();
+
+    fill_cache(&cache, "first", b"1111");
+    fill_cache(&cache, "second", b"2222");
+    cache.set_last_access("first", 1);
+    cache.set_last_access("second", 2);
+    let playing = cache.open("first").unwrap();
+
+    cache.set_max_size(4);
+    let stats = cache.stats();
+    assert_eq!(stats.max_size, 4);
+    assert_eq!(stats.entries, 1);
+    drop(playing);
+    assert!(cache.open("first").is_some());
+    assert!(cache.open("second").is_none());
+
+    let _ = fs::remove_dir_all(dir);
+}
+
+#[test]
+fn test_resume_position() {
+    assert_eq!(RodioPlayer::resume_position(None, 0), Some((0, false)));
+
+    let (sink, _output) = rodio::Sink::new_idle();
+    assert_eq!(RodioPlayer::resume_position(Some(&sink), 0), None);
+
+    sink.append(rodio::source::Zero::<f32>::new(2, 44100));
+    assert_eq!(
+        RodioPlayer::resume_position(Some(&sink), 0),
+        Some((0, true))
+    );
+    // Segment streams seeked by reloading start at the offset
+    assert_eq!(
+        RodioPlayer::resume_position(Some(&sink), 30),
+        Some((30, true))
+    );
+
+    sink.pause();
+    assert_eq!(
+        RodioPlayer::resume_position(Some(&sink), 0),
+        Some((0, false))
+    );
+}
@@ -0,0 +1,164 @@
+// Moosync
+// Copyright (C) 2024, 2025  Moosync <support@moosync.app>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::collections::BTreeMap;
+
+use async_trait::async_trait;
+use serde_json::Value;
+use types::{
+    errors::Result,
+    scrobble::{ScrobbleService, ScrobbleTrack},
+};
+
+use crate::{ScrobbleClient, MAX_BATCH};
+
+pub const LASTFM_API_URL: &str = "https://ws.audioscrobbler.com/2.0/";
+
+/// Client for the Last.fm scrobbling API, https://www.last.fm/api/scrobbling
+pub struct LastFmClient {
+    client: reqwest::Client,
+    api_url: String,
+    api_key: String,
+    secret: String,
+    session_key: String,
+}
+
+impl LastFmClient {
+    #[tracing::instrument(level = "debug", skip(api_key, secret, session_key))]
+    pub fn new(api_url: String, api_key: String, secret: String, session_key: String) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            api_url,
+            api_key,
+            secret,
+            session_key,
+        }
+    }
+
+    /// Exchanges the user's credentials for a session key which doesn't expire
+    #[tracing::instrument(level = "debug", skip(api_key, secret, username, password))]
+    pub async fn get_session(
+        api_url: &str,
+        api_key: &str,
+        secret: &str,
+        username: &str,
+        password: &str,
+    ) -> Result<String> {
+        let mut params = BTreeMap::new();
+        params.insert("method".to_string(), "auth.getMobileSession".to_string());
+        params.insert("api_key".to_string(), api_key.to_string());
+        params.insert("username".to_string(), username.to_string());
+        params.insert("password".to_string(), password.to_string());
+
+        let res = call(&reqwest::Client::new(), api_url, secret, params).await?;
+        res.get("session")
+            .and_then(|s| s.get("key"))
+            .and_then(|k| k.as_str())
+            .map(|k| k.to_string())
+            .ok_or("Last.fm did not return a session key".into())
+    }
+
+    fn params(&self, method: &str) -> BTreeMap<String, String> {
+        let mut params = BTreeMap::new();
+        params.insert("method".to_string(), method.to_string());
+        params.insert("api_key".to_string(), self.api_key.clone());
+        params.insert("sk".to_string(), self.session_key.clone());
+        params
+    }
+}
+
+// Last.fm expects the main artist on its own
+fn add_track_params(params: &mut BTreeMap<String, String>, track: &ScrobbleTrack, suffix: &str) {
+    let mut add = |key: &str, value: String| {
+        params.insert(format!("{}{}", key, suffix), value);
+    };
+    add("track", track.title.clone());
+    add("artist", track.artists.first().cloned().unwrap_or_default());
+    if let Some(album) = &track.album {
+        add("album", album.clone());
+    }
+    if let Some(album_artist) = &track.album_artist {
+        add("albumArtist", album_artist.clone());
+    }
+    if let Some(duration) = track.duration {
+        add("duration", (duration.round() as u64).to_string());
+    }
+}
+
+/// md5 of all parameters, sorted by name, followed by the shared secret
+pub(crate) fn sign(params: &BTreeMap<String, String>, secret: &str) -> String {
+    let mut data = params
+        .iter()
+        .filter(|(key, _)| *key != "format" && *key != "callback")
+        .map(|(key, value)| format!("{}{}", key, value))
+        .collect::<String>();
+    data.push_str(secret);
+    format!("{:x}", md5::compute(data))
+}
+
+async fn call(
+    client: &reqwest::Client,
+    api_url: &str,
+    secret: &str,
+    mut params: BTreeMap<String, String>,
+) -> Result<Value> {
+    let signature = sign(&params, secret);
+    params.insert("api_sig".to_string(), signature);
+    params.insert("format".to_string(), "json".to_string());
+
+    let res = client.post(api_url).form(&params).send().await?;
+    let status = res.status();
+    let body: Value = serde_json::from_str(&res.text().await?).unwrap_or_default();
+
+    if let Some(code) = body.get("error") {
+        let message = body
+            .get("message")
+            .and_then(|m| m.as_str())
+            .unwrap_or_default();
+        return Err(format!("Last.fm error {}: {}", code, message).into());
+    }
+    if !status.is_success() {
+        return Err(format!("Last.fm returned {}", status).into());
+    }
+    Ok(body)
+}
+
+#[async_trait]
+impl ScrobbleClient for LastFmClient {
+    fn service(&self) -> ScrobbleService {
+        ScrobbleService::LastFm
+    }
+
+    #[tracing::instrument(level = "debug", skip(self, track))]
+    async fn now_playing(&self, track: &ScrobbleTrack) -> Result<()> {
+        let mut params = self.params("track.updateNowPlaying");
+        add_track_params(&mut params, track, "");
+        call(&self.client, &self.api_url, &self.secret, params).await?;
+        Ok(())
+    }
+
+    #[tracing::instrument(level = "debug", skip(self, tracks))]
+    async fn scrobble(&self, tracks: &[ScrobbleTrack]) -> Result<()> {
+        let mut params = self.params("track.scrobble");
+        for (i, track) in tracks.iter().take(MAX_BATCH).enumerate() {
+            let suffix = format!("[{}]", i);
+            add_track_params(&mut params, track, &suffix);
+            params.insert(format!("timestamp{}", suffix), track.timestamp.to_string());
+        }
+        call(&self.client, &self.api_url, &self.secret, params).await?;
+        Ok(())
+    }
+}
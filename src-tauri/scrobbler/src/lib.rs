@@ -0,0 +1,73 @@
+// Moosync
+// Copyright (C) 2024, 2025  Moosync <support@moosync.app>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use async_trait::async_trait;
+use types::{
+    errors::Result,
+    scrobble::{ScrobbleService, ScrobbleTrack},
+};
+
+mod lastfm;
+mod listenbrainz;
+
+pub use lastfm::{LastFmClient, LASTFM_API_URL};
+pub use listenbrainz::{ListenBrainzClient, LISTENBRAINZ_API_URL};
+
+#[cfg(test)]
+mod test;
+
+/// Most listens accepted in a single submission by both services
+pub const MAX_BATCH: usize = 50;
+
+/// Songs shorter than this many seconds are never scrobbled
+pub const MIN_TRACK_LENGTH: f64 = 30f64;
+
+#[async_trait]
+pub trait ScrobbleClient: Send + Sync {
+    fn service(&self) -> ScrobbleService;
+
+    async fn now_playing(&self, track: &ScrobbleTrack) -> Result<()>;
+
+    /// Submits up to [MAX_BATCH] finished listens
+    async fn scrobble(&self, tracks: &[ScrobbleTrack]) -> Result<()>;
+}
+
+/// When a song counts as listened to. The defaults follow the Last.fm rules,
+/// half of the song or 4 minutes, whichever comes first.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ScrobbleThreshold {
+    pub percent: f64,
+    pub max_secs: f64,
+}
+
+impl Default for ScrobbleThreshold {
+    fn default() -> Self {
+        Self {
+            percent: 50f64,
+            max_secs: 240f64,
+        }
+    }
+}
+
+impl ScrobbleThreshold {
+    pub fn is_reached(&self, duration: Option<f64>, played: f64) -> bool {
+        match duration {
+            Some(duration) if duration < MIN_TRACK_LENGTH => false,
+            Some(duration) => played >= (duration * self.percent / 100f64).min(self.max_secs),
+            None => played >= self.max_secs,
+        }
+    }
+}
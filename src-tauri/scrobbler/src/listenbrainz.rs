@@ -0,0 +1,125 @@
+// Moosync
+// Copyright (C) 2024, 2025  Moosync <support@moosync.app>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use async_trait::async_trait;
+use serde_json::{json, Value};
+use types::{
+    errors::Result,
+    scrobble::{ScrobbleService, ScrobbleTrack},
+};
+
+use crate::{ScrobbleClient, MAX_BATCH};
+
+pub const LISTENBRAINZ_API_URL: &str = "https://api.listenbrainz.org";
+
+/// Client for the ListenBrainz submission API, https://listenbrainz.readthedocs.io/en/latest/users/api/core.html
+pub struct ListenBrainzClient {
+    client: reqwest::Client,
+    api_url: String,
+    token: String,
+}
+
+impl ListenBrainzClient {
+    #[tracing::instrument(level = "debug", skip(token))]
+    pub fn new(api_url: String, token: String) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            api_url: api_url.trim_end_matches('/').to_string(),
+            token,
+        }
+    }
+
+    async fn submit(&self, listen_type: &str, payload: Vec<Value>) -> Result<()> {
+        let body = json!({
+            "listen_type": listen_type,
+            "payload": payload,
+        });
+
+        let res = self
+            .client
+            .post(format!("{}/1/submit-listens", self.api_url))
+            .header("Authorization", format!("Token {}", self.token))
+            .header("Content-Type", "application/json")
+            .body(serde_json::to_string(&body)?)
+            .send()
+            .await?;
+
+        let status = res.status();
+        if !status.is_success() {
+            let text = res.text().await.unwrap_or_default();
+            let error: Value = serde_json::from_str(&text).unwrap_or_default();
+            let message = error
+                .get("error")
+                .and_then(|e| e.as_str())
+                .unwrap_or(text.as_str());
+            return Err(format!("ListenBrainz returned {}: {}", status, message).into());
+        }
+        Ok(())
+    }
+}
+
+fn track_metadata(track: &ScrobbleTrack) -> Value {
+    let mut additional_info = json!({
+        "artist_names": track.artists,
+        "submission_client": "Moosync",
+    });
+    if let Some(duration) = track.duration {
+        additional_info["duration_ms"] = json!((duration * 1000f64).round() as u64);
+    }
+
+    let mut metadata = json!({
+        "artist_name": track.artists.join(", "),
+        "track_name": track.title,
+        "additional_info": additional_info,
+    });
+    if let Some(album) = &track.album {
+        metadata["release_name"] = json!(album);
+    }
+    metadata
+}
+
+#[async_trait]
+impl ScrobbleClient for ListenBrainzClient {
+    fn service(&self) -> ScrobbleService {
+        ScrobbleService::ListenBrainz
+    }
+
+    #[tracing::instrument(level = "debug", skip(self, track))]
+    async fn now_playing(&self, track: &ScrobbleTrack) -> Result<()> {
+        let payload = json!({ "track_metadata": track_metadata(track) });
+        self.submit("playing_now", vec![payload]).await
+    }
+
+    #[tracing::instrument(level = "debug", skip(self, tracks))]
+    async fn scrobble(&self, tracks: &[ScrobbleTrack]) -> Result<()> {
+        let payload = tracks
+            .iter()
+            .take(MAX_BATCH)
+            .map(|track| {
+                json!({
+                    "listened_at": track.timestamp,
+                    "track_metadata": track_metadata(track),
+                })
+            })
+            .collect::<Vec<_>>();
+        let listen_type = if payload.len() == 1 {
+            "single"
+        } else {
+            "import"
+        };
+        self.submit(listen_type, payload).await
+    }
+}
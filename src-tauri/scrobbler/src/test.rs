@@ -0,0 +1,291 @@
+// Moosync
+// Copyright (C) 2024, 2025  Moosync <support@moosync.app>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::{
+    collections::BTreeMap,
+    io::{BufRead, BufReader, Read, Write},
+    net::TcpListener,
+    sync::mpsc,
+    thread,
+};
+
+use serde_json::Value;
+use types::scrobble::ScrobbleTrack;
+
+use crate::{lastfm::sign, LastFmClient, ListenBrainzClient, ScrobbleClient, ScrobbleThreshold};
+
+struct Request {
+    method: String,
+    path: String,
+    headers: Vec<(String, String)>,
+    body: String,
+}
+
+impl Request {
+    fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(k, _)| k.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    }
+
+    fn form(&self) -> BTreeMap<String, String> {
+        self.body
+            .split('&')
+            .filter_map(|pair| pair.split_once('='))
+            .map(|(k, v)| (decode(k), decode(v)))
+            .collect()
+    }
+}
+
+fn decode(value: &str) -> String {
+    let value = value.replace('+', " ");
+    let bytes = value.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' && i + 2 < bytes.len() {
+            let hex = std::str::from_utf8(&bytes[i + 1..i + 3]).unwrap();
+            out.push(u8::from_str_radix(hex, 16).unwrap());
+            i += 3;
+        } else {
+            out.push(bytes[i]);
+            i += 1;
+        }
+    }
+    String::from_utf8(out).unwrap()
+}
+
+/// Serves a single request with the given response and hands the request back
+fn mock_server(status: u16, response: &'static str) -> (String, mpsc::Receiver<Request>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    let (tx, rx) = mpsc::channel();
+
+    thread::spawn(move || {
+        let (stream, _) = listener.accept().unwrap();
+        let mut reader = BufReader::new(stream.try_clone().unwrap());
+
+        let mut line = String::new();
+        reader.read_line(&mut line).unwrap();
+        let mut parts = line.split_whitespace();
+        let method = parts.next().unwrap_or_default().to_string();
+        let path = parts.next().unwrap_or_default().to_string();
+
+        let mut headers = vec![];
+        loop {
+            let mut line = String::new();
+            reader.read_line(&mut line).unwrap();
+            let line = line.trim_end();
+            if line.is_empty() {
+                break;
+            }
+            if let Some((k, v)) = line.split_once(':') {
+                headers.push((k.trim().to_string(), v.trim().to_string()));
+            }
+        }
+
+        let length = headers
+            .iter()
+            .find(|(k, _)| k.eq_ignore_ascii_case("content-length"))
+            .map(|(_, v)| v.parse::<usize>().unwrap())
+            .unwrap_or_default();
+        let mut body = vec![0; length];
+        reader.read_exact(&mut body).unwrap();
+
+        let mut stream = stream;
+        write!(
+            stream,
+            "HTTP/1.1 {} OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            status,
+            response.len(),
+            response
+        )
+        .unwrap();
+
+        let _ = tx.send(Request {
+            method,
+            path,
+            headers,
+            body: String::from_utf8(body).unwrap(),
+        });
+    });
+
+    (url, rx)
+}
+
+fn track(title: &str, timestamp: i64) -> ScrobbleTrack {
+    ScrobbleTrack {
+        title: title.into(),
+        artists: vec!["Artist One".into(), "Artist Two".into()],
+        album: Some("Album".into()),
+        album_artist: Some("Album Artist".into()),
+        duration: Some(200.4),
+        timestamp,
+    }
+}
+
+#[test]
+fn test_threshold() {
+    let threshold = ScrobbleThreshold::default();
+
+    // Half of the song
+    assert!(!threshold.is_reached(Some(200f64), 99f64));
+    assert!(threshold.is_reached(Some(200f64), 100f64));
+
+    // Capped at 4 minutes for long songs
+    assert!(!threshold.is_reached(Some(3600f64), 239f64));
+    assert!(threshold.is_reached(Some(3600f64), 240f64));
+
+    // Short songs never count
+    assert!(!threshold.is_reached(Some(29f64), 29f64));
+
+    // Unknown duration falls back to the cap
+    assert!(!threshold.is_reached(None, 100f64));
+    assert!(threshold.is_reached(None, 240f64));
+
+    let custom = ScrobbleThreshold {
+        percent: 90f64,
+        max_secs: 60f64,
+    };
+    assert!(!custom.is_reached(Some(50f64), 44f64));
+    assert!(custom.is_reached(Some(50f64), 45f64));
+    assert!(custom.is_reached(Some(600f64), 60f64));
+}
+
+#[test]
+fn test_lastfm_signature() {
+    let mut params = BTreeMap::new();
+    params.insert("method".to_string(), "track.scrobble".to_string());
+    params.insert("api_key".to_string(), "key".to_string());
+    params.insert("format".to_string(), "json".to_string());
+
+    // Parameters are sorted and format is excluded
+    let expected = format!("{:x}", md5::compute("api_keykeymethodtrack.scrobblesecret"));
+    assert_eq!(sign(&params, "secret"), expected);
+}
+
+#[tokio::test]
+async fn test_lastfm_scrobble() {
+    let (url, rx) = mock_server(200, r#"{"scrobbles":{}}"#);
+    let client = LastFmClient::new(url, "key".into(), "secret".into(), "session".into());
+
+    client
+        .scrobble(&[track("First", 1000), track("Second", 1200)])
+        .await
+        .unwrap();
+
+    let req = rx.recv().unwrap();
+    assert_eq!(req.method, "POST");
+
+    let mut form = req.form();
+    assert_eq!(form.get("method").unwrap(), "track.scrobble");
+    assert_eq!(form.get("sk").unwrap(), "session");
+    assert_eq!(form.get("format").unwrap(), "json");
+    assert_eq!(form.get("track[0]").unwrap(), "First");
+    assert_eq!(form.get("track[1]").unwrap(), "Second");
+    assert_eq!(form.get("artist[0]").unwrap(), "Artist One");
+    assert_eq!(form.get("albumArtist[0]").unwrap(), "Album Artist");
+    assert_eq!(form.get("timestamp[1]").unwrap(), "1200");
+    assert_eq!(form.get("duration[0]").unwrap(), "200");
+
+    let signature = form.remove("api_sig").unwrap();
+    assert_eq!(signature, sign(&form, "secret"));
+}
+
+#[tokio::test]
+async fn test_lastfm_error() {
+    let (url, _rx) = mock_server(200, r#"{"error":9,"message":"Invalid session key"}"#);
+    let client = LastFmClient::new(url, "key".into(), "secret".into(), "session".into());
+
+    let err = client.now_playing(&track("First", 0)).await.unwrap_err();
+    assert!(err.to_string().contains("Invalid session key"));
+}
+
+#[tokio::test]
+async fn test_lastfm_session() {
+    let (url, rx) = mock_server(200, r#"{"session":{"name":"user","key":"abc123"}}"#);
+    let key = LastFmClient::get_session(&url, "key", "secret", "user", "pass")
+        .await
+        .unwrap();
+    assert_eq!(key, "abc123");
+
+    let form = rx.recv().unwrap().form();
+    assert_eq!(form.get("method").unwrap(), "auth.getMobileSession");
+    assert_eq!(form.get("username").unwrap(), "user");
+}
+
+#[tokio::test]
+async fn test_listenbrainz_now_playing() {
+    let (url, rx) = mock_server(200, r#"{"status":"ok"}"#);
+    let client = ListenBrainzClient::new(url, "token".into());
+
+    client.now_playing(&track("First", 1000)).await.unwrap();
+
+    let req = rx.recv().unwrap();
+    assert_eq!(req.method, "POST");
+    assert_eq!(req.path, "/1/submit-listens");
+    assert_eq!(req.header("authorization"), Some("Token token"));
+
+    let body: Value = serde_json::from_str(&req.body).unwrap();
+    assert_eq!(body["listen_type"], "playing_now");
+    let listen = &body["payload"][0];
+    assert!(listen.get("listened_at").is_none());
+    assert_eq!(listen["track_metadata"]["track_name"], "First");
+    assert_eq!(
+        listen["track_metadata"]["artist_name"],
+        "Artist One, Artist Two"
+    );
+    assert_eq!(listen["track_metadata"]["release_name"], "Album");
+    assert_eq!(
+        listen["track_metadata"]["additional_info"]["duration_ms"],
+        200400
+    );
+}
+
+#[tokio::test]
+async fn test_listenbrainz_scrobble() {
+    let (url, rx) = mock_server(200, r#"{"status":"ok"}"#);
+    let client = ListenBrainzClient::new(url.clone(), "token".into());
+    client.scrobble(&[track("First", 1000)]).await.unwrap();
+
+    let body: Value = serde_json::from_str(&rx.recv().unwrap().body).unwrap();
+    assert_eq!(body["listen_type"], "single");
+    assert_eq!(body["payload"][0]["listened_at"], 1000);
+
+    let (url, rx) = mock_server(200, r#"{"status":"ok"}"#);
+    let client = ListenBrainzClient::new(url, "token".into());
+    client
+        .scrobble(&[track("First", 1000), track("Second", 1200)])
+        .await
+        .unwrap();
+
+    let body: Value = serde_json::from_str(&rx.recv().unwrap().body).unwrap();
+    assert_eq!(body["listen_type"], "import");
+    assert_eq!(body["payload"].as_array().unwrap().len(), 2);
+}
+
+#[tokio::test]
+async fn test_listenbrainz_error() {
+    let (url, _rx) = mock_server(
+        401,
+        r#"{"code":401,"error":"Invalid authorization token."}"#,
+    );
+    let client = ListenBrainzClient::new(url, "token".into());
+
+    let err = client.scrobble(&[track("First", 0)]).await.unwrap_err();
+    assert!(err.to_string().contains("Invalid authorization token."));
+}
@@ -0,0 +1,413 @@
+// Moosync
+// Copyright (C) 2024, 2025  Moosync <support@moosync.app>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::{
+    env, fs,
+    io::{ErrorKind, Read, Write},
+    net::{Ipv4Addr, TcpListener, TcpStream},
+    path::{Path, PathBuf},
+    process::{self, Command, Stdio},
+    sync::Mutex,
+    thread,
+    time::{Duration, Instant},
+};
+
+use clap::{Parser, Subcommand};
+use database::database::Database;
+use mpris::MediaControlEvent;
+use preferences::preferences::PreferenceConfig;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use tauri::{AppHandle, Listener, Manager, State};
+use types::{
+    errors::Result,
+    extensions::{MainCommand, MainCommandResponse},
+    songs::{GetSongOptions, SearchableSong, Song},
+};
+
+use crate::{
+    extensions::request_handler::ReplyHandler, mpris::handle_media_event, queue::QueueService,
+};
+
+#[cfg(test)]
+mod test;
+
+/// Time to wait for the running instance, or a newly started one, to run the command
+const REPLY_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Emitted by the UI once it listens for media button presses
+const UI_READY_EVENT: &str = "ui-ready";
+
+#[derive(Parser, Debug, Clone)]
+#[command(version, about, long_about = None)]
+pub struct Args {
+    #[arg(short, long)]
+    pub mobile: bool,
+
+    /// Port on which the process that ran the command waits for its output
+    #[arg(long, hide = true)]
+    pub reply_port: Option<u16>,
+
+    #[command(subcommand)]
+    pub command: Option<CliCommand>,
+}
+
+#[derive(Subcommand, Debug, Clone)]
+pub enum CliCommand {
+    /// Resume playback
+    Play,
+    /// Pause playback
+    Pause,
+    /// Toggle between playing and paused
+    Toggle,
+    /// Skip to the next song
+    Next,
+    /// Go back to the previous song
+    Prev,
+    /// Change the play queue
+    Queue {
+        #[command(subcommand)]
+        action: QueueAction,
+    },
+    /// Search the library
+    Search {
+        term: String,
+        #[arg(long)]
+        json: bool,
+    },
+    /// Print the current song
+    NowPlaying {
+        #[arg(long)]
+        json: bool,
+    },
+}
+
+impl CliCommand {
+    /// Playback commands are handled by the UI and can't run before it has loaded
+    fn needs_ui(&self) -> bool {
+        matches!(
+            self,
+            CliCommand::Play
+                | CliCommand::Pause
+                | CliCommand::Toggle
+                | CliCommand::Next
+                | CliCommand::Prev
+        )
+    }
+}
+
+#[derive(Subcommand, Debug, Clone)]
+pub enum QueueAction {
+    /// Add files to the end of the queue
+    Add {
+        #[arg(required = true)]
+        paths: Vec<PathBuf>,
+    },
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct CliReply {
+    success: bool,
+    output: String,
+}
+
+#[derive(Debug)]
+struct PendingCommand {
+    command: CliCommand,
+    cwd: PathBuf,
+    reply_port: Option<u16>,
+}
+
+/// Holds playback commands which arrive before the UI is ready
+pub struct PendingCommands {
+    // None once the UI is ready
+    commands: Mutex<Option<Vec<PendingCommand>>>,
+}
+
+impl PendingCommands {
+    #[tracing::instrument(level = "debug", skip(app))]
+    pub fn new(app: AppHandle) -> Self {
+        let handle = app.clone();
+        app.listen_any(UI_READY_EVENT, move |_| {
+            let pending: State<PendingCommands> = handle.state();
+            pending.run_all(&handle);
+        });
+
+        Self {
+            commands: Mutex::new(Some(vec![])),
+        }
+    }
+
+    /// Keeps the command until the UI is ready, returns it if it can run now
+    fn defer(&self, command: PendingCommand) -> Option<PendingCommand> {
+        match self.commands.lock().unwrap().as_mut() {
+            Some(commands) => {
+                tracing::info!("Waiting for the UI to run {:?}", command.command);
+                commands.push(command);
+                None
+            }
+            None => Some(command),
+        }
+    }
+
+    #[tracing::instrument(level = "debug", skip(self, app))]
+    fn run_all(&self, app: &AppHandle) {
+        let commands = self.commands.lock().unwrap().take().unwrap_or_default();
+        for pending in commands {
+            spawn_command(app.clone(), pending);
+        }
+    }
+}
+
+#[tracing::instrument(level = "debug", skip(app))]
+pub fn get_pending_commands_state(app: AppHandle) -> PendingCommands {
+    PendingCommands::new(app)
+}
+
+/// Runs the command in the running instance and prints its output.
+/// The app is launched again with a reply port so that the single instance
+/// plugin forwards the command. If no instance is running, the relaunched app
+/// becomes the running instance and runs the command itself.
+pub fn forward_command() -> ! {
+    let reply = match send_to_instance() {
+        Ok(reply) => reply,
+        Err(e) => CliReply {
+            success: false,
+            output: e.to_string(),
+        },
+    };
+
+    if reply.success {
+        if !reply.output.is_empty() {
+            println!("{}", reply.output);
+        }
+        process::exit(0)
+    }
+
+    eprintln!("{}", reply.output);
+    process::exit(1)
+}
+
+fn send_to_instance() -> Result<CliReply> {
+    let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0))?;
+    let port = listener.local_addr()?.port();
+
+    Command::new(env::current_exe()?)
+        .arg("--reply-port")
+        .arg(port.to_string())
+        .args(env::args_os().skip(1))
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()?;
+
+    listener.set_nonblocking(true)?;
+    let started = Instant::now();
+    let mut stream = loop {
+        match listener.accept() {
+            Ok((stream, _)) => break stream,
+            Err(e) if e.kind() == ErrorKind::WouldBlock => {
+                if started.elapsed() > REPLY_TIMEOUT {
+                    return Err("Timed out waiting for Moosync".into());
+                }
+                thread::sleep(Duration::from_millis(50));
+            }
+            Err(e) => return Err(e.into()),
+        }
+    };
+
+    stream.set_nonblocking(false)?;
+    stream.set_read_timeout(Some(REPLY_TIMEOUT))?;
+    let mut data = String::new();
+    stream.read_to_string(&mut data)?;
+    Ok(serde_json::from_str(&data)?)
+}
+
+/// Runs the command if the arguments forwarded by the single instance plugin contain one
+#[tracing::instrument(level = "debug", skip(app))]
+pub fn handle_cli_args(app: &AppHandle, argv: &[String], cwd: &str) -> bool {
+    let Ok(args) = Args::try_parse_from(argv) else {
+        return false;
+    };
+    let Some(command) = args.command else {
+        return false;
+    };
+
+    run_command(app.clone(), command, PathBuf::from(cwd), args.reply_port);
+    true
+}
+
+#[tracing::instrument(level = "debug", skip(app))]
+pub fn run_command(app: AppHandle, command: CliCommand, cwd: PathBuf, reply_port: Option<u16>) {
+    let mut pending = PendingCommand {
+        command,
+        cwd,
+        reply_port,
+    };
+    if pending.command.needs_ui() {
+        if let Some(commands) = app.try_state::<PendingCommands>() {
+            match commands.defer(pending) {
+                Some(command) => pending = command,
+                None => return,
+            }
+        }
+    }
+
+    spawn_command(app, pending);
+}
+
+fn spawn_command(app: AppHandle, pending: PendingCommand) {
+    let PendingCommand {
+        command,
+        cwd,
+        reply_port,
+    } = pending;
+    tauri::async_runtime::spawn(async move {
+        let reply = match execute(&app, command, &cwd).await {
+            Ok(output) => CliReply {
+                success: true,
+                output,
+            },
+            Err(e) => {
+                tracing::error!("Failed to run command: {:?}", e);
+                CliReply {
+                    success: false,
+                    output: e.to_string(),
+                }
+            }
+        };
+
+        if let Some(port) = reply_port {
+            if let Err(e) = send_reply(port, &reply) {
+                tracing::error!("Failed to send command output: {:?}", e);
+            }
+        }
+    });
+}
+
+fn send_reply(port: u16, reply: &CliReply) -> Result<()> {
+    let mut stream = TcpStream::connect((Ipv4Addr::LOCALHOST, port))?;
+    stream.write_all(serde_json::to_string(reply)?.as_bytes())?;
+    Ok(())
+}
+
+async fn execute(app: &AppHandle, command: CliCommand, cwd: &Path) -> Result<String> {
+    let event = match command {
+        CliCommand::Play => MediaControlEvent::Play,
+        CliCommand::Pause => MediaControlEvent::Pause,
+        CliCommand::Toggle => MediaControlEvent::Toggle,
+        CliCommand::Next => MediaControlEvent::Next,
+        CliCommand::Prev => MediaControlEvent::Previous,
+        CliCommand::Queue {
+            action: QueueAction::Add { paths },
+        } => {
+            let songs = paths
+                .iter()
+                .map(|path| load_song(app, &cwd.join(path)))
+                .collect::<Result<Vec<_>>>()?;
+            let count = songs.len();
+
+            let queue: State<QueueService> = app.state();
+            queue.add_to_queue(songs)?;
+            return Ok(format!("Added {} songs to the queue", count));
+        }
+        CliCommand::Search { term, json } => {
+            let database: State<Database> = app.state();
+            let result = database.search_all(term)?;
+            if json {
+                return Ok(serde_json::to_string_pretty(&result)?);
+            }
+            return Ok(result
+                .songs
+                .iter()
+                .map(song_line)
+                .collect::<Vec<_>>()
+                .join("\n"));
+        }
+        CliCommand::NowPlaying { json } => return now_playing(app, json).await,
+    };
+
+    handle_media_event(app, event);
+    Ok(String::new())
+}
+
+async fn now_playing(app: &AppHandle, json: bool) -> Result<String> {
+    let queue: State<QueueService> = app.state();
+    let song = queue.get_queue_state()?.queue.current_song().cloned();
+    if !json {
+        return Ok(song.as_ref().map(song_line).unwrap_or_default());
+    }
+
+    // The player state lives in the UI, which might not be loaded yet
+    let handler = ReplyHandler::new(app.clone());
+    let state = match handler.handle_request(MainCommand::GetPlayerState()).await {
+        Ok(MainCommandResponse::GetPlayerState(state)) => Some(state),
+        _ => None,
+    };
+    let time = match handler.handle_request(MainCommand::GetTime()).await {
+        Ok(MainCommandResponse::GetTime(time)) => Some(time),
+        _ => None,
+    };
+
+    Ok(serde_json::to_string_pretty(&json!({
+        "song": song,
+        "state": state,
+        "time": time,
+    }))?)
+}
+
+fn song_line(song: &Song) -> String {
+    let title = song.song.title.clone().unwrap_or_default();
+    let artists = song
+        .artists
+        .iter()
+        .flatten()
+        .filter_map(|a| a.artist_name.clone())
+        .collect::<Vec<_>>();
+    if artists.is_empty() {
+        return title;
+    }
+    format!("{} - {}", artists.join(", "), title)
+}
+
+/// Uses the library entry of the file if there is one, otherwise reads its tags
+fn load_song(app: &AppHandle, path: &Path) -> Result<Song> {
+    let path = dunce::canonicalize(path)?;
+    let path_str = path.to_string_lossy().to_string();
+
+    let database: State<Database> = app.state();
+    let songs = database.get_songs_by_options(GetSongOptions {
+        song: Some(SearchableSong {
+            path: Some(path_str.clone()),
+            ..Default::default()
+        }),
+        ..Default::default()
+    })?;
+    if let Some(song) = songs
+        .into_iter()
+        .find(|s| s.song.path.as_deref() == Some(path_str.as_str()))
+    {
+        return Ok(song);
+    }
+
+    let preferences: State<PreferenceConfig> = app.state();
+    let thumbnail_dir: String = preferences.load_selective("thumbnail_path".into())?;
+    let artist_split: String = preferences
+        .load_selective("artist_splitter".into())
+        .unwrap_or(";".into());
+    let size = fs::metadata(&path)?.len() as f64;
+    file_scanner::scan_file(&path, Path::new(&thumbnail_dir), size, false, &artist_split)
+}
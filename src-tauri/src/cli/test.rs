@@ -0,0 +1,117 @@
+// Moosync
+// Copyright (C) 2024, 2025  Moosync <support@moosync.app>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::path::PathBuf;
+
+use clap::Parser;
+
+use super::{Args, CliCommand, QueueAction};
+
+fn parse(argv: &[&str]) -> Args {
+    Args::try_parse_from(std::iter::once("moosync").chain(argv.iter().copied())).unwrap()
+}
+
+// Test starting the app without a command
+#[test]
+fn test_parse_no_command() {
+    let args = parse(&[]);
+    assert!(args.command.is_none());
+    assert!(args.reply_port.is_none());
+    assert!(!args.mobile);
+}
+
+// Test parsing the playback commands
+#[test]
+fn test_parse_playback_commands() {
+    assert!(matches!(parse(&["play"]).command, Some(CliCommand::Play)));
+    assert!(matches!(parse(&["pause"]).command, Some(CliCommand::Pause)));
+    assert!(matches!(
+        parse(&["toggle"]).command,
+        Some(CliCommand::Toggle)
+    ));
+    assert!(matches!(parse(&["next"]).command, Some(CliCommand::Next)));
+    assert!(matches!(parse(&["prev"]).command, Some(CliCommand::Prev)));
+}
+
+// Test parsing the commands which print output
+#[test]
+fn test_parse_output_commands() {
+    assert!(matches!(
+        parse(&["search", "some song"]).command,
+        Some(CliCommand::Search { term, json: false }) if term == "some song"
+    ));
+    assert!(matches!(
+        parse(&["search", "--json", "term"]).command,
+        Some(CliCommand::Search { json: true, .. })
+    ));
+    assert!(matches!(
+        parse(&["now-playing"]).command,
+        Some(CliCommand::NowPlaying { json: false })
+    ));
+    assert!(matches!(
+        parse(&["now-playing", "--json"]).command,
+        Some(CliCommand::NowPlaying { json: true })
+    ));
+}
+
+// Test parsing the files added to the queue
+#[test]
+fn test_parse_queue_add() {
+    let Some(CliCommand::Queue {
+        action: QueueAction::Add { paths },
+    }) = parse(&["queue", "add", "a.mp3", "dir/b.flac"]).command
+    else {
+        panic!("Expected a queue command");
+    };
+    assert_eq!(
+        paths,
+        vec![PathBuf::from("a.mp3"), PathBuf::from("dir/b.flac")]
+    );
+}
+
+// Test parsing the reply port added when forwarding a command
+#[test]
+fn test_parse_reply_port() {
+    let args = parse(&["--reply-port", "4242", "next"]);
+    assert_eq!(args.reply_port, Some(4242));
+    assert!(matches!(args.command, Some(CliCommand::Next)));
+}
+
+// Test rejecting invalid arguments
+#[test]
+fn test_parse_invalid() {
+    let parse = |argv: &[&str]| {
+        Args::try_parse_from(std::iter::once("moosync").chain(argv.iter().copied()))
+    };
+    assert!(parse(&["rewind"]).is_err());
+    assert!(parse(&["queue", "add"]).is_err());
+    assert!(parse(&["search"]).is_err());
+    assert!(parse(&["--reply-port", "port", "play"]).is_err());
+}
+
+// Test which commands wait for the UI
+#[test]
+fn test_needs_ui() {
+    assert!(CliCommand::Play.needs_ui());
+    assert!(CliCommand::Next.needs_ui());
+    assert!(CliCommand::Prev.needs_ui());
+    assert!(!CliCommand::NowPlaying { json: false }.needs_ui());
+    assert!(!CliCommand::Search {
+        term: "term".into(),
+        json: false
+    }
+    .needs_ui());
+}
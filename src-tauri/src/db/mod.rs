@@ -16,11 +16,14 @@
 
 use database::{cache::CacheHolder, database::Database};
 use macros::generate_command;
+use preferences::preferences::PreferenceConfig;
 use serde_json::Value;
 use std::fs;
 use tauri::{App, AppHandle, Manager, State};
 use tracing::{info, trace};
 use types::errors::Result;
+use types::queue::SavedQueue;
+use types::radio::QueryableStation;
 use types::songs::AllAnalytics;
 use types::{
     entities::{
@@ -31,6 +34,15 @@ use types::{
 
 use crate::window::handler::WindowHandler;
 
+pub const RESUME_MIN_DURATION_KEY: &str = "resume_min_duration";
+
+/// Songs at least this many minutes long get a resume point, unless configured otherwise.
+/// Seeded into the preferences on startup so that the settings show it.
+pub const DEFAULT_RESUME_MIN_DURATION: f64 = 20f64;
+
+/// Positions this many seconds from the start or the end of a song don't need a resume point
+const RESUME_MARGIN: f64 = 10f64;
+
 #[tracing::instrument(level = "debug", skip(app, db, window_handler))]
 #[tauri_invoke_proc::parse_tauri_command]
 #[tauri::command(async)]
@@ -47,6 +59,74 @@ pub fn export_playlist(
     Ok(fs::write(selected_file, exported)?)
 }
 
+#[tracing::instrument(level = "debug", skip(db))]
+#[tauri_invoke_proc::parse_tauri_command]
+#[tauri::command(async)]
+pub fn import_stations(db: State<Database>, path: String) -> Result<Vec<QueryableStation>> {
+    info!("Importing stations from {}", path);
+    let content = fs::read_to_string(path)?;
+    db.import_stations(content)
+}
+
+/// Minimum song duration in seconds for resume points, None if they are disabled
+fn resume_min_duration(preferences: &PreferenceConfig) -> Option<f64> {
+    let minutes = preferences
+        .load_selective::<f64>(RESUME_MIN_DURATION_KEY.into())
+        .unwrap_or(DEFAULT_RESUME_MIN_DURATION);
+    if minutes > 0f64 {
+        Some(minutes * 60f64)
+    } else {
+        None
+    }
+}
+
+fn supports_resume(preferences: &PreferenceConfig, song: &Song) -> bool {
+    match (resume_min_duration(preferences), song.song.duration) {
+        (Some(min_duration), Some(duration)) => duration >= min_duration,
+        _ => false,
+    }
+}
+
+#[tracing::instrument(level = "debug", skip(db, preferences, song))]
+#[tauri_invoke_proc::parse_tauri_command]
+#[tauri::command(async)]
+pub fn save_resume_point(
+    db: State<Database>,
+    preferences: State<PreferenceConfig>,
+    song: Song,
+    position: f64,
+) -> Result<()> {
+    let Some(id) = song.song._id.clone() else {
+        return Ok(());
+    };
+    if !supports_resume(&preferences, &song) {
+        return Ok(());
+    }
+
+    let duration = song.song.duration.unwrap_or_default();
+    if position < RESUME_MARGIN || position > duration - RESUME_MARGIN {
+        return db.remove_resume_point(id);
+    }
+    db.set_resume_point(id, position)
+}
+
+#[tracing::instrument(level = "debug", skip(db, preferences, song))]
+#[tauri_invoke_proc::parse_tauri_command]
+#[tauri::command(async)]
+pub fn get_resume_point(
+    db: State<Database>,
+    preferences: State<PreferenceConfig>,
+    song: Song,
+) -> Result<Option<f64>> {
+    let Some(id) = song.song._id.clone() else {
+        return Ok(None);
+    };
+    if !supports_resume(&preferences, &song) {
+        return Ok(None);
+    }
+    db.get_resume_point(id)
+}
+
 generate_command!(insert_songs, Database, Vec<Song>, songs: Vec<Song>);
 generate_command!(remove_songs, Database, (), songs: Vec<String>);
 generate_command!(update_song, Database, (), a: QueryableSong);
@@ -66,6 +146,14 @@ generate_command!(update_lyrics, Database, (), id: String, lyrics: String);
 generate_command!(increment_play_count, Database, (), id: String);
 generate_command!(increment_play_time, Database, (), id: String, duration: f64);
 generate_command!(get_top_listened_songs, Database, AllAnalytics,);
+generate_command!(get_stations, Database, Vec<QueryableStation>,);
+generate_command!(add_stations, Database, Vec<QueryableStation>, stations: Vec<QueryableStation>);
+generate_command!(update_station, Database, (), station: QueryableStation);
+generate_command!(remove_station, Database, (), id: String);
+generate_command!(get_saved_queues, Database, Vec<SavedQueue>,);
+generate_command!(remove_saved_queue, Database, (), id: String);
+generate_command!(add_to_saved_queue, Database, (), id: String, name: String, songs: Vec<Song>);
+generate_command!(saved_queue_to_playlist, Database, String, id: String);
 
 #[tracing::instrument(level = "debug", skip(app))]
 pub fn get_cache_state(app: &mut App) -> CacheHolder {
@@ -14,26 +14,58 @@
 // You should have received a copy of the GNU General Public License
 // along with this program.  If not, see <http://www.gnu.org/licenses/>.
 
+use std::{collections::HashMap, path::PathBuf, thread, time::Duration};
+
 use database::cache::CacheHolder;
-use extensions::ExtensionHandler;
+use extensions::{ExtensionDisabledReceiver, ExtensionHandler, ExtensionLimits, ExtensionLogs};
+use macros::generate_command;
 use macros::generate_command_async;
 use macros::generate_command_async_cached;
+use preferences::preferences::PreferenceConfig;
 use request_handler::ReplyHandler;
 use serde_json::Value;
 use tauri::async_runtime;
 use tauri::AppHandle;
+use tauri::Emitter;
 use tauri::Manager;
 use tauri::State;
 use types::errors::Result;
 use types::extensions::GenericExtensionHostRequest;
+use types::preferences::{CheckboxPreference, PreferenceTypes};
 use types::ui::extensions::ExtensionDetail;
+use types::ui::extensions::ExtensionExtraEvent;
 use types::ui::extensions::ExtensionExtraEventArgs;
+use types::ui::extensions::ExtensionPermissions;
 use types::ui::extensions::FetchedExtensionManifest;
 use types::ui::extensions::PackageNameArgs;
+use types::ui::extensions::{
+    extension_preference_key, legacy_extension_preference_key, PreferenceArgs,
+};
+use types::ui::extensions::{ExtensionLogEntry, ExtensionSubscriptionInfo};
 
 use crate::providers::handler::ProviderHandler;
 
-mod request_handler;
+pub mod request_handler;
+
+#[cfg(test)]
+mod test;
+
+/// Preference holding the package names of disabled extensions
+pub const DISABLED_EXTENSIONS_KEY: &str = "disabled_extensions";
+
+/// Preference holding the permissions the user approved, keyed by package name
+pub const GRANTED_PERMISSIONS_KEY: &str = "granted_extension_permissions";
+
+/// Set once the extensions installed before permissions were stored have been trusted
+pub const PERMISSIONS_MIGRATED_KEY: &str = "extension_permissions_migrated";
+
+/// Preference holding the unpacked extension directories loaded in developer mode
+pub const DEV_EXTENSIONS_KEY: &str = "extension_dev_paths";
+
+/// Gives extensions time to load before the first update check
+const UPDATE_CHECK_DELAY: Duration = Duration::from_secs(60);
+
+const UPDATE_CHECK_INTERVAL: Duration = Duration::from_secs(6 * 60 * 60);
 
 #[tracing::instrument(level = "debug", skip(app_handle))]
 async fn extension_runner_connected(app_handle: AppHandle) {
@@ -49,8 +81,11 @@ pub fn get_extension_state(app: AppHandle) -> Result<ExtensionHandler> {
     let ext_path = app.path().app_data_dir().unwrap().join("extensions");
     let tmp_dir = app.path().temp_dir().unwrap();
     let cache_dir = app.path().cache_dir().unwrap();
-    let (ext_handler, mut ui_request_rx, ui_reply_tx) =
-        ExtensionHandler::new(ext_path, cache_dir, tmp_dir);
+    let logs: State<ExtensionLogs> = app.state();
+    let (ext_handler, mut ui_request_rx, ui_reply_tx, disabled_rx) =
+        ExtensionHandler::new(ext_path, cache_dir, tmp_dir, logs.inner().clone());
+
+    async_runtime::spawn(handle_disabled_extensions(app.clone(), disabled_rx));
 
     let app_clone = app.clone();
     async_runtime::spawn(async move {
@@ -89,6 +124,248 @@ pub fn get_extension_state(app: AppHandle) -> Result<ExtensionHandler> {
     Ok(ext_handler)
 }
 
+/// Remembers extensions which were disabled for misbehaving and tells the UI why
+#[tracing::instrument(level = "debug", skip(app, disabled_rx))]
+async fn handle_disabled_extensions(app: AppHandle, mut disabled_rx: ExtensionDisabledReceiver) {
+    while let Some(event) = disabled_rx.recv().await {
+        tracing::warn!(
+            "Extension {} was disabled: {}",
+            event.package_name,
+            event.reason
+        );
+        let preferences: State<PreferenceConfig> = app.state();
+        if let Err(e) = save_extension_disabled(&preferences, event.package_name.clone(), true) {
+            tracing::error!("Failed to save disabled extension: {:?}", e);
+        }
+        if let Err(e) = app.emit("extension-disabled", event) {
+            tracing::error!("Failed to emit disabled extension: {:?}", e);
+        }
+    }
+}
+
+#[tracing::instrument(level = "debug", skip(preferences))]
+fn save_extension_disabled(
+    preferences: &PreferenceConfig,
+    package_name: String,
+    disabled: bool,
+) -> Result<()> {
+    let mut disabled_extensions = preferences
+        .load_selective::<Vec<String>>(DISABLED_EXTENSIONS_KEY.into())
+        .unwrap_or_default();
+    disabled_extensions.retain(|p| *p != package_name);
+    if disabled {
+        disabled_extensions.push(package_name);
+    }
+    preferences.save_selective(DISABLED_EXTENSIONS_KEY.into(), Some(disabled_extensions))
+}
+
+/// None only before the one time migration, when permissions were never stored, which
+/// trusts the installed extensions. Permissions missing after that are all revoked.
+#[tracing::instrument(level = "debug", skip(preferences))]
+pub fn load_granted_permissions(
+    preferences: &PreferenceConfig,
+) -> Option<HashMap<String, ExtensionPermissions>> {
+    let Ok(value) = preferences.load_selective::<Value>(GRANTED_PERMISSIONS_KEY.into()) else {
+        let migrated = preferences
+            .load_selective::<bool>(PERMISSIONS_MIGRATED_KEY.into())
+            .unwrap_or_default();
+        return migrated.then(HashMap::new);
+    };
+    match serde_json::from_value(value) {
+        Ok(granted) => Some(granted),
+        Err(e) => {
+            // Unreadable grants are revoked, every extension has to be approved again
+            tracing::error!("Failed to parse granted extension permissions: {:?}", e);
+            Some(HashMap::new())
+        }
+    }
+}
+
+#[tracing::instrument(level = "debug", skip(ext_handler, preferences))]
+pub async fn save_granted_permissions(
+    ext_handler: &ExtensionHandler,
+    preferences: &PreferenceConfig,
+) -> Result<()> {
+    let granted = ext_handler.get_granted_permissions().await;
+    preferences.save_selective(GRANTED_PERMISSIONS_KEY.into(), Some(granted))
+}
+
+/// Stops installed extensions from being trusted when the permissions are missing
+#[tracing::instrument(level = "debug", skip(preferences))]
+pub fn mark_permissions_migrated(preferences: &PreferenceConfig) -> Result<()> {
+    preferences.save_selective(PERMISSIONS_MIGRATED_KEY.into(), Some(true))
+}
+
+/// Reads the extension limits from preferences, falling back to the defaults
+#[tracing::instrument(level = "debug", skip(preferences))]
+pub fn load_extension_limits(preferences: &PreferenceConfig) -> ExtensionLimits {
+    let mut limits = ExtensionLimits::default();
+
+    // Number inputs are saved as floats
+    if let Ok(secs) = preferences.load_selective::<f64>("extension_call_timeout".into()) {
+        if secs > 0f64 {
+            limits.call_timeout = Duration::from_secs_f64(secs);
+        }
+    }
+    if let Ok(mb) = preferences.load_selective::<f64>("extension_memory_max".into()) {
+        if mb > 0f64 {
+            limits.memory_max = (mb * 1024f64 * 1024f64) as u64;
+        }
+    }
+    if let Ok(max_failures) = preferences.load_selective::<f64>("extension_max_failures".into()) {
+        if max_failures >= 0f64 {
+            limits.max_failures = max_failures as u32;
+        }
+    }
+    limits
+}
+
+/// Whether the developer override for unsigned local packages is on
+#[tracing::instrument(level = "debug", skip(preferences))]
+pub fn load_allow_unsigned(preferences: &PreferenceConfig) -> bool {
+    preferences
+        .load_selective_array::<CheckboxPreference>("extension_developer.allow_unsigned".into())
+        .map(|p| p.enabled)
+        .unwrap_or_default()
+}
+
+/// Unpacked extension directories to load, empty unless developer mode is on
+#[tracing::instrument(level = "debug", skip(preferences))]
+pub fn load_dev_extensions(preferences: &PreferenceConfig) -> Vec<PathBuf> {
+    let dev_mode = preferences
+        .load_selective_array::<CheckboxPreference>("extension_developer.dev_mode".into())
+        .map(|p| p.enabled)
+        .unwrap_or_default();
+    if !dev_mode {
+        return vec![];
+    }
+
+    preferences
+        .load_selective::<Vec<String>>(DEV_EXTENSIONS_KEY.into())
+        .unwrap_or_default()
+        .into_iter()
+        .map(PathBuf::from)
+        .collect()
+}
+
+/// Loads a preference declared in the manifest of an extension. Values stored under
+/// the bare key, before preferences were scoped to their extension, are copied over
+/// on first read. Undeclared keys must not come through here, the bare key could
+/// belong to the app
+#[tracing::instrument(level = "debug", skip(preferences))]
+pub fn load_extension_preference(preferences: &PreferenceConfig, key: &str) -> Option<Value> {
+    if let Ok(value) = preferences.load_selective::<Value>(key.to_string()) {
+        return Some(value);
+    }
+
+    let legacy_key = legacy_extension_preference_key(key)?;
+    let value = preferences
+        .load_selective::<Value>(legacy_key.to_string())
+        .ok()?;
+    tracing::info!("Migrating extension preference {} to {}", legacy_key, key);
+    if let Err(e) = preferences.save_selective(key.to_string(), Some(value.clone())) {
+        tracing::error!("Failed to migrate {}: {:?}", legacy_key, e);
+    }
+    Some(value)
+}
+
+/// Migrates the preferences declared by extensions and stores the defaults of those
+/// which were never set
+#[tracing::instrument(level = "debug", skip(preferences, extensions))]
+pub fn seed_extension_preferences(preferences: &PreferenceConfig, extensions: &[ExtensionDetail]) {
+    for pref in extensions.iter().flat_map(|e| e.preferences.iter()) {
+        if load_extension_preference(preferences, &pref.key).is_some() {
+            continue;
+        }
+
+        let value = match pref._type {
+            PreferenceTypes::CheckboxGroup | PreferenceTypes::Dropdown => {
+                let items = pref.items.clone().unwrap_or_default();
+                let selected = match &pref.default {
+                    Some(Value::Array(keys)) => keys
+                        .iter()
+                        .filter_map(|k| k.as_str().map(String::from))
+                        .collect::<Vec<_>>(),
+                    Some(Value::String(key)) => vec![key.clone()],
+                    // Dropdowns always have an item selected
+                    _ if pref._type == PreferenceTypes::Dropdown => {
+                        items.iter().take(1).map(|i| i.key.clone()).collect()
+                    }
+                    _ => vec![],
+                };
+                let checkboxes = items
+                    .into_iter()
+                    .map(|i| CheckboxPreference {
+                        enabled: selected.contains(&i.key),
+                        key: i.key,
+                    })
+                    .collect::<Vec<_>>();
+                serde_json::to_value(checkboxes).unwrap()
+            }
+            _ => match &pref.default {
+                Some(default) => default.clone(),
+                None => continue,
+            },
+        };
+
+        if let Err(e) = preferences.save_selective(pref.key.clone(), Some(value)) {
+            tracing::error!("Failed to save default of {}: {:?}", pref.key, e);
+        }
+    }
+}
+
+/// Tells the extension owning the preference that it changed
+#[tracing::instrument(level = "debug", skip(app, value))]
+pub async fn notify_extension_preference_changed(app: AppHandle, key: String, value: Value) {
+    let ext_handler = get_extension_handler(&app);
+    let Ok(extensions) = ext_handler.get_installed_extensions().await else {
+        return;
+    };
+
+    for extension in extensions {
+        let prefix = format!(
+            "prefs.{}",
+            extension_preference_key(&extension.package_name, "")
+        );
+        if let Some(short_key) = key.strip_prefix(&prefix) {
+            let args = ExtensionExtraEventArgs {
+                data: ExtensionExtraEvent::PreferenceChanged([PreferenceArgs {
+                    key: short_key.to_string(),
+                    value,
+                }]),
+                package_name: extension.package_name,
+            };
+            if let Err(e) = ext_handler.send_extra_event(args).await {
+                tracing::error!("Failed to send preference change: {:?}", e);
+            }
+            break;
+        }
+    }
+}
+
+/// Periodically compares installed extensions with the published ones and
+/// tells the UI about available updates
+#[tracing::instrument(level = "debug", skip(app))]
+pub fn spawn_extension_update_checker(app: AppHandle) {
+    thread::spawn(move || {
+        thread::sleep(UPDATE_CHECK_DELAY);
+        loop {
+            let ext_handler = get_extension_handler(&app);
+            match async_runtime::block_on(ext_handler.check_extension_updates()) {
+                Ok(updates) if !updates.is_empty() => {
+                    tracing::info!("Found {} extension updates", updates.len());
+                    if let Err(e) = app.emit("extension-updates", updates) {
+                        tracing::error!("Failed to emit extension updates: {:?}", e);
+                    }
+                }
+                Ok(_) => {}
+                Err(e) => tracing::warn!("Failed to check for extension updates: {:?}", e),
+            }
+            thread::sleep(UPDATE_CHECK_INTERVAL);
+        }
+    });
+}
+
 #[tracing::instrument(level = "debug", skip(app))]
 pub fn get_extension_handler(app: &AppHandle) -> State<'_, ExtensionHandler> {
     let ext_state = app.state();
@@ -100,8 +377,7 @@ generate_command_async_cached!(
     ExtensionHandler,
     Vec<FetchedExtensionManifest>,
 );
-generate_command_async!(install_extension, ExtensionHandler, (), ext_path: String);
-generate_command_async!(remove_extension, ExtensionHandler, (), ext_path: String);
+generate_command_async!(install_extension, ExtensionHandler, String, ext_path: String);
 generate_command_async!(download_extension, ExtensionHandler, (), fetched_ext: FetchedExtensionManifest);
 generate_command_async!(
     get_installed_extensions,
@@ -114,4 +390,112 @@ generate_command_async!(
     Value,
     args: ExtensionExtraEventArgs
 );
+generate_command_async!(
+    broadcast_extra_event,
+    ExtensionHandler,
+    Value,
+    data: ExtensionExtraEvent
+);
 generate_command_async_cached!(get_extension_icon, ExtensionHandler, String, args: PackageNameArgs);
+generate_command_async!(reload_extension, ExtensionHandler, (), args: PackageNameArgs);
+generate_command_async!(
+    check_extension_updates,
+    ExtensionHandler,
+    Vec<FetchedExtensionManifest>,
+);
+generate_command_async!(
+    get_extension_updates,
+    ExtensionHandler,
+    Vec<FetchedExtensionManifest>,
+);
+generate_command_async!(update_extension, ExtensionHandler, (), package_name: String);
+generate_command!(
+    get_extension_logs,
+    ExtensionHandler,
+    Vec<ExtensionLogEntry>,
+    package_name: String,
+    after: Option<u64>
+);
+generate_command!(clear_extension_logs, ExtensionHandler, (), package_name: String);
+generate_command!(
+    trust_extension_publisher,
+    ExtensionHandler,
+    (),
+    publisher: String,
+    public_key: String
+);
+generate_command_async!(
+    get_extension_subscriptions,
+    ExtensionHandler,
+    Vec<ExtensionSubscriptionInfo>,
+);
+
+/// Loads an unpacked extension directory in place. Takes effect while developer mode is on.
+#[tracing::instrument(level = "debug", skip(preferences))]
+#[tauri_invoke_proc::parse_tauri_command]
+#[tauri::command(async)]
+pub fn add_dev_extension(preferences: State<PreferenceConfig>, path: String) -> Result<()> {
+    let mut dev_paths = preferences
+        .load_selective::<Vec<String>>(DEV_EXTENSIONS_KEY.into())
+        .unwrap_or_default();
+    if !dev_paths.contains(&path) {
+        dev_paths.push(path);
+    }
+    preferences.save_selective(DEV_EXTENSIONS_KEY.into(), Some(dev_paths))
+}
+
+#[tracing::instrument(level = "debug", skip(ext_handler, preferences))]
+#[tauri_invoke_proc::parse_tauri_command]
+#[tauri::command(async)]
+pub async fn remove_extension(
+    ext_handler: State<'_, ExtensionHandler>,
+    preferences: State<'_, PreferenceConfig>,
+    ext_path: String,
+) -> Result<()> {
+    // Dev extensions are only unloaded, their directory belongs to the developer
+    let dev_path = ext_handler
+        .get_installed_extensions()
+        .await?
+        .into_iter()
+        .find(|e| e.package_name == ext_path && e.dev)
+        .and_then(|e| PathBuf::from(e.entry).parent().map(|p| p.to_path_buf()));
+    if let Some(dev_path) = dev_path {
+        let mut dev_paths = preferences
+            .load_selective::<Vec<String>>(DEV_EXTENSIONS_KEY.into())
+            .unwrap_or_default();
+        dev_paths.retain(|p| !dev_path.starts_with(p));
+        return preferences.save_selective(DEV_EXTENSIONS_KEY.into(), Some(dev_paths));
+    }
+
+    ext_handler.remove_extension(ext_path).await?;
+    save_granted_permissions(&ext_handler, &preferences).await
+}
+
+#[tracing::instrument(level = "debug", skip(ext_handler, preferences))]
+#[tauri_invoke_proc::parse_tauri_command]
+#[tauri::command(async)]
+pub async fn grant_extension_permissions(
+    ext_handler: State<'_, ExtensionHandler>,
+    preferences: State<'_, PreferenceConfig>,
+    package_name: String,
+) -> Result<()> {
+    ext_handler
+        .grant_extension_permissions(package_name)
+        .await?;
+    save_granted_permissions(&ext_handler, &preferences).await
+}
+
+#[tracing::instrument(level = "debug", skip(ext_handler, preferences))]
+#[tauri_invoke_proc::parse_tauri_command]
+#[tauri::command(async)]
+pub async fn toggle_extension_status(
+    ext_handler: State<'_, ExtensionHandler>,
+    preferences: State<'_, PreferenceConfig>,
+    package_name: String,
+) -> Result<bool> {
+    let enabled = ext_handler
+        .toggle_extension_status(package_name.clone().into())
+        .await?;
+    save_extension_disabled(&preferences, package_name, !enabled)?;
+    Ok(enabled)
+}
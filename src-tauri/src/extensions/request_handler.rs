@@ -15,6 +15,7 @@
 // along with this program.  If not, see <http://www.gnu.org/licenses/>.
 
 use database::database::Database;
+use extensions::ExtensionHandler;
 use futures::channel::oneshot;
 use preferences::preferences::PreferenceConfig;
 use serde_json::Value;
@@ -27,7 +28,10 @@ use types::{
     ui::extensions::PreferenceData,
 };
 
-use crate::{providers::handler::ProviderHandler, window::handler::WindowHandler};
+use crate::{
+    extensions::seed_extension_preferences, providers::handler::ProviderHandler,
+    queue::QueueService, window::handler::WindowHandler,
+};
 
 #[derive(Clone)]
 pub struct ReplyHandler {
@@ -110,19 +114,13 @@ impl ReplyHandler {
     #[tracing::instrument(level = "debug", skip(self, data))]
     pub fn get_preferences(&self, data: PreferenceData) -> Result<MainCommandResponse> {
         let preferences: State<'_, PreferenceConfig> = self.app_handle.state();
-        let ret: Result<Value> = preferences.load_selective(data.key.clone());
-        Ok(match ret {
-            Ok(v) => MainCommandResponse::GetPreference(PreferenceData {
-                key: data.key,
-                value: Some(v),
-                default_value: None,
-            }),
-            Err(_) => MainCommandResponse::GetPreference(PreferenceData {
-                key: data.key,
-                value: data.default_value,
-                default_value: None,
-            }),
-        })
+        // Declared preferences were migrated when the extensions were loaded
+        let value = preferences.load_selective::<Value>(data.key.clone()).ok();
+        Ok(MainCommandResponse::GetPreference(PreferenceData {
+            key: data.key,
+            value: value.or(data.default_value),
+            default_value: None,
+        }))
     }
 
     #[tracing::instrument(level = "debug", skip(self, data))]
@@ -181,6 +179,15 @@ impl ReplyHandler {
         Ok(MainCommandResponse::UpdateAccounts(true))
     }
 
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub fn get_queue(&self) -> Result<MainCommandResponse> {
+        let queue: State<'_, QueueService> = self.app_handle.state();
+        let state = queue.get_queue_state()?;
+        Ok(MainCommandResponse::GetQueue(serde_json::to_value(
+            state.queue,
+        )?))
+    }
+
     #[tracing::instrument(level = "debug", skip(self, command))]
     async fn send_ui_request(&self, mut command: MainCommand) -> Result<MainCommandResponse> {
         if self.app_handle.webview_windows().is_empty() {
@@ -213,9 +220,6 @@ impl ReplyHandler {
                 MainCommand::GetTime() => {
                     Ok(MainCommandResponse::GetTime(serde_json::from_str(&data)?))
                 }
-                MainCommand::GetQueue() => {
-                    Ok(MainCommandResponse::GetQueue(serde_json::from_str(&data)?))
-                }
                 _ => Err("Not a ui request".into()),
             },
             Err(_) => Err("Failed to get response from UI".into()),
@@ -227,6 +231,10 @@ impl ReplyHandler {
         tracing::debug!("Got extension updated");
         let provider_handle: State<ProviderHandler> = self.app_handle.state();
         provider_handle.discover_provider_extensions().await?;
+
+        let ext_handler: State<ExtensionHandler> = self.app_handle.state();
+        let preferences: State<PreferenceConfig> = self.app_handle.state();
+        seed_extension_preferences(&preferences, &ext_handler.get_installed_extensions().await?);
         tracing::debug!("Updated extension");
         Ok(MainCommandResponse::ExtensionsUpdated(true))
     }
@@ -238,11 +246,11 @@ impl ReplyHandler {
         Ok(match command {
             MainCommand::GetSong(get_song_options) => self.get_songs(get_song_options)?,
             MainCommand::GetEntity(get_entity_options) => self.get_entity(get_entity_options)?,
+            MainCommand::GetQueue() => self.get_queue()?,
             MainCommand::GetCurrentSong()
             | MainCommand::GetPlayerState()
             | MainCommand::GetVolume()
-            | MainCommand::GetTime()
-            | MainCommand::GetQueue() => self.send_ui_request(command).await?,
+            | MainCommand::GetTime() => self.send_ui_request(command).await?,
             MainCommand::GetPreference(preference_data) => self.get_preferences(preference_data)?,
             MainCommand::SetPreference(preference_data) => self.set_preferences(preference_data)?,
             MainCommand::GetSecure(preference_data) => self.get_secure(preference_data)?,
@@ -261,6 +269,9 @@ impl ReplyHandler {
             MainCommand::OpenExternalUrl(url) => self.open_external(url)?,
             MainCommand::UpdateAccounts(key) => self.update_accounts(key)?,
             MainCommand::ExtensionsUpdated() => self.extension_updated().await?,
+            MainCommand::Subscribe(_) | MainCommand::Unsubscribe(_) => {
+                return Err("Subscriptions are handled by the extension runner".into())
+            }
         })
     }
 }
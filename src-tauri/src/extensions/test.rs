@@ -0,0 +1,169 @@
+// Moosync
+// Copyright (C) 2024, 2025  Moosync <support@moosync.app>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::{collections::HashMap, env::temp_dir};
+
+use preferences::preferences::PreferenceConfig;
+use serde_json::{json, Value};
+use types::ui::extensions::{
+    extension_preference_key, ExtensionCapability, ExtensionDetail, ExtensionPermissions,
+};
+
+use super::{
+    load_extension_preference, load_granted_permissions, mark_permissions_migrated,
+    seed_extension_preferences, GRANTED_PERMISSIONS_KEY,
+};
+
+fn new_preferences() -> PreferenceConfig {
+    let dir = temp_dir().join(format!("moosync_test_{}", uuid::Uuid::new_v4()));
+    PreferenceConfig::new(dir).unwrap()
+}
+
+// Test that permissions which were never stored trust the installed extensions
+#[test]
+fn test_load_granted_permissions_missing() {
+    let preferences = new_preferences();
+    assert!(load_granted_permissions(&preferences).is_none());
+}
+
+// Test that permissions missing after the migration revoke every grant
+#[test]
+fn test_load_granted_permissions_missing_after_migration() {
+    let preferences = new_preferences();
+    mark_permissions_migrated(&preferences).unwrap();
+    assert_eq!(load_granted_permissions(&preferences), Some(HashMap::new()));
+}
+
+// Test that stored permissions are loaded
+#[test]
+fn test_load_granted_permissions_stored() {
+    let preferences = new_preferences();
+    let granted = HashMap::from([(
+        "moosync.test".to_string(),
+        ExtensionPermissions {
+            capabilities: vec![ExtensionCapability::LibraryRead],
+            ..Default::default()
+        },
+    )]);
+    preferences
+        .save_selective(GRANTED_PERMISSIONS_KEY.into(), Some(granted.clone()))
+        .unwrap();
+
+    assert_eq!(load_granted_permissions(&preferences), Some(granted));
+}
+
+// Test that unreadable permissions revoke every grant instead of trusting everything
+#[test]
+fn test_load_granted_permissions_corrupt() {
+    let preferences = new_preferences();
+    preferences
+        .save_selective(
+            GRANTED_PERMISSIONS_KEY.into(),
+            Some(json!({ "moosync.test": { "capabilities": ["everything"] } })),
+        )
+        .unwrap();
+
+    assert_eq!(load_granted_permissions(&preferences), Some(HashMap::new()));
+}
+
+// Test that preferences stored under the bare key are copied to the scoped key
+#[test]
+fn test_load_extension_preference_migrates_legacy() {
+    let preferences = new_preferences();
+    preferences
+        .save_selective("token".into(), Some(json!("abc")))
+        .unwrap();
+
+    let key = extension_preference_key("moosync.test", "token");
+    assert_eq!(
+        load_extension_preference(&preferences, &key),
+        Some(json!("abc"))
+    );
+    assert_eq!(
+        preferences.load_selective::<Value>(key).unwrap(),
+        json!("abc")
+    );
+}
+
+// Test that scoped preferences win over the bare key
+#[test]
+fn test_load_extension_preference_scoped() {
+    let preferences = new_preferences();
+    let key = extension_preference_key("moosync.test", "token");
+    preferences
+        .save_selective("token".into(), Some(json!("old")))
+        .unwrap();
+    preferences
+        .save_selective(key.clone(), Some(json!("new")))
+        .unwrap();
+
+    assert_eq!(
+        load_extension_preference(&preferences, &key),
+        Some(json!("new"))
+    );
+}
+
+// Test that preferences which were never stored are missing
+#[test]
+fn test_load_extension_preference_missing() {
+    let preferences = new_preferences();
+    let key = extension_preference_key("moosync.test", "token");
+    assert!(load_extension_preference(&preferences, &key).is_none());
+}
+
+// Test that only preferences declared by the extension are migrated from the bare key
+#[test]
+fn test_seed_extension_preferences_migrates_declared() {
+    let preferences = new_preferences();
+    preferences
+        .save_selective("token".into(), Some(json!("abc")))
+        .unwrap();
+    preferences
+        .save_selective("youtube_api_key".into(), Some(json!("secret")))
+        .unwrap();
+
+    let extension: ExtensionDetail = serde_json::from_value(json!({
+        "name": "Test",
+        "packageName": "moosync.test",
+        "desc": null,
+        "author": null,
+        "version": "1.0.0",
+        "hasStarted": true,
+        "entry": "ext.wasm",
+        "preferences": [{
+            "type": "EditText",
+            "title": "Token",
+            "key": extension_preference_key("moosync.test", "token"),
+            "description": "",
+        }],
+        "extensionPath": "",
+        "extensionIcon": null,
+        "active": true,
+        "enabled": true,
+    }))
+    .unwrap();
+    seed_extension_preferences(&preferences, &[extension]);
+
+    assert_eq!(
+        preferences
+            .load_selective::<Value>(extension_preference_key("moosync.test", "token"))
+            .unwrap(),
+        json!("abc")
+    );
+    assert!(preferences
+        .load_selective::<Value>(extension_preference_key("moosync.test", "youtube_api_key"))
+        .is_err());
+}
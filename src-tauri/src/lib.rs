@@ -18,7 +18,10 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 use std::fs;
 
+#[cfg(desktop)]
 use clap::Parser;
+#[cfg(desktop)]
+use cli::{forward_command, get_pending_commands_state, handle_cli_args, run_command, Args};
 use db::is_song_in_playlist;
 use extensions::get_extension_state;
 use librespot::{
@@ -29,17 +32,21 @@ use librespot::{
 use logger::{get_logger_state, renderer_write};
 use lyrics::{get_lyrics, get_lyrics_state};
 use mobile_player::{
-    mobile_load, mobile_pause, mobile_play, mobile_seek, mobile_stop, MobilePlayer,
+    mobile_load, mobile_pause, mobile_play, mobile_seek, mobile_set_volume, mobile_stop,
+    MobilePlayer,
+};
+use mpris::{
+    get_mpris_state, mpris_queue_details, set_metadata, set_playback_state, set_position,
+    set_volume, update_mpris_queue,
 };
-use mpris::{get_mpris_state, set_metadata, set_playback_state, set_position};
 use preference_holder::{
     get_preference_state, get_secure, handle_pref_changes, initial, load_selective,
     load_selective_array, save_selective, set_secure,
 };
 use providers::handler::get_provider_handler_state;
 use rodio::{
-    get_rodio_state, rodio_get_volume, rodio_load, rodio_pause, rodio_play, rodio_seek,
-    rodio_set_volume, rodio_stop,
+    get_rodio_state, rodio_cache_stats, rodio_clear_cache, rodio_get_volume, rodio_load,
+    rodio_pause, rodio_play, rodio_reinitialize, rodio_seek, rodio_set_volume, rodio_stop,
 };
 use themes::{
     download_theme, export_theme, get_css, get_theme_handler_state, get_themes_manifest,
@@ -47,8 +54,12 @@ use themes::{
 };
 
 use extensions::{
-    download_extension, get_extension_icon, get_extension_manifest, get_installed_extensions,
-    install_extension, remove_extension, send_extra_event,
+    add_dev_extension, broadcast_extra_event, check_extension_updates, clear_extension_logs,
+    download_extension, get_extension_handler, get_extension_icon, get_extension_logs,
+    get_extension_manifest, get_extension_subscriptions, get_extension_updates,
+    get_installed_extensions, grant_extension_permissions, install_extension, reload_extension,
+    remove_extension, send_extra_event, spawn_extension_update_checker, toggle_extension_status,
+    trust_extension_publisher, update_extension,
 };
 use providers::handler::{
     fetch_playback_url, fetch_playlist_content, fetch_user_playlists, get_album_content,
@@ -57,13 +68,27 @@ use providers::handler::{
     get_suggestions, initialize_all_providers, match_url, playlist_from_url, provider_authorize,
     provider_login, provider_search, provider_signout, song_from_url, trigger_context_menu_action,
 };
+use queue::{
+    add_to_queue, change_queue_index, clear_queue, clear_queue_except_current,
+    get_queue_service_state, get_queue_state, import_queue, load_saved_queue, next_song, play_next,
+    play_now, prev_song, remove_from_queue, save_current_queue, set_autoplay, set_repeat,
+    set_shuffle, shuffle_queue, toggle_repeat, toggle_shuffle, QueueService,
+};
+use remote::{get_remote_server_state, get_remote_token, regenerate_remote_token};
 use scanner::{get_scanner_state, start_scan, ScanTask};
+use scrobbler::{get_scrobbler_state, scrobbler_add_played, scrobbler_now_playing};
+use shortcuts::{get_global_shortcut_conflicts, get_global_shortcuts_state, set_global_shortcuts};
+use sleep_timer::{
+    cancel_sleep_timer, get_sleep_timer, get_sleep_timer_state, set_sleep_timer,
+    sleep_timer_expired,
+};
 use tauri::{Listener, Manager, State};
 use tracing_appender::rolling::{RollingFileAppender, Rotation};
 use tracing_subscriber::{
     filter::EnvFilter,
     fmt::{self},
     layer::SubscriberExt,
+    Layer,
 };
 use updater::{fetch_update, get_updater_state, install_update};
 use window::handler::{build_tray_menu, handle_window_close};
@@ -75,11 +100,13 @@ use {
     db::{
         get_cache_state,
         {
-            add_to_playlist, create_playlist, export_playlist, get_db_state, get_entity_by_options,
-            get_songs_by_options, get_top_listened_songs, increment_play_count,
-            increment_play_time, insert_songs, remove_from_playlist, remove_playlist, remove_songs,
-            search_all, update_album, update_artist, update_lyrics, update_playlist, update_song,
-            update_songs,
+            add_stations, add_to_playlist, add_to_saved_queue, create_playlist, export_playlist,
+            get_db_state, get_entity_by_options, get_resume_point, get_saved_queues,
+            get_songs_by_options, get_stations, get_top_listened_songs, import_stations,
+            increment_play_count, increment_play_time, insert_songs, remove_from_playlist,
+            remove_playlist, remove_saved_queue, remove_songs, remove_station, save_resume_point,
+            saved_queue_to_playlist, search_all, update_album, update_artist, update_lyrics,
+            update_playlist, update_song, update_songs, update_station,
         },
     },
     oauth::handler::{get_oauth_state, OAuthHandler},
@@ -92,6 +119,8 @@ use {
     youtube::get_youtube_scraper_state,
 };
 
+#[cfg(desktop)]
+mod cli;
 mod db;
 mod extensions;
 mod librespot;
@@ -102,22 +131,19 @@ mod mpris;
 mod oauth;
 mod preference_holder;
 mod providers;
+mod queue;
+mod remote;
 mod rodio;
 mod scanner;
+mod scrobbler;
+mod shortcuts;
+mod sleep_timer;
 mod themes;
 #[cfg(desktop)]
 mod updater;
 mod window;
 mod youtube;
 
-#[cfg(desktop)]
-#[derive(Parser, Debug)]
-#[command(version, about, long_about = None)]
-struct Args {
-    #[arg(short, long)]
-    mobile: bool,
-}
-
 #[tracing::instrument(level = "debug", skip())]
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
@@ -134,6 +160,12 @@ pub fn run() {
     #[cfg(desktop)]
     let args = Args::parse();
 
+    // Commands are run by the main instance, this process only prints their output
+    #[cfg(desktop)]
+    if args.command.is_some() && args.reply_port.is_none() {
+        forward_command();
+    }
+
     let mut builder = tauri::Builder::default();
 
     #[cfg(desktop)]
@@ -145,7 +177,11 @@ pub fn run() {
 
         builder = builder
             .plugin(tauri_plugin_updater::Builder::new().build())
-            .plugin(tauri_plugin_single_instance::init(|app, argv, _cwd| {
+            .plugin(tauri_plugin_single_instance::init(|app, argv, cwd| {
+                if handle_cli_args(app, &argv, &cwd) {
+                    return;
+                }
+
                 if let Some(url) = argv.get(1) {
                     tracing::info!("Got url {}", url);
                     let state: State<OAuthHandler> = app.state();
@@ -153,6 +189,7 @@ pub fn run() {
                 }
             }))
             .plugin(tauri_plugin_dialog::init())
+            .plugin(tauri_plugin_global_shortcut::Builder::new().build())
             .plugin(tauri_plugin_autostart::init(
                 tauri_plugin_autostart::MacosLauncher::LaunchAgent,
                 None,
@@ -230,6 +267,13 @@ pub fn run() {
             increment_play_time,
             export_playlist,
             get_top_listened_songs,
+            get_stations,
+            add_stations,
+            update_station,
+            remove_station,
+            import_stations,
+            save_resume_point,
+            get_resume_point,
             // Window
             is_maximized,
             has_frame,
@@ -273,6 +317,42 @@ pub fn run() {
             set_metadata,
             set_playback_state,
             set_position,
+            set_volume,
+            // Queue
+            get_queue_state,
+            add_to_queue,
+            play_now,
+            play_next,
+            remove_from_queue,
+            change_queue_index,
+            next_song,
+            prev_song,
+            set_repeat,
+            toggle_repeat,
+            set_shuffle,
+            toggle_shuffle,
+            shuffle_queue,
+            set_autoplay,
+            clear_queue,
+            clear_queue_except_current,
+            import_queue,
+            save_current_queue,
+            load_saved_queue,
+            get_saved_queues,
+            remove_saved_queue,
+            add_to_saved_queue,
+            saved_queue_to_playlist,
+            // Sleep timer
+            set_sleep_timer,
+            cancel_sleep_timer,
+            get_sleep_timer,
+            sleep_timer_expired,
+            // Global shortcuts
+            set_global_shortcuts,
+            get_global_shortcut_conflicts,
+            // Scrobbler
+            scrobbler_now_playing,
+            scrobbler_add_played,
             // Lyrics
             get_lyrics,
             // Extensions
@@ -283,6 +363,18 @@ pub fn run() {
             get_extension_manifest,
             get_extension_icon,
             send_extra_event,
+            broadcast_extra_event,
+            toggle_extension_status,
+            reload_extension,
+            check_extension_updates,
+            get_extension_updates,
+            update_extension,
+            grant_extension_permissions,
+            add_dev_extension,
+            get_extension_logs,
+            clear_extension_logs,
+            get_extension_subscriptions,
+            trust_extension_publisher,
             //Provider Handler
             get_provider_keys,
             initialize_all_providers,
@@ -314,6 +406,12 @@ pub fn run() {
             rodio_seek,
             rodio_set_volume,
             rodio_stop,
+            rodio_reinitialize,
+            rodio_cache_stats,
+            rodio_clear_cache,
+            // Remote control
+            get_remote_token,
+            regenerate_remote_token,
             // Logger
             renderer_write,
             // Mobile player
@@ -322,11 +420,12 @@ pub fn run() {
             mobile_pause,
             mobile_stop,
             mobile_seek,
+            mobile_set_volume,
             // Updater
             fetch_update,
             install_update
         ])
-        .setup(|app| {
+        .setup(move |app| {
             let layer = fmt::layer()
                 .pretty()
                 .with_target(true)
@@ -341,12 +440,14 @@ pub fn run() {
                 .with_ansi(false)
                 .with_target(true)
                 .with_writer(file_appender);
+            // Extension logs are kept regardless of MOOSYNC_LOG
+            let extension_logs = ::extensions::ExtensionLogs::default();
             let subscriber = tracing_subscriber::registry()
-                .with(layer)
-                .with(log_layer)
-                .with(filter);
+                .with(layer.and_then(log_layer).with_filter(filter))
+                .with(extension_logs.layer());
 
             tracing::subscriber::set_global_default(subscriber).unwrap();
+            app.manage(extension_logs);
 
             let db = get_db_state(app);
             app.manage(db);
@@ -354,6 +455,9 @@ pub fn run() {
             let cache = get_cache_state(app);
             app.manage(cache);
 
+            let queue_service = get_queue_service_state(app.app_handle().clone());
+            app.manage(queue_service);
+
             let config = get_preference_state(app)?;
             app.manage(config);
 
@@ -378,16 +482,40 @@ pub fn run() {
             let theme_handler_state = get_theme_handler_state(app);
             app.manage(theme_handler_state);
 
+            let sleep_timer_state = get_sleep_timer_state();
+            app.manage(sleep_timer_state);
+
             let mpris_state = get_mpris_state(app.app_handle().clone())?;
             app.manage(mpris_state);
 
+            let queue_state: State<QueueService> = app.state();
+            update_mpris_queue(
+                app.app_handle(),
+                mpris_queue_details(&queue_state.get_queue_state()?),
+            );
+
+            let remote_state = get_remote_server_state(app.app_handle().clone());
+            app.manage(remote_state);
+
+            #[cfg(desktop)]
+            {
+                let pending_commands_state = get_pending_commands_state(app.app_handle().clone());
+                app.manage(pending_commands_state);
+            }
+
+            let scrobbler_state = get_scrobbler_state();
+            app.manage(scrobbler_state);
+
+            let shortcuts_state = get_global_shortcuts_state();
+            app.manage(shortcuts_state);
+
             let lyrics_state = get_lyrics_state();
             app.manage(lyrics_state);
 
             let provider_handler_state = get_provider_handler_state(app.app_handle().clone());
             app.manage(provider_handler_state);
 
-            let rodio_state = get_rodio_state(app.app_handle().clone());
+            let rodio_state = get_rodio_state(app.app_handle().clone())?;
             app.manage(rodio_state);
 
             let logger = get_logger_state(app.app_handle().clone());
@@ -414,6 +542,7 @@ pub fn run() {
 
             let ext_state = get_extension_state(app.app_handle().clone())?;
             app.manage(ext_state);
+            spawn_extension_update_checker(app.handle().clone());
 
             initial(app);
             handle_pref_changes(app.handle().clone());
@@ -424,10 +553,29 @@ pub fn run() {
 
             build_tray_menu(app)?;
 
+            // Started by a command while no other instance was running
+            #[cfg(desktop)]
+            if let Some(command) = args.command.clone() {
+                run_command(
+                    app.handle().clone(),
+                    command,
+                    std::env::current_dir()?,
+                    args.reply_port,
+                );
+            }
+
             Ok(())
         });
 
     builder
-        .run(tauri::generate_context!())
+        .build(tauri::generate_context!())
         .expect("error while running tauri application")
+        .run(|app, event| {
+            if let tauri::RunEvent::Exit = event {
+                let ext_handler = get_extension_handler(app);
+                if let Err(e) = tauri::async_runtime::block_on(ext_handler.stop_extensions()) {
+                    tracing::error!("Failed to stop extensions: {:?}", e);
+                }
+            }
+        })
 }
@@ -81,6 +81,15 @@ impl MobilePlayer {
         }
         Ok(())
     }
+
+    pub fn mobile_set_volume(&self, app: AppHandle, key: String, volume: f64) -> Result<()> {
+        #[cfg(mobile)]
+        {
+            let player = app.audioplayer();
+            player.set_volume(key, volume)?;
+        }
+        Ok(())
+    }
 }
 
 generate_command!(mobile_load, MobilePlayer, (), app: AppHandle, key: String, src: String, autoplay: bool);
@@ -88,3 +97,4 @@ generate_command!(mobile_play, MobilePlayer, (), app: AppHandle, key: String);
 generate_command!(mobile_pause, MobilePlayer, (), app: AppHandle, key: String);
 generate_command!(mobile_stop, MobilePlayer, (), app: AppHandle, key: String);
 generate_command!(mobile_seek, MobilePlayer, (), app: AppHandle, key: String, pos: f64);
+generate_command!(mobile_set_volume, MobilePlayer, (), app: AppHandle, key: String, volume: f64);
@@ -17,10 +17,130 @@
 use std::thread;
 
 use macros::generate_command;
-use mpris::MprisHolder;
+use mpris::{MprisHolder, SeekDirection};
 use serde_json::{json, Value};
-use tauri::{AppHandle, Emitter, State};
-use types::{errors::Result, mpris::MprisPlayerDetails, ui::player_details::PlayerState};
+use tauri::{AppHandle, Emitter, Manager, State};
+use types::{
+    errors::Result,
+    mpris::{MprisPlayerDetails, MprisQueueDetails},
+    queue::QueueState,
+    ui::player_details::{PlayerEvents, PlayerState},
+};
+
+use crate::{
+    queue::QueueService,
+    remote::{RemoteEvent, RemoteServer},
+    sleep_timer::handle_sleep_timer_uri,
+};
+
+/// Seconds to seek when the OS doesn't say by how much
+const DEFAULT_SEEK: f64 = 5f64;
+
+/// Number of upcoming songs listed in the MPRIS track list
+const TRACK_LIST_SIZE: usize = 20;
+
+fn seek_offset(direction: SeekDirection, seconds: f64) -> Value {
+    match direction {
+        SeekDirection::Forward => json!(seconds),
+        SeekDirection::Backward => json!(-seconds),
+    }
+}
+
+/// Queue details shown in the OS media controls
+pub fn mpris_queue_details(state: &QueueState) -> MprisQueueDetails {
+    let queue = &state.queue;
+    MprisQueueDetails {
+        repeat: state.repeat,
+        shuffle: state.shuffle.enabled,
+        can_go_next: queue.song_queue.len() > 1 || state.autoplay,
+        can_go_previous: queue.song_queue.len() > 1,
+        current_index: queue.current_index,
+        tracks: queue
+            .song_queue
+            .iter()
+            .skip(queue.current_index)
+            .take(TRACK_LIST_SIZE)
+            .filter_map(|id| queue.data.get(id))
+            .map(MprisPlayerDetails::from)
+            .collect(),
+    }
+}
+
+/// Shows the queue details in the OS media controls
+#[tracing::instrument(level = "debug", skip(app, details))]
+pub fn update_mpris_queue(app: &AppHandle, details: MprisQueueDetails) {
+    let Some(mpris_holder) = app.try_state::<MprisHolder>() else {
+        return;
+    };
+
+    if let Err(e) = mpris_holder.set_queue_details(details) {
+        tracing::error!("Failed to set mpris queue details: {:?}", e);
+    }
+}
+
+/// Acts on a media control event, either directly on the queue or by forwarding it to the UI
+#[tracing::instrument(level = "debug", skip(app))]
+pub fn handle_media_event(app: &AppHandle, event: mpris::MediaControlEvent) {
+    let data = match event {
+        mpris::MediaControlEvent::Play => (0, Value::Null),
+        mpris::MediaControlEvent::Pause => (1, Value::Null),
+        mpris::MediaControlEvent::Toggle => (13, Value::Null),
+        mpris::MediaControlEvent::Next => {
+            let queue: State<QueueService> = app.state();
+            if let Err(e) = queue.next_song() {
+                tracing::error!("Failed to skip to next song: {:?}", e);
+            }
+            return;
+        }
+        mpris::MediaControlEvent::Previous => {
+            let queue: State<QueueService> = app.state();
+            if let Err(e) = queue.prev_song() {
+                tracing::error!("Failed to skip to previous song: {:?}", e);
+            }
+            return;
+        }
+        mpris::MediaControlEvent::Stop => (2, Value::Null),
+        mpris::MediaControlEvent::Seek(dir) => (19, seek_offset(dir, DEFAULT_SEEK)),
+        mpris::MediaControlEvent::SeekBy(dir, by) => (19, seek_offset(dir, by.as_secs_f64())),
+        mpris::MediaControlEvent::SetPosition(pos) => (12, json!(pos.0.as_secs_f64())),
+
+        // The UI volume goes from 0 to 100
+        mpris::MediaControlEvent::SetVolume(vol) => (15, json!(vol * 100f64)),
+        mpris::MediaControlEvent::SetLoopStatus(repeat) => {
+            let queue: State<QueueService> = app.state();
+            if let Err(e) = queue.set_repeat(repeat) {
+                tracing::error!("Failed to set repeat mode: {:?}", e);
+            }
+            return;
+        }
+        mpris::MediaControlEvent::SetShuffle(enabled) => {
+            let queue: State<QueueService> = app.state();
+            let res = queue
+                .get_queue_state()
+                .and_then(|state| queue.set_shuffle(enabled, state.shuffle.avoid_same_artist));
+            if let Err(e) = res {
+                tracing::error!("Failed to set shuffle: {:?}", e);
+            }
+            return;
+        }
+        mpris::MediaControlEvent::GoTo(index) => {
+            let queue: State<QueueService> = app.state();
+            if let Err(e) = queue.change_queue_index(index, true) {
+                tracing::error!("Failed to change queue index: {:?}", e);
+            }
+            return;
+        }
+        mpris::MediaControlEvent::OpenUri(uri) => {
+            if handle_sleep_timer_uri(app, &uri) {
+                return;
+            }
+            (16, Value::String(uri))
+        }
+        mpris::MediaControlEvent::Raise => (17, Value::Null),
+        mpris::MediaControlEvent::Quit => (18, Value::Null),
+    };
+    let _ = app.emit("media_button_press", data);
+}
 
 #[tracing::instrument(level = "debug", skip(app))]
 pub fn get_mpris_state(app: AppHandle) -> Result<MprisHolder> {
@@ -35,37 +155,40 @@ pub fn get_mpris_state(app: AppHandle) -> Result<MprisHolder> {
         loop {
             tracing::trace!("Waiting for mpris events");
             let event = receiver.recv().unwrap();
-            let data = match event {
-                mpris::MediaControlEvent::Play => (0, Value::Null),
-                mpris::MediaControlEvent::Pause => (1, Value::Null),
-                mpris::MediaControlEvent::Toggle => (13, Value::Null),
-                mpris::MediaControlEvent::Next => (6, Value::Null),
-                mpris::MediaControlEvent::Previous => (7, Value::Null),
-                mpris::MediaControlEvent::Stop => (2, Value::Null),
-                mpris::MediaControlEvent::Seek(_) => (12, json!(0)),
-                mpris::MediaControlEvent::SeekBy(_dir, pos) => {
-                    // match dir {
-                    //     mpris::SeekDirection::Forward => (12, json!(pos.as_secs())),
-                    //     mpris::SeekDirection::Backward => {
-                    //         (12, json!((pos.as_secs() as i128).neg()))
-                    //     }
-                    // }
-                    (12, json!(pos.as_secs()))
-                }
-                mpris::MediaControlEvent::SetPosition(pos) => (12, json!(pos.0.as_secs())),
-
-                mpris::MediaControlEvent::SetVolume(vol) => (15, json!(vol)),
-                mpris::MediaControlEvent::OpenUri(uri) => (16, Value::String(uri)),
-                mpris::MediaControlEvent::Raise => (17, Value::Null),
-                mpris::MediaControlEvent::Quit => (18, Value::Null),
-            };
-            let _ = app.emit("media_button_press", data);
+            handle_media_event(&app, event);
         }
     });
 
     Ok(mpris_holder)
 }
 
+#[tracing::instrument(level = "debug", skip(app, mpris_holder))]
+#[tauri_invoke_proc::parse_tauri_command]
+#[tauri::command(async)]
+pub fn set_playback_state(
+    app: AppHandle,
+    mpris_holder: State<MprisHolder>,
+    state: PlayerState,
+) -> Result<()> {
+    if let Some(remote) = app.try_state::<RemoteServer>() {
+        remote.send_event(RemoteEvent::Player(match state {
+            PlayerState::Playing => PlayerEvents::Play,
+            PlayerState::Loading => PlayerEvents::Loading,
+            PlayerState::Paused | PlayerState::Stopped => PlayerEvents::Pause,
+        }));
+    }
+    mpris_holder.set_playback_state(state)
+}
+
+#[tracing::instrument(level = "debug", skip(app, mpris_holder))]
+#[tauri_invoke_proc::parse_tauri_command]
+#[tauri::command(async)]
+pub fn set_position(app: AppHandle, mpris_holder: State<MprisHolder>, duration: f64) -> Result<()> {
+    if let Some(remote) = app.try_state::<RemoteServer>() {
+        remote.send_event(RemoteEvent::Player(PlayerEvents::TimeUpdate(duration)));
+    }
+    mpris_holder.set_position(duration)
+}
+
 generate_command!(set_metadata, MprisHolder, (), metadata: MprisPlayerDetails);
-generate_command!(set_playback_state, MprisHolder, (), state: PlayerState);
-generate_command!(set_position, MprisHolder, (), duration: f64);
+generate_command!(set_volume, MprisHolder, (), volume: f64);
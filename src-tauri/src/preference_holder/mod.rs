@@ -19,13 +19,25 @@ use std::thread;
 use extensions::ExtensionHandler;
 use macros::generate_command;
 use preferences::preferences::PreferenceConfig;
+use rodio_player::RodioPlayer;
 use serde_json::Value;
 use tauri::{async_runtime, App, AppHandle, Emitter, Manager, State};
 use types::{errors::Result, preferences::CheckboxPreference};
 
 use crate::{
+    db::{DEFAULT_RESUME_MIN_DURATION, RESUME_MIN_DURATION_KEY},
+    extensions::{
+        load_allow_unsigned, load_dev_extensions, load_extension_limits, load_granted_permissions,
+        mark_permissions_migrated, notify_extension_preference_changed, save_granted_permissions,
+        DISABLED_EXTENSIONS_KEY,
+    },
     providers::handler::ProviderHandler,
+    remote::RemoteServer,
+    rodio::{load_audio_cache_size, AUDIO_CACHE_SIZE_KEY, DEFAULT_AUDIO_CACHE_SIZE},
     scanner::{start_scan, ScanTask},
+    scrobbler::ScrobblerService,
+    shortcuts::GlobalShortcuts,
+    window::handler::update_tray_menu,
 };
 
 const UI_KEYS: &[&str] = &[
@@ -100,6 +112,53 @@ pub fn handle_pref_changes(app: AppHandle) {
                 }
             }
 
+            if key.starts_with("prefs.remote") {
+                let remote_server: State<RemoteServer> = app.state();
+                remote_server.schedule_restart(app.clone());
+            }
+
+            if key == "prefs.i18n_language" {
+                if let Err(e) = update_tray_menu(&app) {
+                    tracing::error!("Failed to update tray menu: {:?}", e);
+                }
+            }
+
+            if key.starts_with("prefs.scrobble") {
+                let scrobbler: State<ScrobblerService> = app.state();
+                scrobbler.restart(app.clone());
+            }
+
+            if key.starts_with("prefs.global_shortcuts") {
+                let shortcuts: State<GlobalShortcuts> = app.state();
+                if let Err(e) = shortcuts.reload(&app) {
+                    tracing::error!("Failed to register global shortcuts: {:?}", e);
+                }
+            }
+
+            if key.starts_with("prefs.extensions.") {
+                async_runtime::spawn(notify_extension_preference_changed(
+                    app.clone(),
+                    key.clone(),
+                    value.clone(),
+                ));
+            }
+
+            if key.starts_with("prefs.extension_") {
+                let extension_handler: State<ExtensionHandler> = app.state();
+                extension_handler
+                    .set_extension_limits(load_extension_limits(&pref_config))
+                    .await;
+                extension_handler.set_allow_unsigned(load_allow_unsigned(&pref_config));
+                extension_handler
+                    .set_dev_extensions(load_dev_extensions(&pref_config))
+                    .await;
+            }
+
+            if key == format!("prefs.{}", AUDIO_CACHE_SIZE_KEY) {
+                let rodio: State<RodioPlayer> = app.state();
+                rodio.set_cache_size(load_audio_cache_size(&pref_config));
+            }
+
             if key.starts_with("prefs.scan_interval") {
                 let scan_task: State<ScanTask> = app.state();
                 scan_task.spawn_scan_task(app.clone(), value.as_u64().unwrap().max(30));
@@ -147,6 +206,33 @@ pub fn initial(app: &mut App) {
         );
     }
 
+    if !pref_config.has_key(RESUME_MIN_DURATION_KEY) {
+        let _ = pref_config.save_selective(
+            RESUME_MIN_DURATION_KEY.to_string(),
+            Some(DEFAULT_RESUME_MIN_DURATION),
+        );
+    }
+
+    if !pref_config.has_key(AUDIO_CACHE_SIZE_KEY) {
+        let _ = pref_config.save_selective(
+            AUDIO_CACHE_SIZE_KEY.to_string(),
+            Some(DEFAULT_AUDIO_CACHE_SIZE as f64),
+        );
+    }
+
+    let remote_server: State<RemoteServer> = app.state();
+    if let Err(e) = remote_server.restart(app.handle().clone()) {
+        tracing::error!("Failed to start remote control server: {:?}", e);
+    }
+
+    let scrobbler: State<ScrobblerService> = app.state();
+    scrobbler.restart(app.handle().clone());
+
+    let shortcuts: State<GlobalShortcuts> = app.state();
+    if let Err(e) = shortcuts.reload(app.handle()) {
+        tracing::error!("Failed to register global shortcuts: {:?}", e);
+    }
+
     // Spawn scan task
     let scan_task: State<ScanTask> = app.state();
     let scan_duration = pref_config.load_selective::<u64>("scan_interval".into());
@@ -159,9 +245,36 @@ pub fn initial(app: &mut App) {
     let handle = app.handle().clone();
     tauri::async_runtime::spawn(async move {
         let extension_handler = handle.state::<ExtensionHandler>();
+        let preferences = handle.state::<PreferenceConfig>();
+        let disabled = preferences
+            .load_selective::<Vec<String>>(DISABLED_EXTENSIONS_KEY.into())
+            .unwrap_or_default();
+        extension_handler.set_disabled_extensions(disabled).await;
+        extension_handler
+            .set_extension_limits(load_extension_limits(&preferences))
+            .await;
+        extension_handler.set_allow_unsigned(load_allow_unsigned(&preferences));
+        let granted = load_granted_permissions(&preferences);
+        let first_run = granted.is_none();
+        extension_handler.set_granted_permissions(granted).await;
         if let Err(e) = extension_handler.find_new_extensions().await {
             tracing::error!("Failed to find extensions: {:?}", e);
         }
+        // Unpacked extensions are never approved implicitly
+        extension_handler
+            .set_dev_extensions(load_dev_extensions(&preferences))
+            .await;
+
+        // Keep what installed extensions were granted implicitly
+        if first_run {
+            if let Err(e) = save_granted_permissions(&extension_handler, &preferences).await {
+                tracing::error!("Failed to save extension permissions: {:?}", e);
+            }
+        }
+        // Only the extensions found on the first run are ever trusted implicitly
+        if let Err(e) = mark_permissions_migrated(&preferences) {
+            tracing::error!("Failed to save extension permissions migration: {:?}", e);
+        }
     });
 
     let handle = app.handle().clone();
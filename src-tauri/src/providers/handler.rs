@@ -172,6 +172,16 @@ impl ProviderHandler {
         let extensions_res = ext_handler.get_installed_extensions().await?;
         for extension in extensions_res {
             if !extension.active {
+                // Drop providers of extensions which were stopped
+                let removed = {
+                    let mut provider_store = self.provider_store.write().await;
+                    provider_store
+                        .remove(&format!("extension:{}", extension.package_name))
+                        .is_some()
+                };
+                if removed {
+                    self.app_handle.emit("providers-updated", Value::Null)?;
+                }
                 continue;
             }
             let provides = ext_handler
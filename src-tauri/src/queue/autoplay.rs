@@ -0,0 +1,147 @@
+// Moosync
+// Copyright (C) 2024, 2025  Moosync <support@moosync.app>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::collections::HashSet;
+
+use database::database::Database;
+use rand::seq::SliceRandom;
+use tauri::{AppHandle, Manager, State};
+use types::{queue::QueueState, songs::Song, ui::extensions::ExtensionProviderScope};
+
+use crate::providers::handler::ProviderHandler;
+
+/// Number of recently played songs used to look for similar ones
+const SEED_SIZE: usize = 5;
+
+/// Number of songs queued every time the queue runs out
+const AUTOPLAY_BATCH: usize = 10;
+
+/// Share of the batch reserved for provider recommendations
+const PROVIDER_SHARE: usize = AUTOPLAY_BATCH / 2;
+
+/// Lowercased names of the artists of the given songs
+fn artist_names(songs: &[Song]) -> HashSet<String> {
+    songs
+        .iter()
+        .flat_map(|s| s.artists.iter().flatten())
+        .filter_map(|a| a.artist_name.as_ref().map(|n| n.to_lowercase()))
+        .collect()
+}
+
+/// Number of artists a song shares with the seeds
+fn seed_overlap(song: &Song, seed_artists: &HashSet<String>) -> usize {
+    song.artists
+        .iter()
+        .flatten()
+        .filter_map(|a| a.artist_name.as_ref())
+        .filter(|n| seed_artists.contains(&n.to_lowercase()))
+        .count()
+}
+
+/// Asks every provider with the recommendations scope for suggestions.
+/// Songs by the seed artists come first, the rest are shuffled.
+#[tracing::instrument(level = "debug", skip(app, seeds, exclude))]
+async fn get_provider_songs(
+    app: &AppHandle,
+    seeds: &[Song],
+    exclude: &mut HashSet<String>,
+) -> Vec<Song> {
+    let provider_handler: State<ProviderHandler> = app.state();
+    let statuses = provider_handler.get_all_status().await.unwrap_or_default();
+
+    let mut songs = vec![];
+    for (key, status) in statuses {
+        if !status
+            .scopes
+            .contains(&ExtensionProviderScope::Recommendations)
+        {
+            continue;
+        }
+
+        match provider_handler.get_suggestions(key.clone()).await {
+            Ok(suggestions) => {
+                for song in suggestions {
+                    if let Some(id) = song.song._id.clone() {
+                        if exclude.insert(id) {
+                            songs.push(song);
+                        }
+                    }
+                }
+            }
+            Err(e) => tracing::debug!("No suggestions from {}: {:?}", key, e),
+        }
+    }
+
+    let seed_artists = artist_names(seeds);
+    songs.shuffle(&mut rand::thread_rng());
+    songs.sort_by_key(|s| std::cmp::Reverse(seed_overlap(s, &seed_artists)));
+    songs
+}
+
+/// Finds songs similar to the recently played ones. Part of the batch comes
+/// from provider recommendations, the rest from the library. Either side
+/// fills in if the other doesn't have enough songs.
+#[tracing::instrument(level = "debug", skip(app, state))]
+pub(super) async fn get_autoplay_songs(app: &AppHandle, state: &QueueState) -> Vec<Song> {
+    let mut seeds = state
+        .history
+        .iter()
+        .rev()
+        .filter_map(|id| state.queue.data.get(id))
+        .take(SEED_SIZE)
+        .cloned()
+        .collect::<Vec<_>>();
+    if seeds.is_empty() {
+        seeds.extend(state.queue.current_song().cloned());
+    }
+
+    // Skip anything played recently or already queued
+    let mut exclude = state
+        .history
+        .iter()
+        .chain(state.queue.song_queue.iter())
+        .cloned()
+        .collect::<HashSet<_>>();
+
+    let database: State<Database> = app.state();
+    let mut library = match database.get_similar_songs(&seeds, &exclude, AUTOPLAY_BATCH * 2) {
+        Ok(songs) => songs,
+        Err(e) => {
+            tracing::error!("Failed to get similar songs: {:?}", e);
+            vec![]
+        }
+    };
+
+    // Pick from the best matches so the same songs don't come up every time
+    library.shuffle(&mut rand::thread_rng());
+    exclude.extend(library.iter().filter_map(|s| s.song._id.clone()));
+
+    let mut provided = get_provider_songs(app, &seeds, &mut exclude).await;
+
+    let provider_count = provided.len().min(
+        AUTOPLAY_BATCH
+            .saturating_sub(library.len())
+            .max(PROVIDER_SHARE),
+    );
+    library.truncate(AUTOPLAY_BATCH - provider_count);
+    provided.truncate(AUTOPLAY_BATCH - library.len());
+
+    let mut songs = library;
+    songs.extend(provided);
+
+    tracing::info!("Found {} songs to autoplay", songs.len());
+    songs
+}
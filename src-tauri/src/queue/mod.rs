@@ -0,0 +1,672 @@
+// Moosync
+// Copyright (C) 2024, 2025  Moosync <support@moosync.app>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::{
+    cmp::min,
+    collections::HashSet,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Mutex,
+    },
+};
+
+use database::database::Database;
+use macros::generate_command;
+use rand::{seq::SliceRandom, Rng};
+use tauri::{AppHandle, Emitter, Manager, State};
+use types::{
+    errors::Result,
+    queue::{Queue, QueueChanged, QueueState, SavedQueue},
+    songs::Song,
+    ui::player_details::RepeatModes,
+};
+
+use crate::{
+    mpris::{mpris_queue_details, update_mpris_queue},
+    window::locale::Translator,
+};
+
+mod autoplay;
+#[cfg(test)]
+mod test;
+
+/// Number of played songs remembered for going back while shuffling
+const HISTORY_LIMIT: usize = 200;
+
+/// Random picks tried before giving up on avoiding the current artists
+const SHUFFLE_ATTEMPTS: usize = 16;
+
+/// Id of the saved queue holding the queue replaced by the last loaded one
+const PREVIOUS_QUEUE_ID: &str = "previous_queue";
+
+/// Owns the play queue, current index and repeat mode. Every change is
+/// persisted to the database and broadcast as a "queue-changed" event.
+pub struct QueueService {
+    app: AppHandle,
+    state: Mutex<QueueState>,
+    // Set while songs are being fetched for autoplay
+    autoplay_running: AtomicBool,
+    // Move to the next song once the autoplay songs are queued
+    autoplay_advance: AtomicBool,
+}
+
+impl QueueService {
+    #[tracing::instrument(level = "debug", skip(app))]
+    pub fn new(app: AppHandle) -> Self {
+        let database: State<Database> = app.state();
+        let state = database.get_queue_state().unwrap_or_else(|e| {
+            tracing::error!("Failed to restore queue: {:?}", e);
+            QueueState::default()
+        });
+
+        Self {
+            app,
+            state: Mutex::new(state),
+            autoplay_running: AtomicBool::new(false),
+            autoplay_advance: AtomicBool::new(false),
+        }
+    }
+
+    /// Applies f to the state, then persists and broadcasts the result.
+    /// songs_changed decides whether the songs or only the details are written.
+    fn update<F>(&self, songs_changed: bool, f: F) -> Result<()>
+    where
+        F: FnOnce(&mut QueueState) -> bool,
+    {
+        let mut state = self.state.lock().unwrap();
+        let previous_queue = songs_changed.then(|| state.queue.song_queue.clone());
+        let force_load = f(&mut state);
+
+        if state.queue.current_index >= state.queue.song_queue.len() {
+            state.queue.current_index = 0;
+        }
+        Self::record_history(&mut state);
+
+        let database: State<Database> = self.app.state();
+        if let Some(previous_queue) = previous_queue {
+            let from = Self::first_changed(&previous_queue, &state.queue.song_queue);
+            database.save_queue_state(&state, from)?;
+        } else {
+            database.save_queue_details(&state)?;
+        }
+
+        self.app.emit(
+            "queue-changed",
+            QueueChanged {
+                state: state.clone(),
+                force_load,
+            },
+        )?;
+
+        // The media controls take their own lock, only hand them a copy
+        let mpris_details = mpris_queue_details(&state);
+
+        // Fetch more songs while the last one plays
+        let needs_autoplay = Self::needs_autoplay(&state);
+        drop(state);
+
+        update_mpris_queue(&self.app, mpris_details);
+        if needs_autoplay {
+            self.request_autoplay();
+        }
+        Ok(())
+    }
+
+    /// Position of the first song which differs between the two queues
+    fn first_changed(previous: &[String], current: &[String]) -> usize {
+        previous
+            .iter()
+            .zip(current)
+            .take_while(|(previous, current)| previous == current)
+            .count()
+    }
+
+    fn needs_autoplay(state: &QueueState) -> bool {
+        state.autoplay
+            && !state.queue.song_queue.is_empty()
+            && state.queue.current_index + 1 >= state.queue.song_queue.len()
+    }
+
+    /// Queues songs similar to the recently played ones in the background
+    fn request_autoplay(&self) {
+        if self.autoplay_running.swap(true, Ordering::SeqCst) {
+            return;
+        }
+
+        let app = self.app.clone();
+        tauri::async_runtime::spawn(async move {
+            let queue_service: State<QueueService> = app.state();
+            let state = queue_service.state.lock().unwrap().clone();
+            let songs = autoplay::get_autoplay_songs(&app, &state).await;
+            queue_service
+                .autoplay_running
+                .store(false, Ordering::SeqCst);
+
+            if !songs.is_empty() {
+                if let Err(e) = queue_service.add_to_queue(songs) {
+                    tracing::error!("Failed to queue autoplay songs: {:?}", e);
+                }
+            }
+
+            // Without any songs found, the queue just starts over
+            if queue_service.autoplay_advance.swap(false, Ordering::SeqCst) {
+                if let Err(e) = queue_service.advance() {
+                    tracing::error!("Failed to play next song: {:?}", e);
+                }
+            }
+        });
+    }
+
+    fn insert_songs(state: &mut QueueState, songs: Vec<Song>, index: usize) {
+        let queue = &mut state.queue;
+        let mut index = min(queue.song_queue.len(), index);
+
+        // Songs added while shuffling go after the same song in the original order
+        let original_order = &mut state.shuffle.original_order;
+        let mut original_index = if !state.shuffle.enabled {
+            None
+        } else if index == 0 {
+            Some(0)
+        } else if index == queue.song_queue.len() {
+            Some(original_order.len())
+        } else {
+            let previous = &queue.song_queue[index - 1];
+            Some(
+                original_order
+                    .iter()
+                    .position(|v| v == previous)
+                    .map(|i| i + 1)
+                    .unwrap_or(original_order.len()),
+            )
+        };
+
+        for song in songs {
+            let Some(song_id) = song.song._id.clone() else {
+                tracing::warn!("Skipping song without id {:?}", song.song.title);
+                continue;
+            };
+            queue.data.insert(song_id.clone(), song);
+            if let Some(original_index) = original_index.as_mut() {
+                original_order.insert(*original_index, song_id.clone());
+                *original_index += 1;
+            }
+            queue.song_queue.insert(index, song_id);
+            index += 1;
+        }
+    }
+
+    fn record_history(state: &mut QueueState) {
+        let Some(song_id) = state.queue.song_queue.get(state.queue.current_index) else {
+            return;
+        };
+        if state.history.last() != Some(song_id) {
+            state.history.push(song_id.clone());
+            if state.history.len() > HISTORY_LIMIT {
+                state.history.drain(..state.history.len() - HISTORY_LIMIT);
+            }
+        }
+    }
+
+    // Steps back through the songs which were actually played and returns the
+    // index of the previous one which is still queued
+    fn pop_history(state: &mut QueueState) -> Option<usize> {
+        let song_queue = &state.queue.song_queue;
+        let current_index = min(state.queue.current_index, song_queue.len());
+        while state.history.len() > 1 {
+            state.history.pop();
+            let song_id = state.history.last()?;
+
+            // Songs can be queued more than once, prefer the closest one before the current
+            let index = song_queue[..current_index]
+                .iter()
+                .rposition(|v| v == song_id)
+                .or_else(|| song_queue.iter().position(|v| v == song_id));
+            if index.is_some() {
+                return index;
+            }
+        }
+        None
+    }
+
+    fn artist_keys(song: Option<&Song>) -> Vec<String> {
+        song.and_then(|s| s.artists.as_ref())
+            .into_iter()
+            .flatten()
+            .filter_map(|a| {
+                a.artist_id
+                    .clone()
+                    .or_else(|| a.artist_name.as_ref().map(|n| n.to_lowercase()))
+            })
+            .collect()
+    }
+
+    /// Picks the next song with a lazy Fisher-Yates shuffle. Songs after the
+    /// current one are yet to be picked in this round, the picked song is
+    /// swapped in right after the current one. Once every song has played, a
+    /// new round starts from the top.
+    fn next_shuffled(state: &mut QueueState) {
+        let len = state.queue.song_queue.len();
+        if len == 0 {
+            return;
+        }
+
+        let current_index = state.queue.current_index;
+        let target = if current_index + 1 < len {
+            current_index + 1
+        } else {
+            0
+        };
+
+        let current_artists = if state.shuffle.avoid_same_artist {
+            Self::artist_keys(state.queue.current_song())
+        } else {
+            vec![]
+        };
+
+        let mut rng = rand::thread_rng();
+        let mut picked = None;
+        for _ in 0..SHUFFLE_ATTEMPTS {
+            let index = rng.gen_range(target..len);
+            // Don't play the same song again when starting a new round
+            if index == current_index && len > 1 {
+                continue;
+            }
+
+            picked = Some(index);
+            let artists = Self::artist_keys(
+                state
+                    .queue
+                    .song_queue
+                    .get(index)
+                    .and_then(|id| state.queue.data.get(id)),
+            );
+            if !artists.iter().any(|a| current_artists.contains(a)) {
+                break;
+            }
+        }
+
+        state
+            .queue
+            .song_queue
+            .swap(target, picked.unwrap_or(target));
+        state.queue.current_index = target;
+    }
+
+    fn apply_shuffle(state: &mut QueueState, enabled: bool) {
+        if state.shuffle.enabled == enabled {
+            return;
+        }
+        state.shuffle.enabled = enabled;
+
+        let current_index = state.queue.current_index;
+        if enabled {
+            state.shuffle.original_order = state.queue.song_queue.clone();
+            // Move the current song to the top so every other song can be picked next
+            if current_index < state.queue.song_queue.len() {
+                state.queue.song_queue.swap(0, current_index);
+                state.queue.current_index = 0;
+            }
+        } else {
+            let original_order = std::mem::take(&mut state.shuffle.original_order);
+            if original_order.len() != state.queue.song_queue.len() {
+                tracing::warn!("Original queue order is out of sync, keeping the shuffled order");
+                return;
+            }
+
+            let current_song = state.queue.song_queue.get(current_index).cloned();
+            state.queue.song_queue = original_order;
+            state.queue.current_index = current_song
+                .and_then(|id| state.queue.song_queue.iter().position(|v| *v == id))
+                .unwrap_or_default();
+        }
+    }
+
+    // Drops data of songs which aren't queued anymore
+    fn retain_queued_data(queue: &mut Queue) {
+        let song_queue = queue.song_queue.iter().collect::<HashSet<_>>();
+        queue.data.retain(|id, _| song_queue.contains(id));
+    }
+
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub fn get_queue_state(&self) -> Result<QueueState> {
+        Ok(self.state.lock().unwrap().clone())
+    }
+
+    #[tracing::instrument(level = "debug", skip(self, songs))]
+    pub fn add_to_queue(&self, songs: Vec<Song>) -> Result<()> {
+        self.update(true, |state| {
+            let len = state.queue.song_queue.len();
+            Self::insert_songs(state, songs, len);
+            false
+        })
+    }
+
+    /// Inserts songs after the current one and starts playing the first of them
+    #[tracing::instrument(level = "debug", skip(self, songs))]
+    pub fn play_now(&self, songs: Vec<Song>) -> Result<()> {
+        if songs.is_empty() {
+            return Ok(());
+        }
+
+        self.update(true, |state| {
+            let index = if state.queue.song_queue.is_empty() {
+                0
+            } else {
+                state.queue.current_index + 1
+            };
+            Self::insert_songs(state, songs, index);
+            state.queue.current_index = min(index, state.queue.song_queue.len());
+            true
+        })
+    }
+
+    #[tracing::instrument(level = "debug", skip(self, songs))]
+    pub fn play_next(&self, songs: Vec<Song>) -> Result<()> {
+        self.update(true, |state| {
+            let index = state.queue.current_index + 1;
+            Self::insert_songs(state, songs, index);
+            false
+        })
+    }
+
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub fn remove_from_queue(&self, index: usize) -> Result<()> {
+        self.update(true, |state| {
+            if index >= state.queue.song_queue.len() {
+                return false;
+            }
+
+            let song_id = state.queue.song_queue.remove(index);
+            let original_order = &mut state.shuffle.original_order;
+            if let Some(original_index) = original_order.iter().position(|v| *v == song_id) {
+                original_order.remove(original_index);
+            }
+            if state.queue.current_index > index {
+                state.queue.current_index -= 1;
+            }
+            Self::retain_queued_data(&mut state.queue);
+            false
+        })
+    }
+
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub fn change_queue_index(&self, index: usize, force: bool) -> Result<()> {
+        self.update(false, |state| {
+            state.queue.current_index = index;
+            force
+        })
+    }
+
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub fn next_song(&self) -> Result<()> {
+        if Self::needs_autoplay(&self.state.lock().unwrap()) {
+            self.autoplay_advance.store(true, Ordering::SeqCst);
+            self.request_autoplay();
+            return Ok(());
+        }
+        self.advance()
+    }
+
+    fn advance(&self) -> Result<()> {
+        let shuffle = self.state.lock().unwrap().shuffle.enabled;
+        self.update(shuffle, |state| {
+            if state.shuffle.enabled {
+                Self::next_shuffled(state);
+            } else {
+                state.queue.current_index += 1;
+            }
+            true
+        })
+    }
+
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub fn prev_song(&self) -> Result<()> {
+        self.update(false, |state| {
+            if state.shuffle.enabled {
+                if let Some(index) = Self::pop_history(state) {
+                    state.queue.current_index = index;
+                    return false;
+                }
+            }
+
+            if state.queue.current_index == 0 {
+                state.queue.current_index = state.queue.song_queue.len().saturating_sub(1);
+            } else {
+                state.queue.current_index -= 1;
+            }
+            false
+        })
+    }
+
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub fn set_repeat(&self, repeat: RepeatModes) -> Result<()> {
+        self.update(false, |state| {
+            state.repeat = repeat;
+            false
+        })
+    }
+
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub fn toggle_repeat(&self) -> Result<()> {
+        self.update(false, |state| {
+            state.repeat = match state.repeat {
+                RepeatModes::None => RepeatModes::Once,
+                RepeatModes::Once => RepeatModes::Loop,
+                RepeatModes::Loop => RepeatModes::None,
+            };
+            false
+        })
+    }
+
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub fn set_autoplay(&self, enabled: bool) -> Result<()> {
+        self.update(false, |state| {
+            state.autoplay = enabled;
+            false
+        })
+    }
+
+    /// Turns shuffle on or off. Turning it off restores the original order at the current song
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub fn set_shuffle(&self, enabled: bool, avoid_same_artist: bool) -> Result<()> {
+        self.update(true, |state| {
+            state.shuffle.avoid_same_artist = avoid_same_artist;
+            Self::apply_shuffle(state, enabled);
+            false
+        })
+    }
+
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub fn toggle_shuffle(&self) -> Result<()> {
+        self.update(true, |state| {
+            Self::apply_shuffle(state, !state.shuffle.enabled);
+            false
+        })
+    }
+
+    /// Shuffles the whole queue at once while keeping the current song playing.
+    /// While shuffle is on, turning it off still restores the original order
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub fn shuffle_queue(&self) -> Result<()> {
+        self.update(true, |state| {
+            Self::shuffle_all(state);
+            false
+        })
+    }
+
+    fn shuffle_all(state: &mut QueueState) {
+        let current_song = state
+            .queue
+            .song_queue
+            .get(state.queue.current_index)
+            .cloned();
+        state.queue.song_queue.shuffle(&mut rand::thread_rng());
+        if let Some(current_song) = current_song {
+            state.queue.current_index = state
+                .queue
+                .song_queue
+                .iter()
+                .position(|v| *v == current_song)
+                .unwrap_or_default();
+        }
+    }
+
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub fn clear_queue(&self) -> Result<()> {
+        self.update(true, |state| {
+            state.queue = Queue::default();
+            state.shuffle.original_order.clear();
+            state.history.clear();
+            false
+        })
+    }
+
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub fn clear_queue_except_current(&self) -> Result<()> {
+        self.update(true, |state| {
+            let current_song = state.queue.current_song().cloned();
+            let only_one_song = state.queue.song_queue.len() == 1;
+            state.queue = Queue::default();
+            state.shuffle.original_order.clear();
+
+            if !only_one_song {
+                if let Some(current_song) = current_song {
+                    Self::insert_songs(state, vec![current_song], 0);
+                }
+            }
+            false
+        })
+    }
+
+    /// Takes over a queue kept by older versions of the UI. Ignored once a queue exists
+    #[tracing::instrument(level = "debug", skip(self, queue))]
+    pub fn import_queue(&self, queue: Queue) -> Result<()> {
+        self.update(true, |state| {
+            if !state.queue.song_queue.is_empty() {
+                tracing::info!("Queue already exists, not importing the old one");
+                return false;
+            }
+
+            let songs = queue
+                .song_queue
+                .iter()
+                .filter_map(|id| queue.data.get(id).cloned())
+                .collect();
+            Self::insert_songs(state, songs, 0);
+            state.queue.current_index = queue.current_index;
+            false
+        })
+    }
+
+    /// Snapshots the current queue. Passing the id of a saved queue overwrites it
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub fn save_current_queue(
+        &self,
+        id: Option<String>,
+        name: String,
+        position: f64,
+    ) -> Result<String> {
+        let saved = {
+            let state = self.state.lock().unwrap();
+            let songs = state
+                .queue
+                .song_queue
+                .iter()
+                .filter_map(|id| state.queue.data.get(id).cloned())
+                .collect();
+            SavedQueue {
+                id: id.unwrap_or_default(),
+                name,
+                songs,
+                current_index: state.queue.current_index,
+                position,
+                repeat: state.repeat,
+                ..Default::default()
+            }
+        };
+
+        let database: State<Database> = self.app.state();
+        database.upsert_saved_queue(saved)
+    }
+
+    /// Position of the saved current song in the loaded queue. Songs without an id
+    /// aren't queued, so they can't be counted.
+    fn resume_index(saved_songs: &[Song], saved_index: usize, queue_len: usize) -> usize {
+        saved_songs
+            .iter()
+            .take(saved_index)
+            .filter(|s| s.song._id.is_some())
+            .count()
+            .min(queue_len.saturating_sub(1))
+    }
+
+    /// Replaces the current queue with a saved one. The saved queue is kept, and the
+    /// returned snapshot tells where to resume the current song. The replaced queue
+    /// is saved as the previous queue, so loading that one swaps them back.
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub fn load_saved_queue(&self, id: String, position: f64) -> Result<SavedQueue> {
+        let database: State<Database> = self.app.state();
+        let Some(saved) = database.get_saved_queue(id.clone())? else {
+            return Err(format!("Saved queue {} not found", id).into());
+        };
+
+        if !self.state.lock().unwrap().queue.song_queue.is_empty() {
+            let name = Translator::new(&self.app).t("queue.previousQueue");
+            self.save_current_queue(Some(PREVIOUS_QUEUE_ID.into()), name, position)?;
+        }
+
+        self.update(true, |state| {
+            state.queue = Queue::default();
+            state.shuffle.enabled = false;
+            state.shuffle.original_order.clear();
+            state.history.clear();
+            state.repeat = saved.repeat;
+            Self::insert_songs(state, saved.songs.clone(), 0);
+            state.queue.current_index = Self::resume_index(
+                &saved.songs,
+                saved.current_index,
+                state.queue.song_queue.len(),
+            );
+            true
+        })?;
+        Ok(saved)
+    }
+}
+
+#[tracing::instrument(level = "debug", skip(app))]
+pub fn get_queue_service_state(app: AppHandle) -> QueueService {
+    QueueService::new(app)
+}
+
+generate_command!(get_queue_state, QueueService, QueueState,);
+generate_command!(add_to_queue, QueueService, (), songs: Vec<Song>);
+generate_command!(play_now, QueueService, (), songs: Vec<Song>);
+generate_command!(play_next, QueueService, (), songs: Vec<Song>);
+generate_command!(remove_from_queue, QueueService, (), index: usize);
+generate_command!(change_queue_index, QueueService, (), index: usize, force: bool);
+generate_command!(next_song, QueueService, (),);
+generate_command!(prev_song, QueueService, (),);
+generate_command!(set_repeat, QueueService, (), repeat: RepeatModes);
+generate_command!(toggle_repeat, QueueService, (),);
+generate_command!(set_shuffle, QueueService, (), enabled: bool, avoid_same_artist: bool);
+generate_command!(toggle_shuffle, QueueService, (),);
+generate_command!(shuffle_queue, QueueService, (),);
+generate_command!(set_autoplay, QueueService, (), enabled: bool);
+generate_command!(clear_queue, QueueService, (),);
+generate_command!(clear_queue_except_current, QueueService, (),);
+generate_command!(import_queue, QueueService, (), queue: Queue);
+generate_command!(save_current_queue, QueueService, String, id: Option<String>, name: String, position: f64);
+generate_command!(load_saved_queue, QueueService, SavedQueue, id: String, position: f64);
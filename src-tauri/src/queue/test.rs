@@ -0,0 +1,316 @@
+// Moosync
+// Copyright (C) 2024, 2025  Moosync <support@moosync.app>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::collections::HashSet;
+
+use types::{
+    entities::QueryableArtist,
+    queue::{Queue, QueueState},
+    songs::{QueryableSong, Song},
+};
+
+use super::{QueueService, HISTORY_LIMIT};
+
+fn song(id: &str) -> Song {
+    Song {
+        song: QueryableSong {
+            _id: Some(id.to_string()),
+            title: Some(id.to_string()),
+            ..Default::default()
+        },
+        ..Default::default()
+    }
+}
+
+fn song_by(id: &str, artist: &str) -> Song {
+    Song {
+        artists: Some(vec![QueryableArtist {
+            artist_name: Some(artist.to_string()),
+            ..Default::default()
+        }]),
+        ..song(id)
+    }
+}
+
+fn ids(ids: &[&str]) -> Vec<String> {
+    ids.iter().map(|id| id.to_string()).collect()
+}
+
+fn queue_state(song_ids: &[&str]) -> QueueState {
+    let mut state = QueueState::default();
+    QueueService::insert_songs(&mut state, song_ids.iter().map(|id| song(id)).collect(), 0);
+    state
+}
+
+// Test finding the first position which needs to be written again
+#[test]
+fn test_first_changed() {
+    let queue = ids(&["a", "b", "c"]);
+    assert_eq!(QueueService::first_changed(&queue, &queue), 3);
+    assert_eq!(
+        QueueService::first_changed(&queue, &ids(&["a", "b", "c", "d"])),
+        3
+    );
+    assert_eq!(
+        QueueService::first_changed(&queue, &ids(&["a", "d", "b", "c"])),
+        1
+    );
+    assert_eq!(QueueService::first_changed(&queue, &ids(&["a", "c"])), 1);
+    assert_eq!(QueueService::first_changed(&queue, &ids(&["a"])), 1);
+    assert_eq!(QueueService::first_changed(&queue, &[]), 0);
+    assert_eq!(QueueService::first_changed(&[], &queue), 0);
+}
+
+// Test inserting songs at the end, in the middle and without ids
+#[test]
+fn test_insert_songs() {
+    let mut state = queue_state(&["a", "b"]);
+    assert_eq!(state.queue.song_queue, ids(&["a", "b"]));
+    assert_eq!(state.queue.data.len(), 2);
+
+    QueueService::insert_songs(&mut state, vec![song("c"), song("d")], 1);
+    assert_eq!(state.queue.song_queue, ids(&["a", "c", "d", "b"]));
+
+    // Indices past the end append
+    QueueService::insert_songs(&mut state, vec![song("e")], 100);
+    assert_eq!(state.queue.song_queue, ids(&["a", "c", "d", "b", "e"]));
+
+    // Songs without ids can't be queued
+    QueueService::insert_songs(&mut state, vec![Song::default()], 0);
+    assert_eq!(state.queue.song_queue.len(), 5);
+    assert_eq!(state.queue.data.len(), 5);
+
+    // Queueing a song twice keeps a single copy of its data
+    QueueService::insert_songs(&mut state, vec![song("a")], 5);
+    assert_eq!(state.queue.song_queue, ids(&["a", "c", "d", "b", "e", "a"]));
+    assert_eq!(state.queue.data.len(), 5);
+}
+
+// Test that songs added while shuffling keep their place in the original order
+#[test]
+fn test_insert_songs_while_shuffled() {
+    let mut state = queue_state(&["a", "b", "c"]);
+    state.shuffle.enabled = true;
+    state.shuffle.original_order = ids(&["a", "b", "c"]);
+    state.queue.song_queue = ids(&["c", "a", "b"]);
+
+    // After "c" in the queue, so after "c" in the original order as well
+    QueueService::insert_songs(&mut state, vec![song("d")], 1);
+    assert_eq!(state.queue.song_queue, ids(&["c", "d", "a", "b"]));
+    assert_eq!(state.shuffle.original_order, ids(&["a", "b", "c", "d"]));
+
+    QueueService::insert_songs(&mut state, vec![song("e")], 0);
+    assert_eq!(
+        state.shuffle.original_order,
+        ids(&["e", "a", "b", "c", "d"])
+    );
+
+    QueueService::insert_songs(&mut state, vec![song("f")], 5);
+    assert_eq!(
+        state.shuffle.original_order,
+        ids(&["e", "a", "b", "c", "d", "f"])
+    );
+}
+
+// Test dropping the data of songs which were removed from the queue
+#[test]
+fn test_retain_queued_data() {
+    let mut queue = Queue {
+        song_queue: ids(&["a", "c"]),
+        current_index: 0,
+        data: ["a", "b", "c"]
+            .into_iter()
+            .map(|id| (id.to_string(), song(id)))
+            .collect(),
+    };
+    QueueService::retain_queued_data(&mut queue);
+    let mut remaining = queue.data.keys().cloned().collect::<Vec<_>>();
+    remaining.sort();
+    assert_eq!(remaining, ids(&["a", "c"]));
+}
+
+// Test recording played songs and stepping back through them
+#[test]
+fn test_history() {
+    let mut state = queue_state(&["a", "b", "c"]);
+    for index in [0, 0, 2, 1] {
+        state.queue.current_index = index;
+        QueueService::record_history(&mut state);
+    }
+    // Repeated songs are only recorded once
+    assert_eq!(state.history, ids(&["a", "c", "b"]));
+
+    assert_eq!(QueueService::pop_history(&mut state), Some(2));
+    assert_eq!(QueueService::pop_history(&mut state), Some(0));
+    assert_eq!(QueueService::pop_history(&mut state), None);
+
+    // Songs which aren't queued anymore are skipped
+    state.history = ids(&["a", "x", "b"]);
+    state.queue.current_index = 1;
+    assert_eq!(QueueService::pop_history(&mut state), Some(0));
+
+    state.history.clear();
+    for i in 0..HISTORY_LIMIT + 10 {
+        let id = i.to_string();
+        QueueService::insert_songs(&mut state, vec![song(&id)], 0);
+        state.queue.current_index = 0;
+        QueueService::record_history(&mut state);
+    }
+    assert_eq!(state.history.len(), HISTORY_LIMIT);
+    assert_eq!(state.history.last(), Some(&(HISTORY_LIMIT + 9).to_string()));
+}
+
+// Test when autoplay should fetch more songs
+#[test]
+fn test_needs_autoplay() {
+    let mut state = queue_state(&["a", "b"]);
+    assert!(!QueueService::needs_autoplay(&state));
+
+    state.autoplay = true;
+    assert!(!QueueService::needs_autoplay(&state));
+
+    state.queue.current_index = 1;
+    assert!(QueueService::needs_autoplay(&state));
+
+    assert!(!QueueService::needs_autoplay(&QueueState {
+        autoplay: true,
+        ..Default::default()
+    }));
+}
+
+// Test that a shuffle round plays every song once before starting over
+#[test]
+fn test_next_shuffled_round() {
+    let song_ids = ["a", "b", "c", "d", "e", "f"];
+    let mut state = queue_state(&song_ids);
+    QueueService::apply_shuffle(&mut state, true);
+
+    for _ in 0..3 {
+        let mut played = HashSet::new();
+        played.insert(state.queue.current_song().unwrap().song._id.clone());
+        for _ in 1..song_ids.len() {
+            let previous = state.queue.current_index;
+            QueueService::next_shuffled(&mut state);
+            assert_eq!(state.queue.current_index, previous + 1);
+            played.insert(state.queue.current_song().unwrap().song._id.clone());
+        }
+        assert_eq!(played.len(), song_ids.len());
+
+        // The next round starts from the top without repeating the last song
+        let last = state.queue.current_song().cloned();
+        QueueService::next_shuffled(&mut state);
+        assert_eq!(state.queue.current_index, 0);
+        assert_ne!(state.queue.current_song().cloned(), last);
+    }
+
+    let mut queued = state.queue.song_queue.clone();
+    queued.sort();
+    assert_eq!(queued, ids(&song_ids));
+
+    let mut single = queue_state(&["a"]);
+    QueueService::next_shuffled(&mut single);
+    assert_eq!(single.queue.current_index, 0);
+    QueueService::next_shuffled(&mut QueueState::default());
+}
+
+// Test that shuffling prefers songs by other artists than the current one
+#[test]
+fn test_next_shuffled_avoids_same_artist() {
+    // Picks are random, a single candidate by another artist is found within a few attempts
+    for _ in 0..5 {
+        let mut state = QueueState::default();
+        QueueService::insert_songs(
+            &mut state,
+            vec![song_by("a", "x"), song_by("b", "x"), song_by("c", "y")],
+            0,
+        );
+        state.shuffle.enabled = true;
+        state.shuffle.avoid_same_artist = true;
+
+        QueueService::next_shuffled(&mut state);
+        assert_eq!(state.queue.current_index, 1);
+        assert_eq!(
+            state.queue.current_song().unwrap().song._id,
+            Some("c".into())
+        );
+    }
+}
+
+// Test turning shuffle on and off restores the original order at the current song
+#[test]
+fn test_apply_shuffle() {
+    let mut state = queue_state(&["a", "b", "c", "d"]);
+    state.queue.current_index = 2;
+
+    QueueService::apply_shuffle(&mut state, true);
+    assert!(state.shuffle.enabled);
+    assert_eq!(state.shuffle.original_order, ids(&["a", "b", "c", "d"]));
+    // The current song moves to the top
+    assert_eq!(state.queue.current_index, 0);
+    assert_eq!(state.queue.song_queue[0], "c");
+
+    QueueService::next_shuffled(&mut state);
+    let current = state.queue.song_queue[state.queue.current_index].clone();
+
+    QueueService::apply_shuffle(&mut state, false);
+    assert!(!state.shuffle.enabled);
+    assert!(state.shuffle.original_order.is_empty());
+    assert_eq!(state.queue.song_queue, ids(&["a", "b", "c", "d"]));
+    assert_eq!(state.queue.song_queue[state.queue.current_index], current);
+
+    // An out of sync original order keeps the shuffled queue
+    QueueService::apply_shuffle(&mut state, true);
+    state.shuffle.original_order.pop();
+    let shuffled = state.queue.song_queue.clone();
+    QueueService::apply_shuffle(&mut state, false);
+    assert_eq!(state.queue.song_queue, shuffled);
+}
+
+// Test shuffling the whole queue keeps the current song playing
+#[test]
+fn test_shuffle_all() {
+    let mut state = queue_state(&["a", "b", "c", "d", "e"]);
+    state.queue.current_index = 3;
+    QueueService::shuffle_all(&mut state);
+
+    assert_eq!(state.queue.song_queue[state.queue.current_index], "d");
+    let mut queued = state.queue.song_queue.clone();
+    queued.sort();
+    assert_eq!(queued, ids(&["a", "b", "c", "d", "e"]));
+}
+
+// Test resuming the saved current song when some songs couldn't be queued
+#[test]
+fn test_resume_index() {
+    let without_id = Song::default();
+    let saved = vec![
+        song("a"),
+        without_id.clone(),
+        song("b"),
+        without_id,
+        song("c"),
+    ];
+    let state = queue_state(&["a", "b", "c"]);
+    let queue_len = state.queue.song_queue.len();
+
+    assert_eq!(QueueService::resume_index(&saved, 0, queue_len), 0);
+    assert_eq!(QueueService::resume_index(&saved, 2, queue_len), 1);
+    assert_eq!(QueueService::resume_index(&saved, 4, queue_len), 2);
+
+    // Out of range indexes are clamped to the loaded queue
+    assert_eq!(QueueService::resume_index(&saved, 9, queue_len), 2);
+    assert_eq!(QueueService::resume_index(&[], 4, 0), 0);
+}
@@ -0,0 +1,284 @@
+// Moosync
+// Copyright (C) 2024, 2025  Moosync <support@moosync.app>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::{
+    net::{Ipv4Addr, SocketAddr},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    thread,
+    time::Duration,
+};
+
+use preferences::preferences::PreferenceConfig;
+use rand::{rngs::OsRng, RngCore};
+use serde::Serialize;
+use serde_json::Value;
+use tauri::{async_runtime::JoinHandle, AppHandle, Listener, Manager, State};
+use tokio::sync::{broadcast, watch};
+use types::{
+    errors::Result, preferences::CheckboxPreference, queue::QueueChanged, songs::Song,
+    ui::player_details::PlayerEvents,
+};
+
+mod routes;
+
+#[cfg(test)]
+mod test;
+
+/// Port used when none is set in the preferences
+pub const DEFAULT_PORT: u16 = 8789;
+
+/// Secure preference holding the pairing token
+pub const TOKEN_KEY: &str = "remote_token";
+
+/// Random bytes in generated pairing tokens
+const TOKEN_BYTES: usize = 32;
+
+/// Shorter pairing tokens are refused, 128 bits in hex
+pub const MIN_TOKEN_LEN: usize = 32;
+
+/// Number of events buffered for each websocket client
+const EVENT_BUFFER: usize = 64;
+
+/// Preference changes within this delay only restart the server once
+const RESTART_DELAY: Duration = Duration::from_millis(750);
+
+/// Events pushed to websocket clients
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "event", content = "data", rename_all = "camelCase")]
+pub enum RemoteEvent {
+    Player(PlayerEvents),
+    SongChanged(Option<Song>),
+    QueueChanged(Value),
+}
+
+struct RunningServer {
+    shutdown_tx: watch::Sender<bool>,
+    task: JoinHandle<()>,
+}
+
+/// Opt-in HTTP and websocket server which lets other devices control the player.
+/// Every request needs the pairing token kept in secure storage.
+pub struct RemoteServer {
+    events_tx: broadcast::Sender<RemoteEvent>,
+    running: Mutex<Option<RunningServer>>,
+    restart_generation: Arc<AtomicU64>,
+    current_song: Arc<Mutex<Option<String>>>,
+}
+
+impl RemoteServer {
+    #[tracing::instrument(level = "debug", skip(app))]
+    pub fn new(app: AppHandle) -> Self {
+        let (events_tx, _) = broadcast::channel(EVENT_BUFFER);
+        let server = Self {
+            events_tx: events_tx.clone(),
+            running: Default::default(),
+            restart_generation: Default::default(),
+            current_song: Default::default(),
+        };
+
+        let current_song = server.current_song.clone();
+        app.listen_any("queue-changed", move |event| {
+            if events_tx.receiver_count() == 0 {
+                return;
+            }
+
+            let state = match serde_json::from_str::<QueueChanged>(event.payload()) {
+                Ok(changed) => changed.state,
+                Err(e) => {
+                    tracing::error!("Failed to parse queue state: {:?}", e);
+                    return;
+                }
+            };
+
+            let song = state.queue.current_song().cloned();
+            let song_id = song.as_ref().and_then(|s| s.song._id.clone());
+            let mut current_song = current_song.lock().unwrap();
+            if *current_song != song_id {
+                *current_song = song_id;
+                let _ = events_tx.send(RemoteEvent::SongChanged(song));
+            }
+
+            match serde_json::to_value(state.queue) {
+                Ok(queue) => {
+                    let _ = events_tx.send(RemoteEvent::QueueChanged(queue));
+                }
+                Err(e) => tracing::error!("Failed to serialize queue: {:?}", e),
+            }
+        });
+
+        server
+    }
+
+    #[tracing::instrument(level = "trace", skip(self))]
+    pub fn send_event(&self, event: RemoteEvent) {
+        // Sending only fails when no client is connected
+        let _ = self.events_tx.send(event);
+    }
+
+    /// Restarts the server once the preferences stop changing
+    #[tracing::instrument(level = "debug", skip(self, app))]
+    pub fn schedule_restart(&self, app: AppHandle) {
+        let generation = self.restart_generation.fetch_add(1, Ordering::SeqCst) + 1;
+        let restart_generation = self.restart_generation.clone();
+        thread::spawn(move || {
+            thread::sleep(RESTART_DELAY);
+            if restart_generation.load(Ordering::SeqCst) != generation {
+                return;
+            }
+
+            let remote_server: State<RemoteServer> = app.state();
+            if let Err(e) = remote_server.restart(app.clone()) {
+                tracing::error!("Failed to restart remote control server: {:?}", e);
+            }
+        });
+    }
+
+    /// Stops the running server and starts it again if it is enabled in the preferences
+    #[tracing::instrument(level = "debug", skip(self, app))]
+    pub fn restart(&self, app: AppHandle) -> Result<()> {
+        let previous_task = self.shutdown();
+
+        let preferences: State<PreferenceConfig> = app.state();
+        let enabled = preferences
+            .load_selective_array::<CheckboxPreference>("remote_settings.enabled".into())
+            .map(|p| p.enabled)
+            .unwrap_or_default();
+        if !enabled {
+            return Ok(());
+        }
+
+        let token = load_token(&preferences)?;
+        if token.len() < MIN_TOKEN_LEN {
+            return Err(format!(
+                "Remote control pairing token must be at least {} characters",
+                MIN_TOKEN_LEN
+            )
+            .into());
+        }
+
+        let allow_lan = preferences
+            .load_selective_array::<CheckboxPreference>("remote_settings.allow_lan".into())
+            .map(|p| p.enabled)
+            .unwrap_or_default();
+        // Number inputs are saved as floats
+        let port = preferences
+            .load_selective::<f64>("remote_port".into())
+            .ok()
+            .filter(|port| (1f64..=u16::MAX as f64).contains(port))
+            .map(|port| port as u16)
+            .unwrap_or(DEFAULT_PORT);
+        let ip = if allow_lan {
+            Ipv4Addr::UNSPECIFIED
+        } else {
+            Ipv4Addr::LOCALHOST
+        };
+        let addr = SocketAddr::from((ip, port));
+
+        let (shutdown_tx, mut shutdown_rx) = watch::channel(false);
+        let router = routes::router(app, token, self.events_tx.clone(), shutdown_rx.clone());
+        let task = tauri::async_runtime::spawn(async move {
+            // The old listener has to be dropped before the port can be bound again
+            if let Some(previous_task) = previous_task {
+                let _ = previous_task.await;
+            }
+
+            let listener = match tokio::net::TcpListener::bind(addr).await {
+                Ok(listener) => listener,
+                Err(e) => {
+                    tracing::error!("Failed to bind remote control server to {}: {:?}", addr, e);
+                    return;
+                }
+            };
+
+            tracing::info!("Remote control server listening on {}", addr);
+            let res = axum::serve(listener, router)
+                .with_graceful_shutdown(async move {
+                    let _ = shutdown_rx.wait_for(|stopped| *stopped).await;
+                })
+                .await;
+            if let Err(e) = res {
+                tracing::error!("Remote control server stopped: {:?}", e);
+            }
+        });
+        *self.running.lock().unwrap() = Some(RunningServer { shutdown_tx, task });
+
+        Ok(())
+    }
+
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub fn stop(&self) {
+        self.shutdown();
+    }
+
+    /// Closes open connections and aborts the server task, which is returned
+    /// so that it can be awaited before binding the port again
+    fn shutdown(&self) -> Option<JoinHandle<()>> {
+        let running = self.running.lock().unwrap().take()?;
+        tracing::info!("Stopping remote control server");
+        let _ = running.shutdown_tx.send(true);
+        running.task.abort();
+        Some(running.task)
+    }
+}
+
+/// Random pairing token, hex encoded
+pub fn generate_token() -> String {
+    let mut bytes = [0u8; TOKEN_BYTES];
+    OsRng.fill_bytes(&mut bytes);
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Loads the pairing token from secure storage, creating one the first time it is needed.
+/// Tokens which were saved in plain text are replaced.
+#[tracing::instrument(level = "debug", skip(preferences))]
+pub fn load_token(preferences: &PreferenceConfig) -> Result<String> {
+    match preferences.get_secure::<String>(TOKEN_KEY.into()) {
+        Ok(token) if !token.trim().is_empty() => Ok(token.trim().to_string()),
+        res => {
+            if let Err(e) = res {
+                tracing::info!("Creating remote control pairing token: {:?}", e);
+            }
+            let token = generate_token();
+            preferences.set_secure(TOKEN_KEY.into(), Some(token.clone()))?;
+            Ok(token)
+        }
+    }
+}
+
+#[tracing::instrument(level = "debug", skip(preferences))]
+#[tauri_invoke_proc::parse_tauri_command]
+#[tauri::command(async)]
+pub fn get_remote_token(preferences: State<PreferenceConfig>) -> Result<String> {
+    load_token(&preferences)
+}
+
+/// Replaces the pairing token, which unpairs every client
+#[tracing::instrument(level = "debug", skip(preferences))]
+#[tauri_invoke_proc::parse_tauri_command]
+#[tauri::command(async)]
+pub fn regenerate_remote_token(preferences: State<PreferenceConfig>) -> Result<String> {
+    let token = generate_token();
+    preferences.set_secure(TOKEN_KEY.into(), Some(token.clone()))?;
+    Ok(token)
+}
+
+#[tracing::instrument(level = "debug", skip(app))]
+pub fn get_remote_server_state(app: AppHandle) -> RemoteServer {
+    RemoteServer::new(app)
+}
@@ -0,0 +1,294 @@
+// Moosync
+// Copyright (C) 2024, 2025  Moosync <support@moosync.app>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::{sync::Arc, time::Duration};
+
+use axum::{
+    extract::{
+        ws::{Message, WebSocket, WebSocketUpgrade},
+        Query, Request, State,
+    },
+    http::{header, HeaderMap, StatusCode},
+    middleware::{self, Next},
+    response::{IntoResponse, Response},
+    routing::{get, post},
+    Json, Router,
+};
+use database::database::Database;
+use mpris::{MediaControlEvent, MediaPosition};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use tauri::{AppHandle, Manager};
+use tokio::sync::{
+    broadcast::{self, error::RecvError},
+    watch,
+};
+use types::{
+    entities::SearchResult,
+    errors::MoosyncError,
+    extensions::{MainCommand, MainCommandResponse},
+    ui::player_details::RepeatModes,
+};
+
+use crate::{extensions::request_handler::ReplyHandler, mpris::handle_media_event};
+
+use super::RemoteEvent;
+
+#[cfg(test)]
+mod test;
+
+#[derive(Clone)]
+struct RemoteContext {
+    app: AppHandle,
+    token: Arc<str>,
+    events_tx: broadcast::Sender<RemoteEvent>,
+    shutdown_rx: watch::Receiver<bool>,
+}
+
+/// Playback commands accepted by `POST /api/player`
+#[derive(Debug, Deserialize)]
+#[serde(tag = "command", content = "value", rename_all = "camelCase")]
+enum TransportCommand {
+    Play,
+    Pause,
+    Toggle,
+    Stop,
+    Next,
+    Previous,
+    /// Position in seconds
+    Seek(f64),
+    /// Volume from 0 to 100, same as the value returned by GetVolume
+    Volume(f64),
+    Repeat(RepeatModes),
+    Shuffle(bool),
+    PlayIndex(usize),
+}
+
+impl From<TransportCommand> for MediaControlEvent {
+    fn from(value: TransportCommand) -> Self {
+        match value {
+            TransportCommand::Play => Self::Play,
+            TransportCommand::Pause => Self::Pause,
+            TransportCommand::Toggle => Self::Toggle,
+            TransportCommand::Stop => Self::Stop,
+            TransportCommand::Next => Self::Next,
+            TransportCommand::Previous => Self::Previous,
+            TransportCommand::Seek(pos) => {
+                Self::SetPosition(MediaPosition(Duration::from_secs_f64(pos.max(0f64))))
+            }
+            TransportCommand::Volume(volume) => {
+                Self::SetVolume(volume.clamp(0f64, 100f64) / 100f64)
+            }
+            TransportCommand::Repeat(repeat) => Self::SetLoopStatus(repeat),
+            TransportCommand::Shuffle(enabled) => Self::SetShuffle(enabled),
+            TransportCommand::PlayIndex(index) => Self::GoTo(index),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct SearchQuery {
+    term: String,
+}
+
+struct ApiError(StatusCode, MoosyncError);
+
+impl<E> From<E> for ApiError
+where
+    E: Into<MoosyncError>,
+{
+    fn from(value: E) -> Self {
+        Self(StatusCode::INTERNAL_SERVER_ERROR, value.into())
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        tracing::warn!("Remote request failed: {:?}", self.1);
+        (self.0, Json(json!({ "error": self.1.to_string() }))).into_response()
+    }
+}
+
+type ApiResult<T> = std::result::Result<Json<T>, ApiError>;
+
+pub(super) fn router(
+    app: AppHandle,
+    token: String,
+    events_tx: broadcast::Sender<RemoteEvent>,
+    shutdown_rx: watch::Receiver<bool>,
+) -> Router {
+    let context = RemoteContext {
+        app,
+        token: token.into(),
+        events_tx,
+        shutdown_rx,
+    };
+
+    Router::new()
+        .route("/api/player", get(get_player).post(player_command))
+        .route("/api/queue", get(get_queue))
+        .route("/api/search", get(search))
+        .route("/api/request", post(main_command))
+        .route("/api/events", get(events))
+        .layer(middleware::from_fn_with_state(context.clone(), authorize))
+        .with_state(context)
+}
+
+// Compares the whole token so that the time taken doesn't leak how much of it matched
+fn tokens_match(expected: &str, received: &str) -> bool {
+    expected.len() == received.len()
+        && expected
+            .bytes()
+            .zip(received.bytes())
+            .fold(0u8, |acc, (a, b)| acc | (a ^ b))
+            == 0
+}
+
+// The token is only accepted in the Authorization header, query strings end up in logs and history
+fn bearer_token(headers: &HeaderMap) -> Option<&str> {
+    headers
+        .get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+        .map(|v| v.trim())
+}
+
+async fn authorize(State(context): State<RemoteContext>, req: Request, next: Next) -> Response {
+    // Kept alive connections may still reach a server which was stopped
+    if *context.shutdown_rx.borrow() {
+        return (
+            StatusCode::SERVICE_UNAVAILABLE,
+            [(header::CONNECTION, "close")],
+        )
+            .into_response();
+    }
+
+    match bearer_token(req.headers()) {
+        Some(token) if tokens_match(&context.token, token) => next.run(req).await,
+        _ => StatusCode::UNAUTHORIZED.into_response(),
+    }
+}
+
+async fn get_player(State(context): State<RemoteContext>) -> ApiResult<Value> {
+    let handler = ReplyHandler::new(context.app);
+    let state = handler
+        .handle_request(MainCommand::GetPlayerState())
+        .await?;
+    let song = handler
+        .handle_request(MainCommand::GetCurrentSong())
+        .await?;
+    let volume = handler.handle_request(MainCommand::GetVolume()).await?;
+    let time = handler.handle_request(MainCommand::GetTime()).await?;
+
+    Ok(Json(json!({
+        "state": state,
+        "song": song,
+        "volume": volume,
+        "time": time,
+    })))
+}
+
+async fn player_command(
+    State(context): State<RemoteContext>,
+    Json(command): Json<TransportCommand>,
+) -> StatusCode {
+    tracing::debug!("Got remote command {:?}", command);
+    handle_media_event(&context.app, command.into());
+    StatusCode::NO_CONTENT
+}
+
+async fn get_queue(State(context): State<RemoteContext>) -> ApiResult<MainCommandResponse> {
+    let handler = ReplyHandler::new(context.app);
+    Ok(Json(handler.handle_request(MainCommand::GetQueue()).await?))
+}
+
+async fn search(
+    State(context): State<RemoteContext>,
+    Query(query): Query<SearchQuery>,
+) -> ApiResult<SearchResult> {
+    let database = context.app.state::<Database>();
+    Ok(Json(database.search_all(query.term)?))
+}
+
+/// Runs the read only requests available to extensions
+async fn main_command(
+    State(context): State<RemoteContext>,
+    Json(command): Json<MainCommand>,
+) -> ApiResult<MainCommandResponse> {
+    match command {
+        MainCommand::GetSong(_)
+        | MainCommand::GetEntity(_)
+        | MainCommand::GetCurrentSong()
+        | MainCommand::GetPlayerState()
+        | MainCommand::GetVolume()
+        | MainCommand::GetTime()
+        | MainCommand::GetQueue() => {}
+        _ => {
+            return Err(ApiError(
+                StatusCode::FORBIDDEN,
+                "Only read requests are allowed remotely".into(),
+            ))
+        }
+    }
+
+    let handler = ReplyHandler::new(context.app);
+    Ok(Json(handler.handle_request(command).await?))
+}
+
+async fn events(State(context): State<RemoteContext>, ws: WebSocketUpgrade) -> Response {
+    let events_rx = context.events_tx.subscribe();
+    ws.on_upgrade(move |socket| forward_events(socket, events_rx, context.shutdown_rx))
+}
+
+#[tracing::instrument(level = "debug", skip(socket, events_rx, shutdown_rx))]
+async fn forward_events(
+    mut socket: WebSocket,
+    mut events_rx: broadcast::Receiver<RemoteEvent>,
+    mut shutdown_rx: watch::Receiver<bool>,
+) {
+    tracing::info!("Remote client connected");
+    loop {
+        tokio::select! {
+            // Clients have to reconnect once the server restarts
+            _ = shutdown_rx.changed() => break,
+            event = events_rx.recv() => {
+                let event = match event {
+                    Ok(event) => event,
+                    Err(RecvError::Lagged(skipped)) => {
+                        tracing::warn!("Remote client skipped {} events", skipped);
+                        continue;
+                    }
+                    Err(RecvError::Closed) => break,
+                };
+
+                let Ok(data) = serde_json::to_string(&event) else {
+                    continue;
+                };
+                if socket.send(Message::Text(data)).await.is_err() {
+                    break;
+                }
+            }
+            // Reading also answers pings
+            msg = socket.recv() => {
+                match msg {
+                    Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                    _ => {}
+                }
+            }
+        }
+    }
+    tracing::info!("Remote client disconnected");
+}
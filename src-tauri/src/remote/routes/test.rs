@@ -0,0 +1,128 @@
+// Moosync
+// Copyright (C) 2024, 2025  Moosync <support@moosync.app>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::time::Duration;
+
+use axum::http::{header, HeaderMap};
+use mpris::{MediaControlEvent, MediaPosition};
+use serde_json::json;
+use types::ui::player_details::RepeatModes;
+
+use super::{bearer_token, tokens_match, TransportCommand};
+
+fn parse(value: serde_json::Value) -> MediaControlEvent {
+    serde_json::from_value::<TransportCommand>(value)
+        .unwrap()
+        .into()
+}
+
+// Test accepting only the exact token
+#[test]
+fn test_tokens_match() {
+    assert!(tokens_match("secret", "secret"));
+    assert!(!tokens_match("secret", "secreT"));
+    assert!(!tokens_match("secret", "secre"));
+    assert!(!tokens_match("secret", "secrets"));
+    assert!(!tokens_match("secret", ""));
+    assert!(tokens_match("", ""));
+}
+
+// Test reading the token only from the Authorization header
+#[test]
+fn test_bearer_token() {
+    let mut headers = HeaderMap::new();
+    assert_eq!(bearer_token(&headers), None);
+
+    headers.insert(header::AUTHORIZATION, "Basic secret".parse().unwrap());
+    assert_eq!(bearer_token(&headers), None);
+
+    headers.insert(header::AUTHORIZATION, "Bearer secret ".parse().unwrap());
+    assert_eq!(bearer_token(&headers), Some("secret"));
+}
+
+// Test parsing commands without a value
+#[test]
+fn test_parse_commands() {
+    assert_eq!(parse(json!({ "command": "play" })), MediaControlEvent::Play);
+    assert_eq!(
+        parse(json!({ "command": "pause" })),
+        MediaControlEvent::Pause
+    );
+    assert_eq!(
+        parse(json!({ "command": "toggle" })),
+        MediaControlEvent::Toggle
+    );
+    assert_eq!(parse(json!({ "command": "stop" })), MediaControlEvent::Stop);
+    assert_eq!(parse(json!({ "command": "next" })), MediaControlEvent::Next);
+    assert_eq!(
+        parse(json!({ "command": "previous" })),
+        MediaControlEvent::Previous
+    );
+}
+
+// Test parsing commands with a value
+#[test]
+fn test_parse_commands_with_value() {
+    assert_eq!(
+        parse(json!({ "command": "seek", "value": 12.5 })),
+        MediaControlEvent::SetPosition(MediaPosition(Duration::from_secs_f64(12.5)))
+    );
+    assert_eq!(
+        parse(json!({ "command": "volume", "value": 40 })),
+        MediaControlEvent::SetVolume(0.4)
+    );
+    assert_eq!(
+        parse(json!({ "command": "repeat", "value": "Loop" })),
+        MediaControlEvent::SetLoopStatus(RepeatModes::Loop)
+    );
+    assert_eq!(
+        parse(json!({ "command": "shuffle", "value": true })),
+        MediaControlEvent::SetShuffle(true)
+    );
+    assert_eq!(
+        parse(json!({ "command": "playIndex", "value": 3 })),
+        MediaControlEvent::GoTo(3)
+    );
+}
+
+// Test clamping out of range values
+#[test]
+fn test_parse_commands_clamped() {
+    assert_eq!(
+        parse(json!({ "command": "seek", "value": -5 })),
+        MediaControlEvent::SetPosition(MediaPosition(Duration::ZERO))
+    );
+    assert_eq!(
+        parse(json!({ "command": "volume", "value": 250 })),
+        MediaControlEvent::SetVolume(1f64)
+    );
+    assert_eq!(
+        parse(json!({ "command": "volume", "value": -10 })),
+        MediaControlEvent::SetVolume(0f64)
+    );
+}
+
+// Test rejecting unknown commands and missing values
+#[test]
+fn test_parse_invalid_commands() {
+    assert!(serde_json::from_value::<TransportCommand>(json!({ "command": "rewind" })).is_err());
+    assert!(serde_json::from_value::<TransportCommand>(json!({ "command": "seek" })).is_err());
+    assert!(serde_json::from_value::<TransportCommand>(
+        json!({ "command": "volume", "value": "loud" })
+    )
+    .is_err());
+    assert!(serde_json::from_value::<TransportCommand>(json!({ "value": 1 })).is_err());
+}
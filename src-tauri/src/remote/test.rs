@@ -0,0 +1,65 @@
+// Moosync
+// Copyright (C) 2024, 2025  Moosync <support@moosync.app>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::env::temp_dir;
+
+use preferences::preferences::PreferenceConfig;
+
+use super::{generate_token, load_token, MIN_TOKEN_LEN, TOKEN_KEY};
+
+fn new_preferences() -> PreferenceConfig {
+    let dir = temp_dir().join(format!("moosync_test_{}", uuid::Uuid::new_v4()));
+    PreferenceConfig::new(dir).unwrap()
+}
+
+// Test that generated tokens are long enough and random
+#[test]
+fn test_generate_token() {
+    let token = generate_token();
+    assert!(token.len() >= MIN_TOKEN_LEN);
+    assert!(token.chars().all(|c| c.is_ascii_hexdigit()));
+    assert_ne!(token, generate_token());
+}
+
+// Test that the token is created once and kept in secure storage
+#[test]
+fn test_load_token_creates_secure_token() {
+    let preferences = new_preferences();
+    let token = load_token(&preferences).unwrap();
+    assert_eq!(load_token(&preferences).unwrap(), token);
+
+    let stored = preferences
+        .load_selective::<String>(TOKEN_KEY.into())
+        .unwrap();
+    assert_ne!(stored, token);
+    assert_eq!(
+        preferences.get_secure::<String>(TOKEN_KEY.into()).unwrap(),
+        token
+    );
+}
+
+// Test that tokens saved in plain text are replaced
+#[test]
+fn test_load_token_replaces_plain_token() {
+    let preferences = new_preferences();
+    preferences
+        .save_selective(TOKEN_KEY.into(), Some("plain"))
+        .unwrap();
+
+    let token = load_token(&preferences).unwrap();
+    assert_ne!(token, "plain");
+    assert!(token.len() >= MIN_TOKEN_LEN);
+}
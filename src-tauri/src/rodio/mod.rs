@@ -18,14 +18,38 @@ use std::thread;
 
 use futures::executor::block_on;
 use macros::generate_command_async;
+use preferences::preferences::PreferenceConfig;
 use rodio_player::RodioPlayer;
 use tauri::{AppHandle, Emitter, Manager, State};
-use types::errors::Result;
+use types::{
+    errors::Result,
+    songs::SongType,
+    ui::player_details::{AudioCacheStats, PlayerEvents},
+};
+
+pub const AUDIO_CACHE_SIZE_KEY: &str = "audio_cache_size";
+/// In MB
+pub const DEFAULT_AUDIO_CACHE_SIZE: u64 = 1024;
+
+/// Reads the audio cache size preference in bytes
+#[tracing::instrument(level = "debug", skip(preferences))]
+pub fn load_audio_cache_size(preferences: &PreferenceConfig) -> u64 {
+    // Number inputs are saved as floats
+    let size = preferences
+        .load_selective::<f64>(AUDIO_CACHE_SIZE_KEY.into())
+        .ok()
+        .filter(|size| size.is_finite() && *size >= 0f64)
+        .map(|size| size as u64)
+        .unwrap_or(DEFAULT_AUDIO_CACHE_SIZE);
+    size.saturating_mul(1024 * 1024)
+}
 
 #[tracing::instrument(level = "debug", skip())]
-pub fn get_rodio_state(app: AppHandle) -> RodioPlayer {
-    let cache_dir = app.path().app_cache_dir().unwrap();
-    let rodio_player = RodioPlayer::new(cache_dir);
+pub fn get_rodio_state(app: AppHandle) -> Result<RodioPlayer> {
+    let cache_dir = app.path().app_cache_dir()?;
+    let rodio_player = RodioPlayer::new(cache_dir)?;
+    let preferences: State<PreferenceConfig> = app.state();
+    rodio_player.set_cache_size(load_audio_cache_size(&preferences));
 
     let events_rx = rodio_player.get_events_rx();
     thread::spawn(move || {
@@ -39,16 +63,26 @@ pub fn get_rodio_state(app: AppHandle) -> RodioPlayer {
         }
     });
 
-    rodio_player
+    Ok(rodio_player)
 }
 
-#[tracing::instrument(level = "debug", skip(app, src))]
+#[tracing::instrument(level = "debug", skip(app, src, cache_key))]
 #[tauri::command(async)]
 #[tauri_invoke_proc::parse_tauri_command]
-pub fn rodio_load(app: AppHandle, src: String) -> Result<()> {
+pub fn rodio_load(
+    app: AppHandle,
+    src: String,
+    cache_key: Option<String>,
+    song_type: Option<SongType>,
+) -> Result<()> {
     tauri::async_runtime::spawn_blocking(move || {
         let rodio: State<'_, RodioPlayer> = app.state();
-        block_on(rodio.rodio_load(src)).unwrap();
+        if let Err(e) = block_on(rodio.rodio_load(src, cache_key, song_type)) {
+            tracing::error!("Failed to load src in rodio: {:?}", e);
+            if let Err(e) = app.emit("rodio_event", PlayerEvents::Error(e)) {
+                tracing::error!("Error sending rodio event {:?}", e);
+            }
+        }
     });
     Ok(())
 }
@@ -60,3 +94,6 @@ generate_command_async!(rodio_stop, RodioPlayer, (),);
 generate_command_async!(rodio_seek, RodioPlayer, (), pos: f64);
 generate_command_async!(rodio_set_volume, RodioPlayer, (), volume: f32);
 generate_command_async!(rodio_get_volume, RodioPlayer, f32,);
+generate_command_async!(rodio_reinitialize, RodioPlayer, (),);
+generate_command_async!(rodio_cache_stats, RodioPlayer, AudioCacheStats,);
+generate_command_async!(rodio_clear_cache, RodioPlayer, (),);
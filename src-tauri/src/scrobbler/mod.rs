@@ -0,0 +1,339 @@
+// Moosync
+// Copyright (C) 2024, 2025  Moosync <support@moosync.app>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::{
+    collections::HashSet,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use database::database::Database;
+use preferences::preferences::PreferenceConfig;
+use scrobbler::{
+    LastFmClient, ListenBrainzClient, ScrobbleClient, ScrobbleThreshold, LASTFM_API_URL,
+    LISTENBRAINZ_API_URL, MAX_BATCH,
+};
+use tauri::{AppHandle, Manager, State};
+use types::{
+    errors::Result,
+    preferences::CheckboxPreference,
+    scrobble::{ScrobbleService, ScrobbleTrack},
+    songs::Song,
+};
+
+#[cfg(test)]
+mod test;
+
+/// Listens which failed this many times are dropped from the retry queue
+const MAX_ATTEMPTS: i32 = 10;
+
+/// Key of the secure preference holding the Last.fm session
+const LASTFM_SESSION_KEY: &str = "lastfm_session";
+
+/// Reads a secret from secure storage. Secrets saved in plain text before they were
+/// moved to secure storage are encrypted in place on first read. If that fails the
+/// plain text is removed and the secret has to be entered again.
+#[tracing::instrument(level = "debug", skip(preferences))]
+fn load_secure(preferences: &PreferenceConfig, key: &str) -> String {
+    let value = match preferences.get_secure::<String>(key.into()) {
+        Ok(value) => value,
+        Err(e) => {
+            let Ok(value) = preferences.load_selective::<String>(key.into()) else {
+                return String::new();
+            };
+            tracing::info!("Moving {} to secure storage: {:?}", key, e);
+            if let Err(e) = preferences.set_secure(key.into(), Some(value.clone())) {
+                tracing::error!("Failed to move {} to secure storage: {:?}", key, e);
+                if let Err(e) = preferences.save_selective::<String>(key.into(), None) {
+                    tracing::error!("Failed to remove plain text {}: {:?}", key, e);
+                }
+            }
+            value
+        }
+    };
+    value.trim().to_string()
+}
+
+#[derive(Debug, Default)]
+struct CurrentListen {
+    track: Option<ScrobbleTrack>,
+    played: f64,
+    scrobbled: bool,
+}
+
+/// Submits now playing updates and listens to Last.fm and ListenBrainz.
+/// Listens which can't be submitted are stored in the database and retried
+/// after the next successful submission.
+#[derive(Default)]
+pub struct ScrobblerService {
+    clients: Mutex<Vec<Arc<dyn ScrobbleClient>>>,
+    threshold: Mutex<ScrobbleThreshold>,
+    current: Mutex<CurrentListen>,
+    // Bumped on every restart so that late logins don't add stale clients
+    generation: AtomicU64,
+    // Services whose pending listens are being submitted
+    retrying: Mutex<HashSet<ScrobbleService>>,
+}
+
+impl ScrobblerService {
+    /// Recreates the clients from the preferences and retries pending listens
+    #[tracing::instrument(level = "debug", skip(self, app))]
+    pub fn restart(&self, app: AppHandle) {
+        let generation = self.generation.fetch_add(1, Ordering::SeqCst) + 1;
+        let preferences: State<PreferenceConfig> = app.state();
+        let enabled = |key: &str| {
+            preferences
+                .load_selective_array::<CheckboxPreference>(format!("scrobble_settings.{}", key))
+                .map(|p| p.enabled)
+                .unwrap_or_default()
+        };
+        let load = |key: &str| {
+            preferences
+                .load_selective::<String>(key.into())
+                .map(|v| v.trim().to_string())
+                .unwrap_or_default()
+        };
+
+        let mut threshold = ScrobbleThreshold::default();
+        // Number inputs are saved as floats
+        if let Ok(percent) = preferences.load_selective::<f64>("scrobble_threshold_percent".into())
+        {
+            if (1f64..=100f64).contains(&percent) {
+                threshold.percent = percent;
+            }
+        }
+        if let Ok(max_secs) = preferences.load_selective::<f64>("scrobble_threshold_max".into()) {
+            if max_secs > 0f64 {
+                threshold.max_secs = max_secs;
+            }
+        }
+        *self.threshold.lock().unwrap() = threshold;
+
+        let mut clients: Vec<Arc<dyn ScrobbleClient>> = vec![];
+        if enabled("listenbrainz") {
+            let token = load_secure(&preferences, "scrobble_listenbrainz_token");
+            if token.is_empty() {
+                tracing::warn!("ListenBrainz scrobbling needs a user token");
+            } else {
+                clients.push(Arc::new(ListenBrainzClient::new(
+                    LISTENBRAINZ_API_URL.to_string(),
+                    token,
+                )));
+            }
+        }
+        *self.clients.lock().unwrap() = clients;
+
+        if enabled("lastfm") {
+            let api_key = load("scrobble_lastfm_api_key");
+            let secret = load_secure(&preferences, "scrobble_lastfm_secret");
+            if api_key.is_empty() || secret.is_empty() {
+                tracing::warn!("Last.fm scrobbling needs an API key and secret");
+            } else {
+                let app = app.clone();
+                let username = load("scrobble_lastfm_username");
+                let password = load_secure(&preferences, "scrobble_lastfm_password");
+                tauri::async_runtime::spawn(async move {
+                    match lastfm_session(&app, &api_key, &secret, &username, &password).await {
+                        Ok(session_key) => {
+                            let client = Arc::new(LastFmClient::new(
+                                LASTFM_API_URL.to_string(),
+                                api_key,
+                                secret,
+                                session_key,
+                            ));
+                            let scrobbler: State<ScrobblerService> = app.state();
+                            if scrobbler.generation.load(Ordering::SeqCst) != generation {
+                                return;
+                            }
+                            scrobbler.clients.lock().unwrap().push(client.clone());
+                            retry_pending(&app, client.as_ref()).await;
+                        }
+                        Err(e) => tracing::error!("Failed to log in to Last.fm: {:?}", e),
+                    }
+                });
+            }
+        }
+
+        for client in self.get_clients() {
+            let app = app.clone();
+            tauri::async_runtime::spawn(async move {
+                retry_pending(&app, client.as_ref()).await;
+            });
+        }
+    }
+
+    fn get_clients(&self) -> Vec<Arc<dyn ScrobbleClient>> {
+        self.clients.lock().unwrap().clone()
+    }
+
+    #[tracing::instrument(level = "debug", skip(self, song))]
+    pub fn now_playing(&self, song: Option<Song>) {
+        let track = song.and_then(|song| ScrobbleTrack::from_song(&song, unix_time()));
+        *self.current.lock().unwrap() = CurrentListen {
+            track: track.clone(),
+            ..Default::default()
+        };
+
+        let Some(track) = track else {
+            return;
+        };
+        for client in self.get_clients() {
+            let track = track.clone();
+            tauri::async_runtime::spawn(async move {
+                if let Err(e) = client.now_playing(&track).await {
+                    tracing::warn!("Failed to update now playing: {:?}", e);
+                }
+            });
+        }
+    }
+
+    /// Adds seconds of actual playback to the current song and submits it once the threshold is reached
+    #[tracing::instrument(level = "debug", skip(self, app))]
+    pub fn add_played(&self, app: AppHandle, seconds: f64) {
+        let track = {
+            let mut current = self.current.lock().unwrap();
+            current.played += seconds.max(0f64);
+
+            let threshold = self.threshold.lock().unwrap();
+            let Some(track) = current.track.clone() else {
+                return;
+            };
+            if current.scrobbled || !threshold.is_reached(track.duration, current.played) {
+                return;
+            }
+            current.scrobbled = true;
+            track
+        };
+
+        for client in self.get_clients() {
+            let app = app.clone();
+            let track = track.clone();
+            tauri::async_runtime::spawn(async move {
+                let database: State<Database> = app.state();
+                match client.scrobble(&[track.clone()]).await {
+                    Ok(_) => retry_pending(&app, client.as_ref()).await,
+                    Err(e) => {
+                        tracing::warn!("Failed to scrobble, queueing for retry: {:?}", e);
+                        if let Err(e) = database.add_pending_scrobble(client.service(), &track) {
+                            tracing::error!("Failed to store scrobble: {:?}", e);
+                        }
+                    }
+                }
+            });
+        }
+    }
+}
+
+fn unix_time() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or_default()
+}
+
+/// Uses the stored session, or logs in and forgets the password once a session is obtained
+async fn lastfm_session(
+    app: &AppHandle,
+    api_key: &str,
+    secret: &str,
+    username: &str,
+    password: &str,
+) -> Result<String> {
+    let preferences: State<PreferenceConfig> = app.state();
+    if password.is_empty() {
+        return preferences.get_secure(LASTFM_SESSION_KEY.into());
+    }
+
+    let session_key =
+        LastFmClient::get_session(LASTFM_API_URL, api_key, secret, username, password).await?;
+    preferences.set_secure(LASTFM_SESSION_KEY.into(), Some(session_key.clone()))?;
+    preferences.set_secure("scrobble_lastfm_password".into(), None::<String>)?;
+    Ok(session_key)
+}
+
+/// Submits stored listens unless they are already being submitted for this service
+#[tracing::instrument(level = "debug", skip(app, client))]
+async fn retry_pending(app: &AppHandle, client: &dyn ScrobbleClient) {
+    let scrobbler: State<ScrobblerService> = app.state();
+    let service = client.service();
+    // A second retry would load and submit the same listens again
+    if !scrobbler.retrying.lock().unwrap().insert(service) {
+        return;
+    }
+    submit_pending(app, client).await;
+    scrobbler.retrying.lock().unwrap().remove(&service);
+}
+
+/// Submits stored listens in batches until the queue is empty or a submission fails
+async fn submit_pending(app: &AppHandle, client: &dyn ScrobbleClient) {
+    let database: State<Database> = app.state();
+    loop {
+        let pending = match database.get_pending_scrobbles(client.service(), MAX_BATCH) {
+            Ok(pending) => pending,
+            Err(e) => {
+                tracing::error!("Failed to load pending scrobbles: {:?}", e);
+                return;
+            }
+        };
+        if pending.is_empty() {
+            return;
+        }
+
+        let (ids, tracks): (Vec<_>, Vec<_>) = pending.into_iter().unzip();
+        let res = match client.scrobble(&tracks).await {
+            Ok(_) => database.remove_pending_scrobbles(&ids),
+            Err(e) => {
+                tracing::warn!("Failed to submit pending scrobbles: {:?}", e);
+                if let Err(e) = database.increment_scrobble_attempts(&ids, MAX_ATTEMPTS) {
+                    tracing::error!("Failed to update pending scrobbles: {:?}", e);
+                }
+                return;
+            }
+        };
+        if let Err(e) = res {
+            tracing::error!("Failed to remove submitted scrobbles: {:?}", e);
+            return;
+        }
+    }
+}
+
+#[tracing::instrument(level = "debug", skip())]
+pub fn get_scrobbler_state() -> ScrobblerService {
+    ScrobblerService::default()
+}
+
+#[tracing::instrument(level = "debug", skip(scrobbler))]
+#[tauri_invoke_proc::parse_tauri_command]
+#[tauri::command(async)]
+pub fn scrobbler_now_playing(scrobbler: State<ScrobblerService>, song: Option<Song>) -> Result<()> {
+    scrobbler.now_playing(song);
+    Ok(())
+}
+
+#[tracing::instrument(level = "debug", skip(app, scrobbler))]
+#[tauri_invoke_proc::parse_tauri_command]
+#[tauri::command(async)]
+pub fn scrobbler_add_played(
+    app: AppHandle,
+    scrobbler: State<ScrobblerService>,
+    seconds: f64,
+) -> Result<()> {
+    scrobbler.add_played(app, seconds);
+    Ok(())
+}
@@ -0,0 +1,63 @@
+// Moosync
+// Copyright (C) 2024, 2025  Moosync <support@moosync.app>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::env::temp_dir;
+
+use preferences::preferences::PreferenceConfig;
+
+use super::load_secure;
+
+fn new_preferences() -> PreferenceConfig {
+    let dir = temp_dir().join(format!("moosync_test_{}", uuid::Uuid::new_v4()));
+    PreferenceConfig::new(dir).unwrap()
+}
+
+// Test that secrets saved in plain text are only kept encrypted after they are read
+#[test]
+fn test_load_secure_migrates_plain_text() {
+    let preferences = new_preferences();
+    preferences
+        .save_selective("scrobble_listenbrainz_token".into(), Some(" token "))
+        .unwrap();
+
+    assert_eq!(
+        load_secure(&preferences, "scrobble_listenbrainz_token"),
+        "token"
+    );
+
+    let stored = preferences
+        .load_selective::<String>("scrobble_listenbrainz_token".into())
+        .unwrap();
+    assert!(!stored.contains("token"));
+    assert_eq!(
+        preferences
+            .get_secure::<String>("scrobble_listenbrainz_token".into())
+            .unwrap(),
+        " token "
+    );
+    assert_eq!(
+        load_secure(&preferences, "scrobble_listenbrainz_token"),
+        "token"
+    );
+}
+
+// Test that missing secrets are empty
+#[test]
+fn test_load_secure_missing() {
+    let preferences = new_preferences();
+    assert_eq!(load_secure(&preferences, "scrobble_lastfm_secret"), "");
+    assert!(!preferences.has_key("scrobble_lastfm_secret"));
+}
@@ -0,0 +1,206 @@
+// Moosync
+// Copyright (C) 2024, 2025  Moosync <support@moosync.app>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::sync::Mutex;
+#[cfg(desktop)]
+use std::{collections::HashMap, str::FromStr, time::Duration};
+
+use macros::generate_command;
+#[cfg(desktop)]
+use mpris::{MediaControlEvent, SeekDirection};
+use preferences::preferences::PreferenceConfig;
+#[cfg(desktop)]
+use serde_json::json;
+#[cfg(desktop)]
+use tauri::Emitter;
+use tauri::{AppHandle, Manager, State};
+#[cfg(desktop)]
+use tauri_plugin_global_shortcut::{GlobalShortcutExt, Shortcut, ShortcutState};
+#[cfg(desktop)]
+use types::shortcuts::{ShortcutAction, ShortcutConflictReason};
+use types::{
+    errors::Result,
+    shortcuts::{ShortcutBindings, ShortcutConflict},
+};
+
+#[cfg(desktop)]
+use crate::mpris::handle_media_event;
+
+#[cfg(all(test, desktop))]
+mod test;
+
+/// Seconds skipped by the seek shortcuts
+#[cfg(desktop)]
+const SEEK_STEP: u64 = 10;
+
+/// Volume percentage changed by the volume shortcuts
+#[cfg(desktop)]
+const VOLUME_STEP: f64 = 5f64;
+
+/// Key chords registered with the OS, so that media keys work without an MPRIS
+/// aware daemon. Bindings which can't be registered are kept as conflicts.
+/// Mobile has no global shortcuts, bindings are only saved there.
+#[derive(Default)]
+pub struct GlobalShortcuts {
+    conflicts: Mutex<Vec<ShortcutConflict>>,
+}
+
+impl GlobalShortcuts {
+    /// Registers the bindings saved in the preferences, replacing the previous ones
+    #[cfg(desktop)]
+    #[tracing::instrument(level = "debug", skip(self, app))]
+    pub fn reload(&self, app: &AppHandle) -> Result<Vec<ShortcutConflict>> {
+        // Held throughout so that concurrent reloads don't register over each other
+        let mut current_conflicts = self.conflicts.lock().unwrap();
+
+        let global_shortcut = app.global_shortcut();
+        global_shortcut
+            .unregister_all()
+            .map_err(|e| e.to_string())?;
+
+        let preferences: State<PreferenceConfig> = app.state();
+        let bindings: ShortcutBindings = preferences
+            .load_selective("global_shortcuts".into())
+            .unwrap_or_default();
+
+        let (shortcuts, mut conflicts) = parse_bindings(&bindings);
+        for (shortcut, action) in shortcuts {
+            let res = global_shortcut.on_shortcut(shortcut, move |app, _, event| {
+                if event.state() == ShortcutState::Pressed {
+                    run_action(app, action);
+                }
+            });
+            if let Err(e) = res {
+                conflicts.push(ShortcutConflict {
+                    action,
+                    shortcut: bindings.get(&action).cloned().unwrap_or_default(),
+                    reason: ShortcutConflictReason::Unavailable(e.to_string()),
+                });
+            }
+        }
+
+        for conflict in &conflicts {
+            tracing::warn!("Global shortcut not registered: {:?}", conflict);
+        }
+        *current_conflicts = conflicts.clone();
+        Ok(conflicts)
+    }
+
+    #[cfg(mobile)]
+    #[tracing::instrument(level = "debug", skip(self, _app))]
+    pub fn reload(&self, _app: &AppHandle) -> Result<Vec<ShortcutConflict>> {
+        Ok(self.conflicts.lock().unwrap().clone())
+    }
+
+    /// Saves the bindings and returns the ones which could not be registered
+    #[tracing::instrument(level = "debug", skip(self, app))]
+    pub fn set_global_shortcuts(
+        &self,
+        app: AppHandle,
+        bindings: ShortcutBindings,
+    ) -> Result<Vec<ShortcutConflict>> {
+        let preferences: State<PreferenceConfig> = app.state();
+        preferences.save_selective("global_shortcuts".into(), Some(bindings))?;
+        self.reload(&app)
+    }
+
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub fn get_global_shortcut_conflicts(&self) -> Result<Vec<ShortcutConflict>> {
+        Ok(self.conflicts.lock().unwrap().clone())
+    }
+}
+
+/// Parses the bindings in a fixed order, so that the first action keeps a chord bound twice
+#[cfg(desktop)]
+fn parse_bindings(
+    bindings: &ShortcutBindings,
+) -> (Vec<(Shortcut, ShortcutAction)>, Vec<ShortcutConflict>) {
+    let mut shortcuts: HashMap<Shortcut, ShortcutAction> = HashMap::new();
+    let mut parsed = vec![];
+    let mut conflicts = vec![];
+
+    for action in ShortcutAction::ALL {
+        let Some(chord) = bindings.get(&action).map(|c| c.trim()) else {
+            continue;
+        };
+        if chord.is_empty() {
+            continue;
+        }
+
+        let reason = match Shortcut::from_str(chord) {
+            Ok(shortcut) => match shortcuts.get(&shortcut) {
+                Some(other) => ShortcutConflictReason::Duplicate(*other),
+                None => {
+                    shortcuts.insert(shortcut, action);
+                    parsed.push((shortcut, action));
+                    continue;
+                }
+            },
+            Err(e) => ShortcutConflictReason::Invalid(e.to_string()),
+        };
+        conflicts.push(ShortcutConflict {
+            action,
+            shortcut: chord.to_string(),
+            reason,
+        });
+    }
+
+    (parsed, conflicts)
+}
+
+#[cfg(desktop)]
+#[tracing::instrument(level = "debug", skip(app))]
+fn run_action(app: &AppHandle, action: ShortcutAction) {
+    let seek_by = Duration::from_secs(SEEK_STEP);
+    let event = match action {
+        ShortcutAction::Play => MediaControlEvent::Play,
+        ShortcutAction::Pause => MediaControlEvent::Pause,
+        ShortcutAction::Toggle => MediaControlEvent::Toggle,
+        ShortcutAction::Stop => MediaControlEvent::Stop,
+        ShortcutAction::Next => MediaControlEvent::Next,
+        ShortcutAction::Prev => MediaControlEvent::Previous,
+        ShortcutAction::SeekForward => MediaControlEvent::SeekBy(SeekDirection::Forward, seek_by),
+        ShortcutAction::SeekBackward => MediaControlEvent::SeekBy(SeekDirection::Backward, seek_by),
+        ShortcutAction::VolumeUp | ShortcutAction::VolumeDown => {
+            let step = if action == ShortcutAction::VolumeUp {
+                VOLUME_STEP
+            } else {
+                -VOLUME_STEP
+            };
+            let _ = app.emit("media_button_press", (20, json!(step)));
+            return;
+        }
+    };
+    handle_media_event(app, event);
+}
+
+#[tracing::instrument(level = "debug", skip())]
+pub fn get_global_shortcuts_state() -> GlobalShortcuts {
+    GlobalShortcuts::default()
+}
+
+generate_command!(
+    set_global_shortcuts,
+    GlobalShortcuts,
+    Vec<ShortcutConflict>,
+    app: AppHandle,
+    bindings: ShortcutBindings
+);
+generate_command!(
+    get_global_shortcut_conflicts,
+    GlobalShortcuts,
+    Vec<ShortcutConflict>,
+);
@@ -0,0 +1,125 @@
+// Moosync
+// Copyright (C) 2024, 2025  Moosync <support@moosync.app>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::str::FromStr;
+
+use tauri_plugin_global_shortcut::Shortcut;
+use types::shortcuts::{ShortcutAction, ShortcutBindings, ShortcutConflictReason};
+
+use super::parse_bindings;
+
+fn bindings(items: &[(ShortcutAction, &str)]) -> ShortcutBindings {
+    items
+        .iter()
+        .map(|(action, chord)| (*action, chord.to_string()))
+        .collect()
+}
+
+// Test parsing valid chords
+#[test]
+fn test_parse_bindings() {
+    let (shortcuts, conflicts) = parse_bindings(&bindings(&[
+        (ShortcutAction::Toggle, "Ctrl+Alt+KeyP"),
+        (ShortcutAction::Next, " MediaTrackNext "),
+    ]));
+
+    assert!(conflicts.is_empty());
+    assert_eq!(
+        shortcuts,
+        vec![
+            (
+                Shortcut::from_str("Ctrl+Alt+KeyP").unwrap(),
+                ShortcutAction::Toggle
+            ),
+            (
+                Shortcut::from_str("MediaTrackNext").unwrap(),
+                ShortcutAction::Next
+            ),
+        ]
+    );
+}
+
+// Test skipping actions without a chord
+#[test]
+fn test_parse_bindings_empty() {
+    let (shortcuts, conflicts) = parse_bindings(&bindings(&[
+        (ShortcutAction::Play, ""),
+        (ShortcutAction::Pause, "   "),
+    ]));
+    assert!(shortcuts.is_empty());
+    assert!(conflicts.is_empty());
+
+    let (shortcuts, conflicts) = parse_bindings(&ShortcutBindings::new());
+    assert!(shortcuts.is_empty());
+    assert!(conflicts.is_empty());
+}
+
+// Test reporting chords which can't be parsed
+#[test]
+fn test_parse_bindings_invalid() {
+    let (shortcuts, conflicts) = parse_bindings(&bindings(&[
+        (ShortcutAction::Stop, "Ctrl+NotAKey"),
+        (ShortcutAction::Prev, "Ctrl+KeyB"),
+    ]));
+
+    assert_eq!(shortcuts.len(), 1);
+    assert_eq!(conflicts.len(), 1);
+    assert_eq!(conflicts[0].action, ShortcutAction::Stop);
+    assert_eq!(conflicts[0].shortcut, "Ctrl+NotAKey");
+    assert!(matches!(
+        conflicts[0].reason,
+        ShortcutConflictReason::Invalid(_)
+    ));
+}
+
+// Test keeping the chord for the action which comes first
+#[test]
+fn test_parse_bindings_duplicate() {
+    let (shortcuts, conflicts) = parse_bindings(&bindings(&[
+        (ShortcutAction::VolumeDown, "Ctrl+KeyV"),
+        (ShortcutAction::Play, "Control+KeyV"),
+    ]));
+
+    assert_eq!(
+        shortcuts,
+        vec![(
+            Shortcut::from_str("Ctrl+KeyV").unwrap(),
+            ShortcutAction::Play
+        )]
+    );
+    assert_eq!(conflicts.len(), 1);
+    assert_eq!(conflicts[0].action, ShortcutAction::VolumeDown);
+    assert_eq!(
+        conflicts[0].reason,
+        ShortcutConflictReason::Duplicate(ShortcutAction::Play)
+    );
+}
+
+// Test that saved bindings use the snake case action names
+#[test]
+fn test_bindings_serde() {
+    let saved = bindings(&[
+        (ShortcutAction::SeekForward, "Ctrl+ArrowRight"),
+        (ShortcutAction::VolumeUp, "Ctrl+ArrowUp"),
+    ]);
+    let value = serde_json::to_value(&saved).unwrap();
+    assert_eq!(value["seek_forward"], "Ctrl+ArrowRight");
+    assert_eq!(value["volume_up"], "Ctrl+ArrowUp");
+
+    let loaded: ShortcutBindings = serde_json::from_value(value).unwrap();
+    assert_eq!(loaded, saved);
+    assert!(serde_json::from_str::<ShortcutAction>("\"fast_forward\"").is_err());
+}
@@ -0,0 +1,189 @@
+// Moosync
+// Copyright (C) 2024, 2025  Moosync <support@moosync.app>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::{
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    thread,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use macros::generate_command;
+use tauri::{AppHandle, Emitter, Manager, State};
+use types::{
+    errors::Result,
+    ui::player_details::{SleepTimer, SleepTimerEvent, SleepTimerTrigger},
+};
+
+/// Fade used by timers set from the tray menu
+pub const DEFAULT_FADE_OUT: u64 = 10;
+
+/// Holds the sleep timer shared by the UI, tray menu and MPRIS.
+/// Duration timers are counted here so they keep running while the webview
+/// is throttled. Track and queue based timers are fired by the UI, which
+/// knows when playback ends.
+#[derive(Default)]
+pub struct SleepTimerHolder {
+    timer: Mutex<Option<SleepTimer>>,
+    // Bumped every time the timer changes so that stale countdowns don't fire
+    generation: Arc<AtomicU64>,
+}
+
+impl SleepTimerHolder {
+    #[tracing::instrument(level = "debug", skip(self, app))]
+    pub fn set_sleep_timer(
+        &self,
+        app: AppHandle,
+        trigger: SleepTimerTrigger,
+        fade_out: u64,
+    ) -> Result<SleepTimer> {
+        let ends_at = match trigger {
+            SleepTimerTrigger::Duration(secs) => Some(now_millis() + secs * 1000),
+            _ => None,
+        };
+
+        let timer = SleepTimer {
+            trigger,
+            fade_out,
+            ends_at,
+        };
+
+        *self.timer.lock().unwrap() = Some(timer.clone());
+        let current_gen = self.generation.fetch_add(1, Ordering::SeqCst) + 1;
+
+        if let SleepTimerTrigger::Duration(secs) = trigger {
+            let generation = self.generation.clone();
+            let app = app.clone();
+            thread::spawn(move || {
+                thread::sleep(Duration::from_secs(secs));
+                if generation.load(Ordering::SeqCst) != current_gen {
+                    return;
+                }
+
+                tracing::info!("Sleep timer expired");
+                let state: State<SleepTimerHolder> = app.state();
+                if let Err(e) = state.sleep_timer_expired(app.clone()) {
+                    tracing::error!("Failed to expire sleep timer: {:?}", e);
+                }
+            });
+        }
+
+        Self::emit(&app, SleepTimerEvent::Changed(Some(timer.clone())));
+        Ok(timer)
+    }
+
+    #[tracing::instrument(level = "debug", skip(self, app))]
+    pub fn cancel_sleep_timer(&self, app: AppHandle) -> Result<()> {
+        self.generation.fetch_add(1, Ordering::SeqCst);
+        if self.timer.lock().unwrap().take().is_some() {
+            Self::emit(&app, SleepTimerEvent::Changed(None));
+        }
+        Ok(())
+    }
+
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub fn get_sleep_timer(&self) -> Result<Option<SleepTimer>> {
+        Ok(self.timer.lock().unwrap().clone())
+    }
+
+    /// Clears the timer and asks the UI to stop playback
+    #[tracing::instrument(level = "debug", skip(self, app))]
+    pub fn sleep_timer_expired(&self, app: AppHandle) -> Result<()> {
+        self.generation.fetch_add(1, Ordering::SeqCst);
+        if self.timer.lock().unwrap().take().is_none() {
+            return Ok(());
+        }
+
+        Self::emit(&app, SleepTimerEvent::Expired);
+        Self::emit(&app, SleepTimerEvent::Changed(None));
+        Ok(())
+    }
+
+    fn emit(app: &AppHandle, event: SleepTimerEvent) {
+        if let Err(e) = app.emit("sleep_timer_event", event) {
+            tracing::error!("Failed to emit sleep timer event: {:?}", e);
+        }
+    }
+}
+
+fn now_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or_default()
+}
+
+/// Controls the timer through uris such as `moosync://sleep-timer?minutes=30&fade=10`,
+/// `moosync://sleep-timer?at=track` or `moosync://sleep-timer?cancel`. MPRIS has no
+/// sleep timer of its own, so these are accepted through OpenUri.
+/// Returns false if uri isn't a sleep timer uri.
+#[tracing::instrument(level = "debug", skip(app))]
+pub fn handle_sleep_timer_uri(app: &AppHandle, uri: &str) -> bool {
+    let Ok(url) = url::Url::parse(uri) else {
+        return false;
+    };
+    if url.scheme() != "moosync" || url.host_str() != Some("sleep-timer") {
+        return false;
+    }
+
+    let mut trigger = None;
+    let mut fade_out = 0;
+    let mut cancel = false;
+    for (key, value) in url.query_pairs() {
+        match key.as_ref() {
+            "minutes" => {
+                trigger = value
+                    .parse::<u64>()
+                    .ok()
+                    .map(|m| SleepTimerTrigger::Duration(m * 60))
+            }
+            "at" => {
+                trigger = match value.as_ref() {
+                    "track" => Some(SleepTimerTrigger::EndOfTrack),
+                    "queue" => Some(SleepTimerTrigger::EndOfQueue),
+                    _ => None,
+                }
+            }
+            "fade" => fade_out = value.parse().unwrap_or_default(),
+            "cancel" => cancel = true,
+            _ => {}
+        }
+    }
+
+    let state: State<SleepTimerHolder> = app.state();
+    let res = match trigger {
+        Some(trigger) if !cancel => state
+            .set_sleep_timer(app.clone(), trigger, fade_out)
+            .map(|_| ()),
+        _ => state.cancel_sleep_timer(app.clone()),
+    };
+    if let Err(e) = res {
+        tracing::error!("Failed to handle sleep timer uri {}: {:?}", uri, e);
+    }
+    true
+}
+
+#[tracing::instrument(level = "debug", skip())]
+pub fn get_sleep_timer_state() -> SleepTimerHolder {
+    SleepTimerHolder::default()
+}
+
+generate_command!(set_sleep_timer, SleepTimerHolder, SleepTimer, app: AppHandle, trigger: SleepTimerTrigger, fade_out: u64);
+generate_command!(cancel_sleep_timer, SleepTimerHolder, (), app: AppHandle);
+generate_command!(get_sleep_timer, SleepTimerHolder, Option<SleepTimer>,);
+generate_command!(sleep_timer_expired, SleepTimerHolder, (), app: AppHandle);
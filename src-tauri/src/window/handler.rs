@@ -281,30 +281,73 @@ pub fn handle_window_close(app: &AppHandle) -> Result<bool> {
     Ok(true)
 }
 
+#[cfg(not(any(target_os = "android", target_os = "ios")))]
+const TRAY_ID: &str = "main";
+
+#[cfg(not(any(target_os = "android", target_os = "ios")))]
+#[tracing::instrument(level = "debug", skip(app))]
+fn tray_menu(app: &AppHandle) -> Result<tauri::menu::Menu<tauri::Wry>> {
+    use tauri::menu::{MenuBuilder, SubmenuBuilder};
+
+    use super::locale::Translator;
+
+    let translator = Translator::new(app);
+    let t = |key: &str| translator.t(&format!("contextMenu.sleepTimer.{}", key));
+    let sleep_menu = SubmenuBuilder::new(app, t("title"))
+        .text("sleep_15", t("minutes15"))
+        .text("sleep_30", t("minutes30"))
+        .text("sleep_60", t("hour1"))
+        .text("sleep_track", t("endOfTrack"))
+        .text("sleep_queue", t("endOfQueue"))
+        .separator()
+        .text("sleep_cancel", t("cancel"))
+        .build()?;
+
+    let menu = MenuBuilder::new(app)
+        .icon(
+            "show",
+            "Show App",
+            app.default_window_icon().cloned().unwrap(),
+        )
+        .icon("play", "Play", app.default_window_icon().cloned().unwrap())
+        .icon(
+            "pause",
+            "Pause",
+            app.default_window_icon().cloned().unwrap(),
+        )
+        .icon("next", "Next", app.default_window_icon().cloned().unwrap())
+        .icon("prev", "Prev", app.default_window_icon().cloned().unwrap())
+        .item(&sleep_menu)
+        .icon("quit", "Quit", app.default_window_icon().cloned().unwrap())
+        .build()?;
+    Ok(menu)
+}
+
+/// Rebuilds the tray menu so that it follows the selected language
+#[tracing::instrument(level = "debug", skip(app))]
+pub fn update_tray_menu(app: &AppHandle) -> Result<()> {
+    #[cfg(not(any(target_os = "android", target_os = "ios")))]
+    {
+        if let Some(tray) = app.tray_by_id(TRAY_ID) {
+            tray.set_menu(Some(tray_menu(app)?))?;
+        }
+    }
+    Ok(())
+}
+
 #[tracing::instrument(level = "debug", skip(app))]
 pub fn build_tray_menu(app: &App) -> Result<()> {
     #[cfg(not(any(target_os = "android", target_os = "ios")))]
     {
-        use tauri::menu::MenuBuilder;
         use tauri::tray::{MouseButton, MouseButtonState, TrayIconEvent};
-        let menu = MenuBuilder::new(app)
-            .icon(
-                "show",
-                "Show App",
-                app.default_window_icon().cloned().unwrap(),
-            )
-            .icon("play", "Play", app.default_window_icon().cloned().unwrap())
-            .icon(
-                "pause",
-                "Pause",
-                app.default_window_icon().cloned().unwrap(),
-            )
-            .icon("next", "Next", app.default_window_icon().cloned().unwrap())
-            .icon("prev", "Prev", app.default_window_icon().cloned().unwrap())
-            .icon("quit", "Quit", app.default_window_icon().cloned().unwrap())
-            .build()?;
+        use types::ui::player_details::SleepTimerTrigger;
+
+        use crate::queue::QueueService;
+        use crate::sleep_timer::{SleepTimerHolder, DEFAULT_FADE_OUT};
 
-        tauri::tray::TrayIconBuilder::new()
+        let menu = tray_menu(app.handle())?;
+
+        tauri::tray::TrayIconBuilder::with_id(TRAY_ID)
             .menu(&menu)
             .on_menu_event(move |app, event| match event.id().as_ref() {
                 "show" => {
@@ -317,14 +360,39 @@ pub fn build_tray_menu(app: &App) -> Result<()> {
                     let _ = app.emit("media_button_press", (1, Value::Null));
                 }
                 "next" => {
-                    let _ = app.emit("media_button_press", (6, Value::Null));
+                    if let Err(e) = app.state::<QueueService>().next_song() {
+                        tracing::error!("Failed to skip to next song: {:?}", e);
+                    }
                 }
                 "prev" => {
-                    let _ = app.emit("media_button_press", (7, Value::Null));
+                    if let Err(e) = app.state::<QueueService>().prev_song() {
+                        tracing::error!("Failed to skip to previous song: {:?}", e);
+                    }
                 }
                 "quit" => {
                     app.exit(0);
                 }
+                id if id.starts_with("sleep_") => {
+                    let trigger = match id {
+                        "sleep_15" => Some(SleepTimerTrigger::Duration(15 * 60)),
+                        "sleep_30" => Some(SleepTimerTrigger::Duration(30 * 60)),
+                        "sleep_60" => Some(SleepTimerTrigger::Duration(60 * 60)),
+                        "sleep_track" => Some(SleepTimerTrigger::EndOfTrack),
+                        "sleep_queue" => Some(SleepTimerTrigger::EndOfQueue),
+                        _ => None,
+                    };
+
+                    let sleep_timer = app.state::<SleepTimerHolder>();
+                    let res = match trigger {
+                        Some(trigger) => sleep_timer
+                            .set_sleep_timer(app.clone(), trigger, DEFAULT_FADE_OUT)
+                            .map(|_| ()),
+                        None => sleep_timer.cancel_sleep_timer(app.clone()),
+                    };
+                    if let Err(e) = res {
+                        tracing::error!("Failed to update sleep timer: {:?}", e);
+                    }
+                }
                 _ => (),
             })
             .on_tray_icon_event(|tray, event| {
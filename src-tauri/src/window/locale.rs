@@ -0,0 +1,85 @@
+// Moosync
+// Copyright (C) 2024, 2025  Moosync <support@moosync.app>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use preferences::preferences::PreferenceConfig;
+use serde_json::Value;
+use tauri::{AppHandle, Manager, State};
+use types::preferences::CheckboxPreference;
+
+const FALLBACK_LOCALE: &str = "en_US";
+
+macro_rules! embed_locales {
+    ($($locale:literal),* $(,)?) => {
+        fn locale_source(locale: &str) -> Option<&'static str> {
+            match locale {
+                $($locale => Some(include_str!(concat!("../../../locales/", $locale, ".json"))),)*
+                _ => None,
+            }
+        }
+    };
+}
+
+embed_locales!(
+    "af_ZA", "ar_SA", "ca_ES", "cs_CZ", "da_DK", "de_DE", "el_GR", "en_US", "es_ES", "fi_FI",
+    "fr_FR", "he_IL", "hi_IN", "hu_HU", "it_IT", "ja_JP", "ko_KR", "nl_NL", "no_NO", "pl_PL",
+    "pt_BR", "pt_PT", "ro_RO", "ru_RU", "sr_SP", "sv_SE", "tr_TR", "uk_UA", "vi_VN", "zh_CN",
+    "zh_TW",
+);
+
+/// Looks up the UI translations for native menus, which are built before
+/// the webview has loaded
+pub struct Translator {
+    locale: Value,
+    fallback: Value,
+}
+
+impl Translator {
+    #[tracing::instrument(level = "debug", skip(app))]
+    pub fn new(app: &AppHandle) -> Self {
+        let preferences: State<PreferenceConfig> = app.state();
+        let locale = preferences
+            .load_selective::<Vec<CheckboxPreference>>("i18n_language".into())
+            .ok()
+            .and_then(|langs| langs.into_iter().find(|l| l.enabled))
+            .map(|l| l.key)
+            .unwrap_or_else(|| FALLBACK_LOCALE.to_string());
+
+        Self {
+            locale: parse_locale(&locale),
+            fallback: parse_locale(FALLBACK_LOCALE),
+        }
+    }
+
+    /// Returns the string at the dotted key, or the key itself if no locale has it
+    pub fn t(&self, key: &str) -> String {
+        lookup(&self.locale, key)
+            .or_else(|| lookup(&self.fallback, key))
+            .unwrap_or_else(|| key.to_string())
+    }
+}
+
+fn parse_locale(locale: &str) -> Value {
+    locale_source(locale)
+        .and_then(|source| serde_json::from_str(source).ok())
+        .unwrap_or_default()
+}
+
+fn lookup(locale: &Value, key: &str) -> Option<String> {
+    key.split('.')
+        .try_fold(locale, |value, part| value.get(part))
+        .and_then(|value| value.as_str())
+        .map(|value| value.to_string())
+}
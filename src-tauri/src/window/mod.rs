@@ -15,3 +15,4 @@
 // along with this program.  If not, see <http://www.gnu.org/licenses/>.
 
 pub mod handler;
+pub mod locale;
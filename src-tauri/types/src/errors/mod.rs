@@ -164,6 +164,9 @@ pub enum MoosyncError {
     RodioSeekError(#[from] rodio::source::SeekError),
     #[cfg(feature = "core")]
     #[error(transparent)]
+    RodioPlayError(#[from] rodio::PlayError),
+    #[cfg(feature = "core")]
+    #[error(transparent)]
     UTF8Error(#[from] str::Utf8Error),
 }
 
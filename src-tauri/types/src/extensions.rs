@@ -32,10 +32,11 @@ use crate::{
     ui::{
         extensions::{
             AccountLoginArgs, AddToPlaylistRequest, ContextMenuReturnType, CustomRequestReturnType,
-            ExtensionAccountDetail, ExtensionDetail, ExtensionExtraEvent, ExtensionExtraEventArgs,
-            ExtensionProviderScope, ExtensionUIRequest, PackageNameArgs, PlaybackDetailsReturnType,
-            PlaylistAndSongsReturnType, PlaylistReturnType, PreferenceData,
-            RecommendationsReturnType, SearchReturnType, SongReturnType,
+            ExtensionAccountDetail, ExtensionCapability, ExtensionDetail, ExtensionEventKind,
+            ExtensionExtraEvent, ExtensionExtraEventArgs, ExtensionPermissions,
+            ExtensionProviderScope, ExtensionUIRequest, LyricsReturnType, PackageNameArgs,
+            PlaybackDetailsReturnType, PlaylistAndSongsReturnType, PlaylistReturnType,
+            PreferenceData, RecommendationsReturnType, SearchReturnType, SongReturnType,
             SongsWithPageTokenReturnType,
         },
         player_details::PlayerState,
@@ -91,6 +92,8 @@ pub enum ExtensionExtraEventResponse {
     RequestedSongContextMenu(Vec<ContextMenuReturnType>),
     RequestedPlaylistContextMenu(Vec<ContextMenuReturnType>),
     ContextMenuAction,
+    // Lyrics picked from the replies of several extensions, never sent by extensions
+    RequestedLyricsFrom(LyricsReturnType),
 }
 
 #[tracing::instrument(level = "debug", skip(field))]
@@ -410,6 +413,7 @@ pub enum RunnerCommand {
     GetExtensionIcon(PackageNameArgs),
     ToggleExtensionStatus(PackageNameArgs),
     RemoveExtension(PackageNameArgs),
+    ReloadExtension(PackageNameArgs),
     StopProcess,
     GetDisplayName(PackageNameArgs),
 }
@@ -431,6 +435,9 @@ impl TryFrom<(&str, &Value)> for RunnerCommand {
             "removeExtension" => Ok(Self::RemoveExtension(
                 serde_json::from_value(data.clone()).unwrap(),
             )),
+            "reloadExtension" => Ok(Self::ReloadExtension(
+                serde_json::from_value(data.clone()).unwrap(),
+            )),
             "stopProcess" => Ok(Self::StopProcess),
             "getDisplayName" => Ok(Self::GetDisplayName(
                 serde_json::from_value(data.clone()).unwrap(),
@@ -445,6 +452,9 @@ impl TryFrom<(&str, &Value)> for RunnerCommand {
 pub struct ManifestPermissions {
     pub hosts: Vec<String>,
     pub paths: HashMap<String, PathBuf>,
+    /// Manifests written before capabilities existed leave this out and ask for all of them
+    #[serde(default)]
+    pub capabilities: Option<Vec<ExtensionCapability>>,
 }
 
 #[derive(Debug, Deserialize, Clone)]
@@ -458,6 +468,37 @@ pub struct ExtensionManifest {
     pub version: String,
     pub icon: String,
     pub permissions: Option<ManifestPermissions>,
+    /// Settings rendered by the host, validated before use
+    #[serde(default)]
+    pub preferences: Vec<Value>,
+    /// Manifests without subscriptions receive every event
+    #[serde(default)]
+    pub subscriptions: Option<Vec<ExtensionEventKind>>,
+}
+
+impl ExtensionManifest {
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub fn requested_permissions(&self) -> ExtensionPermissions {
+        let Some(permissions) = &self.permissions else {
+            return ExtensionPermissions {
+                capabilities: ExtensionCapability::ALL.to_vec(),
+                ..Default::default()
+            };
+        };
+
+        let mut capabilities = permissions
+            .capabilities
+            .clone()
+            .unwrap_or_else(|| ExtensionCapability::ALL.to_vec());
+        capabilities.sort();
+        capabilities.dedup();
+
+        ExtensionPermissions {
+            hosts: permissions.hosts.clone(),
+            paths: permissions.paths.clone().into_iter().collect(),
+            capabilities,
+        }
+    }
 }
 
 #[derive(Debug, Deserialize, Serialize, ToBytes, FromBytes, Clone)]
@@ -483,6 +524,8 @@ pub enum MainCommand {
     OpenExternalUrl(String),
     UpdateAccounts(Option<String>),
     ExtensionsUpdated(),
+    Subscribe(Vec<ExtensionEventKind>),
+    Unsubscribe(Vec<ExtensionEventKind>),
 }
 
 #[derive(Debug, Deserialize, Serialize, ToBytes, FromBytes, Clone)]
@@ -509,10 +552,45 @@ pub enum MainCommandResponse {
     OpenExternalUrl(bool),
     UpdateAccounts(bool),
     ExtensionsUpdated(bool),
+    Subscribe(bool),
+    Unsubscribe(bool),
 }
 
 impl MainCommand {
-    #[cfg(any(not(feature = "extensions"), feature = "extensions-core"))]
+    /// Capability an extension needs to send this command, if any
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub fn required_capability(&self) -> Option<ExtensionCapability> {
+        match self {
+            MainCommand::GetSong(_) | MainCommand::GetEntity(_) => {
+                Some(ExtensionCapability::LibraryRead)
+            }
+            MainCommand::AddSongs(_)
+            | MainCommand::RemoveSong(_)
+            | MainCommand::UpdateSong(_)
+            | MainCommand::AddPlaylist(_)
+            | MainCommand::AddToPlaylist(_) => Some(ExtensionCapability::LibraryWrite),
+            MainCommand::GetPreference(_) | MainCommand::SetPreference(_) => {
+                Some(ExtensionCapability::Preferences)
+            }
+            MainCommand::GetSecure(_) | MainCommand::SetSecure(_) => {
+                Some(ExtensionCapability::SecureStorage)
+            }
+            MainCommand::RegisterOAuth(_) | MainCommand::OpenExternalUrl(_) => {
+                Some(ExtensionCapability::OpenUrl)
+            }
+            MainCommand::GetCurrentSong()
+            | MainCommand::GetPlayerState()
+            | MainCommand::GetVolume()
+            | MainCommand::GetTime()
+            | MainCommand::GetQueue()
+            | MainCommand::UpdateAccounts(_)
+            | MainCommand::ExtensionsUpdated()
+            | MainCommand::Subscribe(_)
+            | MainCommand::Unsubscribe(_) => None,
+        }
+    }
+
+    #[cfg(any(feature = "core", feature = "extensions-core"))]
     #[tracing::instrument(level = "debug", skip(self))]
     pub fn to_request(&self) -> MoosyncResult<GenericExtensionHostRequest<MainCommand>> {
         Ok(GenericExtensionHostRequest {
@@ -521,7 +599,7 @@ impl MainCommand {
         })
     }
 
-    #[cfg(any(not(feature = "extensions"), feature = "extensions-core"))]
+    #[cfg(any(feature = "core", feature = "extensions-core"))]
     pub fn to_ui_request(&mut self) -> MoosyncResult<ExtensionUIRequest> {
         let (r#type, data) = match self {
             MainCommand::GetSong(options) => ("getSongs", serde_json::to_value(options)?),
@@ -558,6 +636,9 @@ impl MainCommand {
             MainCommand::OpenExternalUrl(url) => ("openExternal", Value::String(url.clone())),
             MainCommand::UpdateAccounts(key) => ("updateAccounts", serde_json::to_value(key)?),
             MainCommand::ExtensionsUpdated() => ("extensionsUpdated", Value::Null),
+            MainCommand::Subscribe(_) | MainCommand::Unsubscribe(_) => {
+                return Err("Subscriptions are handled by the extension runner".into())
+            }
         };
 
         Ok(ExtensionUIRequest {
@@ -632,5 +713,6 @@ pub enum RunnerCommandResp {
     ExtensionList(Vec<ExtensionDetail>),
     ExtensionIcon(Option<String>),
     ExtensionName(Option<String>),
+    ExtensionStatus(bool),
     Empty(),
 }
@@ -25,8 +25,14 @@ pub mod errors;
 #[cfg(not(feature = "extensions"))]
 pub mod mpris;
 pub mod preferences;
+pub mod queue;
+pub mod radio;
+#[cfg(not(feature = "extensions"))]
+pub mod scrobble;
 #[cfg(feature = "core")]
 pub mod schema;
+#[cfg(not(feature = "extensions"))]
+pub mod shortcuts;
 pub mod songs;
 
 pub mod providers;
@@ -36,7 +42,12 @@ pub mod ui;
 #[cfg(feature = "core")]
 pub mod oauth;
 
-#[cfg(any(feature = "core", feature = "extensions", feature = "extensions-core"))]
+#[cfg(any(
+    feature = "core",
+    feature = "extensions",
+    feature = "extensions-core",
+    feature = "extensions-sdk"
+))]
 pub mod extensions;
 
 #[cfg(not(feature = "extensions"))]
@@ -16,6 +16,8 @@
 
 use serde::{Deserialize, Serialize};
 
+use crate::{songs::Song, ui::player_details::RepeatModes};
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct MprisPlayerDetails {
     pub id: Option<String>,
@@ -30,3 +32,40 @@ pub struct MprisPlayerDetails {
     pub duration: Option<f64>,
     pub thumbnail: Option<String>,
 }
+
+impl From<&Song> for MprisPlayerDetails {
+    fn from(song: &Song) -> Self {
+        let artists = song.artists.as_ref().map(|artists| {
+            artists
+                .iter()
+                .filter_map(|a| a.artist_name.clone())
+                .collect::<Vec<_>>()
+                .join(", ")
+        });
+        Self {
+            id: song.song._id.clone(),
+            title: song.song.title.clone(),
+            artist_name: artists,
+            album_name: song.album.as_ref().and_then(|a| a.album_name.clone()),
+            album_artist: song.album.as_ref().and_then(|a| a.album_artist.clone()),
+            genres: song
+                .genre
+                .as_ref()
+                .map(|g| g.iter().filter_map(|g| g.genre_name.clone()).collect()),
+            duration: song.song.duration,
+            thumbnail: song.song.song_cover_path_high.clone(),
+        }
+    }
+}
+
+/// Queue details shown by the OS media controls
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct MprisQueueDetails {
+    pub repeat: RepeatModes,
+    pub shuffle: bool,
+    pub can_go_next: bool,
+    pub can_go_previous: bool,
+    pub current_index: usize,
+    /// The current song followed by the upcoming ones
+    pub tracks: Vec<MprisPlayerDetails>,
+}
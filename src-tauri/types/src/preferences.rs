@@ -45,6 +45,9 @@ pub enum PreferenceTypes {
     TextField,
     InfoField,
     Dropdown,
+    Shortcuts,
+    AudioCache,
+    RemoteToken,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -65,6 +68,8 @@ pub struct Page {
 pub enum InputType {
     Text,
     Number,
+    /// Hidden text which is kept in secure storage
+    Password,
 }
 
 #[derive(Debug, Serialize, Clone, Deserialize)]
@@ -0,0 +1,143 @@
+// Moosync
+// Copyright (C) 2024, 2025  Moosync <support@moosync.app>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::collections::HashMap;
+
+use bitcode::{Decode, Encode};
+#[cfg(feature = "core")]
+use diesel::{AsChangeset, Insertable, Queryable};
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "core")]
+use crate::schema::{play_queue, queue_state, saved_queues};
+use crate::{songs::Song, ui::player_details::RepeatModes};
+
+#[derive(Debug, Default, PartialEq, Clone, Serialize, Deserialize, Encode, Decode)]
+pub struct Queue {
+    pub song_queue: Vec<String>,
+    pub current_index: usize,
+    pub data: HashMap<String, Song>,
+}
+
+impl Queue {
+    pub fn current_song(&self) -> Option<&Song> {
+        self.song_queue
+            .get(self.current_index)
+            .and_then(|id| self.data.get(id))
+    }
+}
+
+#[derive(Debug, Default, PartialEq, Clone, Serialize, Deserialize)]
+pub struct ShuffleState {
+    pub enabled: bool,
+    /// Prefer a song by other artists than the current one when picking the next
+    pub avoid_same_artist: bool,
+    /// Queue order from before shuffling, restored when shuffle is turned off
+    pub original_order: Vec<String>,
+}
+
+#[derive(Debug, Default, PartialEq, Clone, Serialize, Deserialize)]
+pub struct QueueState {
+    pub queue: Queue,
+    pub repeat: RepeatModes,
+    pub shuffle: ShuffleState,
+    /// Ids of songs in the order they were played, the last one is the current song
+    pub history: Vec<String>,
+    /// Queue similar songs once the queue runs out
+    pub autoplay: bool,
+}
+
+/// Sent to the UI every time the queue service changes its state
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QueueChanged {
+    pub state: QueueState,
+    /// Reload the current song even if it is unchanged, eg. when repeating
+    pub force_load: bool,
+}
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "core", derive(Insertable, Queryable))]
+#[cfg_attr(feature = "core", diesel(table_name = play_queue))]
+pub struct QueueItem {
+    pub position: i32,
+    pub song_id: String,
+    /// Song serialized as JSON, queued songs don't have to exist in the library
+    pub song: String,
+}
+
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "core", derive(Insertable, Queryable, AsChangeset))]
+#[cfg_attr(feature = "core", diesel(table_name = queue_state))]
+pub struct QueryableQueueState {
+    pub id: i32,
+    pub current_index: i32,
+    pub repeat_mode: i32,
+    pub shuffle: bool,
+    pub avoid_same_artist: bool,
+    /// Song ids serialized as JSON
+    pub original_order: String,
+    /// Song ids serialized as JSON
+    pub history: String,
+    pub autoplay: bool,
+}
+
+/// Named snapshot of a queue which can be swapped in later
+#[derive(Debug, Default, PartialEq, Clone, Serialize, Deserialize)]
+pub struct SavedQueue {
+    pub id: String,
+    pub name: String,
+    pub songs: Vec<Song>,
+    pub current_index: usize,
+    /// Seconds into the current song
+    pub position: f64,
+    pub repeat: RepeatModes,
+    /// Unix time in milliseconds
+    pub updated_at: i64,
+}
+
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "core", derive(Insertable, Queryable, AsChangeset))]
+#[cfg_attr(feature = "core", diesel(table_name = saved_queues))]
+pub struct QueryableSavedQueue {
+    pub id: String,
+    pub name: String,
+    /// Songs serialized as JSON
+    pub songs: String,
+    pub current_index: i32,
+    pub position: f64,
+    pub repeat_mode: i32,
+    pub updated_at: i64,
+}
+
+impl From<RepeatModes> for i32 {
+    fn from(value: RepeatModes) -> Self {
+        match value {
+            RepeatModes::None => 0,
+            RepeatModes::Once => 1,
+            RepeatModes::Loop => 2,
+        }
+    }
+}
+
+impl From<i32> for RepeatModes {
+    fn from(value: i32) -> Self {
+        match value {
+            1 => RepeatModes::Once,
+            2 => RepeatModes::Loop,
+            _ => RepeatModes::None,
+        }
+    }
+}
@@ -0,0 +1,64 @@
+// Moosync
+// Copyright (C) 2024, 2025  Moosync <support@moosync.app>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+#[cfg(feature = "core")]
+use diesel::{AsChangeset, Identifiable, Insertable, Queryable};
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "core")]
+use crate::schema::radio_stations;
+
+use crate::songs::{QueryableSong, Song, SongType};
+
+#[derive(Deserialize, Serialize, Default, Clone, Debug, PartialEq)]
+#[cfg_attr(
+    feature = "core",
+    derive(Insertable, Queryable, Identifiable, AsChangeset)
+)]
+#[cfg_attr(feature = "core", diesel(table_name = radio_stations))]
+#[cfg_attr(feature = "core", diesel(primary_key(station_id)))]
+pub struct QueryableStation {
+    pub station_id: Option<String>,
+    pub station_name: String,
+    pub station_url: String,
+    pub homepage: Option<String>,
+    pub cover: Option<String>,
+    pub genre: Option<String>,
+    pub codec: Option<String>,
+    pub bitrate: Option<f64>,
+    pub date_added: Option<i64>,
+}
+
+impl From<QueryableStation> for Song {
+    fn from(station: QueryableStation) -> Self {
+        Song {
+            song: QueryableSong {
+                _id: station.station_id,
+                title: Some(station.station_name),
+                url: station.homepage,
+                playback_url: Some(station.station_url),
+                song_cover_path_high: station.cover.clone(),
+                song_cover_path_low: station.cover,
+                codec: station.codec,
+                bitrate: station.bitrate,
+                date_added: station.date_added,
+                type_: SongType::URL,
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+    }
+}
@@ -137,6 +137,70 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    radio_stations (station_id) {
+        station_id -> Nullable<Text>,
+        station_name -> Text,
+        station_url -> Text,
+        homepage -> Nullable<Text>,
+        cover -> Nullable<Text>,
+        genre -> Nullable<Text>,
+        codec -> Nullable<Text>,
+        bitrate -> Nullable<Double>,
+        date_added -> Nullable<BigInt>,
+    }
+}
+
+diesel::table! {
+    pending_scrobbles (id) {
+        id -> Integer,
+        service -> Text,
+        track -> Text,
+        attempts -> Integer,
+    }
+}
+
+diesel::table! {
+    play_queue (position) {
+        position -> Integer,
+        song_id -> Text,
+        song -> Text,
+    }
+}
+
+diesel::table! {
+    queue_state (id) {
+        id -> Integer,
+        current_index -> Integer,
+        repeat_mode -> Integer,
+        shuffle -> Bool,
+        avoid_same_artist -> Bool,
+        original_order -> Text,
+        history -> Text,
+        autoplay -> Bool,
+    }
+}
+
+diesel::table! {
+    resume_points (song_id) {
+        song_id -> Text,
+        position -> Double,
+        updated_at -> BigInt,
+    }
+}
+
+diesel::table! {
+    saved_queues (id) {
+        id -> Text,
+        name -> Text,
+        songs -> Text,
+        current_index -> Integer,
+        position -> Double,
+        repeat_mode -> Integer,
+        updated_at -> BigInt,
+    }
+}
+
 diesel::allow_tables_to_appear_in_same_query!(
     album_bridge,
     albums,
@@ -146,6 +210,12 @@ diesel::allow_tables_to_appear_in_same_query!(
     artists,
     genre_bridge,
     genres,
+    pending_scrobbles,
+    play_queue,
     playlist_bridge,
     playlists,
+    queue_state,
+    radio_stations,
+    resume_points,
+    saved_queues,
 );
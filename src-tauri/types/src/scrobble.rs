@@ -0,0 +1,105 @@
+// Moosync
+// Copyright (C) 2024, 2025  Moosync <support@moosync.app>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+#[cfg(feature = "core")]
+use diesel::{Insertable, Queryable};
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "core")]
+use crate::schema::pending_scrobbles;
+use crate::songs::Song;
+
+/// A listen as submitted to scrobbling services
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ScrobbleTrack {
+    pub title: String,
+    pub artists: Vec<String>,
+    pub album: Option<String>,
+    pub album_artist: Option<String>,
+    /// Seconds
+    pub duration: Option<f64>,
+    /// Unix time in seconds at which the song started playing
+    pub timestamp: i64,
+}
+
+impl ScrobbleTrack {
+    /// Songs without a title or artist can't be scrobbled
+    pub fn from_song(song: &Song, timestamp: i64) -> Option<Self> {
+        let title = song.song.title.clone().filter(|t| !t.trim().is_empty())?;
+        let artists = song
+            .artists
+            .iter()
+            .flatten()
+            .filter_map(|a| a.artist_name.clone())
+            .filter(|a| !a.trim().is_empty())
+            .collect::<Vec<_>>();
+        if artists.is_empty() {
+            return None;
+        }
+
+        Some(Self {
+            title,
+            artists,
+            album: song.album.as_ref().and_then(|a| a.album_name.clone()),
+            album_artist: song.album.as_ref().and_then(|a| a.album_artist.clone()),
+            duration: song.song.duration.filter(|d| *d > 0f64),
+            timestamp,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum ScrobbleService {
+    LastFm,
+    ListenBrainz,
+}
+
+impl ScrobbleService {
+    pub fn key(&self) -> &'static str {
+        match self {
+            Self::LastFm => "lastfm",
+            Self::ListenBrainz => "listenbrainz",
+        }
+    }
+
+    pub fn from_key(value: &str) -> Option<Self> {
+        match value {
+            "lastfm" => Some(Self::LastFm),
+            "listenbrainz" => Some(Self::ListenBrainz),
+            _ => None,
+        }
+    }
+}
+
+/// Listen which couldn't be submitted and is retried later
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "core", derive(Queryable))]
+#[cfg_attr(feature = "core", diesel(table_name = pending_scrobbles))]
+pub struct PendingScrobble {
+    pub id: i32,
+    pub service: String,
+    /// ScrobbleTrack serialized as JSON
+    pub track: String,
+    pub attempts: i32,
+}
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "core", derive(Insertable))]
+#[cfg_attr(feature = "core", diesel(table_name = pending_scrobbles))]
+pub struct NewPendingScrobble {
+    pub service: String,
+    pub track: String,
+}
@@ -0,0 +1,71 @@
+// Moosync
+// Copyright (C) 2024, 2025  Moosync <support@moosync.app>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+/// Player actions which can be bound to a global shortcut
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ShortcutAction {
+    Play,
+    Pause,
+    Toggle,
+    Stop,
+    Next,
+    Prev,
+    SeekForward,
+    SeekBackward,
+    VolumeUp,
+    VolumeDown,
+}
+
+impl ShortcutAction {
+    pub const ALL: [ShortcutAction; 10] = [
+        Self::Play,
+        Self::Pause,
+        Self::Toggle,
+        Self::Stop,
+        Self::Next,
+        Self::Prev,
+        Self::SeekForward,
+        Self::SeekBackward,
+        Self::VolumeUp,
+        Self::VolumeDown,
+    ];
+}
+
+/// Saved under the global_shortcuts preference. Actions without a chord are not bound.
+pub type ShortcutBindings = HashMap<ShortcutAction, String>;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", content = "data", rename_all = "snake_case")]
+pub enum ShortcutConflictReason {
+    /// The chord could not be parsed
+    Invalid(String),
+    /// The same chord is bound to another action
+    Duplicate(ShortcutAction),
+    /// The chord is taken by another application or rejected by the OS
+    Unavailable(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ShortcutConflict {
+    pub action: ShortcutAction,
+    pub shortcut: String,
+    pub reason: ShortcutConflictReason,
+}
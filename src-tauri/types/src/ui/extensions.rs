@@ -14,7 +14,7 @@
 // You should have received a copy of the GNU General Public License
 // along with this program.  If not, see <http://www.gnu.org/licenses/>.
 
-use std::hash;
+use std::{collections::BTreeMap, hash, path::PathBuf};
 
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
@@ -45,6 +45,9 @@ pub struct FetchedExtensionManifest {
     pub description: Option<String>,
     pub url: String,
     pub version: String,
+    /// Hex SHA-256 of the package at `url`
+    #[serde(default)]
+    pub sha256: Option<String>,
 }
 
 #[derive(Deserialize, Serialize, Debug, Clone, Eq)]
@@ -61,6 +64,82 @@ pub struct ExtensionDetail {
     pub extension_path: String,
     pub extension_icon: Option<String>,
     pub active: bool,
+    pub enabled: bool,
+    #[serde(default)]
+    pub disabled_reason: Option<String>,
+    /// Permissions the user still has to approve before the extension can start
+    #[serde(default)]
+    pub pending_permissions: Option<ExtensionPermissions>,
+    /// Loaded in place from an unpacked directory and reloaded when it changes
+    #[serde(default)]
+    pub dev: bool,
+}
+
+/// Where the preferences of an extension are stored. Dots in the package name
+/// are percent encoded so they don't nest, and neither collide with other names
+pub fn extension_preference_key(package_name: &str, key: &str) -> String {
+    let package_name = package_name.replace('%', "%25").replace('.', "%2E");
+    format!("extensions.{}.{}", package_name, key)
+}
+
+/// Extensions stored their preferences under the bare key before they were
+/// scoped to their package. Returns that key for a scoped one
+pub fn legacy_extension_preference_key(key: &str) -> Option<&str> {
+    key.strip_prefix("extensions.")?
+        .split_once('.')
+        .map(|(_, key)| key)
+}
+
+/// Host features an extension has to be granted before it can use them
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[serde(rename_all = "camelCase")]
+pub enum ExtensionCapability {
+    LibraryRead,
+    LibraryWrite,
+    Preferences,
+    SecureStorage,
+    OpenUrl,
+    Sockets,
+}
+
+impl ExtensionCapability {
+    pub const ALL: [ExtensionCapability; 6] = [
+        ExtensionCapability::LibraryRead,
+        ExtensionCapability::LibraryWrite,
+        ExtensionCapability::Preferences,
+        ExtensionCapability::SecureStorage,
+        ExtensionCapability::OpenUrl,
+        ExtensionCapability::Sockets,
+    ];
+}
+
+/// Permissions requested by, or granted to, an extension
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct ExtensionPermissions {
+    #[serde(default)]
+    pub hosts: Vec<String>,
+    /// Host paths, which may contain {ENV_VAR} placeholders, mapped to paths inside the extension
+    #[serde(default)]
+    pub paths: BTreeMap<String, PathBuf>,
+    #[serde(default)]
+    pub capabilities: Vec<ExtensionCapability>,
+}
+
+impl ExtensionPermissions {
+    /// Whether everything in `requested` is already part of these permissions
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub fn covers(&self, requested: &ExtensionPermissions) -> bool {
+        requested.hosts.iter().all(|h| self.hosts.contains(h))
+            && requested
+                .paths
+                .iter()
+                .all(|(k, v)| self.paths.get(k) == Some(v))
+            && requested
+                .capabilities
+                .iter()
+                .all(|c| self.capabilities.contains(c))
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -136,6 +215,66 @@ pub enum ExtensionExtraEvent {
     ContextMenuAction([String; 1]),
 }
 
+impl ExtensionExtraEvent {
+    /// Kind of notification, None for requests which every extension is asked to answer
+    pub fn subscription_kind(&self) -> Option<ExtensionEventKind> {
+        Some(match self {
+            ExtensionExtraEvent::SongQueueChanged(_) => ExtensionEventKind::SongQueueChanged,
+            ExtensionExtraEvent::Seeked(_) => ExtensionEventKind::Seeked,
+            ExtensionExtraEvent::VolumeChanged(_) => ExtensionEventKind::VolumeChanged,
+            ExtensionExtraEvent::PlayerStateChanged(_) => ExtensionEventKind::PlayerStateChanged,
+            ExtensionExtraEvent::SongChanged(_) => ExtensionEventKind::SongChanged,
+            ExtensionExtraEvent::SongAdded(_) => ExtensionEventKind::SongAdded,
+            ExtensionExtraEvent::SongRemoved(_) => ExtensionEventKind::SongRemoved,
+            ExtensionExtraEvent::PlaylistAdded(_) => ExtensionEventKind::PlaylistAdded,
+            ExtensionExtraEvent::PlaylistRemoved(_) => ExtensionEventKind::PlaylistRemoved,
+            ExtensionExtraEvent::Scrobble(_) => ExtensionEventKind::Scrobble,
+            _ => return None,
+        })
+    }
+}
+
+/// Notifications an extension can subscribe to
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[serde(rename_all = "camelCase")]
+pub enum ExtensionEventKind {
+    SongQueueChanged,
+    Seeked,
+    VolumeChanged,
+    PlayerStateChanged,
+    SongChanged,
+    SongAdded,
+    SongRemoved,
+    PlaylistAdded,
+    PlaylistRemoved,
+    Scrobble,
+}
+
+impl ExtensionEventKind {
+    pub const ALL: [ExtensionEventKind; 10] = [
+        ExtensionEventKind::SongQueueChanged,
+        ExtensionEventKind::Seeked,
+        ExtensionEventKind::VolumeChanged,
+        ExtensionEventKind::PlayerStateChanged,
+        ExtensionEventKind::SongChanged,
+        ExtensionEventKind::SongAdded,
+        ExtensionEventKind::SongRemoved,
+        ExtensionEventKind::PlaylistAdded,
+        ExtensionEventKind::PlaylistRemoved,
+        ExtensionEventKind::Scrobble,
+    ];
+
+    /// Events which can fire many times a second, only the latest of a burst is delivered
+    pub fn is_batched(&self) -> bool {
+        matches!(
+            self,
+            ExtensionEventKind::Seeked
+                | ExtensionEventKind::VolumeChanged
+                | ExtensionEventKind::SongQueueChanged
+        )
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct ExtensionExtraEventArgs {
@@ -206,6 +345,13 @@ pub struct RecommendationsReturnType {
     pub songs: Vec<Song>,
 }
 
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct LyricsReturnType {
+    pub lyrics: String,
+    pub package_name: String,
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct AddToPlaylistRequest {
     #[serde(rename = "playlistID")]
@@ -221,6 +367,40 @@ pub struct PreferenceData {
     pub default_value: Option<Value>,
 }
 
+/// Sent when an extension is disabled for crashing or timing out too often
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ExtensionDisabledEvent {
+    pub package_name: String,
+    pub reason: String,
+}
+
+/// A line logged by, or about, an extension
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ExtensionLogEntry {
+    /// Increases with every entry, across all extensions
+    pub id: u64,
+    pub package_name: String,
+    pub level: String,
+    pub message: String,
+    /// Milliseconds since the unix epoch
+    pub timestamp: u64,
+}
+
+/// Events an extension receives, for debugging
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ExtensionSubscriptionInfo {
+    pub package_name: String,
+    pub events: Vec<ExtensionEventKind>,
+    /// Extensions which never declared subscriptions receive every event
+    pub declared: bool,
+    pub delivered: u64,
+    /// Events not sent because the extension isn't subscribed to them
+    pub skipped: u64,
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct PackageNameArgs {
@@ -249,6 +429,9 @@ pub struct ContextMenuReturnType {
     pub name: String,
     pub icon: String,
     pub action_id: String,
+    // Set when the entries of several extensions are combined
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub package_name: Option<String>,
 }
 
 #[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq)]
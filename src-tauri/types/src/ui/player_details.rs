@@ -36,6 +36,7 @@ pub enum PlayerEvents {
     Ended,
     Loading,
     TimeUpdate(f64),
+    MetadataChanged(StreamMetadata),
 
     #[serde(
         deserialize_with = "deserialize_moosync_error",
@@ -44,6 +45,36 @@ pub enum PlayerEvents {
     Error(MoosyncError),
 }
 
+/// Now playing information sent inside a stream, eg. ICY StreamTitle of internet radios
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StreamMetadata {
+    pub title: Option<String>,
+    pub artist: Option<String>,
+}
+
+impl StreamMetadata {
+    /// Stations usually send "Artist - Title"
+    pub fn from_stream_title(stream_title: &str) -> Self {
+        let stream_title = stream_title.trim();
+        if stream_title.is_empty() {
+            return Self::default();
+        }
+
+        match stream_title.split_once(" - ") {
+            Some((artist, title)) if !artist.trim().is_empty() && !title.trim().is_empty() => {
+                Self {
+                    title: Some(title.trim().to_string()),
+                    artist: Some(artist.trim().to_string()),
+                }
+            }
+            _ => Self {
+                title: Some(stream_title.to_string()),
+                artist: None,
+            },
+        }
+    }
+}
+
 impl Clone for PlayerEvents {
     fn clone(&self) -> Self {
         match self {
@@ -52,6 +83,9 @@ impl Clone for PlayerEvents {
             PlayerEvents::Ended => PlayerEvents::Ended,
             PlayerEvents::Loading => PlayerEvents::Loading,
             PlayerEvents::TimeUpdate(time) => PlayerEvents::TimeUpdate(*time),
+            PlayerEvents::MetadataChanged(metadata) => {
+                PlayerEvents::MetadataChanged(metadata.clone())
+            }
             PlayerEvents::Error(error) => PlayerEvents::Error(error.to_string().clone().into()),
         }
     }
@@ -80,10 +114,42 @@ pub enum VolumeMode {
     PersistClamp,
 }
 
-#[derive(Debug, Default, PartialEq, Eq, Copy, Clone, Encode, Decode)]
+#[derive(Debug, Default, PartialEq, Eq, Copy, Clone, Encode, Decode, Serialize, Deserialize)]
 pub enum RepeatModes {
     #[default]
     None,
     Once,
     Loop,
 }
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct AudioCacheStats {
+    pub entries: usize,
+    pub size: u64,
+    pub max_size: u64,
+    pub hits: u64,
+    pub misses: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SleepTimerTrigger {
+    /// Seconds from when the timer was set
+    Duration(u64),
+    EndOfTrack,
+    EndOfQueue,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SleepTimer {
+    pub trigger: SleepTimerTrigger,
+    /// Seconds before the timer fires over which the volume is faded out
+    pub fade_out: u64,
+    /// Unix time in millis, only for duration timers
+    pub ends_at: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum SleepTimerEvent {
+    Changed(Option<SleepTimer>),
+    Expired,
+}
@@ -44,8 +44,9 @@ use leptos_router::{
 use leptos_use::use_event_listener;
 use serde::Serialize;
 use types::{
-    preferences::CheckboxPreference, ui::extensions::ExtensionUIRequest,
-    ui::player_details::PlayerState,
+    preferences::CheckboxPreference,
+    ui::extensions::ExtensionUIRequest,
+    ui::player_details::{PlayerState, SleepTimerEvent},
 };
 use wasm_bindgen::{JsCast, JsValue};
 use wasm_bindgen_futures::spawn_local;
@@ -63,6 +64,7 @@ use crate::{
         artists::{AllArtists, SingleArtist},
         genres::{AllGenres, SingleGenre},
         playlists::{AllPlaylists, SinglePlaylist},
+        radio::AllStations,
         search::Search,
         songs::AllSongs,
     },
@@ -134,6 +136,11 @@ pub fn MainApp() -> impl IntoView {
             "Genres",
             "/main/genres",
         ),
+        Tab::new(
+            move || t_string!(use_i18n(), sidebar.tabs.radio),
+            "Radio",
+            "/main/radio",
+        ),
         Tab::new(
             move || t_string!(use_i18n(), sidebar.tabs.explore),
             "Explore",
@@ -402,16 +409,49 @@ pub fn App() -> impl IntoView {
             6 => player_store.update(|p| p.next_song()),
             7 => player_store.update(|p| p.prev_song()),
             12 => player_store.update(|p| p.force_seek(value.unwrap_or_default())),
+            19 => player_store.update(|p| {
+                let duration = p
+                    .get_current_song()
+                    .and_then(|s| s.song.duration)
+                    .unwrap_or(f64::MAX);
+                let new_time = p.get_current_time() + value.unwrap_or_default();
+                p.force_seek(new_time.clamp(0f64, duration));
+            }),
             13 => player_store.update(|p| match p.get_player_state() {
                 PlayerState::Playing => p.set_state(PlayerState::Paused),
                 _ => p.set_state(PlayerState::Playing),
             }),
             15 => player_store.update(|p| p.set_volume(value.unwrap_or_default())),
+            20 => player_store.update(|p| {
+                let volume = p.get_raw_volume() + value.unwrap_or_default();
+                p.set_volume(volume.clamp(0f64, 100f64));
+            }),
 
             _ => {}
         }
     });
 
+    let unlisten_sleep_timer = listen_event("sleep_timer_event", move |data: JsValue| {
+        let payload = js_sys::Reflect::get(&data, &JsValue::from_str("payload")).unwrap();
+        let event: SleepTimerEvent = serde_wasm_bindgen::from_value(payload).unwrap();
+        let player_store: RwSignal<PlayerStore> = expect_context();
+
+        match event {
+            SleepTimerEvent::Changed(sleep_timer) => {
+                player_store.update(|p| p.set_sleep_timer(sleep_timer))
+            }
+            SleepTimerEvent::Expired => player_store.update(|p| p.sleep_timer_expired()),
+        }
+    });
+
+    let player_store: RwSignal<PlayerStore> = expect_context();
+    spawn_local(async move {
+        match crate::utils::invoke::get_sleep_timer().await {
+            Ok(sleep_timer) => player_store.update(|p| p.set_sleep_timer(sleep_timer)),
+            Err(e) => tracing::error!("Failed to get sleep timer: {:?}", e),
+        }
+    });
+
     let window = window();
     if let Err(e) = window.add_event_listener_with_callback("beforeunload", &watch_prefs_unlisten) {
         tracing::error!("Failed to set unmount hook: {:?}", e);
@@ -425,6 +465,18 @@ pub fn App() -> impl IntoView {
         tracing::error!("Failed to set unmount hook: {:?}", e);
     }
 
+    if let Err(e) = window.add_event_listener_with_callback("beforeunload", &unlisten_sleep_timer) {
+        tracing::error!("Failed to set unmount hook: {:?}", e);
+    }
+
+    // Playback commands from the command line wait until the listeners above exist
+    spawn_local(async move {
+        let res = emit("ui-ready", JsValue::NULL);
+        if let Err(e) = wasm_bindgen_futures::JsFuture::from(res).await {
+            tracing::error!("Failed to signal that the UI is ready: {:?}", e);
+        }
+    });
+
     check_for_updates();
 
     view! {
@@ -443,6 +495,7 @@ pub fn App() -> impl IntoView {
                                 <Route path=path!("albums/single") view=SingleAlbum />
                                 <Route path=path!("genres") view=AllGenres />
                                 <Route path=path!("genres/single") view=SingleGenre />
+                                <Route path=path!("radio") view=AllStations />
                                 <Route path=path!("search") view=Search />
                                 <Route path=path!("explore") view=Explore />
                             </ParentRoute>
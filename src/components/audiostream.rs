@@ -48,7 +48,10 @@ use crate::{
     store::{player_store::PlayerStore, provider_store::ProviderStore, ui_store::UiStore},
     utils::{
         extensions::send_extension_event,
-        invoke::{fetch_playback_url, increment_play_count, increment_play_time, update_song},
+        invoke::{
+            fetch_playback_url, get_resume_point, increment_play_count, increment_play_time,
+            update_song,
+        },
         mpris::set_metadata,
     },
 };
@@ -273,7 +276,13 @@ impl PlayerHolder {
         tracing::debug!("Active player: {}", player.key());
 
         let (resolver_tx, resolver_rx) = oneshot::channel();
-        player.load(src.unwrap(), autoplay, resolver_tx);
+        player.load(
+            src.unwrap(),
+            song.song._id.clone(),
+            song.song.type_,
+            autoplay,
+            resolver_tx,
+        );
 
         resolver_rx.await.expect("Load failed to resolve");
         tracing::debug!("Setting volume {}", current_volume);
@@ -374,8 +383,12 @@ impl PlayerHolder {
             store.set_state(state);
         });
 
-        let next_song_setter =
-            create_write_slice(player_store, move |store, _| match store.get_repeat() {
+        let next_song_setter = create_write_slice(player_store, move |store, _| {
+            if store.handle_sleep_timer_song_end() {
+                return;
+            }
+
+            match store.get_repeat() {
                 types::ui::player_details::RepeatModes::None => store.next_song(),
                 types::ui::player_details::RepeatModes::Once => {
                     if !store.get_has_repeated() {
@@ -394,12 +407,17 @@ impl PlayerHolder {
                     store.change_index(store.data.queue.current_index, true);
                     store.set_state(PlayerState::Playing);
                 }
-            });
+            }
+        });
 
         let player_time_setter = create_write_slice(player_store, move |store, time| {
             store.update_time(time);
         });
 
+        let stream_metadata_setter = create_write_slice(player_store, move |store, metadata| {
+            store.set_stream_metadata(metadata);
+        });
+
         let setter = move |ev: PlayerEvents| match ev {
             PlayerEvents::Play => player_state_setter.set(PlayerState::Playing),
             PlayerEvents::Pause => player_state_setter.set(PlayerState::Paused),
@@ -409,6 +427,7 @@ impl PlayerHolder {
                 next_song_setter.set(());
             }
             PlayerEvents::TimeUpdate(t) => player_time_setter.set(t),
+            PlayerEvents::MetadataChanged(metadata) => stream_metadata_setter.set(metadata),
             PlayerEvents::Error(err) => {
                 tracing::error!("Error playing song: {:?}", err);
                 let mut player_blacklist_sender = player_blacklist_sender.clone();
@@ -507,6 +526,8 @@ pub fn AudioStream() -> impl IntoView {
                 let updated_song = players
                     .load_audio(&current_song, current_volume.get_untracked(), player_store)
                     .await;
+                let loaded = updated_song.is_ok();
+                drop(players);
 
                 if let Ok(updated_song) = updated_song {
                     if let Some(updated_song) = updated_song {
@@ -519,6 +540,23 @@ pub fn AudioStream() -> impl IntoView {
                     tracing::error!("Failed to load Song {:?}", updated_song);
                 }
 
+                if let Some(id) = current_song.song._id.clone().filter(|_| loaded) {
+                    let restored = player_store
+                        .try_update(|p| p.song_loaded(id.clone()))
+                        .flatten();
+                    let resume = match restored {
+                        Some(time) => Some(time),
+                        None => get_resume_point(current_song.clone()).await.ok().flatten(),
+                    };
+                    let still_current = current_song_sig
+                        .get_untracked()
+                        .is_some_and(|s| s.song._id.as_ref() == Some(&id));
+                    if let (Some(time), true) = (resume, still_current) {
+                        tracing::debug!("Resuming {} from {}", id, time);
+                        player_store.update(|p| p.resume_from(time));
+                    }
+                }
+
                 if let Some(id) = last_song_sig {
                     let time_diff = Instant::now() - last_song_time.get_untracked();
                     last_song_time.set(Instant::now());
@@ -34,10 +34,12 @@ use crate::icons::play_icon::PlayIcon;
 use crate::icons::prev_track_icon::PrevTrackIcon;
 use crate::icons::repeat_icon::RepeatIcon;
 use crate::icons::shuffle_icon::ShuffleIcon;
+use crate::icons::sleep_timer_icon::SleepTimerIcon;
 use crate::icons::volume_icon::VolumeIcon;
 use crate::store::player_store::PlayerStore;
 use crate::store::ui_store::UiStore;
 use crate::utils::common::{format_duration, get_low_img};
+use crate::utils::context_menu::{create_context_menu, ShuffleContextMenu, SleepTimerContextMenu};
 
 #[tracing::instrument(level = "debug", skip())]
 #[component]
@@ -45,7 +47,7 @@ pub fn Details() -> impl IntoView {
     let player_store = use_context::<RwSignal<PlayerStore>>().unwrap();
 
     let current_song =
-        create_read_slice(player_store, |player_store| player_store.get_current_song());
+        create_read_slice(player_store, |player_store| player_store.get_now_playing());
 
     let title = RwSignal::new("-".to_string());
     let artists_list = RwSignal::<Vec<QueryableArtist>>::new(vec![]);
@@ -124,8 +126,9 @@ pub fn Controls(
     let is_fav = RwSignal::new(false);
     let (repeat_mode, toggle_repeat) =
         create_slice(player_store, |p| p.get_repeat(), |p, _| p.toggle_repeat());
-    let is_shuffle = RwSignal::new(true);
-    let shuffle_queue = create_write_slice(player_store, |p, _| p.shuffle_queue());
+    let (is_shuffle, toggle_shuffle) =
+        create_slice(player_store, |p| p.get_shuffle(), |p, _| p.toggle_shuffle());
+    let shuffle_menu = create_context_menu(ShuffleContextMenu {});
 
     let (current_time_sig, total_duration_sig) = if show_time {
         let current_time_sig = create_read_slice(player_store, |p| {
@@ -247,9 +250,13 @@ pub fn Controls(
             </div>
             <div class="col col-button shuffle-button">
                 <ShuffleIcon
-                    filled=is_shuffle.read_only()
+                    filled=is_shuffle
                     on:click=move |_| {
-                        shuffle_queue.set(());
+                        toggle_shuffle.set(());
+                    }
+                    on:contextmenu=move |ev| {
+                        ev.prevent_default();
+                        shuffle_menu.show(ev);
                     }
                 />
             </div>
@@ -314,6 +321,9 @@ pub fn ExtraControls() -> impl IntoView {
     let show_popup_volume = RwSignal::new(false);
     let interval = RwSignal::<Option<TimeoutHandle>>::new(None);
 
+    let sleep_timer_active = create_read_slice(player_store, |p| p.get_sleep_timer().is_some());
+    let sleep_timer_menu = create_context_menu(SleepTimerContextMenu {});
+
     view! {
         <div class="row no-gutters align-items-center justify-content-end">
             <div
@@ -400,6 +410,9 @@ pub fn ExtraControls() -> impl IntoView {
                     }
                 />
             </div>
+            <div class="col-auto ml-3" on:click=move |ev| sleep_timer_menu.show(ev)>
+                <SleepTimerIcon active=sleep_timer_active />
+            </div>
             <div class="col-auto expand-icon ml-3">
                 <ExpandIcon on:click=move |_| { ui_store.update(move |s| s.toggle_show_queue()) } />
             </div>
@@ -17,6 +17,7 @@
 use leptos::html::Div;
 use leptos::task::spawn_local;
 use leptos::{component, prelude::*, view, IntoView};
+use leptos_i18n::t;
 use leptos_virtual_scroller::VirtualScroller;
 use std::sync::Arc;
 use types::songs::{Song, SongType};
@@ -28,6 +29,7 @@ use web_sys::{ScrollBehavior, ScrollToOptions};
 use crate::components::artist_list::ArtistList;
 use crate::components::audiostream::AudioStream;
 use crate::components::musicbar_components::{Controls, Slider};
+use crate::i18n::use_i18n;
 use crate::icons::song_default_icon::SongDefaultIcon;
 use crate::modals::new_playlist_modal::PlaylistModalState;
 use crate::store::modal_store::{ModalStore, Modals};
@@ -411,6 +413,7 @@ pub fn MusicInfoMobile(
 #[tracing::instrument(level = "debug", skip(show, node_ref))]
 #[component]
 pub fn MusicInfo(#[prop()] show: Signal<bool>, #[prop()] node_ref: NodeRef<Div>) -> impl IntoView {
+    let i18n = use_i18n();
     let player_store = expect_context::<RwSignal<PlayerStore>>();
     let current_song = create_read_slice(player_store, move |p| p.get_current_song());
     let queue_songs = create_read_slice(player_store, move |p| p.get_queue_songs());
@@ -422,6 +425,11 @@ pub fn MusicInfo(#[prop()] show: Signal<bool>, #[prop()] node_ref: NodeRef<Div>)
     let remove_from_queue = create_write_slice(player_store, |p, val| p.remove_from_queue(val));
 
     let clear_queue = create_write_slice(player_store, |p, _| p.clear_queue_except_current());
+    let (autoplay, toggle_autoplay) = create_slice(
+        player_store,
+        |p| p.get_autoplay(),
+        |p, _| p.toggle_autoplay(),
+    );
     let canvaz_sig = RwSignal::new(None);
 
     let get_queue = create_read_slice(player_store, |p| {
@@ -542,12 +550,35 @@ pub fn MusicInfo(#[prop()] show: Signal<bool>, #[prop()] node_ref: NodeRef<Div>)
                                         >
                                             Save as playlist
                                         </div>
+                                        <div
+                                            class="rounded-btn"
+                                            on:click=move |_| {
+                                                modal_store
+                                                    .update(|store| {
+                                                        store.set_active_modal(Modals::SavedQueuesModal)
+                                                    });
+                                            }
+                                        >
+                                            {t!(i18n, queue.savedQueues)}
+                                        </div>
                                         <div
                                             class="rounded-btn"
                                             on:click=move |_| clear_queue.set(())
                                         >
                                             Clear
                                         </div>
+                                        <div
+                                            class="rounded-btn"
+                                            on:click=move |_| toggle_autoplay.set(())
+                                        >
+                                            {move || {
+                                                if autoplay.get() {
+                                                    t!(i18n, queue.autoplayOn).into_any()
+                                                } else {
+                                                    t!(i18n, queue.autoplayOff).into_any()
+                                                }
+                                            }}
+                                        </div>
                                     </div>
                                 </div>
                                 <div class="row queue-container-outer">
@@ -14,15 +14,27 @@
 // You should have received a copy of the GNU General Public License
 // along with this program.  If not, see <http://www.gnu.org/licenses/>.
 
-use std::{collections::HashMap, sync::Arc};
+use std::{collections::HashMap, sync::Arc, time::Duration};
 
 use leptos::{component, prelude::*, view, IntoView};
-use leptos_i18n::t;
-use leptos_use::use_debounce_fn_with_arg;
+use leptos_i18n::{t, t_string};
+use leptos_router::{
+    hooks::{use_navigate, use_query_map},
+    NavigateOptions,
+};
+use leptos_use::{use_debounce_fn_with_arg, use_interval_fn};
 use types::{
-    preferences::{CheckboxItems, CheckboxPreference},
+    preferences::{CheckboxItems, CheckboxPreference, InputType, PreferenceTypes},
+    shortcuts::{ShortcutAction, ShortcutBindings, ShortcutConflict, ShortcutConflictReason},
     themes::ThemeDetails,
-    ui::{extensions::ExtensionDetail, themes::ThemeModalState},
+    ui::{
+        extensions::{
+            ExtensionCapability, ExtensionDetail, ExtensionLogEntry, ExtensionPermissions,
+            ExtensionSubscriptionInfo, FetchedExtensionManifest,
+        },
+        player_details::AudioCacheStats,
+        themes::ThemeModalState,
+    },
     window::DialogFilter,
 };
 use wasm_bindgen_futures::spawn_local;
@@ -39,10 +51,19 @@ use crate::{
     },
     utils::{
         context_menu::{create_context_menu, ThemesContextMenu},
-        invoke::{get_installed_extensions, load_all_themes, remove_extension},
+        extensions::prompt_extension_permissions,
+        invoke::{
+            add_dev_extension, check_extension_updates, clear_extension_logs,
+            get_extension_logs, get_extension_subscriptions, get_extension_updates,
+            get_global_shortcut_conflicts,
+            get_installed_extensions, get_remote_token, grant_extension_permissions,
+            load_all_themes, regenerate_remote_token, reload_extension, remove_extension,
+            rodio_cache_stats, rodio_clear_cache, set_global_shortcuts,
+            toggle_extension_status, update_extension,
+        },
         prefs::{
-            load_selective, open_file_browser, open_file_browser_single, save_selective,
-            save_selective_number,
+            load_secure, load_selective, open_file_browser, open_file_browser_single,
+            save_secure, save_selective, save_selective_number,
         },
     },
 };
@@ -161,9 +182,9 @@ pub fn InputPref<K, H, K1, H1>(
 ) -> impl IntoView
 where
     K: Fn() -> H + Send + Sync + 'static,
-    H: IntoView + Copy + 'static,
+    H: IntoView + 'static,
     K1: Fn() -> H1 + Send + Sync + 'static,
-    H1: IntoView + Copy + 'static,
+    H1: IntoView + 'static,
 {
     let ui_store = expect_context::<RwSignal<UiStore>>();
     let is_mobile = create_read_slice(ui_store, |u| u.get_is_mobile()).get();
@@ -182,6 +203,8 @@ where
             let num_pref = num_pref.get();
             pref_value.set(format!("{}", num_pref));
         });
+    } else if inp_type == "password" {
+        load_secure(pref_key.clone(), pref_value.write_only());
     } else {
         load_selective(pref_key.clone(), pref_value.write_only());
     }
@@ -196,6 +219,8 @@ where
         tracing::debug!("Input type - {}", inp_type_clone);
         if inp_type_clone.clone() == "number" {
             save_selective_number(pref_key.clone(), value);
+        } else if inp_type_clone == "password" {
+            save_secure(pref_key.clone(), value);
         } else {
             save_selective(pref_key.clone(), value);
         }
@@ -277,9 +302,9 @@ pub fn CheckboxPref<K, H, K1, H1>(
 ) -> impl IntoView
 where
     K: Fn() -> H + Send + Sync + 'static,
-    H: IntoView + Copy + 'static,
+    H: IntoView + 'static,
     K1: Fn() -> H1 + Send + Sync + 'static,
-    H1: IntoView + Copy + 'static,
+    H1: IntoView + 'static,
 {
     let ui_store = expect_context::<RwSignal<UiStore>>();
     let is_mobile = create_read_slice(ui_store, |u| u.get_is_mobile()).get();
@@ -512,6 +537,60 @@ where
     }
 }
 
+#[tracing::instrument(level = "debug", skip(permissions))]
+#[component]
+pub fn PendingPermissions(#[prop()] permissions: ExtensionPermissions) -> impl IntoView {
+    let i18n = use_i18n();
+    let capabilities = permissions
+        .capabilities
+        .into_iter()
+        .map(|capability| {
+            let label = match capability {
+                ExtensionCapability::LibraryRead => {
+                    t!(i18n, settings.extensions.permissions.libraryRead).into_any()
+                }
+                ExtensionCapability::LibraryWrite => {
+                    t!(i18n, settings.extensions.permissions.libraryWrite).into_any()
+                }
+                ExtensionCapability::Preferences => {
+                    t!(i18n, settings.extensions.permissions.preferences).into_any()
+                }
+                ExtensionCapability::SecureStorage => {
+                    t!(i18n, settings.extensions.permissions.secureStorage).into_any()
+                }
+                ExtensionCapability::OpenUrl => {
+                    t!(i18n, settings.extensions.permissions.openUrl).into_any()
+                }
+                ExtensionCapability::Sockets => {
+                    t!(i18n, settings.extensions.permissions.sockets).into_any()
+                }
+            };
+            view! { <li>{label}</li> }
+        })
+        .collect_view();
+    let hosts = permissions
+        .hosts
+        .into_iter()
+        .map(|host| {
+            view! { <li>{t!(i18n, settings.extensions.permissions.host)} {host}</li> }
+        })
+        .collect_view();
+    let paths = permissions
+        .paths
+        .into_keys()
+        .map(|path| {
+            view! { <li>{t!(i18n, settings.extensions.permissions.path)} {path}</li> }
+        })
+        .collect_view();
+
+    view! {
+        <div class="item-text">
+            {t!(i18n, settings.extensions.permissions.requires)}
+            <ul class="mb-0">{capabilities} {hosts} {paths}</ul>
+        </div>
+    }
+}
+
 #[tracing::instrument(level = "debug", skip(title, tooltip))]
 #[component]
 pub fn ExtensionPref<K, H, K1, H1>(#[prop()] title: K, #[prop()] tooltip: K1) -> impl IntoView
@@ -522,6 +601,7 @@ where
     H1: IntoView + Copy + 'static,
 {
     let extensions = RwSignal::<Vec<ExtensionDetail>>::new(Default::default());
+    let updates = RwSignal::<Vec<FetchedExtensionManifest>>::new(Default::default());
     let fetch_extensions = move || {
         spawn_local(async move {
             let res = get_installed_extensions().await;
@@ -533,11 +613,26 @@ where
                     tracing::error!("Failed to get installed extensions {:?}", e);
                 }
             }
+
+            match get_extension_updates().await {
+                Ok(val) => updates.set(val),
+                Err(e) => tracing::error!("Failed to get extension updates {:?}", e),
+            }
         })
     };
     fetch_extensions();
 
+    let check_updates = move |_| {
+        spawn_local(async move {
+            match check_extension_updates().await {
+                Ok(val) => updates.set(val),
+                Err(e) => tracing::error!("Failed to check for extension updates {:?}", e),
+            }
+        })
+    };
+
     let i18n = use_i18n();
+    let modal_store = expect_context::<RwSignal<ModalStore>>();
 
     let extension_path = RwSignal::new(String::new());
     let install_extension = move |_| {
@@ -558,14 +653,37 @@ where
         }
 
         spawn_local(async move {
-            crate::utils::invoke::install_extension(extension_path)
-                .await
-                .unwrap();
+            match crate::utils::invoke::install_extension(extension_path).await {
+                Ok(package_name) => {
+                    prompt_extension_permissions(modal_store, package_name, fetch_extensions)
+                }
+                Err(e) => tracing::error!("Failed to install extension {:?}", e),
+            }
             fetch_extensions()
         });
     });
 
-    let modal_store = expect_context::<RwSignal<ModalStore>>();
+    let developer = RwSignal::<Vec<CheckboxPreference>>::new(Default::default());
+    load_selective("extension_developer".into(), developer.write_only());
+    let dev_mode = move || developer.get().iter().any(|p| p.key == "dev_mode" && p.enabled);
+
+    let dev_path = RwSignal::new(String::new());
+    let load_unpacked = move |_| open_file_browser_single(true, vec![], dev_path);
+
+    Effect::new(move || {
+        let dev_path = dev_path.get();
+        if dev_path.is_empty() {
+            return;
+        }
+
+        spawn_local(async move {
+            if let Err(e) = add_dev_extension(dev_path).await {
+                tracing::error!("Failed to load unpacked extension {:?}", e);
+            }
+            // The extension is loaded once the preference change is handled
+            set_timeout(fetch_extensions, Duration::from_secs(1));
+        });
+    });
 
     view! {
         <div class="container-fluid mt-4">
@@ -590,6 +708,21 @@ where
                             })
                     }>{"Discover"}</div>
                 </div>
+                <div class="col-auto new-directories ml-4">
+                    <div on:click=check_updates>{t!(i18n, settings.extensions.checkUpdates)}</div>
+                </div>
+                {move || {
+                    dev_mode()
+                        .then(|| {
+                            view! {
+                                <div class="col-auto new-directories ml-4">
+                                    <div on:click=load_unpacked>
+                                        {t!(i18n, settings.extensions.loadUnpacked)}
+                                    </div>
+                                </div>
+                            }
+                        })
+                }}
                 <div class="col-auto new-directories ml-4">
                     <div class="add-directories-button" on:click=install_extension>
                         {"Install from file"}
@@ -599,16 +732,216 @@ where
             <div class="row no-gutters path-prefs-background w-100 mt-2 d-flex">
                 <For
                     each=move || extensions.get()
-                    key=|e| e.clone()
+                    key=|e| (e.package_name.clone(), e.enabled, e.pending_permissions.is_some())
                     children=move |extension: ExtensionDetail| {
+                        let toggle_package = extension.package_name.clone();
+                        let grant_package = extension.package_name.clone();
+                        let deny_package = extension.package_name.clone();
+                        let reload_package = extension.package_name.clone();
+                        let update_package = extension.package_name.clone();
+                        let settings_package = extension.package_name.clone();
+                        let logs_package = extension.package_name.clone();
+                        let update = move || {
+                            let package_name = update_package.clone();
+                            updates
+                                .get()
+                                .into_iter()
+                                .find(|u| u.package_name == package_name)
+                                .map(|update| {
+                                    view! {
+                                        <div class="col-auto align-self-center mr-3">
+                                            <div
+                                                class="remove-button w-100"
+                                                on:click=move |_| {
+                                                    let package_name = update.package_name.clone();
+                                                    spawn_local(async move {
+                                                        match update_extension(package_name.clone()).await {
+                                                            // Updates may ask for more permissions
+                                                            Ok(_) => {
+                                                                prompt_extension_permissions(
+                                                                    modal_store,
+                                                                    package_name,
+                                                                    fetch_extensions,
+                                                                )
+                                                            }
+                                                            Err(e) => {
+                                                                tracing::error!("Failed to update extension {:?}", e)
+                                                            }
+                                                        }
+                                                        fetch_extensions()
+                                                    });
+                                                }
+                                            >
+                                                {t!(i18n, settings.extensions.update)}
+                                                {format!(" {}", update.version)}
+                                            </div>
+                                        </div>
+                                    }
+                                })
+                        };
                         view! {
                             <div class="row no-gutters mt-3 item w-100">
-                                <div class="col col-md-8 col-lg-9 align-self-center justify-content-start ml-3 no-checkbox-margin">
+                                <div class="col col-md-6 col-lg-7 align-self-center justify-content-start ml-3 no-checkbox-margin">
                                     <div class="item-text text-truncate">
                                         {extension.name.clone()}
+                                        {extension
+                                            .dev
+                                            .then(|| {
+                                                view! {
+                                                    " ("
+                                                    {t!(i18n, settings.extensions.dev)}
+                                                    ")"
+                                                }
+                                            })}
                                     </div>
+                                    {extension
+                                        .disabled_reason
+                                        .clone()
+                                        .map(|reason| {
+                                            view! {
+                                                <div class="item-text text-truncate" title=reason.clone()>
+                                                    {t!(i18n, settings.extensions.disabledReason)}
+                                                    {format!(": {}", reason)}
+                                                </div>
+                                            }
+                                        })}
+                                    {extension
+                                        .pending_permissions
+                                        .clone()
+                                        .map(|permissions| {
+                                            view! { <PendingPermissions permissions=permissions /> }
+                                        })}
                                 </div>
-                                <div class="col-auto align-self-center ml-auto">
+                                <div class="col-auto align-self-center ml-auto" />
+                                // Disabled extensions ask again once they are enabled
+                                {(extension.pending_permissions.is_some() && extension.enabled)
+                                    .then(|| {
+                                        view! {
+                                            <div class="col-auto align-self-center mr-3">
+                                                <div
+                                                    class="permission-deny-button w-100"
+                                                    on:click=move |_| {
+                                                        let package_name = deny_package.clone();
+                                                        spawn_local(async move {
+                                                            if let Err(e) = toggle_extension_status(
+                                                                    package_name,
+                                                                )
+                                                                .await
+                                                            {
+                                                                tracing::error!(
+                                                                    "Failed to disable extension {:?}", e
+                                                                );
+                                                            }
+                                                            fetch_extensions()
+                                                        });
+                                                    }
+                                                >
+                                                    {t!(i18n, settings.extensions.permissions.deny)}
+                                                </div>
+                                            </div>
+                                            <div class="col-auto align-self-center mr-3">
+                                                <div
+                                                    class="permission-allow-button w-100"
+                                                    on:click=move |_| {
+                                                        let package_name = grant_package.clone();
+                                                        spawn_local(async move {
+                                                            if let Err(e) = grant_extension_permissions(
+                                                                    package_name,
+                                                                )
+                                                                .await
+                                                            {
+                                                                tracing::error!(
+                                                                    "Failed to grant extension permissions {:?}", e
+                                                                );
+                                                            }
+                                                            fetch_extensions()
+                                                        });
+                                                    }
+                                                >
+                                                    {t!(i18n, settings.extensions.permissions.allow)}
+                                                </div>
+                                            </div>
+                                        }
+                                    })}
+                                {(!extension.preferences.is_empty())
+                                    .then(|| {
+                                        view! {
+                                            <div class="col-auto align-self-center mr-3">
+                                                <div
+                                                    class="remove-button w-100"
+                                                    on:click=move |_| {
+                                                        use_navigate()(
+                                                            &format!(
+                                                                "/prefs/extension?package={}",
+                                                                url_escape::encode_component(&settings_package),
+                                                            ),
+                                                            NavigateOptions::default(),
+                                                        );
+                                                    }
+                                                >
+                                                    {t!(i18n, settings.extensions.settings)}
+                                                </div>
+                                            </div>
+                                        }
+                                    })}
+                                {update}
+                                <div class="col-auto align-self-center mr-3">
+                                    <div
+                                        class="remove-button w-100"
+                                        on:click=move |_| {
+                                            use_navigate()(
+                                                &format!(
+                                                    "/prefs/extension_logs?package={}",
+                                                    url_escape::encode_component(&logs_package),
+                                                ),
+                                                NavigateOptions::default(),
+                                            );
+                                        }
+                                    >
+                                        {t!(i18n, settings.extensions.logs.title)}
+                                    </div>
+                                </div>
+                                <div class="col-auto align-self-center mr-3">
+                                    <div
+                                        class="remove-button w-100"
+                                        on:click=move |_| {
+                                            let package_name = toggle_package.clone();
+                                            spawn_local(async move {
+                                                if let Err(e) = toggle_extension_status(package_name)
+                                                    .await
+                                                {
+                                                    tracing::error!("Failed to toggle extension {:?}", e);
+                                                }
+                                                fetch_extensions()
+                                            });
+                                        }
+                                    >
+                                        {if extension.enabled {
+                                            t!(i18n, settings.extensions.disable).into_any()
+                                        } else {
+                                            t!(i18n, settings.extensions.enable).into_any()
+                                        }}
+                                    </div>
+                                </div>
+                                <div class="col-auto align-self-center mr-3">
+                                    <div
+                                        class="remove-button w-100"
+                                        on:click=move |_| {
+                                            let package_name = reload_package.clone();
+                                            spawn_local(async move {
+                                                if let Err(e) = reload_extension(package_name.into())
+                                                    .await
+                                                {
+                                                    tracing::error!("Failed to reload extension {:?}", e);
+                                                }
+                                                fetch_extensions()
+                                            });
+                                        }
+                                    >
+                                        {t!(i18n, settings.extensions.reload)}
+                                    </div>
+                                </div>
+                                <div class="col-auto align-self-center">
                                     <div
                                         class="remove-button w-100"
                                         on:click=move |_| {
@@ -627,61 +960,74 @@ where
                     }
                 />
             </div>
-            <div class="row no-gutters w-100 mt-4 d-flex">
-                <For
-                    each=move || extensions.get()
-                    key=|e| e.package_name.clone()
-                    children=move |extension: ExtensionDetail| {
-                        view! {
-                            <div class="row no-gutters align-items-center">
-                                <div class="row no-gutters">
-                                    <div class="col-auto align-self-center title d-flex preference-title">
-                                        {extension.name}
-                                    </div>
+        </div>
+    }
+}
+
+/// Settings page of a single extension, rendered from the preferences declared in its manifest
+#[tracing::instrument(level = "debug")]
+#[component]
+pub fn ExtensionSettingsPage() -> impl IntoView {
+    let params = use_query_map();
+    let package_name = move || params.with(|p| p.get("package").unwrap_or_default());
+    let extension = RwSignal::<Option<ExtensionDetail>>::new(None);
+
+    Effect::new(move || {
+        let package_name = package_name();
+        spawn_local(async move {
+            match get_installed_extensions().await {
+                Ok(val) => {
+                    extension.set(val.into_iter().find(|e| e.package_name == package_name))
+                }
+                Err(e) => tracing::error!("Failed to get installed extensions {:?}", e),
+            }
+        })
+    });
 
+    view! {
+        <div class="prefs-container">
+            {move || {
+                extension
+                    .get()
+                    .map(|extension| {
+                        view! {
+                            <div class="row no-gutters">
+                                <div class="col-auto align-self-center title d-flex preference-title">
+                                    {extension.name.clone()}
                                 </div>
                             </div>
-
                             <For
                                 each=move || extension.preferences.clone()
                                 key=|p| p.key.clone()
                                 children=move |preference| {
+                                    let title = preference.title.clone();
+                                    let tooltip = preference.description.clone();
+                                    let title = move || title.clone();
+                                    let tooltip = move || tooltip.clone();
                                     match preference._type {
-                                        types::preferences::PreferenceTypes::DirectoryGroup => {
-                                            view! {
-                                                <PathsPref
-                                                    key=preference.key
-                                                    title=|| view! { title }
-                                                    tooltip=|| view! { tooltip }
-                                                    mobile=true
-                                                />
-                                            }
-                                                .into_any()
-                                        }
-                                        types::preferences::PreferenceTypes::EditText => {
+                                        PreferenceTypes::EditText => {
+                                            let inp_type = match preference.input_type {
+                                                Some(InputType::Number) => "number",
+                                                _ => "text",
+                                            };
                                             view! {
                                                 <InputPref
                                                     key=preference.key
-                                                    title=|| view! { title }
-                                                    tooltip=|| view! { tooltip }
+                                                    title=title
+                                                    tooltip=tooltip
                                                     show_input=true
-                                                    inp_type=serde_wasm_bindgen::to_value(
-                                                            &preference.input_type,
-                                                        )
-                                                        .unwrap()
-                                                        .as_string()
-                                                        .unwrap_or("text".to_string())
+                                                    inp_type=inp_type.to_string()
                                                     mobile=true
                                                 />
                                             }
                                                 .into_any()
                                         }
-                                        types::preferences::PreferenceTypes::FilePicker => {
+                                        PreferenceTypes::FilePicker => {
                                             view! {
                                                 <InputPref
                                                     key=preference.key
-                                                    title=|| view! { title }
-                                                    tooltip=|| view! { tooltip }
+                                                    title=title
+                                                    tooltip=tooltip
                                                     show_input=false
                                                     inp_type="".to_string()
                                                     mobile=true
@@ -689,12 +1035,12 @@ where
                                             }
                                                 .into_any()
                                         }
-                                        types::preferences::PreferenceTypes::CheckboxGroup => {
+                                        PreferenceTypes::CheckboxGroup => {
                                             view! {
                                                 <CheckboxPref
                                                     key=preference.key
-                                                    title=|| view! { title }
-                                                    tooltip=|| view! { tooltip }
+                                                    title=title
+                                                    tooltip=tooltip
                                                     items=preference.items.unwrap_or_default()
                                                     single=preference.single.unwrap_or_default()
                                                     mobile=true
@@ -702,34 +1048,131 @@ where
                                             }
                                                 .into_any()
                                         }
-                                        types::preferences::PreferenceTypes::Dropdown => {
+                                        PreferenceTypes::Dropdown => {
                                             view! {
                                                 <DropdownPref
                                                     key=preference.key
-                                                    title=|| view! { title }
-                                                    tooltip=|| view! { tooltip }
+                                                    title=title
+                                                    tooltip=tooltip
                                                     mobile=true
                                                 />
                                             }
                                                 .into_any()
                                         }
-                                        types::preferences::PreferenceTypes::ThemeSelector
-                                        | types::preferences::PreferenceTypes::Extensions => {
-                                            ().into_any()
-                                        }
-                                        types::preferences::PreferenceTypes::ButtonGroup
-                                        | types::preferences::PreferenceTypes::ProgressBar
-                                        | types::preferences::PreferenceTypes::TextField
-                                        | types::preferences::PreferenceTypes::InfoField => {
-                                            ().into_any()
-                                        }
+                                        _ => ().into_any(),
                                     }
                                 }
                             />
                         }
-                    }
-                />
+                    })
+            }}
+        </div>
+    }
+}
+
+/// Tails the log buffer of a single extension
+#[tracing::instrument(level = "debug")]
+#[component]
+pub fn ExtensionLogsPage() -> impl IntoView {
+    let params = use_query_map();
+    let package_name = move || params.with(|p| p.get("package").unwrap_or_default());
+    let entries = RwSignal::<Vec<ExtensionLogEntry>>::new(Default::default());
+    let subscription = RwSignal::<Option<ExtensionSubscriptionInfo>>::new(None);
 
+    let fetch_logs = move || {
+        let after = entries.with_untracked(|e| e.last().map(|e| e.id));
+        let package_name = package_name();
+        spawn_local(async move {
+            match get_extension_logs(package_name.clone(), after).await {
+                Ok(new_entries) if !new_entries.is_empty() => {
+                    entries.update(|e| e.extend(new_entries))
+                }
+                Ok(_) => {}
+                Err(e) => tracing::error!("Failed to get extension logs {:?}", e),
+            }
+            match get_extension_subscriptions().await {
+                Ok(subscriptions) => subscription.set(
+                    subscriptions
+                        .into_iter()
+                        .find(|s| s.package_name == package_name),
+                ),
+                Err(e) => tracing::error!("Failed to get extension subscriptions {:?}", e),
+            }
+        })
+    };
+    fetch_logs();
+    use_interval_fn(fetch_logs, 1000);
+
+    let clear_logs = move |_| {
+        let package_name = package_name();
+        spawn_local(async move {
+            if let Err(e) = clear_extension_logs(package_name).await {
+                tracing::error!("Failed to clear extension logs {:?}", e);
+            }
+            entries.set(vec![]);
+        })
+    };
+
+    let i18n = use_i18n();
+    view! {
+        <div class="prefs-container">
+            <div class="container-fluid mt-4">
+                <div class="row no-gutters align-items-center">
+                    <div class="col-auto align-self-center title d-flex preference-title">
+                        {t!(i18n, settings.extensions.logs.title)}
+                        {move || format!(" - {}", package_name())}
+                    </div>
+                    <div class="col-auto new-directories ml-auto">
+                        <div on:click=clear_logs>{t!(i18n, settings.extensions.logs.clear)}</div>
+                    </div>
+                </div>
+                <Show when=move || subscription.with(|s| s.is_some())>
+                    <div class="row no-gutters mt-2 w-100">
+                        <div class="item-text">
+                            {t!(i18n, settings.extensions.logs.subscriptions)}
+                            {move || {
+                                subscription
+                                    .get()
+                                    .map(|s| {
+                                        let events = if s.declared {
+                                            s.events
+                                                .iter()
+                                                .map(|e| format!("{:?}", e))
+                                                .collect::<Vec<_>>()
+                                                .join(", ")
+                                        } else {
+                                            "*".to_string()
+                                        };
+                                        format!(
+                                            ": {} ({} {}, {} {})",
+                                            events,
+                                            s.delivered,
+                                            t_string!(i18n, settings.extensions.logs.delivered),
+                                            s.skipped,
+                                            t_string!(i18n, settings.extensions.logs.skipped),
+                                        )
+                                    })
+                            }}
+                        </div>
+                    </div>
+                </Show>
+                <div class="row no-gutters path-prefs-background w-100 mt-2 d-flex">
+                    <For
+                        each=move || entries.get()
+                        key=|e| e.id
+                        children=move |entry: ExtensionLogEntry| {
+                            let time = js_sys::Date::new(&(entry.timestamp as f64).into())
+                                .to_locale_time_string("default");
+                            view! {
+                                <div class="row no-gutters mt-1 w-100 ml-3">
+                                    <div class="item-text">
+                                        {format!("[{}] {} {}", time, entry.level, entry.message)}
+                                    </div>
+                                </div>
+                            }
+                        }
+                    />
+                </div>
             </div>
         </div>
     }
@@ -745,9 +1188,9 @@ pub fn DropdownPref<K, H, K1, H1>(
 ) -> impl IntoView
 where
     K: Fn() -> H + Send + Sync + 'static,
-    H: IntoView + Copy + 'static,
+    H: IntoView + 'static,
     K1: Fn() -> H1 + Send + Sync + 'static,
-    H1: IntoView + Copy + 'static,
+    H1: IntoView + 'static,
 {
     let ui_store = expect_context::<RwSignal<UiStore>>();
     let is_mobile = create_read_slice(ui_store, |u| u.get_is_mobile()).get();
@@ -829,3 +1272,320 @@ where
     }
     .into_any()
 }
+
+/// Preference item keys are the serialized action names
+fn shortcut_action(key: &str) -> Option<ShortcutAction> {
+    serde_json::from_value(serde_json::Value::String(key.to_string())).ok()
+}
+
+/// Builds a chord such as "Ctrl+Alt+KeyP" from a key press. Escape or Backspace
+/// without modifiers clears the binding.
+fn chord_from_event(ev: &web_sys::KeyboardEvent) -> Option<String> {
+    if matches!(ev.key().as_str(), "Control" | "Shift" | "Alt" | "Meta") {
+        return None;
+    }
+
+    let mut parts = vec![];
+    if ev.ctrl_key() {
+        parts.push("Ctrl".to_string());
+    }
+    if ev.alt_key() {
+        parts.push("Alt".to_string());
+    }
+    if ev.shift_key() {
+        parts.push("Shift".to_string());
+    }
+    if ev.meta_key() {
+        parts.push("Super".to_string());
+    }
+
+    let code = ev.code();
+    if parts.is_empty() && (code == "Escape" || code == "Backspace") {
+        return Some(String::new());
+    }
+    parts.push(code);
+    Some(parts.join("+"))
+}
+
+#[tracing::instrument(level = "debug", skip(key, title, tooltip, items))]
+#[component]
+pub fn ShortcutsPref<K, H, K1, H1>(
+    #[prop()] key: String,
+    #[prop()] title: K,
+    #[prop()] tooltip: K1,
+    #[prop()] items: Vec<CheckboxItems>,
+    #[prop()] mobile: bool,
+) -> impl IntoView
+where
+    K: Fn() -> H + Send + Sync + 'static,
+    H: IntoView + Copy + 'static,
+    K1: Fn() -> H1 + Send + Sync + 'static,
+    H1: IntoView + Copy + 'static,
+{
+    let ui_store = expect_context::<RwSignal<UiStore>>();
+    let is_mobile = create_read_slice(ui_store, |u| u.get_is_mobile()).get();
+    if is_mobile && !mobile {
+        return ().into_any();
+    }
+
+    let i18n = use_i18n();
+
+    let bindings = RwSignal::<ShortcutBindings>::new(Default::default());
+    load_selective(key, bindings.write_only());
+
+    let conflicts = RwSignal::<Vec<ShortcutConflict>>::new(Default::default());
+    spawn_local(async move {
+        match get_global_shortcut_conflicts().await {
+            Ok(res) => conflicts.set(res),
+            Err(e) => tracing::error!("Failed to get shortcut conflicts {:?}", e),
+        }
+    });
+
+    let set_binding = move |action: ShortcutAction, chord: String| {
+        bindings.update(|b| {
+            if chord.is_empty() {
+                b.remove(&action);
+            } else {
+                b.insert(action, chord);
+            }
+        });
+
+        let value = bindings.get_untracked();
+        spawn_local(async move {
+            match set_global_shortcuts(value).await {
+                Ok(res) => conflicts.set(res),
+                Err(e) => tracing::error!("Failed to set global shortcuts {:?}", e),
+            }
+        });
+    };
+
+    let action_titles = items
+        .iter()
+        .filter_map(|item| Some((shortcut_action(&item.key)?, item.title.clone())))
+        .collect::<HashMap<_, _>>();
+
+    view! {
+        <div class="container-fluid mt-4">
+            <div class="row no-gutters">
+                <div class="col-auto align-self-center title d-flex preference-title">
+                    {title()}
+                </div>
+                <div class="col-auto ml-2">
+                    <Tooltip>{tooltip()}</Tooltip>
+                </div>
+            </div>
+            {items
+                .into_iter()
+                .filter_map(|item| Some((shortcut_action(&item.key)?, item.title)))
+                .map(|(action, item_title)| {
+                    let action_titles = action_titles.clone();
+                    let conflict = move || {
+                        conflicts
+                            .get()
+                            .into_iter()
+                            .find(|c| c.action == action)
+                            .map(|c| c.reason)
+                    };
+                    view! {
+                        <div class="row no-gutters mt-2">
+                            <div class="col-4 align-self-center">
+                                <div class="item-text text-truncate">{item_title}</div>
+                            </div>
+                            <div class="col input-prefs-background d-flex align-content-center">
+                                <input
+                                    class="ext-input w-100 ext-input-hover ml-3 mr-3"
+                                    type="text"
+                                    readonly=true
+                                    prop:value=move || {
+                                        bindings.get().get(&action).cloned().unwrap_or_default()
+                                    }
+                                    on:keydown=move |ev| {
+                                        if ev.key() == "Tab" {
+                                            return;
+                                        }
+                                        ev.prevent_default();
+                                        if let Some(chord) = chord_from_event(&ev) {
+                                            set_binding(action, chord);
+                                        }
+                                    }
+                                />
+                            </div>
+                            <div class="col-12 item-text">
+                                {move || match conflict() {
+                                    Some(ShortcutConflictReason::Invalid(_)) => {
+                                        view! { {t!(i18n, settings.system.shortcuts.invalid)} }
+                                            .into_any()
+                                    }
+                                    Some(ShortcutConflictReason::Duplicate(other)) => {
+                                        let other = action_titles
+                                            .get(&other)
+                                            .cloned()
+                                            .unwrap_or(format!("{:?}", other));
+                                        view! {
+                                            {t!(i18n, settings.system.shortcuts.duplicate)}
+                                            " "
+                                            {other}
+                                        }
+                                            .into_any()
+                                    }
+                                    Some(ShortcutConflictReason::Unavailable(_)) => {
+                                        view! { {t!(i18n, settings.system.shortcuts.unavailable)} }
+                                            .into_any()
+                                    }
+                                    None => ().into_any(),
+                                }}
+                            </div>
+                        </div>
+                    }
+                })
+                .collect_view()}
+        </div>
+    }
+    .into_any()
+}
+
+fn format_megabytes(bytes: u64) -> String {
+    format!("{:.1} MB", bytes as f64 / (1024f64 * 1024f64))
+}
+
+#[tracing::instrument(level = "debug", skip(title, tooltip))]
+#[component]
+pub fn AudioCachePref<K, H, K1, H1>(
+    #[prop()] title: K,
+    #[prop()] tooltip: K1,
+    #[prop()] mobile: bool,
+) -> impl IntoView
+where
+    K: Fn() -> H + Send + Sync + 'static,
+    H: IntoView + Copy + 'static,
+    K1: Fn() -> H1 + Send + Sync + 'static,
+    H1: IntoView + Copy + 'static,
+{
+    let ui_store = expect_context::<RwSignal<UiStore>>();
+    let is_mobile = create_read_slice(ui_store, |u| u.get_is_mobile()).get();
+    if is_mobile && !mobile {
+        return ().into_any();
+    }
+
+    let i18n = use_i18n();
+    let stats = RwSignal::<Option<AudioCacheStats>>::new(None);
+    let fetch_stats = move || {
+        spawn_local(async move {
+            match rodio_cache_stats().await {
+                Ok(res) => stats.set(Some(res)),
+                Err(e) => tracing::error!("Failed to get audio cache stats {:?}", e),
+            }
+        })
+    };
+    fetch_stats();
+
+    let clear_cache = move |_| {
+        spawn_local(async move {
+            if let Err(e) = rodio_clear_cache().await {
+                tracing::error!("Failed to clear audio cache {:?}", e);
+            }
+            fetch_stats();
+        })
+    };
+
+    view! {
+        <div class="container-fluid mt-4">
+            <div class="row no-gutters align-items-center">
+                <div class="col-auto align-self-center title d-flex preference-title">
+                    {title()}
+                </div>
+                <div class="col-auto ml-2">
+                    <Tooltip>{tooltip()}</Tooltip>
+                </div>
+                <div class="col-auto new-directories ml-auto">
+                    <div on:click=clear_cache>{t!(i18n, settings.system.audioCache.clear)}</div>
+                </div>
+            </div>
+            <div class="row no-gutters mt-2">
+                <div class="col-12 item-text">
+                    {move || {
+                        stats
+                            .get()
+                            .map(|stats| {
+                                format!(
+                                    "{} {}, {} / {}, {} {}, {} {}",
+                                    stats.entries,
+                                    t_string!(i18n, settings.system.audioCache.songs),
+                                    format_megabytes(stats.size),
+                                    format_megabytes(stats.max_size),
+                                    stats.hits,
+                                    t_string!(i18n, settings.system.audioCache.hits),
+                                    stats.misses,
+                                    t_string!(i18n, settings.system.audioCache.misses),
+                                )
+                            })
+                    }}
+                </div>
+            </div>
+        </div>
+    }
+    .into_any()
+}
+
+/// Shows the remote control pairing token, which is kept in secure storage
+#[tracing::instrument(level = "debug", skip(title, tooltip))]
+#[component]
+pub fn RemoteTokenPref<K, H, K1, H1>(
+    #[prop()] title: K,
+    #[prop()] tooltip: K1,
+    #[prop()] mobile: bool,
+) -> impl IntoView
+where
+    K: Fn() -> H + Send + Sync + 'static,
+    H: IntoView + Copy + 'static,
+    K1: Fn() -> H1 + Send + Sync + 'static,
+    H1: IntoView + Copy + 'static,
+{
+    let ui_store = expect_context::<RwSignal<UiStore>>();
+    let is_mobile = create_read_slice(ui_store, |u| u.get_is_mobile()).get();
+    if is_mobile && !mobile {
+        return ().into_any();
+    }
+
+    let i18n = use_i18n();
+    let token = RwSignal::new(String::new());
+    spawn_local(async move {
+        match get_remote_token().await {
+            Ok(res) => token.set(res),
+            Err(e) => tracing::error!("Failed to get remote control token {:?}", e),
+        }
+    });
+
+    let regenerate = move |_| {
+        spawn_local(async move {
+            match regenerate_remote_token().await {
+                Ok(res) => token.set(res),
+                Err(e) => tracing::error!("Failed to regenerate remote control token {:?}", e),
+            }
+        })
+    };
+
+    view! {
+        <div class="container-fluid mt-4">
+            <div class="row no-gutters align-items-center">
+                <div class="col-auto align-self-center title d-flex preference-title">
+                    {title()}
+                </div>
+                <div class="col-auto ml-2">
+                    <Tooltip>{tooltip()}</Tooltip>
+                </div>
+                <div class="col-auto new-directories ml-auto">
+                    <div on:click=regenerate>
+                        {t!(i18n, settings.system.remoteControl.regenerateToken)}
+                    </div>
+                </div>
+            </div>
+            <div class="row no-gutters input-prefs-background w-100 mt-2 d-flex align-content-center">
+                <div class="col-auto ml-3 mr-3 h-100 align-self-center flex-grow-1 d-flex">
+                    <input class="ext-input w-100" type="text" readonly prop:value=token />
+                </div>
+            </div>
+        </div>
+    }
+    .into_any()
+}
@@ -28,6 +28,7 @@ use crate::{
         paths_icon::{PathsIcon, PathsIconProps},
         playlists_icon::{PlaylistsIcon, PlaylistsIconProps},
         queue_icon::{QueueIcon, QueueIconProps},
+        radio_icon::{RadioIcon, RadioIconProps},
         sidebar_toggle_icon::SidebarToggleIcon,
         system_icon::{SystemIcon, SystemIconProps},
         themes_icon::{ThemesIcon, ThemesIconProps},
@@ -52,6 +53,7 @@ impl Tab {
             "Artists" => |active| ArtistsIcon(ArtistsIconProps { active }).into_any(),
             "Albums" => |active| AlbumsIcon(AlbumsIconProps { active }).into_any(),
             "Genres" => |active| GenresIcon(GenresIconProps { active }).into_any(),
+            "Radio" => |active| RadioIcon(RadioIconProps { active }).into_any(),
             "Explore" => |active| ExploreIcon(ExploreIconProps { active }).into_any(),
             "Paths" => |active| PathsIcon(PathsIconProps { active }).into_any(),
             "System" => |active| SystemIcon(SystemIconProps { active }).into_any(),
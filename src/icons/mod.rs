@@ -49,6 +49,7 @@ pub mod plus_button;
 pub mod prev_icon;
 pub mod prev_track_icon;
 pub mod queue_icon;
+pub mod radio_icon;
 pub mod random_icon;
 pub mod repeat_icon;
 pub mod repeat_once_icon;
@@ -56,6 +57,7 @@ pub mod search_icon;
 pub mod settings_icon;
 pub mod shuffle_icon;
 pub mod sidebar_toggle_icon;
+pub mod sleep_timer_icon;
 pub mod song_default_icon;
 pub mod sort_icon;
 pub mod spotify_icon;
@@ -0,0 +1,39 @@
+// Moosync
+// Copyright (C) 2024, 2025  Moosync <support@moosync.app>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use leptos::{component, prelude::*, view, IntoView};
+
+#[tracing::instrument(level = "debug", skip(active))]
+#[component]
+pub fn RadioIcon(#[prop()] active: ReadSignal<bool>) -> impl IntoView {
+    view! {
+        <svg
+            width="20"
+            height="20"
+            viewBox="0 0 20 20"
+            fill="none"
+            xmlns="http://www.w3.org/2000/svg"
+        >
+            <title>Radio</title>
+            <path
+                fill-rule="evenodd"
+                clip-rule="evenodd"
+                d="M15.6 1.1C15.95 0.95 16.36 1.11 16.51 1.46C16.66 1.81 16.5 2.22 16.15 2.37L7.5 6H17.5C18.88 6 20 7.12 20 8.5V17C20 18.38 18.88 19.5 17.5 19.5H2.5C1.12 19.5 0 18.38 0 17V8.5C0 7.3 0.85 6.29 1.98 6.05L15.6 1.1ZM6 16.5C7.93 16.5 9.5 14.93 9.5 13C9.5 11.07 7.93 9.5 6 9.5C4.07 9.5 2.5 11.07 2.5 13C2.5 14.93 4.07 16.5 6 16.5ZM12 10.25C12 9.84 12.34 9.5 12.75 9.5H16.75C17.16 9.5 17.5 9.84 17.5 10.25C17.5 10.66 17.16 11 16.75 11H12.75C12.34 11 12 10.66 12 10.25ZM12.75 12.25C12.34 12.25 12 12.59 12 13C12 13.41 12.34 13.75 12.75 13.75H16.75C17.16 13.75 17.5 13.41 17.5 13C17.5 12.59 17.16 12.25 16.75 12.25H12.75ZM12 15.75C12 15.34 12.34 15 12.75 15H16.75C17.16 15 17.5 15.34 17.5 15.75C17.5 16.16 17.16 16.5 16.75 16.5H12.75C12.34 16.5 12 16.16 12 15.75Z"
+                fill=move || if active.get() { "var(--accent)" } else { "var(--textPrimary)" }
+            ></path>
+        </svg>
+    }
+}
@@ -18,8 +18,9 @@ use leptos::{component, prelude::*, task::spawn_local, view, IntoView};
 use types::ui::extensions::FetchedExtensionManifest;
 
 use crate::{
-    modals::common::GenericModal, store::modal_store::ModalStore,
-    utils::invoke::get_extension_manifest,
+    modals::common::GenericModal,
+    store::modal_store::ModalStore,
+    utils::{extensions::prompt_extension_permissions, invoke::get_extension_manifest},
 };
 
 #[tracing::instrument(level = "debug", skip())]
@@ -82,14 +83,28 @@ pub fn DiscoverExtensionsModal() -> impl IntoView {
                                             on:click=move |_| {
                                                 let extension = extension_clone.clone();
                                                 spawn_local(async move {
-                                                    crate::utils::invoke::download_extension(extension)
+                                                    let package_name = extension.package_name.clone();
+                                                    if let Err(e) = crate::utils::invoke::download_extension(
+                                                            extension,
+                                                        )
                                                         .await
-                                                        .unwrap();
-                                                    let close_modal = create_write_slice(
+                                                    {
+                                                        tracing::error!("Failed to download extension {:?}", e);
+                                                        return;
+                                                    }
+                                                    // Refreshes the extension list again once the permissions are answered
+                                                    let on_close = modal_store
+                                                        .with_untracked(|m| m.on_modal_close.clone());
+                                                    modal_store.update(|m| m.clear_active_modal());
+                                                    prompt_extension_permissions(
                                                         modal_store,
-                                                        |m, _: ()| m.clear_active_modal(),
+                                                        package_name,
+                                                        move || {
+                                                            if let Some(on_close) = &on_close {
+                                                                on_close()
+                                                            }
+                                                        },
                                                     );
-                                                    close_modal.set(());
                                                 });
                                             }
                                         >
@@ -0,0 +1,94 @@
+// Moosync
+// Copyright (C) 2024, 2025  Moosync <support@moosync.app>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use leptos::{component, prelude::*, task::spawn_local, view, IntoView};
+use leptos_i18n::t;
+use types::ui::extensions::ExtensionDetail;
+
+use crate::{
+    components::prefs::components::PendingPermissions,
+    i18n::use_i18n,
+    modals::common::GenericModal,
+    store::modal_store::ModalStore,
+    utils::invoke::{grant_extension_permissions, toggle_extension_status},
+};
+
+#[tracing::instrument(level = "debug", skip(extension))]
+#[component]
+pub fn ExtensionPermissionsModal(#[prop()] extension: ExtensionDetail) -> impl IntoView {
+    let modal_store: RwSignal<ModalStore> = expect_context();
+    let i18n = use_i18n();
+
+    let allow_package = extension.package_name.clone();
+    let allow = move |_| {
+        let package_name = allow_package.clone();
+        spawn_local(async move {
+            if let Err(e) = grant_extension_permissions(package_name).await {
+                tracing::error!("Failed to grant extension permissions {:?}", e);
+            }
+            modal_store.update(|m| m.clear_active_modal());
+        });
+    };
+
+    // Denied extensions stay installed but disabled, enabling them asks again
+    let deny_package = extension.package_name.clone();
+    let deny = move |_| {
+        let package_name = deny_package.clone();
+        spawn_local(async move {
+            if let Err(e) = toggle_extension_status(package_name).await {
+                tracing::error!("Failed to disable extension {:?}", e);
+            }
+            modal_store.update(|m| m.clear_active_modal());
+        });
+    };
+
+    view! {
+        <GenericModal size=move || "modal-lg".into()>
+            <div class="container-fluid p-0 mt-4">
+                <div class="row no-gutters d-flex">
+                    <div class="col">
+                        <h4>
+                            {t!(i18n, settings.extensions.permissions.title)} " "
+                            <span class="keyword">{extension.name.clone()}</span>
+                        </h4>
+                        {extension
+                            .pending_permissions
+                            .clone()
+                            .map(|permissions| {
+                                view! { <PendingPermissions permissions=permissions /> }
+                            })}
+                    </div>
+                </div>
+                <div class="row row-cols-auto mt-3 mr-4">
+                    <button
+                        on:click=deny
+                        class="btn btn-secondary cancel-button ml-auto"
+                        type="button"
+                    >
+                        {t!(i18n, settings.extensions.permissions.deny)}
+                    </button>
+                    <button
+                        on:click=allow
+                        class="btn btn-secondary confirm-button ml-3"
+                        type="button"
+                    >
+                        {t!(i18n, settings.extensions.permissions.allow)}
+                    </button>
+                </div>
+            </div>
+        </GenericModal>
+    }
+}
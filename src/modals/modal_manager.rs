@@ -18,10 +18,11 @@ use leptos::{component, prelude::*, view, IntoView};
 
 use crate::{
     modals::{
-        discover_extensions::DiscoverExtensionsModal, login_modal::LoginModal,
+        discover_extensions::DiscoverExtensionsModal,
+        extension_permissions_modal::ExtensionPermissionsModal, login_modal::LoginModal,
         new_playlist_modal::NewPlaylistModal, new_theme_modal::NewThemeModal,
-        signout_modal::SignoutModal, song_from_url_modal::SongFromUrlModal,
-        update_modal::UpdateModal,
+        saved_queues_modal::SavedQueuesModal, signout_modal::SignoutModal,
+        song_from_url_modal::SongFromUrlModal, update_modal::UpdateModal,
     },
     store::modal_store::{ModalStore, Modals},
 };
@@ -49,6 +50,7 @@ pub fn ModalManager() -> impl IntoView {
                             .into_any()
                     }
                     Modals::SongFromUrlModal => view! { <SongFromUrlModal /> }.into_any(),
+                    Modals::SavedQueuesModal => view! { <SavedQueuesModal /> }.into_any(),
                     Modals::SignoutModal(key, name, account_id) => {
                         view! { <SignoutModal key=key name=name account_id=account_id /> }
                             .into_any()
@@ -59,6 +61,9 @@ pub fn ModalManager() -> impl IntoView {
                     Modals::UpdateModal(metadata) => {
                         view! { <UpdateModal metadata=metadata /> }.into_any()
                     }
+                    Modals::ExtensionPermissionsModal(extension) => {
+                        view! { <ExtensionPermissionsModal extension=extension /> }.into_any()
+                    }
                 }
             }}
 
@@ -0,0 +1,152 @@
+// Moosync
+// Copyright (C) 2024, 2025  Moosync <support@moosync.app>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use leptos::task::spawn_local;
+use leptos::{component, prelude::*, view, IntoView};
+use leptos_i18n::{t, t_string};
+use types::queue::SavedQueue;
+
+use crate::i18n::use_i18n;
+use crate::modals::common::GenericModal;
+use crate::store::{modal_store::ModalStore, player_store::PlayerStore};
+use crate::utils::invoke::{
+    get_saved_queues, remove_saved_queue, save_current_queue, saved_queue_to_playlist,
+};
+
+#[tracing::instrument(level = "debug", skip())]
+#[component]
+pub fn SavedQueuesModal() -> impl IntoView {
+    let i18n = use_i18n();
+    let modal_store: RwSignal<ModalStore> = expect_context();
+    let player_store: RwSignal<PlayerStore> = expect_context();
+    let close_modal = move || modal_store.update(|m| m.clear_active_modal());
+
+    let saved_queues = RwSignal::new(Vec::<SavedQueue>::new());
+    let queue_name = RwSignal::new("".to_string());
+
+    let refresh = move || {
+        spawn_local(async move {
+            match get_saved_queues().await {
+                Ok(queues) => saved_queues.set(queues),
+                Err(e) => tracing::error!("Failed to get saved queues: {:?}", e),
+            }
+        });
+    };
+    refresh();
+
+    let save_current = move |_| {
+        let name = queue_name.get_untracked();
+        let position = player_store.with_untracked(|p| p.get_current_time());
+        queue_name.set("".to_string());
+        spawn_local(async move {
+            if let Err(e) = save_current_queue(None, name, position).await {
+                tracing::error!("Failed to save queue: {:?}", e);
+            }
+            refresh();
+        });
+    };
+
+    let load_queue = move |id: String| {
+        player_store.update(|p| p.load_saved_queue(id));
+        close_modal();
+    };
+
+    let convert_to_playlist = move |id: String| {
+        spawn_local(async move {
+            if let Err(e) = saved_queue_to_playlist(id).await {
+                tracing::error!("Failed to create playlist from saved queue: {:?}", e);
+            }
+        });
+    };
+
+    let remove_queue = move |id: String| {
+        spawn_local(async move {
+            if let Err(e) = remove_saved_queue(id).await {
+                tracing::error!("Failed to remove saved queue: {:?}", e);
+            }
+            refresh();
+        });
+    };
+
+    view! {
+        <GenericModal size=move || "modal-lg".into()>
+            <div class="modal-content-container">
+                <div class="container-fluid p-0">
+                    <div class="row no-gutters d-flex">
+                        <div class="col">
+                            <input
+                                class="form-control ext-input"
+                                type="text"
+                                placeholder=move || t_string!(i18n, queue.namePlaceholder)
+                                prop:value=move || queue_name.get()
+                                on:input=move |e| queue_name.set(event_target_value(&e))
+                            />
+                        </div>
+                        <div class="col-auto ml-3">
+                            <div class="rounded-btn" on:click=save_current>
+                                {t!(i18n, queue.saveCurrent)}
+                            </div>
+                        </div>
+                    </div>
+                    <For
+                        each=move || saved_queues.get()
+                        key=|q| (q.id.clone(), q.updated_at)
+                        children=move |saved_queue| {
+                            let load_id = saved_queue.id.clone();
+                            let playlist_id = saved_queue.id.clone();
+                            let remove_id = saved_queue.id.clone();
+                            let song_count = saved_queue.songs.len();
+                            view! {
+                                <div class="row no-gutters d-flex mt-3 align-items-center">
+                                    <div class="col text-truncate">
+                                        {saved_queue.name.clone()} " - "
+                                        {move || {
+                                            t_string!(i18n, queue.songCount, count = song_count)
+                                                .to_string()
+                                        }}
+                                    </div>
+                                    <div class="col-auto d-flex">
+                                        <div
+                                            class="rounded-btn"
+                                            on:click=move |_| load_queue(load_id.clone())
+                                        >
+                                            {t!(i18n, queue.load)}
+                                        </div>
+                                        <div
+                                            class="rounded-btn"
+                                            on:click=move |_| convert_to_playlist(playlist_id.clone())
+                                        >
+                                            {t!(i18n, queue.saveAsPlaylist)}
+                                        </div>
+                                        <div
+                                            class="rounded-btn"
+                                            on:click=move |_| remove_queue(remove_id.clone())
+                                        >
+                                            {t!(i18n, queue.remove)}
+                                        </div>
+                                    </div>
+                                </div>
+                            }
+                        }
+                    />
+                </div>
+                <button class="btn btn-secondary close-button ml-3" on:click=move |_| close_modal()>
+                    {t!(i18n, queue.close)}
+                </button>
+            </div>
+        </GenericModal>
+    }
+}
@@ -19,5 +19,6 @@ pub mod artists;
 pub mod explore;
 pub mod genres;
 pub mod playlists;
+pub mod radio;
 pub mod search;
 pub mod songs;
@@ -0,0 +1,191 @@
+// Moosync
+// Copyright (C) 2024, 2025  Moosync <support@moosync.app>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::sync::Arc;
+
+use leptos::{component, prelude::*, task::spawn_local, view, IntoView};
+use leptos_i18n::{t, t_string};
+use types::{radio::QueryableStation, songs::Song, window::DialogFilter};
+
+use crate::components::cardview::{CardView, SimplifiedCardItem};
+use crate::i18n::use_i18n;
+use crate::store::player_store::PlayerStore;
+use crate::utils::context_menu::{create_context_menu, RadioStationContextMenu};
+use crate::utils::invoke::{add_stations, get_stations, import_stations};
+use crate::utils::prefs::open_file_browser_single;
+
+#[tracing::instrument(level = "debug", skip())]
+#[component()]
+pub fn AllStations() -> impl IntoView {
+    let stations = RwSignal::new(vec![]);
+
+    let owner = Owner::new();
+    let refresh_stations: Arc<Box<dyn Fn() + Send + Sync>> = Arc::new(Box::new(move || {
+        tracing::debug!("Refreshing stations");
+        owner.with(|| {
+            spawn_local(async move {
+                match get_stations().await {
+                    Ok(res) => stations.set(res),
+                    Err(e) => tracing::error!("Failed to get stations: {:?}", e),
+                }
+            });
+        });
+    }));
+    refresh_stations.as_ref()();
+
+    let player_store = expect_context::<RwSignal<PlayerStore>>();
+    let play_station = create_write_slice(player_store, |p, song| p.play_now(song));
+
+    let station_name = RwSignal::new(String::new());
+    let station_url = RwSignal::new(String::new());
+    let refresh_cb = refresh_stations.clone();
+    let add_station = move |_| {
+        let url = station_url.get_untracked();
+        if url.trim().is_empty() {
+            return;
+        }
+        let name = station_name.get_untracked();
+        let station = QueryableStation {
+            station_name: if name.trim().is_empty() {
+                url.clone()
+            } else {
+                name
+            },
+            station_url: url,
+            ..Default::default()
+        };
+        station_name.set(String::new());
+        station_url.set(String::new());
+
+        let refresh_cb = refresh_cb.clone();
+        spawn_local(async move {
+            if let Err(e) = add_stations(vec![station]).await {
+                tracing::error!("Failed to add station: {:?}", e);
+            }
+            refresh_cb.as_ref()();
+        });
+    };
+
+    let import_path = RwSignal::new(String::new());
+    let import_playlist = move |_| {
+        open_file_browser_single(
+            false,
+            vec![DialogFilter {
+                name: "Playlist (.m3u, .pls)".into(),
+                extensions: vec!["m3u".into(), "m3u8".into(), "pls".into()],
+            }],
+            import_path,
+        );
+    };
+
+    let refresh_cb = refresh_stations.clone();
+    Effect::new(move || {
+        let path = import_path.get();
+        if path.is_empty() {
+            return;
+        }
+
+        let refresh_cb = refresh_cb.clone();
+        spawn_local(async move {
+            if let Err(e) = import_stations(path).await {
+                tracing::error!("Failed to import stations: {:?}", e);
+            }
+            refresh_cb.as_ref()();
+        });
+    });
+
+    let station_context_menu = create_context_menu(RadioStationContextMenu {
+        station: None,
+        refresh_cb: refresh_stations,
+    });
+
+    let i18n = use_i18n();
+    view! {
+        <div class="w-100 h-100">
+            <div class="container-fluid song-container h-100 d-flex flex-column">
+                <div class="row page-title no-gutters">
+
+                    <div class="col-auto">{t!(i18n, pages.radio)}</div>
+                    <div class="col align-self-center"></div>
+                </div>
+
+                <div class="row no-gutters d-flex mb-3">
+                    <div class="col-3">
+                        <input
+                            class="form-control ext-input"
+                            type="text"
+                            placeholder=move || t_string!(i18n, radio.name_placeholder)
+                            prop:value=move || station_name.get()
+                            on:input=move |e| station_name.set(event_target_value(&e))
+                        />
+                    </div>
+                    <div class="col ml-3">
+                        <input
+                            class="form-control ext-input"
+                            type="text"
+                            placeholder=move || t_string!(i18n, radio.url_placeholder)
+                            prop:value=move || station_url.get()
+                            on:input=move |e| station_url.set(event_target_value(&e))
+                        />
+                    </div>
+                    <div class="col-auto ml-3 d-flex">
+                        <div class="rounded-btn" on:click=add_station>
+                            {t!(i18n, radio.add)}
+                        </div>
+                        <div class="rounded-btn" on:click=import_playlist>
+                            {t!(i18n, radio.import)}
+                        </div>
+                    </div>
+                </div>
+
+                <div
+                    class="row no-gutters w-100 flex-grow-1"
+                    style="align-items: flex-start; height: 70%"
+                >
+                    <CardView
+                        items=stations
+                        key=|a| a.station_id.clone()
+                        songs_view=true
+                        on_click=Box::new(move |station: QueryableStation| {
+                            play_station.set(Song::from(station))
+                        })
+                        card_item=move |(_, item)| {
+                            let station_context_menu = station_context_menu.clone();
+                            SimplifiedCardItem {
+                                title: item.station_name.clone(),
+                                cover: item.cover.clone(),
+                                id: item.clone(),
+                                icon: None,
+                                context_menu: Some(
+                                    Arc::new(
+                                        Box::new(move |ev, station| {
+                                            ev.prevent_default();
+                                            ev.stop_propagation();
+                                            let mut data = station_context_menu.get_data();
+                                            data.station = Some(station);
+                                            drop(data);
+                                            station_context_menu.show(ev);
+                                        }),
+                                    ),
+                                ),
+                            }
+                        }
+                    />
+                </div>
+            </div>
+        </div>
+    }
+}
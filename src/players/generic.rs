@@ -30,7 +30,14 @@ pub type PlayerEventsSender = Rc<Box<dyn Fn(PlayerEvents)>>;
 pub trait GenericPlayer: std::fmt::Debug + DynClone {
     fn initialize(&self, element: NodeRef<Div>);
     fn key(&self) -> String;
-    fn load(&self, src: String, autoplay: bool, resolver: OneShotSender<()>);
+    fn load(
+        &self,
+        src: String,
+        song_id: Option<String>,
+        song_type: SongType,
+        autoplay: bool,
+        resolver: OneShotSender<()>,
+    );
     fn stop(&mut self) -> Result<()>;
     fn play(&self) -> Result<()>;
     fn pause(&self) -> Result<()>;
@@ -17,7 +17,7 @@
 use std::{cell::RefCell, rc::Rc, sync::Mutex, time::Duration};
 
 use leptos::{leptos_dom::helpers::IntervalHandle, prelude::*};
-use types::{preferences::CheckboxPreference, ui::player_details::PlayerEvents};
+use types::{preferences::CheckboxPreference, songs::SongType, ui::player_details::PlayerEvents};
 use wasm_bindgen::JsValue;
 use wasm_bindgen_futures::spawn_local;
 
@@ -209,7 +209,14 @@ impl GenericPlayer for LibrespotPlayer {
     }
 
     #[tracing::instrument(level = "debug", skip(self, src, resolver))]
-    fn load(&self, src: String, autoplay: bool, resolver: tokio::sync::oneshot::Sender<()>) {
+    fn load(
+        &self,
+        src: String,
+        _song_id: Option<String>,
+        _song_type: SongType,
+        autoplay: bool,
+        resolver: tokio::sync::oneshot::Sender<()>,
+    ) {
         let player_state_tx = self.player_state_tx.clone();
         spawn_local(async move {
             let res = librespot_load(src.clone(), false).await;
@@ -127,7 +127,14 @@ impl GenericPlayer for LocalPlayer {
     }
 
     #[tracing::instrument(level = "debug", skip(self, src, resolver))]
-    fn load(&self, src: String, autoplay: bool, resolver: OneShotSender<()>) {
+    fn load(
+        &self,
+        src: String,
+        _song_id: Option<String>,
+        _song_type: SongType,
+        autoplay: bool,
+        resolver: OneShotSender<()>,
+    ) {
         let mut src = convert_file_src(src);
         tracing::debug!("Loading audio {}", src);
 
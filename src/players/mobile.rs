@@ -14,7 +14,7 @@
 // You should have received a copy of the GNU General Public License
 // along with this program.  If not, see <http://www.gnu.org/licenses/>.
 
-use std::rc::Rc;
+use std::{cell::Cell, rc::Rc};
 
 use leptos::{html::Div, prelude::*, task::spawn_local};
 
@@ -25,7 +25,7 @@ use wasm_bindgen::JsValue;
 
 use crate::utils::{
     common::listen_plugin_event,
-    invoke::{mobile_load, mobile_pause, mobile_play, mobile_seek, mobile_stop},
+    invoke::{mobile_load, mobile_pause, mobile_play, mobile_seek, mobile_set_volume, mobile_stop},
 };
 
 use super::generic::{GenericPlayer, PlayerEventsSender};
@@ -46,6 +46,8 @@ pub struct MobilePlayer {
     key: String,
     listeners: Vec<js_sys::Function>,
     event_tx: Option<PlayerEventsSender>,
+    // Last volume passed to the native player, from 0 to 100
+    volume: Rc<Cell<f64>>,
 }
 
 macro_rules! listen_event {
@@ -92,6 +94,7 @@ impl MobilePlayer {
             key,
             listeners: vec![],
             event_tx: None,
+            volume: Rc::new(Cell::new(100f64)),
         }
     }
 
@@ -117,7 +120,14 @@ impl GenericPlayer for MobilePlayer {
     }
 
     #[tracing::instrument(level = "debug", skip(self, src, resolver))]
-    fn load(&self, src: String, autoplay: bool, resolver: OneShotSender<()>) {
+    fn load(
+        &self,
+        src: String,
+        _song_id: Option<String>,
+        _song_type: SongType,
+        autoplay: bool,
+        resolver: OneShotSender<()>,
+    ) {
         tracing::debug!("Loading audio {}", src);
 
         let key = self.key.clone();
@@ -164,14 +174,22 @@ impl GenericPlayer for MobilePlayer {
         ]
     }
 
-    #[tracing::instrument(level = "debug", skip(self, _volume))]
-    fn set_volume(&self, _volume: f64) -> Result<()> {
+    #[tracing::instrument(level = "debug", skip(self, volume))]
+    fn set_volume(&self, volume: f64) -> Result<()> {
+        self.volume.set(volume);
+        let key = self.key.clone();
+        spawn_local(async move {
+            let res = mobile_set_volume(key, volume / 100f64).await;
+            if let Err(e) = res {
+                tracing::error!("Failed to set volume in mobile player {:?}", e);
+            }
+        });
         Ok(())
     }
 
     #[tracing::instrument(level = "debug", skip(self))]
     fn get_volume(&self) -> Result<f64> {
-        Ok(100f64)
+        Ok(self.volume.get())
     }
 
     #[tracing::instrument(level = "debug", skip(self, tx))]
@@ -58,10 +58,17 @@ impl GenericPlayer for RodioPlayer {
         "rodio".into()
     }
 
-    #[tracing::instrument(level = "debug", skip(self, src, resolver))]
-    fn load(&self, src: String, autoplay: bool, resolver: tokio::sync::oneshot::Sender<()>) {
+    #[tracing::instrument(level = "debug", skip(self, src, song_id, resolver))]
+    fn load(
+        &self,
+        src: String,
+        song_id: Option<String>,
+        song_type: SongType,
+        autoplay: bool,
+        resolver: tokio::sync::oneshot::Sender<()>,
+    ) {
         spawn_local(async move {
-            let res = rodio_load(src).await;
+            let res = rodio_load(src, song_id, Some(song_type)).await;
             if let Err(err) = res {
                 tracing::error!("Rodio error {:?}", err);
             } else {
@@ -143,6 +150,8 @@ impl GenericPlayer for RodioPlayer {
             SongType::URL,
             SongType::YOUTUBE,
             SongType::SPOTIFY,
+            SongType::HLS,
+            SongType::DASH,
         ]
     }
 
@@ -261,6 +270,7 @@ impl GenericPlayer for RodioPlayer {
                     tx(PlayerEvents::TimeUpdate(pos));
                 }
                 PlayerEvents::Error(_) => stop_timer(timer.clone(), time.clone(), tx.clone()),
+                PlayerEvents::MetadataChanged(_) => {}
             }
 
             let tx = tx.borrow_mut();
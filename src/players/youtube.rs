@@ -126,7 +126,14 @@ impl GenericPlayer for YoutubePlayer {
     }
 
     #[tracing::instrument(level = "debug", skip(self, src, resolver))]
-    fn load(&self, src: String, autoplay: bool, resolver: OneShotSender<()>) {
+    fn load(
+        &self,
+        src: String,
+        _song_id: Option<String>,
+        _song_type: SongType,
+        autoplay: bool,
+        resolver: OneShotSender<()>,
+    ) {
         self.player.load(src.as_str(), false);
         self.last_src.set(Some(src.clone()));
         tracing::debug!("Loaded youtube embed {}, {}", src, autoplay);
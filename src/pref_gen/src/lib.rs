@@ -114,7 +114,7 @@ fn generate_component(config: &PreferenceUIFile) -> proc_macro2::TokenStream {
 
     quote! {
         use crate::components::{
-            prefs::components::{CheckboxPref, ExtensionPref, InputPref, PathsPref, ThemesPref, DropdownPref},
+            prefs::components::{CheckboxPref, ExtensionLogsPage, ExtensionPref, ExtensionSettingsPage, InputPref, PathsPref, ThemesPref, DropdownPref, ShortcutsPref, AudioCachePref, RemoteTokenPref},
             sidebar::{Sidebar, Tab},
         };
         use crate::i18n::*;
@@ -159,6 +159,8 @@ fn generate_component(config: &PreferenceUIFile) -> proc_macro2::TokenStream {
             view! {
                 <ParentRoute path=path!("/prefs") view=PrefApp >
                     #(#routes)*
+                    <Route path=path!("extension") view=ExtensionSettingsPage />
+                    <Route path=path!("extension_logs") view=ExtensionLogsPage />
                     <Route path=path!("") view=RedirectPrefs />
                 </ParentRoute>
             }.into_inner()
@@ -179,6 +181,9 @@ fn generate_children(data: &[PreferenceUIData]) -> Vec<(syn::Ident, proc_macro2:
             types::preferences::PreferenceTypes::ThemeSelector => generate_themes(item),
             types::preferences::PreferenceTypes::Extensions => generate_extensions(item),
             types::preferences::PreferenceTypes::Dropdown => generate_dropdowns(item),
+            types::preferences::PreferenceTypes::Shortcuts => generate_shortcuts(item),
+            types::preferences::PreferenceTypes::AudioCache => generate_audio_cache(item),
+            types::preferences::PreferenceTypes::RemoteToken => generate_remote_token(item),
             types::preferences::PreferenceTypes::ButtonGroup
             | types::preferences::PreferenceTypes::InfoField
             | types::preferences::PreferenceTypes::ProgressBar
@@ -265,6 +270,7 @@ fn generate_input(data: &PreferenceUIData) -> (syn::Ident, proc_macro2::TokenStr
         PreferenceTypes::EditText => match data.input_type.clone().unwrap() {
             InputType::Text => (true, "text"),
             InputType::Number => (true, "number"),
+            InputType::Password => (true, "password"),
         },
         // Below case should never happen
         _ => (true, ""),
@@ -399,3 +405,115 @@ fn generate_dropdowns(data: &PreferenceUIData) -> (syn::Ident, proc_macro2::Toke
 
     (fn_name, stream)
 }
+
+#[tracing::instrument(level = "debug", skip(data))]
+fn generate_shortcuts(data: &PreferenceUIData) -> (syn::Ident, proc_macro2::TokenStream) {
+    let key = data.key.clone();
+    let mobile = data.mobile.unwrap_or(true);
+
+    let name = get_path(data.title.clone());
+
+    let tooltip = get_path(data.description.clone());
+
+    let fn_name = syn::Ident::new(
+        format!("Shortcuts{}Pref", data.key)
+            .replace(".", "")
+            .as_str(),
+        proc_macro2::Span::call_site(),
+    );
+
+    let mut shortcuts = vec![];
+    for items in data.items.clone().unwrap() {
+        let item_key = items.key.clone();
+        let item_name = get_path(items.title.clone());
+
+        let stream = quote! {
+            CheckboxItems {
+                title: t!(i18n, #item_name)().to_html(),
+                key: #item_key.to_string(),
+            },
+        };
+
+        shortcuts.push(stream);
+    }
+
+    let stream = quote! {
+        #[component]
+        pub fn #fn_name() -> impl IntoView {
+            let i18n = use_i18n();
+            let shortcut_items = vec![
+                #(#shortcuts)*
+            ];
+
+            view! {
+                <ShortcutsPref
+                    mobile=#mobile
+                    key=#key.to_string()
+                    title=t!(i18n, #name)
+                    tooltip=t!(i18n, #tooltip)
+                    items=shortcut_items
+                />
+            }
+        }
+    };
+
+    (fn_name, stream)
+}
+
+#[tracing::instrument(level = "debug", skip(data))]
+fn generate_audio_cache(data: &PreferenceUIData) -> (syn::Ident, proc_macro2::TokenStream) {
+    let mobile = data.mobile.unwrap_or(true);
+
+    let name = get_path(data.title.clone());
+
+    let tooltip = get_path(data.description.clone());
+
+    let fn_name = syn::Ident::new(
+        format!("AudioCache{}Pref", data.key)
+            .replace(".", "")
+            .as_str(),
+        proc_macro2::Span::call_site(),
+    );
+
+    let stream = quote! {
+        #[component]
+        pub fn #fn_name() -> impl IntoView {
+            let i18n = use_i18n();
+
+            view! {
+                <AudioCachePref title=t!(i18n, #name) tooltip=t!(i18n, #tooltip) mobile=#mobile />
+            }
+        }
+    };
+
+    (fn_name, stream)
+}
+
+#[tracing::instrument(level = "debug", skip(data))]
+fn generate_remote_token(data: &PreferenceUIData) -> (syn::Ident, proc_macro2::TokenStream) {
+    let mobile = data.mobile.unwrap_or(true);
+
+    let name = get_path(data.title.clone());
+
+    let tooltip = get_path(data.description.clone());
+
+    let fn_name = syn::Ident::new(
+        format!("RemoteToken{}Pref", data.key)
+            .replace(".", "")
+            .as_str(),
+        proc_macro2::Span::call_site(),
+    );
+
+    let stream = quote! {
+        #[component]
+        pub fn #fn_name() -> impl IntoView {
+            let i18n = use_i18n();
+
+            view! {
+                <RemoteTokenPref title=t!(i18n, #name) tooltip=t!(i18n, #tooltip) mobile=#mobile />
+            }
+        }
+    };
+
+    (fn_name, stream)
+}
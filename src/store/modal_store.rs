@@ -18,7 +18,7 @@ use std::sync::Arc;
 
 use types::{
     songs::Song,
-    ui::{themes::ThemeModalState, updater::UpdateMetadata},
+    ui::{extensions::ExtensionDetail, themes::ThemeModalState, updater::UpdateMetadata},
 };
 
 use crate::modals::new_playlist_modal::PlaylistModalState;
@@ -30,8 +30,10 @@ pub enum Modals {
     DiscoverExtensions,
     NewPlaylistModal(PlaylistModalState, Option<Vec<Song>>),
     SongFromUrlModal,
+    SavedQueuesModal,
     ThemeModal(Box<ThemeModalState>),
     UpdateModal(UpdateMetadata),
+    ExtensionPermissionsModal(ExtensionDetail),
 }
 
 #[derive(Clone, Default)]
@@ -15,35 +15,53 @@
 // along with this program.  If not, see <http://www.gnu.org/licenses/>.
 
 use bitcode::{Decode, Encode};
+use futures::{
+    channel::mpsc::{unbounded, UnboundedReceiver, UnboundedSender},
+    StreamExt,
+};
 use indexed_db_futures::{database::Database, prelude::*};
 use itertools::Itertools;
 use leptos::prelude::*;
-use rand::seq::SliceRandom;
-use serde::Serialize;
-use std::{cmp::min, collections::HashMap};
+use std::collections::HashMap;
 use types::{
+    entities::QueryableArtist,
     preferences::CheckboxPreference,
+    queue::{QueueChanged, QueueState, SavedQueue, ShuffleState},
     songs::Song,
     ui::extensions::ExtensionExtraEvent,
-    ui::player_details::{PlayerState, RepeatModes, VolumeMode},
+    ui::player_details::{
+        PlayerState, RepeatModes, SleepTimer, SleepTimerTrigger, StreamMetadata, VolumeMode,
+    },
 };
+use wasm_bindgen::JsValue;
 use wasm_bindgen_futures::spawn_local;
 
 use crate::{
     store::ui_store::UiStore,
     utils::{
-        db_utils::{read_from_indexed_db, write_to_indexed_db},
+        common::listen_event,
+        db_utils::{read_from_indexed_db, remove_from_indexed_db, write_to_indexed_db},
         extensions::send_extension_event,
-        mpris::{set_playback_state, set_position},
+        invoke,
+        mpris::{set_metadata, set_playback_state, set_position, set_seeked, set_volume},
+        scrobbler::{scrobble_add_played, scrobble_now_playing},
+        songs::save_resume_point,
     },
 };
 
-#[derive(Debug, Default, PartialEq, Clone, Serialize, Encode, Decode)]
-pub struct Queue {
-    pub song_queue: Vec<String>,
-    pub current_index: usize,
-    pub data: HashMap<String, Song>,
-}
+pub use types::queue::Queue;
+
+/// Keys under which older versions kept the queue in IndexedDB
+const LEGACY_QUEUE_KEYS: [&str; 3] = ["dump_song_queue", "dump_current_index", "dump_queue_data"];
+
+/// Seconds of playback collected before they are reported to the scrobbler
+const SCROBBLE_REPORT_INTERVAL: f64 = 5f64;
+
+/// Seconds of playback between saves of the position restored on startup
+const POSITION_DUMP_INTERVAL: f64 = 5f64;
+
+/// Seconds of playback between saves of the resume point of long songs
+const RESUME_SAVE_INTERVAL: f64 = 15f64;
 
 #[derive(Debug, Default, Clone, Encode, Decode)]
 pub struct PlayerDetails {
@@ -73,17 +91,50 @@ pub struct PlayerStoreData {
 #[derive(Debug)]
 pub struct PlayerStore {
     pub data: PlayerStoreData,
+    stream_metadata: Option<StreamMetadata>,
+    shuffle: ShuffleState,
+    autoplay: bool,
+    sleep_timer: Option<SleepTimer>,
+    // Multiplier applied to the volume while the sleep timer fades out
+    sleep_fade: f64,
     scrobble_time: f64,
+    // Part of scrobble_time already sent to the built in scrobbler
+    scrobble_reported: f64,
     scrobbled: bool,
+    // Position restored from the last session, applied once its song is loaded
+    resume_position: Option<(String, f64)>,
+    loaded_song: Option<String>,
+    position_dumped: f64,
+    resume_saved: f64,
     is_mobile: bool,
+    queue_tx: UnboundedSender<QueueCommand>,
 }
 
 #[derive(Debug)]
 enum DumpType {
     PlayerState,
-    SongQueue,
-    CurrentIndex,
-    QueueData,
+    Position,
+}
+
+/// Changes requested from the queue service in the backend. They are sent in
+/// order, and the store is updated once the service reports the new state.
+#[derive(Debug)]
+enum QueueCommand {
+    Add(Vec<Song>),
+    PlayNow(Vec<Song>),
+    PlayNext(Vec<Song>),
+    Remove(usize),
+    ChangeIndex(usize, bool),
+    Next,
+    Prev,
+    ToggleRepeat,
+    SetShuffle(bool, bool),
+    ToggleShuffle,
+    Shuffle,
+    SetAutoplay(bool),
+    Clear,
+    ClearExceptCurrent,
+    LoadSaved(String, f64),
 }
 
 impl PlayerStore {
@@ -95,26 +146,158 @@ impl PlayerStore {
         let ui_store = expect_context::<RwSignal<UiStore>>();
         let is_mobile = create_read_slice(ui_store, |u| u.get_is_mobile()).get();
 
+        let (queue_tx, queue_rx) = unbounded();
+
         let player_store = Self {
             data: PlayerStoreData::default(),
+            stream_metadata: None,
+            shuffle: ShuffleState::default(),
+            autoplay: false,
+            sleep_timer: None,
+            sleep_fade: 1f64,
             scrobble_time: 0f64,
+            scrobble_reported: 0f64,
             scrobbled: false,
+            resume_position: None,
+            loaded_song: None,
+            position_dumped: 0f64,
+            resume_saved: 0f64,
             is_mobile,
+            queue_tx,
         };
 
         tracing::debug!("Created player store {:?}", player_store);
         let signal = RwSignal::new(player_store);
 
+        Self::run_queue_commands(queue_rx, signal);
         Self::load_state_from_idb(signal);
+        Self::listen_queue_changes(signal);
 
         signal
     }
 
+    #[tracing::instrument(level = "debug", skip(rx, signal))]
+    fn run_queue_commands(mut rx: UnboundedReceiver<QueueCommand>, signal: RwSignal<PlayerStore>) {
+        spawn_local(async move {
+            while let Some(command) = rx.next().await {
+                tracing::debug!("Sending queue command {:?}", command);
+                let res = match command {
+                    QueueCommand::Add(songs) => invoke::add_to_queue(songs).await,
+                    QueueCommand::PlayNow(songs) => invoke::play_now(songs).await,
+                    QueueCommand::PlayNext(songs) => invoke::play_next(songs).await,
+                    QueueCommand::Remove(index) => invoke::remove_from_queue(index).await,
+                    QueueCommand::ChangeIndex(index, force) => {
+                        invoke::change_queue_index(index, force).await
+                    }
+                    QueueCommand::Next => invoke::next_song().await,
+                    QueueCommand::Prev => invoke::prev_song().await,
+                    QueueCommand::ToggleRepeat => invoke::toggle_repeat().await,
+                    QueueCommand::SetShuffle(enabled, avoid_same_artist) => {
+                        invoke::set_shuffle(enabled, avoid_same_artist).await
+                    }
+                    QueueCommand::ToggleShuffle => invoke::toggle_shuffle().await,
+                    QueueCommand::Shuffle => invoke::shuffle_queue().await,
+                    QueueCommand::SetAutoplay(enabled) => invoke::set_autoplay(enabled).await,
+                    QueueCommand::Clear => invoke::clear_queue().await,
+                    QueueCommand::ClearExceptCurrent => invoke::clear_queue_except_current().await,
+                    QueueCommand::LoadSaved(id, position) => {
+                        invoke::load_saved_queue(id, position)
+                            .await
+                            .map(|saved| signal.update(|s| s.resume_saved_queue(saved)))
+                    }
+                };
+                if let Err(e) = res {
+                    tracing::error!("Queue command failed: {:?}", e);
+                }
+            }
+        });
+    }
+
+    #[tracing::instrument(level = "debug", skip(signal))]
+    fn listen_queue_changes(signal: RwSignal<PlayerStore>) {
+        listen_event("queue-changed", move |data| {
+            let payload = js_sys::Reflect::get(&data, &JsValue::from_str("payload")).unwrap();
+            match serde_wasm_bindgen::from_value::<QueueChanged>(payload) {
+                Ok(changed) => {
+                    signal.update(|s| s.set_queue_state(changed.state, changed.force_load))
+                }
+                Err(e) => tracing::error!("Failed to parse queue change: {:?}", e),
+            }
+        });
+
+        spawn_local(async move {
+            match invoke::get_queue_state().await {
+                Ok(state) => signal.update(|s| s.set_queue_state(state, false)),
+                Err(e) => tracing::error!("Failed to get queue: {:?}", e),
+            }
+        });
+    }
+
+    #[tracing::instrument(level = "debug", skip(self))]
+    fn send_queue_command(&self, command: QueueCommand) {
+        if let Err(e) = self.queue_tx.unbounded_send(command) {
+            tracing::error!("Failed to queue command: {:?}", e);
+        }
+    }
+
+    #[tracing::instrument(level = "debug", skip(self, state))]
+    fn set_queue_state(&mut self, state: QueueState, force_load: bool) {
+        self.data.queue = state.queue;
+        self.data.player_details.repeat = state.repeat;
+        self.shuffle = state.shuffle;
+        self.autoplay = state.autoplay;
+        self.update_current_song(force_load);
+    }
+
     #[tracing::instrument(level = "debug", skip(self))]
     pub fn get_current_song(&self) -> Option<Song> {
         self.data.current_song.clone()
     }
 
+    /// Current song with the title and artist sent by the stream, if any
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub fn get_now_playing(&self) -> Option<Song> {
+        let mut song = self.get_current_song()?;
+        if let Some(metadata) = &self.stream_metadata {
+            if let Some(title) = &metadata.title {
+                song.song.title = Some(title.clone());
+            }
+            if let Some(artist) = &metadata.artist {
+                song.artists = Some(vec![QueryableArtist {
+                    artist_name: Some(artist.clone()),
+                    ..Default::default()
+                }]);
+            }
+        }
+        Some(song)
+    }
+
+    #[tracing::instrument(level = "debug", skip(self, metadata))]
+    pub fn set_stream_metadata(&mut self, metadata: StreamMetadata) {
+        let metadata = if metadata == StreamMetadata::default() {
+            None
+        } else {
+            Some(metadata)
+        };
+        if metadata == self.stream_metadata {
+            return;
+        }
+
+        self.stream_metadata = metadata;
+
+        // Every title on a radio stream is a separate track to scrobble
+        self.scrobble_time = 0f64;
+        self.scrobble_reported = 0f64;
+        self.scrobbled = false;
+
+        let now_playing = self.get_now_playing();
+        if let Some(now_playing) = &now_playing {
+            set_metadata(now_playing);
+        }
+        scrobble_now_playing(now_playing.clone());
+        send_extension_event(ExtensionExtraEvent::SongChanged([now_playing]));
+    }
+
     #[tracing::instrument(level = "debug", skip(self))]
     pub fn get_queue(&self) -> Queue {
         self.data.queue.clone()
@@ -155,6 +338,21 @@ impl PlayerStore {
         self.data.player_details.repeat
     }
 
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub fn get_shuffle(&self) -> bool {
+        self.shuffle.enabled
+    }
+
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub fn get_avoid_same_artist(&self) -> bool {
+        self.shuffle.avoid_same_artist
+    }
+
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub fn get_autoplay(&self) -> bool {
+        self.autoplay
+    }
+
     #[tracing::instrument(level = "debug", skip(self))]
     pub fn get_force_seek(&self) -> f64 {
         self.data.player_details.force_seek
@@ -194,10 +392,15 @@ impl PlayerStore {
         }
 
         tracing::debug!("Upading song in queue");
-        self.data.current_song = song.clone();
-        if self.data.current_song.is_none() {
-            self.data.player_details.current_time = 0f64;
+        if let Some(last_song) = self.data.current_song.clone() {
+            save_resume_point(last_song, self.data.player_details.current_time);
         }
+        self.data.current_song = song.clone();
+        self.stream_metadata = None;
+        self.data.player_details.current_time = 0f64;
+        self.loaded_song = None;
+        self.position_dumped = 0f64;
+        self.resume_saved = 0f64;
 
         self.clear_blacklist();
 
@@ -206,60 +409,26 @@ impl PlayerStore {
         }
 
         self.scrobble_time = 0f64;
+        self.scrobble_reported = 0f64;
         self.scrobbled = false;
+        scrobble_now_playing(self.get_now_playing());
 
-        self.dump_store(&[DumpType::CurrentIndex, DumpType::PlayerState]);
+        self.dump_store(&[DumpType::PlayerState]);
     }
 
     #[tracing::instrument(level = "debug", skip(self, songs))]
     pub fn add_to_queue(&mut self, songs: Vec<Song>) {
-        self.add_to_queue_at_index(songs, self.data.queue.song_queue.len());
-        self.update_current_song(false);
-    }
-
-    #[tracing::instrument(level = "debug", skip(self, songs, index))]
-    fn add_to_queue_at_index(&mut self, songs: Vec<Song>, index: usize) {
-        let mut index = index;
-        for song in songs {
-            self.insert_song_at_index(song, index, false);
-            index += 1;
-        }
-
-        self.dump_store(&[DumpType::QueueData, DumpType::SongQueue]);
+        self.send_queue_command(QueueCommand::Add(songs));
     }
 
     #[tracing::instrument(level = "debug", skip(self, index))]
     pub fn remove_from_queue(&mut self, index: usize) {
-        self.data.queue.song_queue.remove(index);
-        if self.data.queue.current_index > index {
-            self.data.queue.current_index -= 1;
-        }
-
-        if self.data.queue.current_index == index {
-            self.update_current_song(false);
-        }
-
-        self.dump_store(&[DumpType::SongQueue, DumpType::QueueData]);
-    }
-
-    #[tracing::instrument(level = "debug", skip(self, song, index))]
-    fn insert_song_at_index(&mut self, song: Song, index: usize, dump: bool) {
-        let song_id = song.song._id.clone().unwrap();
-        self.data.queue.data.insert(song_id.clone(), song);
-        let insertion_index = min(self.data.queue.song_queue.len(), index);
-        self.data.queue.song_queue.insert(insertion_index, song_id);
-
-        if dump {
-            self.dump_store(&[DumpType::QueueData, DumpType::SongQueue]);
-        }
+        self.send_queue_command(QueueCommand::Remove(index));
     }
 
     #[tracing::instrument(level = "debug", skip(self, song))]
     pub fn play_now(&mut self, song: Song) {
-        self.set_state(PlayerState::Playing);
-        self.insert_song_at_index(song, self.data.queue.current_index + 1, true);
-        self.data.queue.current_index += 1;
-        self.update_current_song(true);
+        self.play_now_multiple(vec![song]);
     }
 
     #[tracing::instrument(level = "debug", skip(self, songs))]
@@ -268,19 +437,13 @@ impl PlayerStore {
             return;
         }
 
-        let first_song = songs.first();
-        if let Some(first_song) = first_song {
-            self.play_now(first_song.clone())
-        }
-
-        if songs.len() > 1 {
-            self.add_to_queue_at_index(songs[1..].to_vec(), self.data.queue.current_index + 1);
-        }
+        self.set_state(PlayerState::Playing);
+        self.send_queue_command(QueueCommand::PlayNow(songs));
     }
 
     #[tracing::instrument(level = "debug", skip(self, song))]
     pub fn play_next(&mut self, song: Song) {
-        self.insert_song_at_index(song, self.data.queue.current_index + 1, true);
+        self.play_next_multiple(vec![song]);
     }
 
     #[tracing::instrument(level = "debug", skip(self, songs))]
@@ -289,20 +452,12 @@ impl PlayerStore {
             return;
         }
 
-        let first_song = songs.first();
-        if let Some(first_song) = first_song {
-            self.play_next(first_song.clone())
-        }
-
-        if songs.len() > 1 {
-            self.add_to_queue_at_index(songs[1..].to_vec(), self.data.queue.current_index + 1);
-        }
+        self.send_queue_command(QueueCommand::PlayNext(songs));
     }
 
     #[tracing::instrument(level = "debug", skip(self, new_index))]
     pub fn change_index(&mut self, new_index: usize, force: bool) {
-        self.data.queue.current_index = new_index;
-        self.update_current_song(force);
+        self.send_queue_command(QueueCommand::ChangeIndex(new_index, force));
     }
 
     #[tracing::instrument(level = "debug", skip(self, new_time))]
@@ -311,15 +466,153 @@ impl PlayerStore {
         self.data.player_details.current_time = new_time;
 
         if self.scrobble_time > 20f64 && !self.scrobbled {
-            if let Some(current_song) = self.get_current_song() {
+            if let Some(current_song) = self.get_now_playing() {
                 self.scrobbled = true;
                 send_extension_event(ExtensionExtraEvent::Scrobble([current_song]));
             }
         }
 
+        if self.scrobble_time - self.scrobble_reported >= SCROBBLE_REPORT_INTERVAL {
+            scrobble_add_played(self.scrobble_time - self.scrobble_reported);
+            self.scrobble_reported = self.scrobble_time;
+        }
+
+        if (new_time - self.position_dumped).abs() >= POSITION_DUMP_INTERVAL {
+            self.position_dumped = new_time;
+            self.dump_store(&[DumpType::Position]);
+        }
+
+        if (new_time - self.resume_saved).abs() >= RESUME_SAVE_INTERVAL {
+            self.resume_saved = new_time;
+            if let Some(current_song) = self.data.current_song.clone() {
+                save_resume_point(current_song, new_time);
+            }
+        }
+
+        self.update_sleep_fade();
         set_position(new_time);
     }
 
+    /// Marks the song as loaded by the player and returns the position it should resume from,
+    /// if it was playing when the app was closed
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub fn song_loaded(&mut self, id: String) -> Option<f64> {
+        self.loaded_song = Some(id.clone());
+        match self.resume_position.take() {
+            Some((song_id, time)) if song_id == id => Some(time),
+            _ => None,
+        }
+    }
+
+    /// Seeks without counting the skipped part as played
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub fn resume_from(&mut self, time: f64) {
+        self.data.player_details.current_time = time;
+        self.position_dumped = time;
+        self.resume_saved = time;
+        self.force_seek(time);
+    }
+
+    #[tracing::instrument(level = "debug", skip(self))]
+    fn restore_position(&mut self, id: String, time: f64) {
+        match &self.loaded_song {
+            Some(loaded_song) if *loaded_song == id => self.resume_from(time),
+            // Another song started playing before the position was restored
+            Some(_) => {}
+            None => self.resume_position = Some((id, time)),
+        }
+    }
+
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub fn get_sleep_timer(&self) -> Option<SleepTimer> {
+        self.sleep_timer.clone()
+    }
+
+    #[tracing::instrument(level = "debug", skip(self, sleep_timer))]
+    pub fn set_sleep_timer(&mut self, sleep_timer: Option<SleepTimer>) {
+        self.sleep_timer = sleep_timer;
+        self.sleep_fade = 1f64;
+        self.update_sleep_fade();
+    }
+
+    // Seconds of playback left before the sleep timer fires
+    #[tracing::instrument(level = "debug", skip(self))]
+    fn get_sleep_timer_remaining(&self) -> Option<f64> {
+        let sleep_timer = self.sleep_timer.as_ref()?;
+        let song_remaining = || {
+            let duration = self.data.current_song.as_ref()?.song.duration?;
+            if duration <= 0f64 {
+                return None;
+            }
+            Some(duration - self.data.player_details.current_time)
+        };
+
+        match sleep_timer.trigger {
+            SleepTimerTrigger::Duration(_) => sleep_timer
+                .ends_at
+                .map(|ends_at| (ends_at as f64 - js_sys::Date::now()) / 1000f64),
+            SleepTimerTrigger::EndOfTrack => song_remaining(),
+            SleepTimerTrigger::EndOfQueue if self.is_last_in_queue() => song_remaining(),
+            SleepTimerTrigger::EndOfQueue => None,
+        }
+    }
+
+    #[tracing::instrument(level = "debug", skip(self))]
+    fn is_last_in_queue(&self) -> bool {
+        self.data.player_details.repeat == RepeatModes::None
+            && self.data.queue.current_index + 1 >= self.data.queue.song_queue.len()
+    }
+
+    #[tracing::instrument(level = "debug", skip(self))]
+    fn update_sleep_fade(&mut self) {
+        let fade_out = self
+            .sleep_timer
+            .as_ref()
+            .map(|t| t.fade_out)
+            .unwrap_or_default();
+        self.sleep_fade = match self.get_sleep_timer_remaining() {
+            Some(remaining) if fade_out > 0 => (remaining / fade_out as f64).clamp(0f64, 1f64),
+            _ => 1f64,
+        };
+    }
+
+    /// Pauses playback once the sleep timer fires and restores the faded volume
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub fn sleep_timer_expired(&mut self) {
+        self.sleep_timer = None;
+        self.set_state(PlayerState::Paused);
+        self.sleep_fade = 1f64;
+    }
+
+    /// Called when a song ends. Returns true if the sleep timer stopped playback
+    /// instead of moving on to the next song.
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub fn handle_sleep_timer_song_end(&mut self) -> bool {
+        let Some(sleep_timer) = &self.sleep_timer else {
+            return false;
+        };
+
+        let should_stop = match sleep_timer.trigger {
+            SleepTimerTrigger::EndOfTrack => true,
+            SleepTimerTrigger::EndOfQueue => self.is_last_in_queue(),
+            SleepTimerTrigger::Duration(_) => false,
+        };
+        if !should_stop {
+            return false;
+        }
+
+        self.sleep_timer_expired();
+        // Resuming starts from the next song rather than replaying the finished one
+        self.next_song();
+
+        spawn_local(async move {
+            if let Err(err) = crate::utils::invoke::sleep_timer_expired().await {
+                tracing::error!("Failed to expire sleep timer {:?}", err);
+            }
+        });
+        true
+    }
+
     #[tracing::instrument(level = "debug", skip(self))]
     pub fn get_time(&self) -> f64 {
         self.data.player_details.current_time
@@ -335,20 +628,22 @@ impl PlayerStore {
 
         tracing::debug!("Got seek {}", new_time);
         self.data.player_details.force_seek = new_time;
-        // send_extension_event(ExtensionExtraEvent::Seeked([new_time]))
+        set_seeked(new_time);
+        send_extension_event(ExtensionExtraEvent::Seeked([new_time]))
     }
 
     #[tracing::instrument(level = "debug", skip(self, new_time))]
     pub fn force_seek(&mut self, new_time: f64) {
         self.data.player_details.force_seek = new_time;
-        // send_extension_event(ExtensionExtraEvent::Seeked([new_time]))
+        set_seeked(new_time);
+        send_extension_event(ExtensionExtraEvent::Seeked([new_time]))
     }
 
     #[tracing::instrument(level = "debug", skip(self, state))]
     pub fn set_state(&mut self, state: PlayerState) {
         tracing::debug!("Setting player state {:?}", state);
         self.data.player_details.state = state;
-        self.dump_store(&[DumpType::PlayerState]);
+        self.dump_store(&[DumpType::PlayerState, DumpType::Position]);
 
         set_playback_state(state);
         send_extension_event(ExtensionExtraEvent::PlayerStateChanged([state]))
@@ -376,6 +671,7 @@ impl PlayerStore {
             }
         }
         self.data.player_details.volume = volume;
+        set_volume(volume);
 
         self.dump_store(&[DumpType::PlayerState]);
         send_extension_event(ExtensionExtraEvent::VolumeChanged([volume]))
@@ -418,7 +714,7 @@ impl PlayerStore {
         let scale = maxv / 100f64;
         let volume = volume.clamp(0f64, 100f64);
         if volume > 0f64 {
-            return volume.ln() / scale;
+            return volume.ln() / scale * self.sleep_fade;
         }
         volume
     }
@@ -472,76 +768,74 @@ impl PlayerStore {
 
     #[tracing::instrument(level = "debug", skip(self))]
     pub fn next_song(&mut self) {
-        self.data.queue.current_index += 1;
-        if self.data.queue.current_index >= self.data.queue.song_queue.len() {
-            self.data.queue.current_index = 0;
-        }
-        self.update_current_song(true);
+        self.send_queue_command(QueueCommand::Next);
     }
 
     #[tracing::instrument(level = "debug", skip(self))]
     pub fn prev_song(&mut self) {
-        if self.data.queue.current_index == 0 {
-            self.data.queue.current_index = self.data.queue.song_queue.len() - 1;
-        } else {
-            self.data.queue.current_index -= 1;
-        }
-        self.update_current_song(false);
+        self.send_queue_command(QueueCommand::Prev);
     }
 
     #[tracing::instrument(level = "debug", skip(self))]
     pub fn toggle_repeat(&mut self) {
-        let new_mode = match self.data.player_details.repeat {
-            RepeatModes::None => RepeatModes::Once,
-            RepeatModes::Once => RepeatModes::Loop,
-            RepeatModes::Loop => RepeatModes::None,
-        };
+        self.send_queue_command(QueueCommand::ToggleRepeat);
+    }
 
-        self.data.player_details.repeat = new_mode;
-        self.dump_store(&[DumpType::PlayerState]);
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub fn toggle_shuffle(&mut self) {
+        self.send_queue_command(QueueCommand::ToggleShuffle);
+    }
+
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub fn set_avoid_same_artist(&mut self, avoid_same_artist: bool) {
+        self.send_queue_command(QueueCommand::SetShuffle(
+            self.shuffle.enabled,
+            avoid_same_artist,
+        ));
     }
 
     #[tracing::instrument(level = "debug", skip(self))]
     pub fn shuffle_queue(&mut self) {
-        let binding = self.data.queue.song_queue.clone();
-        let current_song = binding.get(self.data.queue.current_index).unwrap();
-        let mut rng = rand::thread_rng();
-        self.data.queue.song_queue.shuffle(&mut rng);
-        let new_index = self
-            .data
-            .queue
-            .song_queue
-            .iter()
-            .position(|v| v == current_song)
-            .unwrap();
-        self.data.queue.current_index = new_index;
+        self.send_queue_command(QueueCommand::Shuffle);
+    }
 
-        self.dump_store(&[DumpType::CurrentIndex, DumpType::SongQueue]);
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub fn toggle_autoplay(&mut self) {
+        self.send_queue_command(QueueCommand::SetAutoplay(!self.autoplay));
     }
 
     #[tracing::instrument(level = "debug", skip(self))]
     pub fn clear_queue(&mut self) {
-        self.data.queue.song_queue.clear();
-        self.data.queue.current_index = 0;
-        self.update_current_song(false);
+        self.send_queue_command(QueueCommand::Clear);
     }
 
     #[tracing::instrument(level = "debug", skip(self))]
     pub fn clear_queue_except_current(&mut self) {
-        let current_song = self.get_current_song();
+        self.send_queue_command(QueueCommand::ClearExceptCurrent);
+    }
 
-        let only_one_song = self.get_queue().song_queue.len() == 1;
-        self.data.queue.song_queue.clear();
-        self.data.queue.current_index = 0;
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub fn load_saved_queue(&mut self, id: String) {
+        // The replaced queue is saved with the current position
+        let position = self.get_current_time();
+        self.send_queue_command(QueueCommand::LoadSaved(id, position));
+    }
 
-        if !only_one_song {
-            if let Some(current_song) = current_song {
-                self.add_to_queue(vec![current_song]);
-            }
+    #[tracing::instrument(level = "debug", skip(self, saved))]
+    fn resume_saved_queue(&mut self, saved: SavedQueue) {
+        let Some(id) = saved
+            .songs
+            .get(saved.current_index)
+            .and_then(|s| s.song._id.clone())
+        else {
+            return;
+        };
+        // The song may already be loaded by the time the saved queue is returned
+        if self.loaded_song.as_ref() == Some(&id) {
+            self.resume_from(saved.position);
+        } else {
+            self.resume_position = Some((id, saved.position));
         }
-
-        self.update_current_song(false);
-        self.dump_store(&[DumpType::QueueData, DumpType::SongQueue]);
     }
 
     #[tracing::instrument(level = "debug", skip(self, key))]
@@ -575,17 +869,28 @@ impl PlayerStore {
                 }
                 Ok(db) => {
                     let data_signal = RwSignal::new(None);
-                    Self::restore_store(data_signal, db);
+                    let position_signal = RwSignal::new(None);
+                    Self::restore_store(data_signal, position_signal, db);
                     Effect::new(move || {
                         let data = data_signal.get();
                         signal.update(|s| {
                             if let Some(data) = data {
-                                tracing::debug!("Restored player store data {:?}", data);
-                                s.data = data;
+                                tracing::debug!("Restored player details {:?}", data);
+                                // Repeat mode is owned by the queue service
+                                let repeat = s.data.player_details.repeat;
+                                s.data.player_details = data;
+                                s.data.player_details.repeat = repeat;
                                 s.data.player_details.current_time = 0f64;
+                                set_volume(s.get_raw_volume());
                             }
                         });
                     });
+                    Effect::new(move || {
+                        if let Some((id, time)) = position_signal.get() {
+                            tracing::debug!("Restoring position {} of {}", time, id);
+                            signal.update(|s| s.restore_position(id, time));
+                        }
+                    });
                 }
             }
         });
@@ -597,20 +902,19 @@ impl PlayerStore {
 
         let data = dump_types
             .iter()
-            .map(|d| match d {
-                DumpType::PlayerState => (
+            .filter_map(|d| match d {
+                DumpType::PlayerState => Some((
                     "dump_player_state",
                     bitcode::encode(&self.data.player_details),
-                ),
-                DumpType::SongQueue => (
-                    "dump_song_queue",
-                    bitcode::encode(&self.data.queue.song_queue),
-                ),
-                DumpType::CurrentIndex => (
-                    "dump_current_index",
-                    bitcode::encode(&self.data.queue.current_index),
-                ),
-                DumpType::QueueData => ("dump_queue_data", bitcode::encode(&self.data.queue.data)),
+                )),
+                DumpType::Position => {
+                    // Nothing to save until the song is loaded and its position restored
+                    let id = self.loaded_song.clone()?;
+                    Some((
+                        "dump_position",
+                        bitcode::encode(&(id, self.data.player_details.current_time)),
+                    ))
+                }
             })
             .collect_vec();
 
@@ -629,59 +933,94 @@ impl PlayerStore {
         }
     }
 
-    #[tracing::instrument(level = "debug", skip(db, signal))]
-    fn restore_store(signal: RwSignal<Option<PlayerStoreData>>, db: Database) {
+    #[tracing::instrument(level = "debug", skip(db, signal, position_signal))]
+    fn restore_store(
+        signal: RwSignal<Option<PlayerDetails>>,
+        position_signal: RwSignal<Option<(String, f64)>>,
+        db: Database,
+    ) {
         spawn_local(async move {
-            let mut restored_data = PlayerStoreData::default();
+            Self::migrate_legacy_queue(&db).await;
 
             if let Ok(Some(bytes)) =
                 read_from_indexed_db(db.clone(), "player_store", "dump_player_state").await
             {
                 let bytes = js_sys::Uint8Array::new(&bytes).to_vec();
-                if let Ok(data) = bitcode::decode::<PlayerDetails>(&bytes) {
-                    restored_data.player_details = data;
-                }
-            }
-            if let Ok(Some(bytes)) =
-                read_from_indexed_db(db.clone(), "player_store", "dump_song_queue").await
-            {
-                let bytes = js_sys::Uint8Array::new(&bytes).to_vec();
-                if let Ok(data) = bitcode::decode::<Vec<String>>(&bytes) {
-                    restored_data.queue.song_queue = data;
-                }
-            }
-            if let Ok(Some(bytes)) =
-                read_from_indexed_db(db.clone(), "player_store", "dump_current_index").await
-            {
-                let bytes = js_sys::Uint8Array::new(&bytes).to_vec();
-                if let Ok(data) = bitcode::decode::<usize>(&bytes) {
-                    restored_data.queue.current_index = data;
+                if let Ok(mut data) = bitcode::decode::<PlayerDetails>(&bytes) {
+                    if data.state == PlayerState::Playing && Self::start_paused().await {
+                        data.state = PlayerState::Paused;
+                    }
+                    signal.set(Some(data));
                 }
             }
+
             if let Ok(Some(bytes)) =
-                read_from_indexed_db(db.clone(), "player_store", "dump_queue_data").await
+                read_from_indexed_db(db.clone(), "player_store", "dump_position").await
             {
                 let bytes = js_sys::Uint8Array::new(&bytes).to_vec();
-                if let Ok(data) = bitcode::decode::<HashMap<String, Song>>(&bytes) {
-                    restored_data.queue.data = data;
+                if let Ok(data) = bitcode::decode::<(String, f64)>(&bytes) {
+                    position_signal.set(Some(data));
                 }
             }
+        });
+    }
 
-            tracing::debug!("Restored {:?}", restored_data);
+    async fn read_legacy_key<T: for<'a> Decode<'a>>(db: &Database, key: &str) -> Option<T> {
+        let bytes = read_from_indexed_db(db.clone(), "player_store", key)
+            .await
+            .ok()
+            .flatten()?;
+        let bytes = js_sys::Uint8Array::new(&bytes).to_vec();
+        bitcode::decode(&bytes).ok()
+    }
 
-            if let Some(song_id) = restored_data
-                .queue
-                .song_queue
-                .get(restored_data.queue.current_index)
-            {
-                restored_data.current_song = restored_data.queue.data.get(song_id).cloned();
+    /// Hands a queue kept in IndexedDB by older versions to the queue service
+    /// once, then removes it
+    async fn migrate_legacy_queue(db: &Database) {
+        let Ok(Some(_)) =
+            read_from_indexed_db(db.clone(), "player_store", LEGACY_QUEUE_KEYS[0]).await
+        else {
+            return;
+        };
+
+        let queue = Queue {
+            song_queue: Self::read_legacy_key::<Vec<String>>(db, LEGACY_QUEUE_KEYS[0])
+                .await
+                .unwrap_or_default(),
+            current_index: Self::read_legacy_key::<usize>(db, LEGACY_QUEUE_KEYS[1])
+                .await
+                .unwrap_or_default(),
+            data: Self::read_legacy_key::<HashMap<String, Song>>(db, LEGACY_QUEUE_KEYS[2])
+                .await
+                .unwrap_or_default(),
+        };
+
+        if !queue.song_queue.is_empty() {
+            tracing::info!(
+                "Importing {} songs from the old queue",
+                queue.song_queue.len()
+            );
+            // Kept for the next start if the import fails
+            if let Err(e) = invoke::import_queue(queue).await {
+                tracing::error!("Failed to import old queue: {:?}", e);
+                return;
+            }
+        }
+
+        for key in LEGACY_QUEUE_KEYS {
+            if let Err(e) = remove_from_indexed_db(db, "player_store", key).await {
+                tracing::error!("Failed to remove {} from indexed db: {:?}", key, e);
             }
+        }
+    }
 
-            signal.set(Some(PlayerStoreData {
-                player_blacklist: vec![],
-                force_load_song: false,
-                ..restored_data
-            }));
-        });
+    async fn start_paused() -> bool {
+        let Ok(prefs) = invoke::load_selective("queue_settings".into()).await else {
+            return false;
+        };
+        serde_wasm_bindgen::from_value::<Vec<CheckboxPreference>>(prefs)
+            .unwrap_or_default()
+            .into_iter()
+            .any(|p| p.key == "start_paused" && p.enabled)
     }
 }
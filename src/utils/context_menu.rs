@@ -27,8 +27,12 @@ use leptos_router::{
 };
 use types::{
     entities::{QueryableArtist, QueryablePlaylist},
+    radio::QueryableStation,
     songs::Song,
-    ui::extensions::ExtensionProviderScope,
+    ui::{
+        extensions::{ContextMenuReturnType, ExtensionExtraEvent},
+        player_details::SleepTimerTrigger,
+    },
 };
 
 use crate::{
@@ -37,7 +41,6 @@ use crate::{
     store::{
         modal_store::{ModalStore, Modals},
         player_store::PlayerStore,
-        provider_store::ProviderStore,
         ui_store::UiStore,
     },
     utils::{entities::get_playlist_sort_cx_items, songs::get_songs_from_indices},
@@ -49,11 +52,46 @@ use super::{
         remove_songs_from_library,
     },
     invoke::{
-        get_playlist_context_menu, get_song_context_menu, load_theme, trigger_context_menu_action,
+        add_to_saved_queue, broadcast_extra_event, load_theme, trigger_context_menu_action,
     },
     songs::get_sort_cx_items,
 };
 
+/// Asks every extension for its entries at once. Each entry is tagged with the
+/// extension which added it.
+async fn get_extension_context_menu(event: ExtensionExtraEvent) -> Vec<ContextMenuReturnType> {
+    match broadcast_extra_event(event).await {
+        Ok(res) => serde_wasm_bindgen::from_value::<Option<Vec<ContextMenuReturnType>>>(res)
+            .unwrap_or_else(|e| {
+                tracing::error!("Failed to parse extension context menu: {:?}", e);
+                None
+            })
+            .unwrap_or_default(),
+        Err(e) => {
+            tracing::error!("Failed to get extension context menu: {:?}", e);
+            vec![]
+        }
+    }
+}
+
+/// Runs the action on the extension which added the entry
+fn trigger_extension_action(item: &ContextMenuReturnType) {
+    let Some(package_name) = item.package_name.clone() else {
+        return;
+    };
+    // Same key the extension is registered under as a provider
+    let key = format!("extension:{}", package_name);
+    let action = item.action_id.clone();
+    spawn_local(async move {
+        if let Err(e) = trigger_context_menu_action(key, action).await {
+            tracing::error!("Failed to trigger context menu action: {:?}", e);
+        }
+    });
+}
+
+/// Id of the saved queue which collects songs to play later
+const PLAY_LATER_ID: &str = "play_later";
+
 #[derive(Clone)]
 pub struct SongItemContextMenu<T>
 where
@@ -105,6 +143,20 @@ where
         player_store.update(|store| store.play_next_multiple(self.current_or_list()));
     }
 
+    /// Keeps the songs in a saved queue to be played some other time
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub fn play_later(&self) {
+        let i18n = use_i18n();
+        let name = t_string!(i18n, contextMenu.song.playLater).to_string();
+        let songs = self.current_or_list();
+        spawn_local(async move {
+            let res = add_to_saved_queue(PLAY_LATER_ID.into(), name, songs).await;
+            if let Err(e) = res {
+                tracing::error!("Failed to add songs to play later: {:?}", e);
+            }
+        });
+    }
+
     #[tracing::instrument(level = "debug", skip(self))]
     pub fn clear_queue_and_play(&self) {
         let player_store = use_context::<RwSignal<PlayerStore>>().unwrap();
@@ -270,6 +322,11 @@ where
                 |_, cx| cx.add_to_queue(),
                 None,
             ),
+            ContextMenuItemInner::new_with_handler(
+                t_string!(i18n, contextMenu.song.playLater).to_string(),
+                |_, cx| cx.play_later(),
+                None,
+            ),
             ContextMenuItemInner::new(
                 t_string!(i18n, contextMenu.playlist.add).to_string(),
                 Some(playlist_items),
@@ -294,7 +351,7 @@ where
         if location.contains("playlists/single") {
             ret.update(|ret| {
                 ret.insert(
-                    5,
+                    6,
                     ContextMenuItemInner::new_with_handler(
                         t_string!(i18n, contextMenu.song.removeFromPlaylist).to_string(),
                         |_, cx| cx.remove_from_playlist(),
@@ -304,37 +361,22 @@ where
             });
         }
 
-        let provider_store = expect_context::<Arc<ProviderStore>>();
         let song_list = self.current_or_list();
         spawn_local(async move {
-            let valid_providers =
-                provider_store.get_provider_keys(ExtensionProviderScope::SongContextMenu);
-            for key in valid_providers {
-                let song_list = song_list.clone();
-                let ctx_menu = get_song_context_menu(key.clone(), song_list).await;
-                match ctx_menu {
-                    Ok(ctx_menu) => {
-                        let key = key.clone();
-                        ret.update(move |menu| {
-                            menu.extend(ctx_menu.into_iter().map(move |v| {
-                                let key = key.clone();
-                                ContextMenuItemInner::new_with_handler(
-                                    v.name,
-                                    move |_, _| {
-                                        let key = key.clone();
-                                        let action = v.action_id.clone();
-                                        spawn_local(async move {
-                                            trigger_context_menu_action(key, action).await.unwrap()
-                                        });
-                                    },
-                                    None,
-                                )
-                            }));
-                        });
-                    }
-                    Err(e) => tracing::error!("Failed to get context menu from {}: {:?}", key, e),
-                }
-            }
+            let items =
+                get_extension_context_menu(ExtensionExtraEvent::RequestedSongContextMenu([
+                    song_list,
+                ]))
+                .await;
+            ret.update(move |menu| {
+                menu.extend(items.into_iter().map(|item| {
+                    ContextMenuItemInner::new_with_handler(
+                        item.name.clone(),
+                        move |_, _| trigger_extension_action(&item),
+                        None,
+                    )
+                }));
+            });
         });
 
         ret.read_only()
@@ -350,6 +392,100 @@ impl ContextMenuData<Self> for SortContextMenu {
     }
 }
 
+/// Fade out used by timers set from the musicbar
+const SLEEP_TIMER_FADE_OUT: u64 = 10;
+
+pub struct SleepTimerContextMenu {}
+
+impl SleepTimerContextMenu {
+    #[tracing::instrument(level = "debug", skip())]
+    fn set_timer(trigger: Option<SleepTimerTrigger>) {
+        spawn_local(async move {
+            let res = match trigger {
+                Some(trigger) => {
+                    crate::utils::invoke::set_sleep_timer(trigger, SLEEP_TIMER_FADE_OUT)
+                        .await
+                        .map(|_| ())
+                }
+                None => crate::utils::invoke::cancel_sleep_timer().await,
+            };
+            if let Err(err) = res {
+                tracing::error!("Error setting sleep timer {:?}", err);
+            }
+        });
+    }
+}
+
+impl ContextMenuData<Self> for SleepTimerContextMenu {
+    #[tracing::instrument(level = "debug", skip(self))]
+    fn get_menu_items(&self) -> ReadSignal<ContextMenuItems<Self>> {
+        let i18n = use_i18n();
+        RwSignal::new(vec![
+            ContextMenuItemInner::<Self>::new_with_handler(
+                t_string!(i18n, contextMenu.sleepTimer.minutes15).into(),
+                |_, _| Self::set_timer(Some(SleepTimerTrigger::Duration(15 * 60))),
+                None,
+            ),
+            ContextMenuItemInner::new_with_handler(
+                t_string!(i18n, contextMenu.sleepTimer.minutes30).into(),
+                |_, _| Self::set_timer(Some(SleepTimerTrigger::Duration(30 * 60))),
+                None,
+            ),
+            ContextMenuItemInner::new_with_handler(
+                t_string!(i18n, contextMenu.sleepTimer.hour1).into(),
+                |_, _| Self::set_timer(Some(SleepTimerTrigger::Duration(60 * 60))),
+                None,
+            ),
+            ContextMenuItemInner::new_with_handler(
+                t_string!(i18n, contextMenu.sleepTimer.endOfTrack).into(),
+                |_, _| Self::set_timer(Some(SleepTimerTrigger::EndOfTrack)),
+                None,
+            ),
+            ContextMenuItemInner::new_with_handler(
+                t_string!(i18n, contextMenu.sleepTimer.endOfQueue).into(),
+                |_, _| Self::set_timer(Some(SleepTimerTrigger::EndOfQueue)),
+                None,
+            ),
+            ContextMenuItemInner::new_with_handler(
+                t_string!(i18n, contextMenu.sleepTimer.cancel).into(),
+                |_, _| Self::set_timer(None),
+                None,
+            ),
+        ])
+        .read_only()
+    }
+}
+
+pub struct ShuffleContextMenu {}
+
+impl ContextMenuData<Self> for ShuffleContextMenu {
+    #[tracing::instrument(level = "debug", skip(self))]
+    fn get_menu_items(&self) -> ReadSignal<ContextMenuItems<Self>> {
+        let i18n = use_i18n();
+        let player_store = use_context::<RwSignal<PlayerStore>>().unwrap();
+        let avoid_same_artist = player_store.with_untracked(|p| p.get_avoid_same_artist());
+        RwSignal::new(vec![
+            ContextMenuItemInner::<Self>::new_with_handler(
+                if avoid_same_artist {
+                    t_string!(i18n, contextMenu.shuffle.allowSameArtist).into()
+                } else {
+                    t_string!(i18n, contextMenu.shuffle.avoidSameArtist).into()
+                },
+                move |_, _| {
+                    player_store.update(|p| p.set_avoid_same_artist(!avoid_same_artist));
+                },
+                None,
+            ),
+            ContextMenuItemInner::new_with_handler(
+                t_string!(i18n, contextMenu.shuffle.shuffleNow).into(),
+                move |_, _| player_store.update(|p| p.shuffle_queue()),
+                None,
+            ),
+        ])
+        .read_only()
+    }
+}
+
 pub struct ThemesContextMenu {
     pub id: Option<String>,
     pub refresh_cb: Arc<Box<dyn Fn() + Send + Sync>>,
@@ -522,41 +658,21 @@ impl ContextMenuData<Self> for PlaylistItemContextMenu {
                 )]);
             }
 
-            let provider_store = expect_context::<Arc<ProviderStore>>();
             let playlist = playlist.clone();
             spawn_local(async move {
-                let vaild_providers =
-                    provider_store.get_provider_keys(ExtensionProviderScope::PlaylistContextMenu);
-                for key in vaild_providers {
-                    let playlist = playlist.clone();
-                    let ctx_menu = get_playlist_context_menu(key.clone(), playlist).await;
-                    match ctx_menu {
-                        Ok(ctx_menu) => {
-                            let key = key.clone();
-                            ret.update(move |menu| {
-                                menu.extend(ctx_menu.into_iter().map(move |v| {
-                                    let key = key.clone();
-                                    ContextMenuItemInner::new_with_handler(
-                                        v.name,
-                                        move |_, _| {
-                                            let key = key.clone();
-                                            let action = v.action_id.clone();
-                                            spawn_local(async move {
-                                                trigger_context_menu_action(key, action)
-                                                    .await
-                                                    .unwrap()
-                                            });
-                                        },
-                                        None,
-                                    )
-                                }));
-                            });
-                        }
-                        Err(e) => {
-                            tracing::error!("Failed to get context menu from {}: {:?}", key, e)
-                        }
-                    }
-                }
+                let items = get_extension_context_menu(
+                    ExtensionExtraEvent::RequestedPlaylistContextMenu([playlist]),
+                )
+                .await;
+                ret.update(move |menu| {
+                    menu.extend(items.into_iter().map(|item| {
+                        ContextMenuItemInner::new_with_handler(
+                            item.name.clone(),
+                            move |_, _| trigger_extension_action(&item),
+                            None,
+                        )
+                    }));
+                });
             });
 
             return ret.read_only();
@@ -565,6 +681,40 @@ impl ContextMenuData<Self> for PlaylistItemContextMenu {
     }
 }
 
+pub struct RadioStationContextMenu {
+    pub station: Option<QueryableStation>,
+    pub refresh_cb: Arc<Box<dyn Fn() + Send + Sync>>,
+}
+
+impl RadioStationContextMenu {
+    #[tracing::instrument(level = "debug", skip(self))]
+    fn remove_station(&self) {
+        let Some(id) = self.station.as_ref().and_then(|s| s.station_id.clone()) else {
+            return;
+        };
+        let refresh_cb = self.refresh_cb.clone();
+        spawn_local(async move {
+            if let Err(err) = crate::utils::invoke::remove_station(id).await {
+                tracing::error!("Error removing station {:?}", err);
+            }
+            refresh_cb.as_ref()();
+        });
+    }
+}
+
+impl ContextMenuData<Self> for RadioStationContextMenu {
+    #[tracing::instrument(level = "debug", skip(self))]
+    fn get_menu_items(&self) -> ReadSignal<ContextMenuItems<Self>> {
+        let i18n = use_i18n();
+        RwSignal::new(vec![ContextMenuItemInner::<Self>::new_with_handler(
+            t_string!(i18n, contextMenu.radio.remove).into(),
+            |_, cx| cx.remove_station(),
+            None,
+        )])
+        .read_only()
+    }
+}
+
 pub struct SongsContextMenu {
     song_update_request: Option<Arc<Box<dyn Fn() + Send + Sync>>>,
 }
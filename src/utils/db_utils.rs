@@ -401,6 +401,25 @@ pub async fn write_to_indexed_db(
     Ok(())
 }
 
+#[tracing::instrument(level = "debug", skip(db, store, key))]
+pub async fn remove_from_indexed_db(
+    db: &Database,
+    store: &str,
+    key: &str,
+) -> Result<(), DomException> {
+    let tx = db
+        .transaction(store)
+        .with_mode(IdbTransactionMode::Readwrite)
+        .build()
+        .unwrap();
+    let store = tx.object_store(store).unwrap();
+    store.delete(key).await.unwrap();
+    tx.commit().await.unwrap();
+    tracing::debug!("Removed {} from indexed db", key);
+
+    Ok(())
+}
+
 #[tracing::instrument(level = "debug", skip(db, store, key))]
 pub async fn read_from_indexed_db(
     db: Database,
@@ -14,10 +14,13 @@
 // You should have received a copy of the GNU General Public License
 // along with this program.  If not, see <http://www.gnu.org/licenses/>.
 
-use leptos::task::spawn_local;
+use leptos::{prelude::*, task::spawn_local};
 use types::ui::extensions::{ExtensionExtraEvent, ExtensionExtraEventArgs};
 
-use crate::utils::invoke::send_extra_event;
+use crate::{
+    store::modal_store::{ModalStore, Modals},
+    utils::invoke::{get_installed_extensions, send_extra_event},
+};
 
 #[tracing::instrument(level = "debug", skip(args))]
 pub fn send_extension_event(args: ExtensionExtraEvent) {
@@ -33,3 +36,35 @@ pub fn send_extension_event(args: ExtensionExtraEvent) {
         }
     });
 }
+
+/// Asks the user to approve the permissions an installed or updated extension is waiting on.
+/// `on_close` runs once the user answered, or right away if nothing needs approval.
+#[tracing::instrument(level = "debug", skip(modal_store, on_close))]
+pub fn prompt_extension_permissions<T>(
+    modal_store: RwSignal<ModalStore>,
+    package_name: String,
+    on_close: T,
+) where
+    T: Fn() + Send + Sync + 'static,
+{
+    spawn_local(async move {
+        let extension = match get_installed_extensions().await {
+            Ok(extensions) => extensions.into_iter().find(|e| {
+                e.package_name == package_name && e.enabled && e.pending_permissions.is_some()
+            }),
+            Err(e) => {
+                tracing::error!("Failed to get installed extensions {:?}", e);
+                None
+            }
+        };
+
+        let Some(extension) = extension else {
+            on_close();
+            return;
+        };
+        modal_store.update(|m| {
+            m.set_active_modal(Modals::ExtensionPermissionsModal(extension));
+            m.on_modal_close(on_close);
+        });
+    });
+}
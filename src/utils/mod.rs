@@ -23,6 +23,7 @@ pub mod invoke;
 pub mod mpris;
 pub mod prefs;
 pub mod providers;
+pub mod scrobbler;
 pub mod songs;
 pub mod tracing_writer;
 pub mod window;
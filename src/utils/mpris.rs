@@ -16,23 +16,13 @@
 
 use std::sync::Mutex;
 
-use crate::utils::entities::get_artist_string;
 use lazy_static::lazy_static;
 use leptos::task::spawn_local;
 use types::{mpris::MprisPlayerDetails, songs::Song, ui::player_details::PlayerState};
 
 #[tracing::instrument(level = "debug", skip(song))]
 pub fn set_metadata(song: &Song) {
-    let metadata = MprisPlayerDetails {
-        title: song.song.title.clone(),
-        id: song.song._id.clone(),
-        artist_name: Some(get_artist_string(song.artists.clone())),
-        album_name: song.album.clone().map(|a| a.album_name.unwrap_or_default()),
-        album_artist: None,
-        genres: None,
-        duration: song.song.duration,
-        thumbnail: song.song.song_cover_path_high.clone(),
-    };
+    let metadata = MprisPlayerDetails::from(song);
     spawn_local(async move {
         let res = crate::utils::invoke::set_metadata(metadata).await;
         if let Err(err) = res {
@@ -75,3 +65,26 @@ pub fn set_position(duration: f64) {
         });
     }
 }
+
+/// Reports a seek right away instead of waiting for the next position update
+#[tracing::instrument(level = "debug", skip(duration))]
+pub fn set_seeked(duration: f64) {
+    *last_time_update.lock().unwrap() = wasm_timer::Instant::now();
+    spawn_local(async move {
+        let res = crate::utils::invoke::set_position(duration).await;
+        if let Err(err) = res {
+            tracing::error!("Failed to set mpris position {:?}", err);
+        }
+    });
+}
+
+#[tracing::instrument(level = "debug", skip(volume))]
+pub fn set_volume(volume: f64) {
+    spawn_local(async move {
+        // MPRIS volume goes from 0 to 1
+        let res = crate::utils::invoke::set_volume(volume / 100f64).await;
+        if let Err(err) = res {
+            tracing::error!("Failed to set mpris volume {:?}", err);
+        }
+    });
+}
@@ -60,6 +60,33 @@ where
     });
 }
 
+/// Loads a preference kept in secure storage, missing values are left as they are
+#[tracing::instrument(level = "debug", skip(key, setter))]
+pub fn load_secure<T>(key: String, setter: impl Set<Value = T> + 'static)
+where
+    T: DeserializeOwned,
+{
+    spawn_local(async move {
+        match super::invoke::get_secure(key.clone()).await {
+            Ok(value) => setter.set(serde_wasm_bindgen::from_value(value).unwrap()),
+            Err(e) => tracing::debug!("No secure preference {}: {:?}", key, e),
+        }
+    });
+}
+
+#[tracing::instrument(level = "debug", skip(key, value))]
+pub fn save_secure<T>(key: String, value: T)
+where
+    T: Serialize + 'static,
+{
+    spawn_local(async move {
+        let res = super::invoke::set_secure(key.clone(), Some(value)).await;
+        if let Err(e) = res {
+            tracing::error!("Error saving secure preference {}: {:?}", key, e);
+        }
+    });
+}
+
 #[tracing::instrument(level = "debug", skip(key))]
 #[cfg(feature = "mock")]
 pub fn load_selective_mock(key: &'static str) -> Result<Value> {
@@ -0,0 +1,38 @@
+// Moosync
+// Copyright (C) 2024, 2025  Moosync <support@moosync.app>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use leptos::task::spawn_local;
+use types::songs::Song;
+
+#[tracing::instrument(level = "debug", skip(song))]
+pub fn scrobble_now_playing(song: Option<Song>) {
+    spawn_local(async move {
+        let res = crate::utils::invoke::scrobbler_now_playing(song).await;
+        if let Err(err) = res {
+            tracing::error!("Failed to update scrobbler song {:?}", err);
+        }
+    })
+}
+
+#[tracing::instrument(level = "debug", skip(seconds))]
+pub fn scrobble_add_played(seconds: f64) {
+    spawn_local(async move {
+        let res = crate::utils::invoke::scrobbler_add_played(seconds).await;
+        if let Err(err) = res {
+            tracing::error!("Failed to report played time {:?}", err);
+        }
+    })
+}
@@ -14,13 +14,13 @@
 // You should have received a copy of the GNU General Public License
 // along with this program.  If not, see <http://www.gnu.org/licenses/>.
 
-use leptos::prelude::*;
+use leptos::{prelude::*, task::spawn_local};
 use leptos_context_menu::ContextMenuItemInner;
 use types::songs::Song;
 
 use crate::{
     store::ui_store::{SongSortBy, SongSortByColumns, UiStore},
-    utils::invoke::get_lyrics,
+    utils::invoke::{self, get_lyrics},
 };
 
 #[tracing::instrument(level = "debug", skip(song_list, song_indices))]
@@ -149,3 +149,13 @@ pub async fn fetch_lyrics(song: &Option<Song>) -> Option<String> {
 
     None
 }
+
+/// Stores where a long song was left off. Short songs are ignored by the backend.
+#[tracing::instrument(level = "debug", skip(song, position))]
+pub fn save_resume_point(song: Song, position: f64) {
+    spawn_local(async move {
+        if let Err(err) = invoke::save_resume_point(song, position).await {
+            tracing::error!("Failed to save resume point {:?}", err);
+        }
+    })
+}